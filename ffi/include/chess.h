@@ -0,0 +1,33 @@
+#ifndef CHESS_H
+#define CHESS_H
+
+/* Link against the chess_ffi library built from this crate. */
+
+#ifdef __cplusplus
+extern "C" {
+#endif
+
+typedef struct ChessBoard ChessBoard;
+
+/* Boards start from the standard position or a FEN. from_fen returns NULL
+ * for an invalid FEN. */
+ChessBoard *chess_board_new(void);
+ChessBoard *chess_board_from_fen(const char *fen);
+void chess_board_free(ChessBoard *board);
+
+/* Plays a move in UCI or SAN notation. Returns 0, or -1 if it is illegal. */
+int chess_board_push(ChessBoard *board, const char *move);
+
+/* Returned strings are owned by the caller; release them with
+ * chess_string_free. */
+char *chess_board_fen(const ChessBoard *board);
+char *chess_board_legal_moves(const ChessBoard *board);
+char *chess_board_best_move(const ChessBoard *board, unsigned int depth);
+int chess_board_is_game_over(const ChessBoard *board);
+void chess_string_free(char *text);
+
+#ifdef __cplusplus
+}
+#endif
+
+#endif
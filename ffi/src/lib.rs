@@ -0,0 +1,140 @@
+use std::{
+    ffi::{c_char, c_int, c_uint, CStr, CString},
+    ptr,
+};
+
+use rayon::iter::ParallelIterator;
+
+use chess::{
+    ai::AI,
+    logic::{ChessBoard, Move},
+};
+
+fn to_c_string(text: String) -> *mut c_char {
+    CString::new(text).map_or(ptr::null_mut(), CString::into_raw)
+}
+
+unsafe fn to_str<'a>(text: *const c_char) -> Option<&'a str> {
+    if text.is_null() {
+        return None;
+    }
+    CStr::from_ptr(text).to_str().ok()
+}
+
+fn legal_moves(board: &ChessBoard) -> Vec<Move> {
+    let mut moves = board.legal_moves(board.turn).collect::<Vec<_>>();
+    moves.sort_by_key(|mv| (mv.original, mv.target));
+    moves
+}
+
+#[no_mangle]
+pub extern "C" fn chess_board_new() -> *mut ChessBoard {
+    Box::into_raw(Box::new(ChessBoard::new()))
+}
+
+/// # Safety
+/// `fen` must be null or a valid NUL-terminated string.
+#[no_mangle]
+pub unsafe extern "C" fn chess_board_from_fen(fen: *const c_char) -> *mut ChessBoard {
+    let Some(fen) = to_str(fen) else {
+        return ptr::null_mut();
+    };
+    let mut board = ChessBoard::new();
+    if board.set_from_fen(fen).is_err() || board.validate().is_err() {
+        return ptr::null_mut();
+    }
+    Box::into_raw(Box::new(board))
+}
+
+/// # Safety
+/// `board` must be null or a pointer returned by this library that has not
+/// been freed yet.
+#[no_mangle]
+pub unsafe extern "C" fn chess_board_free(board: *mut ChessBoard) {
+    if !board.is_null() {
+        drop(Box::from_raw(board));
+    }
+}
+
+/// # Safety
+/// `board` must be a live board from this library and `mv` a valid
+/// NUL-terminated string in UCI or SAN notation.
+#[no_mangle]
+pub unsafe extern "C" fn chess_board_push(board: *mut ChessBoard, mv: *const c_char) -> c_int {
+    let (Some(board), Some(text)) = (board.as_mut(), to_str(mv)) else {
+        return -1;
+    };
+    match Move::parse(text, board) {
+        Ok(mv) => {
+            mv.perform(board);
+            0
+        }
+        Err(_) => -1,
+    }
+}
+
+/// # Safety
+/// `board` must be a live board from this library. The result must be
+/// released with `chess_string_free`.
+#[no_mangle]
+pub unsafe extern "C" fn chess_board_fen(board: *const ChessBoard) -> *mut c_char {
+    match board.as_ref() {
+        Some(board) => to_c_string(board.to_fen()),
+        None => ptr::null_mut(),
+    }
+}
+
+/// # Safety
+/// `board` must be a live board from this library. The result is a
+/// space-separated list of UCI moves and must be released with
+/// `chess_string_free`.
+#[no_mangle]
+pub unsafe extern "C" fn chess_board_legal_moves(board: *const ChessBoard) -> *mut c_char {
+    let Some(board) = board.as_ref() else {
+        return ptr::null_mut();
+    };
+    let moves = legal_moves(board)
+        .iter()
+        .map(|mv| mv.to_uci(board))
+        .collect::<Vec<_>>();
+    to_c_string(moves.join(" "))
+}
+
+/// # Safety
+/// `board` must be a live board from this library. Returns null when the
+/// side to move has no legal moves; otherwise the UCI move must be released
+/// with `chess_string_free`.
+#[no_mangle]
+pub unsafe extern "C" fn chess_board_best_move(
+    board: *const ChessBoard,
+    depth: c_uint,
+) -> *mut c_char {
+    let Some(board) = board.as_ref() else {
+        return ptr::null_mut();
+    };
+    if !board.has_legal_move() {
+        return ptr::null_mut();
+    }
+    let depth = (depth as usize).max(1);
+    let mv = AI::with_depth(depth).best_move(board, depth);
+    to_c_string(mv.to_uci(board))
+}
+
+/// # Safety
+/// `board` must be a live board from this library.
+#[no_mangle]
+pub unsafe extern "C" fn chess_board_is_game_over(board: *const ChessBoard) -> c_int {
+    board
+        .as_ref()
+        .map_or(0, |board| board.win_state().is_some() as c_int)
+}
+
+/// # Safety
+/// `text` must be null or a string returned by this library that has not
+/// been freed yet.
+#[no_mangle]
+pub unsafe extern "C" fn chess_string_free(text: *mut c_char) {
+    if !text.is_null() {
+        drop(CString::from_raw(text));
+    }
+}
@@ -1,167 +1,1127 @@
 use std::{
     cmp::Ordering,
-    collections::HashMap,
-    sync::{Arc, RwLock},
+    io,
+    sync::{
+        atomic::{AtomicBool, AtomicUsize, Ordering as AtomicOrdering},
+        Arc, RwLock,
+    },
+    thread::{self, JoinHandle},
+    time::{Duration, Instant},
 };
 
-use rayon::iter::{IntoParallelRefMutIterator, ParallelIterator};
+use rand::{rngs::StdRng, seq::IndexedRandom, Rng, SeedableRng};
+use rayon::{
+    iter::{IndexedParallelIterator, IntoParallelRefIterator, ParallelIterator},
+    ThreadPool, ThreadPoolBuildError, ThreadPoolBuilder,
+};
+use strum::IntoEnumIterator;
 
 use crate::{
-    game::Player,
-    logic::{ChessBoard, Move, PieceType, WinState},
+    analysis::{AnalysisLine, LiveEval},
+    book::OpeningBook,
+    clock::Clock,
+    engine::{Engine, SearchLimits},
+    fairy,
+    game::{Player, PlayerAction},
+    logic::{ChessBoard, Move, MoveType, PieceColor, PieceType, Variant},
+    movepick::MovePicker,
+    tt::{Bound, TranspositionTable, TtEntry},
 };
 
-#[derive(Clone, Debug, PartialEq, Default)]
-pub struct BoardNode {
-    pub board: ChessBoard,
+const DRAWISH_MARGIN: f64 = 0.5;
+const MAX_PLY: usize = 64;
+pub const MATE_SCORE: f64 = 100_000.0;
+const MATE_THRESHOLD: f64 = MATE_SCORE - MAX_PLY as f64;
+const FUTILITY_MARGINS: [f64; 4] = [0.0, 2.0, 3.5, 5.0];
+const REVERSE_FUTILITY_MARGINS: [f64; 4] = [0.0, 1.2, 2.5, 4.0];
+const RACE_WEIGHT: f64 = 1.5;
+pub const MAX_SKILL: u8 = 10;
+const BLUNDER_GAP_SCALE: f64 = 0.5;
+const STATS_PV_LENGTH: usize = 8;
+const CURRMOVE_DELAY: Duration = Duration::from_secs(1);
+
+pub type RootMoveCallback = Box<dyn Fn(&ChessBoard, usize, Move, usize) + Send + Sync>;
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct EvalSettings {
+    pub center_weight: f64,
+    pub unmoved_king_bonus: f64,
+    pub contempt: f64,
+}
+
+impl Default for EvalSettings {
+    fn default() -> Self {
+        Self {
+            center_weight: 1.0,
+            unmoved_king_bonus: 0.5,
+            contempt: 0.25,
+        }
+    }
+}
+
+fn mate_score(ply: usize) -> f64 {
+    MATE_SCORE - ply as f64
+}
+
+fn score_to_tt(score: f64, ply: usize) -> f64 {
+    if score >= MATE_THRESHOLD {
+        score + ply as f64
+    } else if score <= -MATE_THRESHOLD {
+        score - ply as f64
+    } else {
+        score
+    }
+}
+
+fn score_from_tt(score: f64, ply: usize) -> f64 {
+    if score >= MATE_THRESHOLD {
+        score - ply as f64
+    } else if score <= -MATE_THRESHOLD {
+        score + ply as f64
+    } else {
+        score
+    }
+}
+
+fn move_difficulty(board: &ChessBoard, mv: Move) -> f64 {
+    let Some(piece) = board.piece_at(mv.original) else {
+        return 1.0;
+    };
+    let capture = board.piece_at(mv.target).is_some() || mv.move_type == MoveType::EnPassant;
+    if capture || matches!(mv.move_type, MoveType::Promotion(_)) {
+        return 0.3;
+    }
+    let mut child = board.clone();
+    mv.perform(&mut child);
+    if child.is_in_check(child.turn) {
+        return 0.4;
+    }
+    let forward = match piece.color {
+        PieceColor::White => mv.target.1 < mv.original.1,
+        PieceColor::Black => mv.target.1 > mv.original.1,
+    };
+    let sideways = mv.target.1 == mv.original.1;
+    let distance = mv
+        .target
+        .0
+        .abs_diff(mv.original.0)
+        .max(mv.target.1.abs_diff(mv.original.1));
+    let difficulty = match (forward, sideways) {
+        (true, _) => 0.6,
+        (false, true) => 0.8,
+        (false, false) => 1.0,
+    };
+    if distance >= 3 {
+        (difficulty + 0.2_f64).min(1.0)
+    } else {
+        difficulty
+    }
+}
+
+pub fn mate_in_score(moves: i32) -> f64 {
+    if moves > 0 {
+        mate_score(2 * moves as usize - 1)
+    } else {
+        -mate_score(2 * moves.unsigned_abs() as usize)
+    }
+}
+
+pub fn mate_in(score: f64) -> Option<i32> {
+    if score >= MATE_THRESHOLD {
+        Some(((MATE_SCORE - score) as i32 + 1) / 2)
+    } else if score <= -MATE_THRESHOLD {
+        Some(-(((MATE_SCORE + score) as i32 + 1) / 2))
+    } else {
+        None
+    }
+}
+
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct EvalTerms {
+    pub material: [f64; 2],
+    pub center: [f64; 2],
+    pub king_safety: [f64; 2],
+    pub race: [f64; 2],
+}
+
+impl EvalTerms {
+    pub fn named(&self) -> [(&'static str, [f64; 2]); 4] {
+        [
+            ("Material", self.material),
+            ("Center", self.center),
+            ("King safety", self.king_safety),
+            ("King race", self.race),
+        ]
+    }
+
+    pub fn score(&self, color: PieceColor) -> f64 {
+        let (us, them) = (color as usize, color.opposite() as usize);
+        self.named()
+            .iter()
+            .map(|(_, values)| values[us] - values[them])
+            .sum()
+    }
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct RootMoveStats {
+    pub mv: Move,
     pub score: f64,
-    pub children: HashMap<Move, BoardNode>,
+    pub nodes: usize,
+    pub pv: Vec<Move>,
+}
+
+#[derive(Clone, Debug)]
+pub struct SearchStats {
+    pub board: ChessBoard,
+    pub depth: usize,
+    pub nodes: usize,
+    pub tt_probes: usize,
+    pub tt_hits: usize,
+    pub tt_entries: usize,
+    pub tt_capacity: usize,
+    pub elapsed: Duration,
+    pub root_moves: Vec<RootMoveStats>,
+}
+
+impl SearchStats {
+    pub fn tt_hit_rate(&self) -> f64 {
+        if self.tt_probes == 0 {
+            0.0
+        } else {
+            self.tt_hits as f64 / self.tt_probes as f64
+        }
+    }
+
+    pub fn nodes_per_second(&self) -> f64 {
+        self.nodes as f64 / self.elapsed.as_secs_f64().max(1e-3)
+    }
+}
+
+pub struct SearchContext {
+    pub eval: EvalSettings,
+    pub root_color: PieceColor,
+    pub draw_score: f64,
+    pub nodes: usize,
+    pub tt_probes: usize,
+    pub tt_hits: usize,
+    killers: [[Option<Move>; 2]; MAX_PLY],
+    stop: Arc<AtomicBool>,
+    cancel: Option<Arc<AtomicBool>>,
+    deadline: Option<Instant>,
+    node_limit: Option<usize>,
+    shared_nodes: Arc<AtomicUsize>,
+}
+
+impl SearchContext {
+    pub fn new(root: &ChessBoard, eval: EvalSettings, stop: Arc<AtomicBool>) -> Self {
+        let root_eval = AI::static_eval(root, &eval);
+        let draw_score = if root_eval > DRAWISH_MARGIN {
+            -eval.contempt
+        } else if root_eval < -DRAWISH_MARGIN {
+            eval.contempt
+        } else {
+            0.0
+        };
+        Self {
+            eval,
+            root_color: root.turn,
+            draw_score,
+            nodes: 0,
+            tt_probes: 0,
+            tt_hits: 0,
+            killers: [[None; 2]; MAX_PLY],
+            stop,
+            cancel: None,
+            deadline: None,
+            node_limit: None,
+            shared_nodes: Arc::new(AtomicUsize::new(0)),
+        }
+    }
+
+    // A context for another search thread that shares the limits of this one.
+    fn fork(&self) -> Self {
+        Self {
+            eval: self.eval,
+            root_color: self.root_color,
+            draw_score: self.draw_score,
+            nodes: 0,
+            tt_probes: 0,
+            tt_hits: 0,
+            killers: [[None; 2]; MAX_PLY],
+            stop: self.stop.clone(),
+            cancel: self.cancel.clone(),
+            deadline: self.deadline,
+            node_limit: self.node_limit,
+            shared_nodes: self.shared_nodes.clone(),
+        }
+    }
+
+    fn join(&mut self, other: &Self) {
+        self.nodes += other.nodes;
+        self.tt_probes += other.tt_probes;
+        self.tt_hits += other.tt_hits;
+    }
+
+    fn count_node(&mut self) {
+        self.nodes += 1;
+        if self.node_limit.is_some() {
+            self.shared_nodes.fetch_add(1, AtomicOrdering::Relaxed);
+        }
+    }
+
+    pub fn with_deadline(mut self, deadline: Option<Instant>) -> Self {
+        self.deadline = deadline;
+        self
+    }
+
+    pub fn with_cancel(mut self, cancel: Option<Arc<AtomicBool>>) -> Self {
+        self.cancel = cancel;
+        self
+    }
+
+    pub fn with_node_limit(mut self, node_limit: Option<usize>) -> Self {
+        self.node_limit = node_limit;
+        self
+    }
+
+    fn is_stopped(&self) -> bool {
+        self.stop.load(AtomicOrdering::Relaxed)
+            || self
+                .cancel
+                .as_ref()
+                .is_some_and(|cancel| cancel.load(AtomicOrdering::Relaxed))
+            || self
+                .deadline
+                .is_some_and(|deadline| Instant::now() >= deadline)
+            || self
+                .node_limit
+                .is_some_and(|limit| self.shared_nodes.load(AtomicOrdering::Relaxed) >= limit)
+    }
+
+    fn draw_score_for(&self, color: PieceColor) -> f64 {
+        if color == self.root_color {
+            self.draw_score
+        } else {
+            -self.draw_score
+        }
+    }
+
+    fn add_killer(&mut self, ply: usize, m: Move) {
+        let killers = &mut self.killers[ply.min(MAX_PLY - 1)];
+        if killers[0] != Some(m) {
+            killers[1] = killers[0];
+            killers[0] = Some(m);
+        }
+    }
 }
 
 pub struct AI {
-    pub tree: BoardNode,
+    pub tt: TranspositionTable,
+    pub depth: usize,
+    pub eval: EvalSettings,
+    pub nodes: usize,
+    pub variety: f64,
+    pub skill: Option<u8>,
+    pub temperature: f64,
+    pub book: Option<OpeningBook>,
+    pub stop: Arc<AtomicBool>,
+    pub ponder: bool,
+    pub move_time: Option<Duration>,
+    // When set, each move as a player also fits the time left on this clock.
+    pub clock: Option<Arc<RwLock<Clock>>>,
+    pub stats: Arc<RwLock<Option<SearchStats>>>,
+    pub on_root_move: Option<RootMoveCallback>,
+    seed: Option<u64>,
+    rng: StdRng,
+    deadline: Option<Instant>,
+    cancel: Option<Arc<AtomicBool>>,
+    pool: Option<Arc<ThreadPool>>,
+    position: Option<ChessBoard>,
+    ponder_thread: Option<(Arc<AtomicBool>, JoinHandle<TranspositionTable>)>,
+}
+
+impl Default for AI {
+    fn default() -> Self {
+        Self::new()
+    }
 }
 
 impl AI {
     pub fn new() -> Self {
+        Self::with_depth(4)
+    }
+
+    pub fn with_depth(depth: usize) -> Self {
         Self {
-            tree: BoardNode {
-                board: ChessBoard::new(),
-                score: 0.0,
-                children: HashMap::new(),
-            },
+            tt: TranspositionTable::new(),
+            depth,
+            eval: EvalSettings::default(),
+            nodes: 0,
+            variety: 0.0,
+            skill: None,
+            temperature: 0.0,
+            book: None,
+            stop: Arc::new(AtomicBool::new(false)),
+            ponder: false,
+            move_time: None,
+            clock: None,
+            stats: Arc::new(RwLock::new(None)),
+            on_root_move: None,
+            seed: None,
+            rng: StdRng::from_os_rng(),
+            deadline: None,
+            cancel: None,
+            pool: None,
+            position: None,
+            ponder_thread: None,
         }
     }
 
-    pub fn evaluate_tree(tree: &mut BoardNode, depth: usize) {
-        if tree.children.is_empty() {
-            if let Some(win_state) = tree.board.win_state() {
-                tree.score = match win_state {
-                    WinState::Checkmate(winner) => {
-                        if winner == tree.board.turn {
-                            f64::NEG_INFINITY
-                        } else {
-                            f64::INFINITY
-                        }
+    // On failure the search falls back to the global rayon pool.
+    pub fn set_threads(&mut self, threads: usize) -> Result<(), ThreadPoolBuildError> {
+        self.pool = None;
+        let pool = ThreadPoolBuilder::new()
+            .num_threads(threads)
+            .thread_name(|i| format!("search-{}", i))
+            .build()?;
+        self.pool = Some(Arc::new(pool));
+        Ok(())
+    }
+
+    pub fn threads(&self) -> usize {
+        self.pool
+            .as_ref()
+            .map_or_else(rayon::current_num_threads, |pool| {
+                pool.current_num_threads()
+            })
+    }
+
+    fn in_pool<T: Send>(&mut self, f: impl FnOnce(&mut Self) -> T + Send) -> T {
+        match self.pool.clone() {
+            Some(pool) => pool.install(|| f(self)),
+            None => f(self),
+        }
+    }
+
+    pub fn seed(&self) -> Option<u64> {
+        self.seed
+    }
+
+    pub fn set_seed(&mut self, seed: Option<u64>) {
+        self.seed = seed;
+        self.rng = match seed {
+            Some(seed) => StdRng::seed_from_u64(seed),
+            None => StdRng::from_os_rng(),
+        };
+    }
+
+    pub fn eval_terms(board: &ChessBoard, eval: &EvalSettings) -> EvalTerms {
+        let mut terms = EvalTerms::default();
+        let center = (
+            (board.width as f64 - 1.0) / 2.0,
+            (board.height as f64 - 1.0) / 2.0,
+        );
+        for piece in board.pieces.iter().filter_map(|x| x.as_ref()) {
+            let side = piece.color as usize;
+            match piece.piece_type {
+                PieceType::Pawn => terms.material[side] += 1.0,
+                PieceType::Knight => terms.material[side] += 3.0,
+                PieceType::Bishop => terms.material[side] += 3.0,
+                PieceType::Rook => terms.material[side] += 5.0,
+                PieceType::Queen => terms.material[side] += 9.0,
+                PieceType::Fairy(index) => {
+                    terms.material[side] += fairy::piece(index).map_or(3.0, |p| p.value)
+                }
+                PieceType::King => {
+                    if board.variant == Variant::RacingKings {
+                        terms.race[side] += (board.height - 1 - piece.pos.1) as f64 * RACE_WEIGHT;
+                    } else if piece.first_move_at.is_none() {
+                        terms.king_safety[side] += eval.unmoved_king_bonus;
                     }
-                    WinState::Stalemate => 0.0,
-                };
-                return;
-            }
-            if depth > 0 {
-                let valid_moves = tree
-                    .board
-                    .valid_moves(false, tree.board.turn)
-                    .collect::<Vec<_>>();
-                for m in valid_moves {
-                    let mut new_board = tree.board.clone();
-                    m.perform(&mut new_board);
-                    let child_node = BoardNode {
-                        board: new_board,
-                        score: 0.0,
-                        children: HashMap::new(),
-                    };
-                    tree.children.insert(m, child_node);
                 }
             }
+            let dist_to_center =
+                (piece.pos.0 as f64 - center.0).abs() + (piece.pos.1 as f64 - center.1).abs();
+            let center_score = (1.0 - (dist_to_center / (center.0 + center.1)))
+                / (3.0 + piece.first_move_at.unwrap_or_default() as f64);
+            terms.center[side] += center_score * eval.center_weight;
+        }
+        terms
+    }
+
+    pub fn static_eval(board: &ChessBoard, eval: &EvalSettings) -> f64 {
+        Self::eval_terms(board, eval).score(board.turn)
+    }
+
+    fn is_rule_draw(board: &ChessBoard) -> bool {
+        board.halfmove_clock >= 100
+            || (board.variant == Variant::Standard
+                && PieceColor::iter().all(|color| !board.has_mating_material(color)))
+    }
+
+    fn search(
+        tt: &TranspositionTable,
+        board: &ChessBoard,
+        depth: usize,
+        ply: usize,
+        mut alpha: f64,
+        beta: f64,
+        ctx: &mut SearchContext,
+    ) -> f64 {
+        ctx.count_node();
+        let draw_score = ctx.draw_score_for(board.turn);
+        if board.repetition_count() >= 2 || Self::is_rule_draw(board) {
+            return draw_score;
+        }
+        if let Some(win_state) = board.variant_result() {
+            return match win_state.winner() {
+                Some(winner) if winner == board.turn => mate_score(ply),
+                Some(_) => -mate_score(ply),
+                None => draw_score,
+            };
         }
         if depth == 0 {
-            let mut score = 0.0;
-            for piece in tree.board.pieces.iter().filter_map(|x| x.as_ref()) {
-                let mut piece_score = match piece.piece_type {
-                    PieceType::Pawn => 1.0,
-                    PieceType::Knight => 3.0,
-                    PieceType::Bishop => 3.0,
-                    PieceType::Rook => 5.0,
-                    PieceType::Queen => 9.0,
-                    PieceType::King => {
-                        if piece.first_move_at.is_none() {
-                            0.5
-                        } else {
-                            0.0
-                        }
-                    }
-                };
-                let dist_to_center =
-                    (piece.pos.0 as f64 - 3.5).abs() + (piece.pos.1 as f64 - 3.5).abs();
-                let center_score = (1.0 - (dist_to_center / 7.0))
-                    / (3.0 + piece.first_move_at.unwrap_or_default() as f64);
-                piece_score += center_score;
-                if piece.color == tree.board.turn {
-                    score -= piece_score;
-                } else {
-                    score += piece_score;
+            if board.has_legal_move() {
+                return Self::static_eval(board, &ctx.eval);
+            } else if board.is_in_check(board.turn) {
+                return -mate_score(ply);
+            } else {
+                return draw_score;
+            }
+        }
+
+        let key = board.zobrist_key();
+        let mut tt_move = None;
+        ctx.tt_probes += 1;
+        let entry = tt.get(key);
+        if let Some(entry) = entry {
+            ctx.tt_hits += 1;
+            tt_move = entry.best_move;
+            if entry.depth >= depth {
+                let score = score_from_tt(entry.score, ply);
+                match entry.bound {
+                    Bound::Exact => return score,
+                    Bound::Lower if score >= beta => return score,
+                    Bound::Upper if score <= alpha => return score,
+                    _ => {}
                 }
             }
-            tree.score = score;
-        } else {
-            let mut children: Vec<_> = tree.children.values_mut().collect();
-            let score = if depth >= 2 {
-                children
-                    .iter_mut()
-                    .map(|child| {
-                        Self::evaluate_tree(child, depth - 1);
-                        child.score
-                    })
-                    .max_by(|a, b| a.partial_cmp(b).unwrap_or(Ordering::Equal))
-                    .unwrap_or_default()
+        }
+
+        let in_check = board.is_in_check(board.turn);
+        let mut futility_score = None;
+        if !in_check && depth < FUTILITY_MARGINS.len() {
+            let eval = Self::static_eval(board, &ctx.eval);
+            if eval - REVERSE_FUTILITY_MARGINS[depth] >= beta {
+                return eval;
+            }
+            if eval + FUTILITY_MARGINS[depth] <= alpha {
+                futility_score = Some(eval + FUTILITY_MARGINS[depth]);
+            }
+        }
+
+        let alpha_orig = alpha;
+        let mut best_score = f64::NEG_INFINITY;
+        let mut best_move = None;
+        let mut has_legal_move = false;
+        let killers = ctx.killers[ply.min(MAX_PLY - 1)];
+        for m in MovePicker::new(board, tt_move, killers) {
+            let mut child = board.clone();
+            m.perform(&mut child);
+            if !child.is_legal_after_move(board.turn) {
+                continue;
+            }
+            has_legal_move = true;
+            if let Some(futility_score) = futility_score {
+                if best_move.is_some()
+                    && !m.is_capture(board)
+                    && !m.is_promotion()
+                    && !child.is_in_check(child.turn)
+                {
+                    best_score = best_score.max(futility_score);
+                    continue;
+                }
+            }
+            let score = -Self::search(tt, &child, depth - 1, ply + 1, -beta, -alpha, ctx);
+            if ctx.is_stopped() {
+                return 0.0;
+            }
+            if best_move.is_none() || score > best_score {
+                best_score = score;
+                best_move = Some(m);
+            }
+            if score > alpha {
+                alpha = score;
+            }
+            if alpha >= beta {
+                if !m.is_capture(board) && !m.is_promotion() {
+                    ctx.add_killer(ply, m);
+                }
+                break;
+            }
+        }
+        if !has_legal_move {
+            return if in_check {
+                -mate_score(ply)
             } else {
-                children
-                    .par_iter_mut()
-                    .map(|child| {
-                        Self::evaluate_tree(child, depth - 1);
-                        child.score
-                    })
-                    .max_by(|a, b| a.partial_cmp(b).unwrap_or(Ordering::Equal))
-                    .unwrap_or_default()
+                draw_score
             };
-            tree.score = -score;
         }
+
+        let bound = if best_score <= alpha_orig {
+            Bound::Upper
+        } else if best_score >= beta {
+            Bound::Lower
+        } else {
+            Bound::Exact
+        };
+        tt.insert(
+            key,
+            board.piece_count(),
+            TtEntry {
+                depth,
+                score: score_to_tt(best_score, ply),
+                bound,
+                best_move,
+            },
+        );
+        best_score
+    }
+
+    pub fn book_move(&mut self, board: &ChessBoard) -> Option<Move> {
+        self.book
+            .as_ref()
+            .and_then(|book| book.pick(board, &mut self.rng))
     }
 
     pub fn best_move(&mut self, board: &ChessBoard, depth: usize) -> Move {
-        if &self.tree.board != board {
-            if self
-                .tree
-                .children
-                .iter()
-                .flat_map(|(_, child)| child.children.iter())
-                .any(|(_, child)| &child.board == board)
-            {
-                self.tree = self
-                    .tree
-                    .clone()
-                    .children
-                    .into_iter()
-                    .flat_map(|(_, child)| child.children.into_iter())
-                    .find(|(_, child)| &child.board == board)
-                    .unwrap()
-                    .1;
-            } else {
-                self.tree = BoardNode {
-                    board: board.clone(),
-                    score: 0.0,
-                    children: HashMap::new(),
-                };
+        if let Some(mv) = self.book_move(board) {
+            return mv;
+        }
+        let scores = self.analyze(board, depth);
+        self.pick_move(board, &scores)
+    }
+
+    pub fn pick_move(&mut self, board: &ChessBoard, scores: &[(Move, f64)]) -> Move {
+        let (best_move, best_score) = *scores
+            .first()
+            .expect("Board should always have valid moves");
+        if let Some(skill) = self.skill.filter(|&skill| skill < MAX_SKILL) {
+            return self.pick_human_move(board, scores, skill);
+        }
+        if self.temperature > 0.0 {
+            return self.pick_softmax_move(scores);
+        }
+        if self.variety <= 0.0 {
+            return best_move;
+        }
+        let candidates = scores
+            .iter()
+            .take_while(|(_, score)| best_score - score <= self.variety)
+            .map(|(m, _)| *m)
+            .collect::<Vec<_>>();
+        *candidates.choose(&mut self.rng).unwrap_or(&best_move)
+    }
+
+    fn pick_softmax_move(&mut self, scores: &[(Move, f64)]) -> Move {
+        let best_score = scores[0].1;
+        let weights = scores
+            .iter()
+            .map(|&(_, score)| ((score - best_score) / self.temperature).exp())
+            .collect::<Vec<_>>();
+        let mut choice = self.rng.random_range(0.0..weights.iter().sum::<f64>());
+        for (&(mv, _), weight) in scores.iter().zip(weights) {
+            if choice < weight {
+                return mv;
             }
+            choice -= weight;
         }
-        Self::evaluate_tree(&mut self.tree, depth);
+        scores[0].0
+    }
+
+    fn pick_human_move(&mut self, board: &ChessBoard, scores: &[(Move, f64)], skill: u8) -> Move {
+        let weakness = (MAX_SKILL - skill) as f64 / MAX_SKILL as f64;
+        let gap_scale = BLUNDER_GAP_SCALE * (1.0 + 4.0 * weakness);
+        for (i, &(mv, score)) in scores.iter().enumerate() {
+            let Some(&(_, next_score)) = scores.get(i + 1) else {
+                return mv;
+            };
+            let gap = (score - next_score).max(0.0);
+            let miss = weakness * move_difficulty(board, mv) * (-gap / gap_scale).exp();
+            if !self.rng.random_bool(miss.clamp(0.0, 1.0)) {
+                return mv;
+            }
+        }
+        scores[0].0
+    }
+
+    pub fn analyze(&mut self, board: &ChessBoard, depth: usize) -> Vec<(Move, f64)> {
+        self.in_pool(|ai| ai.analyze_depth(board, depth))
+    }
 
-        let chosen_move = self
-            .tree
-            .children
+    fn analyze_depth(&mut self, board: &ChessBoard, depth: usize) -> Vec<(Move, f64)> {
+        let depth = depth.max(1);
+        let started = Instant::now();
+        let stop = if depth > 1 {
+            self.stop.clone()
+        } else {
+            Arc::new(AtomicBool::new(false))
+        };
+        let mut ctx = SearchContext::new(board, self.eval, stop)
+            .with_deadline(self.deadline.filter(|_| depth > 1))
+            .with_cancel(self.cancel.clone());
+        let key = board.zobrist_key();
+        let tt_move = self.tt.get(key).and_then(|entry| entry.best_move);
+
+        // Root moves are searched with a full window, so they can be split
+        // across the threads of the pool sharing the transposition table.
+        let root_moves = MovePicker::new(board, tt_move, [None; 2])
+            .filter_map(|m| {
+                let mut child = board.clone();
+                m.perform(&mut child);
+                child.is_legal_after_move(board.turn).then_some((m, child))
+            })
+            .collect::<Vec<_>>();
+        let tt = &self.tt;
+        let on_root_move = self.on_root_move.as_ref();
+        let results = root_moves
+            .par_iter()
+            .enumerate()
+            .map(|(i, (m, child))| {
+                if let Some(on_root_move) =
+                    on_root_move.filter(|_| started.elapsed() >= CURRMOVE_DELAY)
+                {
+                    on_root_move(board, depth, *m, i + 1);
+                }
+                let mut worker = ctx.fork();
+                let score = -Self::search(
+                    tt,
+                    child,
+                    depth - 1,
+                    1,
+                    f64::NEG_INFINITY,
+                    f64::INFINITY,
+                    &mut worker,
+                );
+                let stopped = worker.is_stopped();
+                (*m, score, stopped, worker)
+            })
+            .collect::<Vec<_>>();
+
+        let mut scores = Vec::new();
+        let mut root_nodes = Vec::new();
+        for (m, score, stopped, worker) in results {
+            ctx.join(&worker);
+            if !stopped {
+                scores.push((m, score));
+                root_nodes.push((m, worker.nodes));
+            }
+        }
+        self.nodes = ctx.nodes;
+
+        scores.sort_by(|(_, a), (_, b)| b.partial_cmp(a).unwrap_or(Ordering::Equal));
+        if ctx.is_stopped() {
+            return scores;
+        }
+        if let Some(&(best_move, score)) = scores.first() {
+            self.tt.insert(
+                key,
+                board.piece_count(),
+                TtEntry {
+                    depth,
+                    score,
+                    bound: Bound::Exact,
+                    best_move: Some(best_move),
+                },
+            );
+        }
+        self.record_stats(board, depth, &ctx, started.elapsed(), &scores, &root_nodes);
+        scores
+    }
+
+    fn record_stats(
+        &self,
+        board: &ChessBoard,
+        depth: usize,
+        ctx: &SearchContext,
+        elapsed: Duration,
+        scores: &[(Move, f64)],
+        root_nodes: &[(Move, usize)],
+    ) {
+        let root_moves = scores
             .iter()
-            .max_by(|(_, a), (_, b)| a.score.partial_cmp(&b.score).unwrap_or(Ordering::Equal))
-            .map(|(m, _)| m.clone())
-            .expect("Board should always have valid moves");
+            .map(|&(mv, score)| RootMoveStats {
+                mv,
+                score,
+                nodes: root_nodes
+                    .iter()
+                    .find(|(m, _)| *m == mv)
+                    .map_or(0, |&(_, nodes)| nodes),
+                pv: self.root_pv(board, mv),
+            })
+            .collect();
+        *self.stats.write().unwrap() = Some(SearchStats {
+            board: board.clone(),
+            depth,
+            nodes: ctx.nodes,
+            tt_probes: ctx.tt_probes,
+            tt_hits: ctx.tt_hits,
+            tt_entries: self.tt.len(),
+            tt_capacity: self.tt.capacity(),
+            elapsed,
+            root_moves,
+        });
+    }
+
+    pub fn find_mate(
+        &mut self,
+        board: &ChessBoard,
+        moves: usize,
+        deadline: Option<Instant>,
+        node_limit: Option<usize>,
+    ) -> Option<(usize, Vec<Move>)> {
+        self.in_pool(|ai| ai.find_mate_within(board, moves, deadline, node_limit))
+    }
+
+    fn find_mate_within(
+        &mut self,
+        board: &ChessBoard,
+        moves: usize,
+        deadline: Option<Instant>,
+        node_limit: Option<usize>,
+    ) -> Option<(usize, Vec<Move>)> {
+        let mut ctx = SearchContext::new(board, self.eval, self.stop.clone())
+            .with_deadline(deadline)
+            .with_cancel(self.cancel.clone())
+            .with_node_limit(node_limit);
+        let mut found = None;
+        for n in 1..=moves {
+            if let Some(line) = Self::mate_line(board, n, &mut ctx) {
+                found = Some((n, line));
+                break;
+            }
+            if ctx.is_stopped() {
+                break;
+            }
+        }
+        self.nodes = ctx.nodes;
+        found
+    }
+
+    fn mate_candidates(board: &ChessBoard) -> Vec<(Move, ChessBoard)> {
+        let mut candidates = board
+            .legal_move_list()
+            .iter()
+            .map(|&mv| {
+                let mut child = board.clone();
+                mv.perform(&mut child);
+                (mv, child)
+            })
+            .collect::<Vec<_>>();
+        candidates.sort_by_key(|(_, child)| !child.is_in_check(child.turn));
+        candidates
+    }
+
+    fn mates_after(child: &ChessBoard, moves: usize, ctx: &mut SearchContext) -> bool {
+        let replies = child.legal_move_list();
+        if replies.is_empty() {
+            return child.is_in_check(child.turn);
+        }
+        moves > 1
+            && replies.iter().all(|reply| {
+                let mut after = child.clone();
+                reply.perform(&mut after);
+                Self::mate_move(&after, moves - 1, ctx).is_some()
+            })
+    }
+
+    fn mate_move(board: &ChessBoard, moves: usize, ctx: &mut SearchContext) -> Option<Move> {
+        ctx.count_node();
+        for (mv, child) in Self::mate_candidates(board) {
+            if ctx.is_stopped() {
+                return None;
+            }
+            if Self::mates_after(&child, moves, ctx) {
+                return Some(mv);
+            }
+        }
+        None
+    }
+
+    fn parallel_mate_move(
+        board: &ChessBoard,
+        moves: usize,
+        ctx: &mut SearchContext,
+    ) -> Option<Move> {
+        ctx.count_node();
+        let nodes = AtomicUsize::new(0);
+        let shared = &*ctx;
+        let found = Self::mate_candidates(board)
+            .par_iter()
+            .find_first(|(_, child)| {
+                let mut worker = shared.fork();
+                let mates = !worker.is_stopped() && Self::mates_after(child, moves, &mut worker);
+                nodes.fetch_add(worker.nodes, AtomicOrdering::Relaxed);
+                mates
+            })
+            .map(|(mv, _)| *mv);
+        ctx.nodes += nodes.into_inner();
+        found
+    }
+
+    fn mate_line(board: &ChessBoard, moves: usize, ctx: &mut SearchContext) -> Option<Vec<Move>> {
+        let mv = Self::parallel_mate_move(board, moves, ctx)?;
+        let mut child = board.clone();
+        mv.perform(&mut child);
+        let mut line = vec![mv];
+        let mut longest: Option<(usize, Move, Vec<Move>)> = None;
+        for &reply in child.legal_move_list().iter() {
+            let mut after = child.clone();
+            reply.perform(&mut after);
+            let Some((n, rest)) =
+                (1..moves).find_map(|n| Self::mate_line(&after, n, ctx).map(|rest| (n, rest)))
+            else {
+                return Some(line);
+            };
+            if longest.as_ref().is_none_or(|(longest, _, _)| n > *longest) {
+                longest = Some((n, reply, rest));
+            }
+        }
+        if let Some((_, reply, rest)) = longest {
+            line.push(reply);
+            line.extend(rest);
+        }
+        Some(line)
+    }
+
+    fn root_pv(&self, board: &ChessBoard, mv: Move) -> Vec<Move> {
+        let mut child = board.clone();
+        mv.perform(&mut child);
+        let mut pv = vec![mv];
+        pv.extend(self.principal_variation(&child, STATS_PV_LENGTH - 1));
+        pv
+    }
 
-        chosen_move
+    pub fn lines(
+        &self,
+        board: &ChessBoard,
+        scores: &[(Move, f64)],
+        count: usize,
+    ) -> Vec<AnalysisLine> {
+        scores
+            .iter()
+            .take(count)
+            .map(|&(mv, score)| AnalysisLine {
+                score,
+                pv: self.root_pv(board, mv),
+            })
+            .collect()
+    }
+
+    pub fn principal_variation(&self, board: &ChessBoard, max_len: usize) -> Vec<Move> {
+        let mut board = board.clone();
+        let mut seen = Vec::new();
+        let mut pv = Vec::new();
+        while pv.len() < max_len {
+            let key = board.zobrist_key();
+            if seen.contains(&key) {
+                break;
+            }
+            seen.push(key);
+            let Some(mv) = self
+                .tt
+                .get(key)
+                .and_then(|entry| entry.best_move)
+                .filter(|mv| mv.is_legal(&board))
+            else {
+                break;
+            };
+            mv.perform(&mut board);
+            pv.push(mv);
+        }
+        pv
+    }
+
+    pub fn think(&mut self, board: &ChessBoard) -> Vec<(Move, f64)> {
+        self.iterate(board, self.depth, self.move_time, |_, _, _| {})
+    }
+
+    fn iterate(
+        &mut self,
+        board: &ChessBoard,
+        max_depth: usize,
+        move_time: Option<Duration>,
+        mut on_depth: impl FnMut(&Self, usize, &[(Move, f64)]),
+    ) -> Vec<(Move, f64)> {
+        self.tt.new_search(board.piece_count());
+        self.deadline = move_time.map(|move_time| Instant::now() + move_time);
+        let mut scores = Vec::new();
+        for depth in 1..=max_depth.max(1) {
+            let result = self.analyze(board, depth);
+            let interrupted = self.stop.load(AtomicOrdering::Relaxed)
+                || self.is_cancelled()
+                || self
+                    .deadline
+                    .is_some_and(|deadline| Instant::now() >= deadline);
+            if interrupted && depth > 1 {
+                break;
+            }
+            scores = result;
+            on_depth(self, depth, &scores);
+        }
+        self.deadline = None;
+        scores
+    }
+
+    fn is_cancelled(&self) -> bool {
+        self.cancel
+            .as_ref()
+            .is_some_and(|cancel| cancel.load(AtomicOrdering::Relaxed))
+    }
+
+    fn start_pondering(&mut self, board: &ChessBoard, mv: Move) {
+        let mut board = board.clone();
+        mv.perform(&mut board);
+        let Some(reply) = self
+            .tt
+            .get(board.zobrist_key())
+            .and_then(|entry| entry.best_move)
+            .filter(|reply| reply.is_legal(&board))
+        else {
+            return;
+        };
+        reply.perform(&mut board);
+        if board.win_state().is_some() {
+            return;
+        }
+
+        let stop = Arc::new(AtomicBool::new(false));
+        let mut ponderer = AI::with_depth(self.depth);
+        ponderer.tt = std::mem::take(&mut self.tt);
+        ponderer.eval = self.eval;
+        ponderer.stop = stop.clone();
+        ponderer.stats = self.stats.clone();
+        ponderer.pool = self.pool.clone();
+        let handle = thread::spawn(move || {
+            let depth = ponderer.depth;
+            ponderer.analyze(&board, depth);
+            std::mem::take(&mut ponderer.tt)
+        });
+        self.ponder_thread = Some((stop, handle));
+    }
+
+    fn stop_pondering(&mut self) {
+        if let Some((stop, handle)) = self.ponder_thread.take() {
+            stop.store(true, AtomicOrdering::Relaxed);
+            if let Ok(tt) = handle.join() {
+                self.tt = tt;
+            }
+        }
+    }
+}
+
+impl Drop for AI {
+    fn drop(&mut self) {
+        self.stop_pondering();
     }
 }
 
 impl Player for AI {
     fn get_move(&mut self, board: Arc<RwLock<ChessBoard>>) -> Move {
-        let board = board.read().unwrap();
-        return self.best_move(&board, 4);
+        match self.get_action(board, false) {
+            PlayerAction::Move(mv) => mv,
+            _ => panic!("The game was cancelled while the AI was thinking"),
+        }
+    }
+
+    fn get_action(&mut self, board: Arc<RwLock<ChessBoard>>, draw_offered: bool) -> PlayerAction {
+        self.stop_pondering();
+        self.stop.store(false, AtomicOrdering::Relaxed);
+        let board = board.read().unwrap().clone();
+        if let Some(mv) = self.book_move(&board) {
+            return PlayerAction::Move(mv);
+        }
+        let budget = self
+            .clock
+            .as_ref()
+            .and_then(|clock| SearchLimits::clock(&clock.read().unwrap()).time_budget(&board));
+        let move_time = match (self.move_time, budget) {
+            (Some(move_time), Some(budget)) => Some(move_time.min(budget)),
+            (move_time, budget) => move_time.or(budget),
+        };
+        let scores = self.iterate(&board, self.depth, move_time, |_, _, _| {});
+        if self.is_cancelled() {
+            return PlayerAction::Abort;
+        }
+        if draw_offered && scores[0].1 < -DRAWISH_MARGIN {
+            return PlayerAction::AcceptDraw;
+        }
+        let mv = self.pick_move(&board, &scores);
+        if self.ponder {
+            self.start_pondering(&board, mv);
+        }
+        PlayerAction::Move(mv)
+    }
+
+    fn set_cancel(&mut self, cancel: Arc<AtomicBool>) {
+        self.cancel = Some(cancel);
+    }
+}
+
+impl Engine for AI {
+    fn name(&self) -> String {
+        "ChessAI".to_string()
+    }
+
+    fn new_game(&mut self) -> io::Result<()> {
+        self.stop_pondering();
+        self.tt.clear();
+        Ok(())
+    }
+
+    fn set_position(&mut self, board: &ChessBoard) -> io::Result<()> {
+        self.position = Some(board.clone());
+        Ok(())
+    }
+
+    fn search(
+        &mut self,
+        limits: &SearchLimits,
+        on_info: &mut dyn FnMut(&LiveEval),
+    ) -> io::Result<Move> {
+        let board = self
+            .position
+            .clone()
+            .ok_or_else(|| io::Error::new(io::ErrorKind::InvalidInput, "no position set"))?;
+        if let Some(mv) = self.book_move(&board) {
+            return Ok(mv);
+        }
+        let scores = self.iterate(
+            &board,
+            limits.depth.unwrap_or(self.depth),
+            limits.time_budget(&board),
+            |ai, depth, scores| {
+                on_info(&LiveEval {
+                    depth,
+                    lines: ai.lines(&board, scores, limits.lines()),
+                })
+            },
+        );
+        if scores.is_empty() {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidInput,
+                "no legal moves",
+            ));
+        }
+        Ok(self.pick_move(&board, &scores))
+    }
+
+    fn set_stop(&mut self, stop: Arc<AtomicBool>) {
+        self.stop = stop;
+    }
+
+    fn set_cancel(&mut self, cancel: Arc<AtomicBool>) {
+        self.cancel = Some(cancel);
     }
 }
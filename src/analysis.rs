@@ -0,0 +1,286 @@
+use std::{
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        Arc, Mutex, RwLock,
+    },
+    thread,
+};
+
+use crate::{
+    ai::{mate_in, AI},
+    engine::{Engine, SearchLimits},
+    logic::{ChessBoard, Move, PieceColor},
+    record::GameRecord,
+};
+
+const INACCURACY_LOSS: f64 = 0.5;
+const MISTAKE_LOSS: f64 = 1.0;
+const BLUNDER_LOSS: f64 = 3.0;
+const SCORE_CAP: f64 = 10.0;
+pub const MAX_MULTI_PV: usize = 5;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum MoveClassification {
+    Best,
+    Good,
+    Inaccuracy,
+    Mistake,
+    Blunder,
+}
+
+impl MoveClassification {
+    pub fn from_loss(loss: f64) -> Self {
+        if loss <= 0.0 {
+            MoveClassification::Best
+        } else if loss < INACCURACY_LOSS {
+            MoveClassification::Good
+        } else if loss < MISTAKE_LOSS {
+            MoveClassification::Inaccuracy
+        } else if loss < BLUNDER_LOSS {
+            MoveClassification::Mistake
+        } else {
+            MoveClassification::Blunder
+        }
+    }
+
+    pub fn glyph(&self) -> Option<&'static str> {
+        match self {
+            MoveClassification::Inaccuracy => Some("?!"),
+            MoveClassification::Mistake => Some("?"),
+            MoveClassification::Blunder => Some("??"),
+            _ => None,
+        }
+    }
+
+    pub fn readable(&self) -> &'static str {
+        match self {
+            MoveClassification::Best => "Best move",
+            MoveClassification::Good => "Good move",
+            MoveClassification::Inaccuracy => "Inaccuracy",
+            MoveClassification::Mistake => "Mistake",
+            MoveClassification::Blunder => "Blunder",
+        }
+    }
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct MoveAnalysis {
+    pub color: PieceColor,
+    pub played: Move,
+    pub played_score: f64,
+    pub best: Move,
+    pub best_score: f64,
+    pub classification: MoveClassification,
+}
+
+impl MoveAnalysis {
+    pub fn loss(&self) -> f64 {
+        if self.best_score == self.played_score {
+            0.0
+        } else {
+            self.best_score - self.played_score
+        }
+    }
+
+    pub fn white_score(&self) -> f64 {
+        match self.color {
+            PieceColor::White => self.played_score,
+            PieceColor::Black => -self.played_score,
+        }
+    }
+
+    pub fn white_best_score(&self) -> f64 {
+        match self.color {
+            PieceColor::White => self.best_score,
+            PieceColor::Black => -self.best_score,
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct GameSummary {
+    pub moves: usize,
+    pub accuracy: f64,
+    pub average_loss: f64,
+    pub inaccuracies: usize,
+    pub mistakes: usize,
+    pub blunders: usize,
+}
+
+pub fn win_percent(score: f64) -> f64 {
+    let centipawns = score.clamp(-SCORE_CAP, SCORE_CAP) * 100.0;
+    50.0 + 50.0 * (2.0 / (1.0 + (-0.00368208 * centipawns).exp()) - 1.0)
+}
+
+fn move_accuracy(analysis: &MoveAnalysis) -> f64 {
+    let lost = win_percent(analysis.best_score) - win_percent(analysis.played_score);
+    (103.1668 * (-0.04354 * lost.max(0.0)).exp() - 3.1669).clamp(0.0, 100.0)
+}
+
+pub fn summarize(analyses: &[MoveAnalysis], color: PieceColor) -> GameSummary {
+    let mut summary = GameSummary::default();
+    for analysis in analyses.iter().filter(|analysis| analysis.color == color) {
+        summary.moves += 1;
+        summary.accuracy += move_accuracy(analysis);
+        summary.average_loss += analysis.loss().clamp(0.0, SCORE_CAP);
+        match analysis.classification {
+            MoveClassification::Inaccuracy => summary.inaccuracies += 1,
+            MoveClassification::Mistake => summary.mistakes += 1,
+            MoveClassification::Blunder => summary.blunders += 1,
+            _ => {}
+        }
+    }
+    if summary.moves > 0 {
+        summary.accuracy /= summary.moves as f64;
+        summary.average_loss /= summary.moves as f64;
+    }
+    summary
+}
+
+pub fn format_score(score: f64) -> String {
+    match mate_in(score) {
+        Some(moves) if moves > 0 => format!("+M{}", moves),
+        Some(moves) => format!("-M{}", -moves),
+        None => format!("{:+.2}", score),
+    }
+}
+
+pub fn analyze_move(ai: &mut AI, board: &ChessBoard, played: Move, depth: usize) -> MoveAnalysis {
+    let scores = ai.analyze(board, depth);
+    let (best, best_score) = scores[0];
+    let played_score = scores
+        .iter()
+        .find(|(m, _)| *m == played)
+        .map(|(_, score)| *score)
+        .unwrap_or(f64::NEG_INFINITY);
+    let mut analysis = MoveAnalysis {
+        color: board.turn,
+        played,
+        played_score,
+        best,
+        best_score,
+        classification: MoveClassification::Best,
+    };
+    analysis.classification = MoveClassification::from_loss(analysis.loss());
+    analysis
+}
+
+pub fn analyze_game(
+    record: &GameRecord,
+    depth: usize,
+    mut on_progress: impl FnMut(usize),
+) -> Vec<MoveAnalysis> {
+    let mut ai = AI::with_depth(depth);
+    let mut board = record.start_board.clone();
+    let moves = record.move_list();
+    let mut analyses = Vec::with_capacity(moves.len());
+    for (i, &mv) in moves.iter().enumerate() {
+        on_progress(i);
+        analyses.push(analyze_move(&mut ai, &board, mv, depth));
+        mv.perform(&mut board);
+    }
+    analyses
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct AnalysisLine {
+    pub score: f64,
+    pub pv: Vec<Move>,
+}
+
+impl AnalysisLine {
+    pub fn white_score(&self, board: &ChessBoard) -> f64 {
+        match board.turn {
+            PieceColor::White => self.score,
+            PieceColor::Black => -self.score,
+        }
+    }
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct LiveEval {
+    pub depth: usize,
+    pub lines: Vec<AnalysisLine>,
+}
+
+impl LiveEval {
+    pub fn best(&self) -> &AnalysisLine {
+        &self.lines[0]
+    }
+
+    pub fn white_score(&self, board: &ChessBoard) -> f64 {
+        self.best().white_score(board)
+    }
+}
+
+pub type SharedEngine = Arc<Mutex<Box<dyn Engine>>>;
+
+pub struct AnalysisSession {
+    pub board: ChessBoard,
+    pub limits: SearchLimits,
+    latest: Arc<RwLock<Option<LiveEval>>>,
+    error: Arc<RwLock<Option<String>>>,
+    stop: Arc<AtomicBool>,
+}
+
+impl AnalysisSession {
+    pub fn start(
+        board: ChessBoard,
+        engine: SharedEngine,
+        limits: SearchLimits,
+        on_update: impl Fn() + Send + 'static,
+    ) -> Self {
+        let latest = Arc::new(RwLock::new(None));
+        let error = Arc::new(RwLock::new(None));
+        let stop = Arc::new(AtomicBool::new(false));
+        let session = Self {
+            board: board.clone(),
+            limits: limits.clone(),
+            latest: latest.clone(),
+            error: error.clone(),
+            stop: stop.clone(),
+        };
+        thread::spawn(move || {
+            if board.win_state().is_some() {
+                return;
+            }
+            let mut engine = engine.lock().unwrap();
+            if stop.load(Ordering::Relaxed) {
+                return;
+            }
+            engine.set_stop(stop.clone());
+            let result = engine.set_position(&board).and_then(|_| {
+                engine.search(&limits, &mut |eval| {
+                    if !stop.load(Ordering::Relaxed) && !eval.lines.is_empty() {
+                        *latest.write().unwrap() = Some(eval.clone());
+                        on_update();
+                    }
+                })
+            });
+            if let Err(err) = result {
+                *error.write().unwrap() =
+                    Some(format!("Analysis with {} failed: {}", engine.name(), err));
+                on_update();
+            }
+        });
+        session
+    }
+
+    pub fn latest(&self) -> Option<LiveEval> {
+        self.latest.read().unwrap().clone()
+    }
+
+    pub fn error(&self) -> Option<String> {
+        self.error.read().unwrap().clone()
+    }
+
+    pub fn stop(&self) {
+        self.stop.store(true, Ordering::Relaxed);
+    }
+}
+
+impl Drop for AnalysisSession {
+    fn drop(&mut self) {
+        self.stop();
+    }
+}
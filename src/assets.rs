@@ -0,0 +1,107 @@
+use std::{fs, path::PathBuf};
+
+use image::RgbaImage;
+use include_dir::{include_dir, Dir};
+
+use crate::{
+    config,
+    logic::{PieceColor, PieceType},
+    profile::DEFAULT_PIECE_SET,
+    svg::{Svg, SvgError},
+};
+
+const USER_ASSETS_DIR: &str = "assets";
+const PIECES_DIR: &str = "pieces";
+static ASSETS: Dir = include_dir!("$CARGO_MANIFEST_DIR/src/assets");
+
+pub fn user_assets_dir() -> Option<PathBuf> {
+    config::config_file(USER_ASSETS_DIR)
+}
+
+pub fn user_piece_sets_dir() -> Option<PathBuf> {
+    user_assets_dir().map(|dir| dir.join(PIECES_DIR))
+}
+
+fn is_valid_set_name(set: &str) -> bool {
+    !set.is_empty() && !set.starts_with('.') && !set.contains(['/', '\\'])
+}
+
+pub fn piece_file_name(piece: PieceType, color: PieceColor, extension: &str) -> String {
+    format!(
+        "{}{}.{}",
+        color,
+        piece.to_string().to_uppercase(),
+        extension
+    )
+}
+
+pub fn piece_sets() -> Vec<String> {
+    let mut sets = ASSETS
+        .dirs()
+        .filter_map(|dir| dir.path().file_name()?.to_str().map(str::to_string))
+        .collect::<Vec<_>>();
+    let user_sets = user_piece_sets_dir()
+        .and_then(|dir| fs::read_dir(dir).ok())
+        .into_iter()
+        .flatten()
+        .flatten()
+        .filter(|entry| entry.path().is_dir())
+        .filter_map(|entry| entry.file_name().into_string().ok())
+        .filter(|set| is_valid_set_name(set));
+    for set in user_sets {
+        if !sets.contains(&set) {
+            sets.push(set);
+        }
+    }
+    sets.sort_by_key(|set| (set != DEFAULT_PIECE_SET, set.clone()));
+    sets
+}
+
+fn embedded_piece_image(set: &str, file: &str) -> Option<&'static [u8]> {
+    ASSETS
+        .get_file(format!("{}/{}", set, file))
+        .map(|file| file.contents())
+}
+
+fn decode(data: &[u8]) -> Option<RgbaImage> {
+    image::load_from_memory(data)
+        .ok()
+        .map(|image| image.to_rgba8())
+}
+
+pub fn piece_image(set: &str, piece: PieceType, color: PieceColor) -> Option<RgbaImage> {
+    let file = piece_file_name(piece, color, "png");
+    user_piece_sets_dir()
+        .filter(|_| is_valid_set_name(set))
+        .and_then(|dir| fs::read(dir.join(set).join(&file)).ok())
+        .and_then(|data| decode(&data))
+        .or_else(|| embedded_piece_image(set, &file).and_then(decode))
+        .or_else(|| embedded_piece_image(DEFAULT_PIECE_SET, &file).and_then(decode))
+}
+
+fn parse_svg(file: &str, text: &str, errors: &mut Vec<(String, SvgError)>) -> Option<Svg> {
+    text.parse()
+        .map_err(|err| errors.push((file.to_string(), err)))
+        .ok()
+}
+
+// Files that fail to parse are skipped and added to `errors`.
+pub fn piece_svg(
+    set: &str,
+    piece: PieceType,
+    color: PieceColor,
+    errors: &mut Vec<(String, SvgError)>,
+) -> Option<Svg> {
+    let file = piece_file_name(piece, color, "svg");
+    let user_file = user_piece_sets_dir()
+        .filter(|_| is_valid_set_name(set))
+        .map(|dir| dir.join(set).join(&file));
+    user_file
+        .and_then(|path| Some((fs::read_to_string(&path).ok()?, path)))
+        .and_then(|(text, path)| parse_svg(&path.display().to_string(), &text, errors))
+        .or_else(|| {
+            let path = format!("{}/{}", set, file);
+            let text = ASSETS.get_file(&path)?.contents_utf8()?;
+            parse_svg(&path, text, errors)
+        })
+}
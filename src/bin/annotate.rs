@@ -0,0 +1,74 @@
+use std::{
+    fs,
+    io::{self, Write},
+};
+
+use chess::{
+    analysis::{analyze_game, format_score},
+    pgn::PgnGame,
+    record::GameRecord,
+};
+
+fn usage() -> ! {
+    eprintln!("Usage: annotate INPUT.pgn [-o OUTPUT.pgn] [--depth N]");
+    std::process::exit(2);
+}
+
+fn main() -> Result<(), Box<dyn std::error::Error>> {
+    let mut input = None;
+    let mut output = None;
+    let mut depth = 3;
+
+    let mut args = std::env::args().skip(1);
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "-o" | "--output" => output = Some(args.next().unwrap_or_else(|| usage())),
+            "--depth" => depth = args.next().unwrap_or_else(|| usage()).parse()?,
+            _ if input.is_none() && !arg.starts_with('-') => input = Some(arg),
+            _ => usage(),
+        }
+    }
+    let input = input.unwrap_or_else(|| usage());
+
+    let mut games = PgnGame::parse_all(&fs::read_to_string(&input)?)?;
+    let total = games.len();
+    for (game_idx, game) in games.iter_mut().enumerate() {
+        let record = GameRecord::from_pgn(game);
+        let analyses = analyze_game(&record, depth, |ply| {
+            eprint!(
+                "\rGame {}/{}: analyzing ply {}/{}",
+                game_idx + 1,
+                total,
+                ply + 1,
+                record.moves.len()
+            );
+        });
+        eprintln!();
+
+        let mut board = game.starting_board();
+        for (pgn_move, analysis) in game.moves.iter_mut().zip(&analyses) {
+            let mut comment = format_score(analysis.white_score());
+            pgn_move.glyph = analysis.classification.glyph().map(str::to_string);
+            if pgn_move.glyph.is_some() {
+                comment.push_str(&format!(
+                    " {}. {} was best ({})",
+                    analysis.classification.readable(),
+                    analysis.best.to_san(&board),
+                    format_score(analysis.white_best_score())
+                ));
+            }
+            pgn_move.add_comment(&comment);
+            pgn_move.mv.perform(&mut board);
+        }
+        game.set_tag("Annotator", format!("chess-rs depth {}", depth));
+    }
+
+    let mut out: Box<dyn Write> = match output {
+        Some(path) => Box::new(fs::File::create(path)?),
+        None => Box::new(io::stdout()),
+    };
+    for game in &games {
+        writeln!(out, "{}", game)?;
+    }
+    Ok(())
+}
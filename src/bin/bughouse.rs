@@ -0,0 +1,321 @@
+use std::{
+    collections::HashMap,
+    sync::{mpsc::Sender, Arc, RwLock},
+    thread::JoinHandle,
+};
+
+use chess::{
+    ai::AI,
+    assets,
+    bughouse::{team, BughouseGame, BughouseResult, PocketAI, Pockets},
+    game::{ChannelPlayer, Player, PlayerAction},
+    logic::{ChessBoard, Move, MoveType, PieceColor, PieceType},
+    profile::DEFAULT_PIECE_SET,
+    settings::EngineSettings,
+};
+use eframe::{
+    egui::{
+        self, Color32, ColorImage, Context, Frame, PointerButton, Rect, RichText, Sense,
+        TextureHandle, TextureOptions, Ui, Vec2,
+    },
+    CreationContext,
+};
+use strum::IntoEnumIterator;
+
+const BOARD_SIZE: usize = 8;
+const HUMAN_BOARD: usize = 0;
+const HUMAN_COLOR: PieceColor = PieceColor::White;
+const POCKET_HEIGHT: f32 = 36.0;
+const DARK_SQUARE: Color32 = Color32::from_rgb(181, 136, 99);
+const LIGHT_SQUARE: Color32 = Color32::from_rgb(240, 217, 181);
+const SELECTED_SQUARE: Color32 = Color32::from_rgba_premultiplied(115, 154, 222, 128);
+const VALID_MOVE: Color32 = Color32::from_rgba_premultiplied(81, 173, 94, 128);
+
+fn to_color_image(image: &image::RgbaImage) -> ColorImage {
+    let size = [image.width() as _, image.height() as _];
+    ColorImage::from_rgba_unmultiplied(size, image.as_flat_samples().as_slice())
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+enum Selection {
+    Square((usize, usize)),
+    Pocket(PieceType),
+}
+
+struct BughouseApp {
+    images: HashMap<(PieceType, PieceColor), TextureHandle>,
+    boards: [Arc<RwLock<ChessBoard>>; 2],
+    pockets: Arc<RwLock<Pockets>>,
+    channel: Option<Sender<PlayerAction>>,
+    game_thread: Option<JoinHandle<Option<BughouseResult>>>,
+    result: Option<BughouseResult>,
+    selection: Option<Selection>,
+    valid_moves: Vec<Move>,
+    engine_settings: EngineSettings,
+}
+
+impl BughouseApp {
+    fn new(cc: &CreationContext) -> Self {
+        let mut app = Self {
+            images: HashMap::new(),
+            boards: [
+                Arc::new(RwLock::new(ChessBoard::new())),
+                Arc::new(RwLock::new(ChessBoard::new())),
+            ],
+            pockets: Arc::new(RwLock::new(Pockets::default())),
+            channel: None,
+            game_thread: None,
+            result: None,
+            selection: None,
+            valid_moves: Vec::new(),
+            engine_settings: EngineSettings::load_saved().unwrap_or_else(|err| {
+                eprintln!("Ignoring the saved settings: {}", err);
+                EngineSettings::default()
+            }),
+        };
+        app.load_assets(cc);
+        app.start_game(&cc.egui_ctx);
+        app
+    }
+
+    fn load_assets(&mut self, cc: &CreationContext) {
+        for piece in PieceType::iter() {
+            for color in PieceColor::iter() {
+                let Some(image) = assets::piece_image(DEFAULT_PIECE_SET, piece, color) else {
+                    panic!(
+                        "Could not find asset file: {}",
+                        assets::piece_file_name(piece, color, "png")
+                    );
+                };
+                self.images.insert(
+                    (piece, color),
+                    cc.egui_ctx.load_texture(
+                        "image",
+                        to_color_image(&image),
+                        TextureOptions::default(),
+                    ),
+                );
+            }
+        }
+    }
+
+    fn start_game(&mut self, context: &Context) {
+        let context = context.clone();
+        self.result = None;
+        self.selection = None;
+        self.valid_moves.clear();
+        self.pockets = Arc::new(RwLock::new(Pockets::default()));
+
+        let (channel, human) = ChannelPlayer::new();
+        self.channel = Some(channel);
+        let engine = |board: usize| -> Box<dyn Player> {
+            let mut ai = AI::new();
+            if let Err(err) = self.engine_settings.apply(&mut ai) {
+                eprintln!("Could not build search thread pool: {}", err);
+            }
+            Box::new(PocketAI::new(ai, board, self.pockets.clone()))
+        };
+        let players = [
+            [Box::new(human) as Box<dyn Player>, engine(0)],
+            [engine(1), engine(1)],
+        ];
+        let game = BughouseGame::new(players, self.pockets.clone(), move || {
+            context.request_repaint();
+        });
+        self.boards = game.boards.clone();
+        self.game_thread = Some(game.create_game_thread());
+    }
+
+    fn status(&self) -> String {
+        let Some(result) = self.result else {
+            let turn = self.boards[HUMAN_BOARD].read().unwrap().turn;
+            return if turn == HUMAN_COLOR {
+                "Your move".to_string()
+            } else {
+                "Waiting for your opponent".to_string()
+            };
+        };
+        let outcome = match result.winning_team() {
+            Some(winner) if winner == team(HUMAN_BOARD, HUMAN_COLOR) => "Your team wins",
+            Some(_) => "Your team loses",
+            None => "Draw",
+        };
+        format!(
+            "{} by {} on board {}",
+            outcome,
+            result.win_state.reason(),
+            result.board + 1
+        )
+    }
+
+    fn pocket(&mut self, ui: &mut Ui, board: usize, color: PieceColor) {
+        let pocket = self.pockets.read().unwrap()[board][color as usize];
+        let interactive = self.result.is_none() && board == HUMAN_BOARD && color == HUMAN_COLOR;
+        ui.horizontal(|ui| {
+            ui.set_min_height(POCKET_HEIGHT);
+            for piece_type in PieceType::iter().rev() {
+                let count = pocket.count(piece_type);
+                if count == 0 {
+                    continue;
+                }
+                let selected = self.selection == Some(Selection::Pocket(piece_type));
+                let image = egui::Image::new(&self.images[&(piece_type, color)])
+                    .fit_to_exact_size(Vec2::splat(POCKET_HEIGHT));
+                let response = ui.add(egui::ImageButton::new(image).selected(selected));
+                ui.label(RichText::new(format!("x{}", count)).strong());
+                if interactive && response.clicked() {
+                    self.select_drop(piece_type);
+                }
+            }
+        });
+    }
+
+    fn select_drop(&mut self, piece_type: PieceType) {
+        let board = self.boards[HUMAN_BOARD].read().unwrap();
+        if board.turn != HUMAN_COLOR {
+            return;
+        }
+        self.selection = Some(Selection::Pocket(piece_type));
+        self.valid_moves = board.legal_drops(&[piece_type]);
+    }
+
+    fn board(&mut self, ui: &mut Ui, index: usize, size: f32) {
+        let flipped = index != HUMAN_BOARD;
+        let to_screen = |pos: (usize, usize)| {
+            if flipped {
+                (BOARD_SIZE - 1 - pos.0, BOARD_SIZE - 1 - pos.1)
+            } else {
+                pos
+            }
+        };
+        let (response, painter) = ui.allocate_painter(Vec2::splat(size), Sense::click());
+        let square_size = size / BOARD_SIZE as f32;
+        let square = |pos: (usize, usize)| {
+            let (col, row) = to_screen(pos);
+            Rect::from_min_size(
+                response.rect.min + Vec2::new(col as f32, row as f32) * square_size,
+                Vec2::splat(square_size),
+            )
+        };
+
+        let board = self.boards[index].read().unwrap().clone();
+        for row in 0..BOARD_SIZE {
+            for col in 0..BOARD_SIZE {
+                let color = if (row + col) % 2 == 0 {
+                    DARK_SQUARE
+                } else {
+                    LIGHT_SQUARE
+                };
+                painter.rect_filled(square((col, row)), 0.0, color);
+            }
+        }
+        if index == HUMAN_BOARD {
+            if let Some(Selection::Square(pos)) = self.selection {
+                painter.rect_filled(square(pos), 0.0, SELECTED_SQUARE);
+            }
+            for valid_move in &self.valid_moves {
+                painter.rect_filled(square(valid_move.target), 0.0, VALID_MOVE);
+            }
+        }
+        for piece in board.pieces.iter().flatten() {
+            egui::Image::new(&self.images[&(piece.piece_type, piece.color)])
+                .paint_at(ui, square(piece.pos));
+        }
+
+        if index != HUMAN_BOARD
+            || self.result.is_some()
+            || board.turn != HUMAN_COLOR
+            || !response.clicked_by(PointerButton::Primary)
+        {
+            return;
+        }
+        let pos = response.interact_pointer_pos().unwrap() - response.rect.min;
+        let col = (pos.x / square_size).floor() as usize;
+        let row = (pos.y / square_size).floor() as usize;
+        if col >= BOARD_SIZE || row >= BOARD_SIZE {
+            return;
+        }
+        let target = to_screen((col, row));
+        let chosen = self
+            .valid_moves
+            .iter()
+            .filter(|m| m.target == target)
+            .find(|m| matches!(m.move_type, MoveType::Promotion(PieceType::Queen)))
+            .or_else(|| self.valid_moves.iter().find(|m| m.target == target))
+            .copied();
+        match chosen {
+            Some(mv) => {
+                if let Some(channel) = &self.channel {
+                    let _ = channel.send(PlayerAction::Move(mv));
+                }
+                self.selection = None;
+                self.valid_moves.clear();
+            }
+            None => match board.piece_at(target) {
+                Some(piece) if piece.color == HUMAN_COLOR => {
+                    self.selection = Some(Selection::Square(target));
+                    self.valid_moves = piece.legal_moves(&board).collect();
+                }
+                _ => {
+                    self.selection = None;
+                    self.valid_moves.clear();
+                }
+            },
+        }
+    }
+
+    fn linked_board(&mut self, ui: &mut Ui, index: usize, size: f32) {
+        let bottom = if index == HUMAN_BOARD {
+            PieceColor::White
+        } else {
+            PieceColor::Black
+        };
+        ui.vertical(|ui| {
+            ui.label(RichText::new(format!("Board {}", index + 1)).strong());
+            self.pocket(ui, index, bottom.opposite());
+            Frame::canvas(ui.style()).show(ui, |ui| self.board(ui, index, size));
+            self.pocket(ui, index, bottom);
+        });
+    }
+}
+
+impl eframe::App for BughouseApp {
+    fn update(&mut self, ctx: &egui::Context, _frame: &mut eframe::Frame) {
+        if self.game_thread.as_ref().is_some_and(|x| x.is_finished()) {
+            self.result = self.game_thread.take().unwrap().join().ok().flatten();
+            self.selection = None;
+            self.valid_moves.clear();
+        }
+        egui::TopBottomPanel::top("Status").show(ctx, |ui| {
+            ui.horizontal(|ui| {
+                ui.heading(self.status());
+                if self.result.is_some() && ui.button("New game").clicked() {
+                    self.start_game(ui.ctx());
+                }
+            });
+        });
+        egui::CentralPanel::default().show(ctx, |ui| {
+            let available = ui.available_size();
+            let size = (available.x / 2.0 - 16.0)
+                .min(available.y - 2.0 * POCKET_HEIGHT - 48.0)
+                .max(BOARD_SIZE as f32 * 16.0);
+            ui.horizontal_top(|ui| {
+                self.linked_board(ui, 0, size);
+                ui.add_space(8.0);
+                self.linked_board(ui, 1, size);
+            });
+        });
+    }
+}
+
+fn main() -> Result<(), eframe::Error> {
+    let options = eframe::NativeOptions {
+        viewport: egui::ViewportBuilder::default().with_inner_size([1100.0, 640.0]),
+        ..Default::default()
+    };
+    eframe::run_native(
+        "Bughouse",
+        options,
+        Box::new(|cc| Ok(Box::new(BughouseApp::new(cc)))),
+    )
+}
@@ -0,0 +1,202 @@
+use std::{
+    io::{self, BufRead, Write},
+    sync::{atomic::AtomicBool, Arc, RwLock},
+};
+
+use chess::{
+    ai::AI,
+    game::{ChessGame, Player, PlayerAction},
+    logic::{ChessBoard, Move},
+    notation::SanLanguage,
+    script::ScriptPlayer,
+};
+
+struct TerminalPlayer {
+    language: SanLanguage,
+    offered_draw: bool,
+}
+
+impl Player for TerminalPlayer {
+    fn get_move(&mut self, board: Arc<RwLock<ChessBoard>>) -> Move {
+        loop {
+            if let PlayerAction::Move(mv) = self.get_action(board.clone(), false) {
+                return mv;
+            }
+        }
+    }
+
+    fn get_action(&mut self, board: Arc<RwLock<ChessBoard>>, draw_offered: bool) -> PlayerAction {
+        let board = board.read().unwrap().clone();
+        let stdin = io::stdin();
+        let mut input = String::new();
+        if draw_offered {
+            println!(
+                "Your opponent offers a draw. Type 'accept' to accept or make a move to decline."
+            );
+        }
+        loop {
+            print!("{} to move: ", board.turn.readable());
+            io::stdout().flush().unwrap();
+            input.clear();
+            if stdin.lock().read_line(&mut input).unwrap_or(0) == 0 {
+                std::process::exit(0);
+            }
+            match input.trim() {
+                "" => continue,
+                "quit" | "exit" => std::process::exit(0),
+                "resign" => return PlayerAction::Resign,
+                "draw" if self.offered_draw => println!("You have already offered a draw"),
+                "draw" => {
+                    self.offered_draw = true;
+                    return PlayerAction::OfferDraw;
+                }
+                "accept" if draw_offered => return PlayerAction::AcceptDraw,
+                text => match Move::parse_in(text, &board, self.language) {
+                    Ok(mv) => {
+                        self.offered_draw = false;
+                        return PlayerAction::Move(mv);
+                    }
+                    Err(_) => println!("Illegal or unrecognized move: {}", text),
+                },
+            }
+        }
+    }
+}
+
+struct PrintingPlayer {
+    player: Box<dyn Player>,
+    unicode: bool,
+    language: SanLanguage,
+    error: Arc<RwLock<Option<String>>>,
+}
+
+impl Player for PrintingPlayer {
+    fn get_move(&mut self, board: Arc<RwLock<ChessBoard>>) -> Move {
+        let snapshot = board.read().unwrap().clone();
+        println!("\n{}", snapshot.render(self.unicode));
+        let mv = self.player.get_move(board);
+        println!(
+            "{} plays {}",
+            snapshot.turn.readable(),
+            mv.to_san_in(&snapshot, self.language)
+        );
+        mv
+    }
+
+    fn get_action(&mut self, board: Arc<RwLock<ChessBoard>>, draw_offered: bool) -> PlayerAction {
+        let snapshot = board.read().unwrap().clone();
+        println!("\n{}", snapshot.render(self.unicode));
+        let action = self.player.get_action(board, draw_offered);
+        if let Some(err) = self.error.write().unwrap().take() {
+            eprintln!("{}", err);
+        }
+        let color = snapshot.turn.readable();
+        match action {
+            PlayerAction::Move(mv) => {
+                println!("{} plays {}", color, mv.to_san_in(&snapshot, self.language))
+            }
+            PlayerAction::OfferDraw => println!("{} offers a draw", color),
+            PlayerAction::AcceptDraw => println!("{} accepts the draw", color),
+            PlayerAction::Resign => println!("{} resigns", color),
+            PlayerAction::Abort => {}
+        }
+        action
+    }
+
+    fn set_cancel(&mut self, cancel: Arc<AtomicBool>) {
+        self.player.set_cancel(cancel);
+    }
+}
+
+fn parse_player(
+    spec: &str,
+    language: SanLanguage,
+    error: &Arc<RwLock<Option<String>>>,
+) -> Result<Box<dyn Player>, String> {
+    match spec.split_once(':') {
+        None if spec == "human" => Ok(Box::new(TerminalPlayer {
+            language,
+            offered_draw: false,
+        })),
+        None if spec == "ai" => Ok(Box::new(AI::new())),
+        Some(("ai", depth)) => depth
+            .parse()
+            .map(|depth| Box::new(AI::with_depth(depth)) as Box<dyn Player>)
+            .map_err(|_| format!("Invalid AI depth: {}", depth)),
+        Some(("script", bot)) => ScriptPlayer::find(bot)
+            .map(|mut player| {
+                player.error = error.clone();
+                Box::new(player) as Box<dyn Player>
+            })
+            .map_err(|err| format!("Could not load bot {}: {}", bot, err)),
+        _ => Err(format!("Unknown player type: {}", spec)),
+    }
+}
+
+fn usage() -> ! {
+    eprintln!(
+        "Usage: cli [--white PLAYER] [--black PLAYER] [--fen FEN] [--unicode] [--letters LANG]\n\nPLAYER is human, ai[:depth] or script:BOT, where BOT is a .rhai file, a bot in the bots config directory or one of random, greedy, opening"
+    );
+    std::process::exit(2);
+}
+
+fn main() -> Result<(), Box<dyn std::error::Error>> {
+    let mut white = "human".to_string();
+    let mut black = "ai".to_string();
+    let mut fen = None;
+    let mut unicode = false;
+    let mut language = SanLanguage::English;
+
+    let mut args = std::env::args().skip(1);
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "--white" => white = args.next().unwrap_or_else(|| usage()),
+            "--black" => black = args.next().unwrap_or_else(|| usage()),
+            "--fen" => fen = Some(args.next().unwrap_or_else(|| usage())),
+            "--unicode" => unicode = true,
+            "--letters" => {
+                language = args
+                    .next()
+                    .and_then(|lang| lang.parse().ok())
+                    .unwrap_or_else(|| usage())
+            }
+            _ => usage(),
+        }
+    }
+
+    let error = Arc::new(RwLock::new(None));
+    let white_player = PrintingPlayer {
+        player: parse_player(&white, language, &error)?,
+        unicode,
+        language,
+        error,
+    };
+    let error = Arc::new(RwLock::new(None));
+    let black_player = PrintingPlayer {
+        player: parse_player(&black, language, &error)?,
+        unicode,
+        language,
+        error,
+    };
+    let mut game = ChessGame::new(Box::new(white_player), Box::new(black_player), || {});
+    if let Some(fen) = fen {
+        let mut board = game.board.write().unwrap();
+        board.set_from_fen(&fen)?;
+        board.validate()?;
+    }
+
+    let Some(win_state) = game.play() else {
+        return Ok(());
+    };
+    println!("\n{}", game.board.read().unwrap().render(unicode));
+    match win_state.winner() {
+        Some(color) => println!(
+            "{} {} wins by {}",
+            win_state.result(),
+            color.readable(),
+            win_state.reason()
+        ),
+        None => println!("{} Draw by {}", win_state.result(), win_state.reason()),
+    }
+    Ok(())
+}
@@ -0,0 +1,251 @@
+use std::collections::HashMap;
+
+use chess::{
+    ai::AI,
+    logic::{ChessBoard, Move},
+};
+use rayon::iter::ParallelIterator;
+use serde_json::Value;
+use tiny_http::{Header, Method, Request, Response, Server};
+
+const DEFAULT_PORT: u16 = 8080;
+const MAX_DEPTH: usize = 8;
+
+struct ServerGame {
+    start: ChessBoard,
+    board: ChessBoard,
+    moves: Vec<String>,
+    ai: AI,
+}
+
+impl ServerGame {
+    fn new(start: ChessBoard) -> Self {
+        Self {
+            board: start.clone(),
+            start,
+            moves: Vec::new(),
+            ai: AI::new(),
+        }
+    }
+
+    fn play(&mut self, mv: Move) {
+        self.moves.push(mv.to_uci(&self.board));
+        mv.perform(&mut self.board);
+    }
+
+    fn to_json(&self, id: usize) -> String {
+        let moves = self
+            .moves
+            .iter()
+            .map(|m| json_string(m))
+            .collect::<Vec<_>>();
+        let (result, termination) = match self.board.win_state() {
+            Some(win_state) => (
+                json_string(win_state.result()),
+                json_string(win_state.reason()),
+            ),
+            None => ("null".to_string(), "null".to_string()),
+        };
+        format!(
+            "{{\"id\":{},\"fen\":{},\"start_fen\":{},\"turn\":{},\"moves\":[{}],\"check\":{},\"result\":{},\"termination\":{}}}",
+            id,
+            json_string(&self.board.to_fen()),
+            json_string(&self.start.to_fen()),
+            json_string(self.board.turn.readable()),
+            moves.join(","),
+            self.board.is_in_check(self.board.turn),
+            result,
+            termination
+        )
+    }
+}
+
+fn json_string(s: &str) -> String {
+    let mut out = String::with_capacity(s.len() + 2);
+    out.push('"');
+    for c in s.chars() {
+        match c {
+            '"' => out.push_str("\\\""),
+            '\\' => out.push_str("\\\\"),
+            '\n' => out.push_str("\\n"),
+            c if c.is_control() => out.push_str(&format!("\\u{:04x}", c as u32)),
+            c => out.push(c),
+        }
+    }
+    out.push('"');
+    out
+}
+
+fn json_field(body: &str, key: &str) -> Option<String> {
+    let body: Value = serde_json::from_str(body).ok()?;
+    body.get(key)?.as_str().map(str::to_string)
+}
+
+fn error_json(message: &str) -> String {
+    format!("{{\"error\":{}}}", json_string(message))
+}
+
+struct Api {
+    games: HashMap<usize, ServerGame>,
+    next_id: usize,
+}
+
+impl Api {
+    fn handle(&mut self, method: &Method, path: &str, query: &str, body: &str) -> (u16, String) {
+        let segments = path
+            .split('/')
+            .filter(|s| !s.is_empty())
+            .collect::<Vec<_>>();
+        match (method, segments.as_slice()) {
+            (Method::Get, ["games"]) => {
+                let mut ids = self.games.keys().collect::<Vec<_>>();
+                ids.sort();
+                let ids = ids.iter().map(|id| id.to_string()).collect::<Vec<_>>();
+                (200, format!("{{\"games\":[{}]}}", ids.join(",")))
+            }
+            (Method::Post, ["games"]) => {
+                let mut start = ChessBoard::new();
+                if let Some(fen) = json_field(body, "fen") {
+                    if let Err(err) = start.set_from_fen(&fen) {
+                        return (400, error_json(&err.to_string()));
+                    }
+                    if let Err(err) = start.validate() {
+                        return (400, error_json(&err.to_string()));
+                    }
+                }
+                let id = self.next_id;
+                self.next_id += 1;
+                let game = ServerGame::new(start);
+                let json = game.to_json(id);
+                self.games.insert(id, game);
+                (201, json)
+            }
+            (method, ["games", id, rest @ ..]) => {
+                let Some(id) = id
+                    .parse::<usize>()
+                    .ok()
+                    .filter(|id| self.games.contains_key(id))
+                else {
+                    return (404, error_json("no such game"));
+                };
+                match (method, rest) {
+                    (Method::Get, []) => (200, self.games[&id].to_json(id)),
+                    (Method::Delete, []) => {
+                        self.games.remove(&id);
+                        (204, String::new())
+                    }
+                    (Method::Get, ["legal"]) => {
+                        let board = &self.games[&id].board;
+                        let mut moves = board
+                            .legal_moves(board.turn)
+                            .map(|m| m.to_uci(board))
+                            .collect::<Vec<_>>();
+                        moves.sort();
+                        let moves = moves.iter().map(|m| json_string(m)).collect::<Vec<_>>();
+                        (200, format!("{{\"moves\":[{}]}}", moves.join(",")))
+                    }
+                    (Method::Post, ["moves"]) => self.post_move(id, body),
+                    (Method::Post, ["engine-move"]) => self.engine_move(id, query),
+                    _ => (404, error_json("not found")),
+                }
+            }
+            _ => (404, error_json("not found")),
+        }
+    }
+
+    fn post_move(&mut self, id: usize, body: &str) -> (u16, String) {
+        let game = self.games.get_mut(&id).unwrap();
+        if game.board.win_state().is_some() {
+            return (409, error_json("game is over"));
+        }
+        let text = json_field(body, "move").unwrap_or_else(|| body.trim().to_string());
+        match Move::parse(&text, &game.board) {
+            Ok(mv) => {
+                game.play(mv);
+                (200, game.to_json(id))
+            }
+            Err(_) => (400, error_json(&format!("illegal move: {}", text))),
+        }
+    }
+
+    fn engine_move(&mut self, id: usize, query: &str) -> (u16, String) {
+        let game = self.games.get_mut(&id).unwrap();
+        if game.board.win_state().is_some() {
+            return (409, error_json("game is over"));
+        }
+        let depth = query
+            .split('&')
+            .find_map(|pair| pair.strip_prefix("depth="))
+            .and_then(|depth| depth.parse::<usize>().ok())
+            .unwrap_or(game.ai.depth)
+            .clamp(1, MAX_DEPTH);
+        let board = game.board.clone();
+        let mv = game.ai.best_move(&board, depth);
+        let uci = mv.to_uci(&board);
+        game.play(mv);
+        let json = game.to_json(id);
+        (
+            200,
+            format!("{{\"move\":{},\"game\":{}}}", json_string(&uci), json),
+        )
+    }
+}
+
+fn respond(request: Request, status: u16, body: String) {
+    let mut response = Response::from_string(body)
+        .with_status_code(status)
+        .with_header(Header::from_bytes("Access-Control-Allow-Origin", "*").unwrap())
+        .with_header(
+            Header::from_bytes("Access-Control-Allow-Methods", "GET, POST, DELETE, OPTIONS")
+                .unwrap(),
+        )
+        .with_header(Header::from_bytes("Access-Control-Allow-Headers", "Content-Type").unwrap());
+    if status != 204 {
+        response =
+            response.with_header(Header::from_bytes("Content-Type", "application/json").unwrap());
+    }
+    if let Err(err) = request.respond(response) {
+        eprintln!("Could not send response: {}", err);
+    }
+}
+
+fn usage() -> ! {
+    eprintln!("Usage: server [--host HOST] [--port PORT]");
+    std::process::exit(2);
+}
+
+fn main() -> Result<(), Box<dyn std::error::Error>> {
+    let mut host = "127.0.0.1".to_string();
+    let mut port = DEFAULT_PORT;
+    let mut args = std::env::args().skip(1);
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "--host" => host = args.next().unwrap_or_else(|| usage()),
+            "--port" => port = args.next().unwrap_or_else(|| usage()).parse()?,
+            _ => usage(),
+        }
+    }
+
+    let server = Server::http((host.as_str(), port)).map_err(|err| err.to_string())?;
+    println!("Listening on http://{}:{}", host, port);
+    let mut api = Api {
+        games: HashMap::new(),
+        next_id: 1,
+    };
+    for mut request in server.incoming_requests() {
+        if *request.method() == Method::Options {
+            respond(request, 204, String::new());
+            continue;
+        }
+        let mut body = String::new();
+        if request.as_reader().read_to_string(&mut body).is_err() {
+            respond(request, 400, error_json("body is not valid UTF-8"));
+            continue;
+        }
+        let url = request.url().to_string();
+        let (path, query) = url.split_once('?').unwrap_or((&url, ""));
+        let (status, body) = api.handle(request.method(), path, query, &body);
+        respond(request, status, body);
+    }
+    Ok(())
+}
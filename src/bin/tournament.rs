@@ -0,0 +1,576 @@
+use std::{error::Error, fs::File, io::Write, path::PathBuf, str::FromStr, time::Duration};
+
+use chess::{
+    ai::{EvalSettings, AI},
+    clock::{Clock, TimeControl},
+    engine::{Engine, SearchLimits},
+    game::timeout_result,
+    logic::{ChessBoard, Move, PieceColor, WinState},
+    pgn::{PgnGame, PgnMove},
+    uci_engine::UciEngine,
+};
+
+#[derive(Clone, Debug)]
+enum EngineKind {
+    Builtin {
+        depth: usize,
+        eval: EvalSettings,
+        variety: f64,
+        temperature: f64,
+        seed: Option<u64>,
+    },
+    Uci {
+        path: PathBuf,
+        options: Vec<(String, String)>,
+        depth: Option<usize>,
+    },
+}
+
+#[derive(Clone, Debug)]
+struct EngineConfig {
+    name: String,
+    kind: EngineKind,
+    time_control: Option<TimeControl>,
+    move_time: Option<Duration>,
+}
+
+fn parse_move_time(value: &str) -> Option<Duration> {
+    value
+        .parse::<f64>()
+        .ok()
+        .filter(|secs| secs.is_finite() && *secs > 0.0)
+        .map(Duration::from_secs_f64)
+}
+
+impl FromStr for EngineConfig {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let mut name = None;
+        let mut depth = 4;
+        let mut eval = EvalSettings::default();
+        let mut variety = 0.0;
+        let mut temperature = 0.0;
+        let mut seed = None;
+        let mut time_control = None;
+        let mut move_time = None;
+        for option in s.split(',') {
+            let (key, value) = option
+                .split_once('=')
+                .ok_or_else(|| format!("Expected key=value, got: {}", option))?;
+            let invalid = || format!("Invalid value for {}: {}", key, value);
+            match key {
+                "name" => name = Some(value.to_string()),
+                "depth" => depth = value.parse().map_err(|_| invalid())?,
+                "center" => eval.center_weight = value.parse().map_err(|_| invalid())?,
+                "king" => eval.unmoved_king_bonus = value.parse().map_err(|_| invalid())?,
+                "contempt" => eval.contempt = value.parse().map_err(|_| invalid())?,
+                "variety" => variety = value.parse().map_err(|_| invalid())?,
+                "temperature" => temperature = value.parse().map_err(|_| invalid())?,
+                "seed" => seed = Some(value.parse().map_err(|_| invalid())?),
+                "tc" => time_control = Some(value.parse().map_err(|_| invalid())?),
+                "st" => move_time = Some(parse_move_time(value).ok_or_else(invalid)?),
+                _ => return Err(format!("Unknown engine option: {}", key)),
+            }
+        }
+        Ok(Self {
+            name: name.unwrap_or_else(|| format!("depth{}", depth)),
+            kind: EngineKind::Builtin {
+                depth,
+                eval,
+                variety,
+                temperature,
+                seed,
+            },
+            time_control,
+            move_time,
+        })
+    }
+}
+
+fn choose_move(
+    engine: &mut dyn Engine,
+    config: &EngineConfig,
+    board: &ChessBoard,
+    clock: &Clock,
+) -> Result<Move, String> {
+    let limits = SearchLimits {
+        depth: match config.kind {
+            EngineKind::Uci { depth, .. } => depth,
+            EngineKind::Builtin { .. } => None,
+        },
+        move_time: config.move_time,
+        ..SearchLimits::clock(clock)
+    };
+    engine
+        .set_position(board)
+        .and_then(|_| engine.search(&limits, &mut |_| {}))
+        .map_err(|err| format!("{}: {}", engine.name(), err))
+}
+
+impl EngineConfig {
+    fn parse_uci(s: &str) -> Result<Self, String> {
+        let mut parts = s.split(',');
+        let path = PathBuf::from(
+            parts
+                .next()
+                .filter(|p| !p.is_empty())
+                .ok_or("Missing engine path")?,
+        );
+        let mut name = None;
+        let mut options = Vec::new();
+        let mut depth = None;
+        let mut time_control = None;
+        let mut move_time = None;
+        for option in parts {
+            let (key, value) = option
+                .split_once('=')
+                .ok_or_else(|| format!("Expected key=value, got: {}", option))?;
+            let invalid = || format!("Invalid value for {}: {}", key, value);
+            match key.strip_prefix("option.") {
+                Some(option) => options.push((option.to_string(), value.to_string())),
+                None if key == "name" => name = Some(value.to_string()),
+                None if key == "depth" => depth = Some(value.parse().map_err(|_| invalid())?),
+                None if key == "tc" => time_control = Some(value.parse().map_err(|_| invalid())?),
+                None if key == "st" => {
+                    move_time = Some(parse_move_time(value).ok_or_else(invalid)?)
+                }
+                None => return Err(format!("Unknown UCI engine option: {}", key)),
+            }
+        }
+        Ok(Self {
+            name: name.unwrap_or_else(|| {
+                path.file_stem().map_or_else(
+                    || "uci".to_string(),
+                    |stem| stem.to_string_lossy().to_string(),
+                )
+            }),
+            kind: EngineKind::Uci {
+                path,
+                options,
+                depth,
+            },
+            time_control,
+            move_time,
+        })
+    }
+
+    fn create_player(&self) -> Result<Box<dyn Engine>, String> {
+        match &self.kind {
+            EngineKind::Builtin {
+                depth,
+                eval,
+                variety,
+                temperature,
+                seed,
+            } => {
+                let mut ai = AI::with_depth(*depth);
+                ai.eval = *eval;
+                ai.variety = *variety;
+                ai.temperature = *temperature;
+                ai.set_seed(*seed);
+                Ok(Box::new(ai))
+            }
+            EngineKind::Uci { path, options, .. } => {
+                let start = || -> std::io::Result<Box<dyn Engine>> {
+                    let mut engine = UciEngine::spawn(path)?;
+                    for (name, value) in options {
+                        engine.set_option(name, value)?;
+                    }
+                    engine.new_game()?;
+                    Ok(Box::new(engine))
+                };
+                start().map_err(|err| format!("Could not start {}: {}", path.display(), err))
+            }
+        }
+    }
+}
+
+#[derive(Default)]
+struct Standing {
+    wins: usize,
+    draws: usize,
+    losses: usize,
+}
+
+impl Standing {
+    fn games(&self) -> usize {
+        self.wins + self.draws + self.losses
+    }
+
+    fn points(&self) -> f64 {
+        self.wins as f64 + self.draws as f64 * 0.5
+    }
+
+    fn elo(&self) -> f64 {
+        if self.games() == 0 {
+            return 0.0;
+        }
+        let score = (self.points() / self.games() as f64).clamp(0.01, 0.99);
+        -400.0 * (1.0 / score - 1.0).log10()
+    }
+}
+
+fn play_game(
+    white: &EngineConfig,
+    black: &EngineConfig,
+    time_control: &TimeControl,
+) -> Result<(WinState, PgnGame), String> {
+    let mut players = [white.create_player()?, black.create_player()?];
+    let mut forfeit = None;
+    let mut board = ChessBoard::new();
+    let mut clock = Clock::with_time_controls(
+        [white, black].map(|config| config.time_control.clone().unwrap_or(time_control.clone())),
+    );
+    let mut pgn = PgnGame::new();
+    pgn.set_tag("White", &white.name);
+    pgn.set_tag("Black", &black.name);
+    if clock.has_odds() {
+        pgn.set_tag(
+            "WhiteTimeControl",
+            clock.time_control(PieceColor::White).to_string(),
+        );
+        pgn.set_tag(
+            "BlackTimeControl",
+            clock.time_control(PieceColor::Black).to_string(),
+        );
+    } else {
+        pgn.set_tag("TimeControl", time_control.to_string());
+    }
+
+    let win_state = loop {
+        if let Some(win_state) = board.win_state() {
+            break win_state;
+        }
+        let color = board.turn;
+        let side = if color == PieceColor::White { 0 } else { 1 };
+        clock.start(color);
+        let config = [white, black][side];
+        let mv = match choose_move(players[side].as_mut(), config, &board, &clock) {
+            Ok(mv) => mv,
+            Err(err) => {
+                forfeit = Some(err);
+                break WinState::Resignation(color.opposite());
+            }
+        };
+        if !clock.stop() {
+            break timeout_result(&board, color);
+        }
+        pgn.moves.push(PgnMove::new(mv));
+        mv.perform(&mut board);
+    };
+
+    pgn.set_result(win_state.result());
+    match forfeit {
+        Some(err) => {
+            eprintln!("Forfeit: {}", err);
+            pgn.set_tag("Termination", "rules infraction");
+        }
+        None => pgn.set_tag("Termination", win_state.reason()),
+    }
+    Ok((win_state, pgn))
+}
+
+#[derive(Clone, Copy, Debug)]
+struct SprtParams {
+    elo0: f64,
+    elo1: f64,
+    alpha: f64,
+    beta: f64,
+    max_games: usize,
+}
+
+impl Default for SprtParams {
+    fn default() -> Self {
+        Self {
+            elo0: 0.0,
+            elo1: 10.0,
+            alpha: 0.05,
+            beta: 0.05,
+            max_games: 10_000,
+        }
+    }
+}
+
+impl FromStr for SprtParams {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let mut params = Self::default();
+        for option in s.split(',').filter(|o| !o.is_empty()) {
+            let (key, value) = option
+                .split_once('=')
+                .ok_or_else(|| format!("Expected key=value, got: {}", option))?;
+            let invalid = || format!("Invalid value for {}: {}", key, value);
+            match key {
+                "elo0" => params.elo0 = value.parse().map_err(|_| invalid())?,
+                "elo1" => params.elo1 = value.parse().map_err(|_| invalid())?,
+                "alpha" => params.alpha = value.parse().map_err(|_| invalid())?,
+                "beta" => params.beta = value.parse().map_err(|_| invalid())?,
+                "max" => params.max_games = value.parse().map_err(|_| invalid())?,
+                _ => return Err(format!("Unknown SPRT option: {}", key)),
+            }
+        }
+        Ok(params)
+    }
+}
+
+fn elo_to_score(elo: f64) -> f64 {
+    1.0 / (1.0 + 10f64.powf(-elo / 400.0))
+}
+
+fn score_to_elo(score: f64) -> f64 {
+    let score = score.clamp(1e-6, 1.0 - 1e-6);
+    -400.0 * (1.0 / score - 1.0).log10()
+}
+
+impl Standing {
+    fn score_stats(&self) -> (f64, f64) {
+        let games = self.games() as f64;
+        let mean = self.points() / games;
+        let variance = (self.wins as f64 * (1.0 - mean).powi(2)
+            + self.draws as f64 * (0.5 - mean).powi(2)
+            + self.losses as f64 * mean.powi(2))
+            / games;
+        (mean, variance)
+    }
+
+    fn log_likelihood_ratio(&self, params: &SprtParams) -> f64 {
+        let (mean, variance) = self.score_stats();
+        if variance <= 0.0 {
+            return 0.0;
+        }
+        let (score0, score1) = (elo_to_score(params.elo0), elo_to_score(params.elo1));
+        self.games() as f64 * (score1 - score0) * (2.0 * mean - score0 - score1) / (2.0 * variance)
+    }
+
+    fn elo_with_error(&self) -> (f64, f64) {
+        let (mean, variance) = self.score_stats();
+        let margin = 1.96 * (variance / self.games() as f64).sqrt();
+        let elo = score_to_elo(mean);
+        let upper = score_to_elo(mean + margin);
+        let lower = score_to_elo(mean - margin);
+        (elo, (upper - lower) / 2.0)
+    }
+}
+
+struct Runner {
+    engines: Vec<EngineConfig>,
+    time_control: TimeControl,
+    pgn_file: Option<File>,
+    standings: Vec<Standing>,
+    round: usize,
+}
+
+impl Runner {
+    fn play(&mut self, white: usize, black: usize) -> Result<WinState, Box<dyn Error>> {
+        let (win_state, mut pgn) = play_game(
+            &self.engines[white],
+            &self.engines[black],
+            &self.time_control,
+        )?;
+        pgn.set_tag("Event", "Self-play tournament");
+        pgn.set_tag("Round", self.round.to_string());
+
+        match win_state.winner() {
+            Some(PieceColor::White) => {
+                self.standings[white].wins += 1;
+                self.standings[black].losses += 1;
+            }
+            Some(PieceColor::Black) => {
+                self.standings[black].wins += 1;
+                self.standings[white].losses += 1;
+            }
+            None => {
+                self.standings[white].draws += 1;
+                self.standings[black].draws += 1;
+            }
+        }
+        println!(
+            "Round {}: {} vs {}: {} ({})",
+            self.round,
+            self.engines[white].name,
+            self.engines[black].name,
+            win_state.result(),
+            win_state.reason()
+        );
+        if let Some(file) = self.pgn_file.as_mut() {
+            writeln!(file, "{}", pgn)?;
+        }
+        self.round += 1;
+        Ok(win_state)
+    }
+
+    fn round_robin(&mut self, games_per_pairing: usize) -> Result<(), Box<dyn Error>> {
+        for i in 0..self.engines.len() {
+            for j in (i + 1)..self.engines.len() {
+                for game in 0..games_per_pairing {
+                    let (white, black) = if game % 2 == 0 { (i, j) } else { (j, i) };
+                    self.play(white, black)?;
+                }
+            }
+        }
+        Ok(())
+    }
+
+    fn sprt(&mut self, params: &SprtParams) -> Result<(), Box<dyn Error>> {
+        let lower_bound = (params.beta / (1.0 - params.alpha)).ln();
+        let upper_bound = ((1.0 - params.beta) / params.alpha).ln();
+        let mut llr = 0.0;
+        while self.standings[0].games() < params.max_games {
+            self.play(0, 1)?;
+            self.play(1, 0)?;
+            let standing = &self.standings[0];
+            llr = standing.log_likelihood_ratio(params);
+            let (elo, error) = standing.elo_with_error();
+            println!(
+                "SPRT: {}-{}-{} Elo {:+.1} +/- {:.1} LLR {:.2} ({:.2}, {:.2})",
+                standing.wins,
+                standing.losses,
+                standing.draws,
+                elo,
+                error,
+                llr,
+                lower_bound,
+                upper_bound
+            );
+            if llr <= lower_bound || llr >= upper_bound {
+                break;
+            }
+        }
+
+        let (elo, error) = self.standings[0].elo_with_error();
+        println!();
+        println!(
+            "{} vs {}: Elo difference {:+.1} +/- {:.1} (95%)",
+            self.engines[0].name, self.engines[1].name, elo, error
+        );
+        if llr >= upper_bound {
+            println!("H1 accepted: elo >= {} (LLR {:.2})", params.elo1, llr);
+        } else if llr <= lower_bound {
+            println!("H0 accepted: elo <= {} (LLR {:.2})", params.elo0, llr);
+        } else {
+            println!(
+                "Inconclusive after {} games (LLR {:.2})",
+                params.max_games, llr
+            );
+        }
+        Ok(())
+    }
+
+    fn gauntlet(&mut self, games_per_opponent: usize) -> Result<(), Box<dyn Error>> {
+        for opponent in 1..self.engines.len() {
+            for game in 0..games_per_opponent {
+                let (white, black) = if game % 2 == 0 {
+                    (0, opponent)
+                } else {
+                    (opponent, 0)
+                };
+                self.play(white, black)?;
+            }
+        }
+
+        println!();
+        for opponent in 1..self.engines.len() {
+            let standing = &self.standings[opponent];
+            if standing.games() == 0 {
+                continue;
+            }
+            let (elo, error) = standing.elo_with_error();
+            println!(
+                "{} vs {}: {}-{}-{} Elo difference {:+.1} +/- {:.1} (95%)",
+                self.engines[0].name,
+                self.engines[opponent].name,
+                standing.losses,
+                standing.wins,
+                standing.draws,
+                -elo,
+                error
+            );
+        }
+        Ok(())
+    }
+
+    fn print_standings(&self) {
+        let mut order: Vec<usize> = (0..self.engines.len()).collect();
+        order.sort_by(|&a, &b| {
+            self.standings[b]
+                .points()
+                .total_cmp(&self.standings[a].points())
+        });
+        println!();
+        println!(
+            "{:<4} {:<20} {:>7} {:>5} {:>5} {:>5} {:>7}",
+            "#", "Engine", "Points", "W", "D", "L", "Elo"
+        );
+        for (rank, &idx) in order.iter().enumerate() {
+            let standing = &self.standings[idx];
+            println!(
+                "{:<4} {:<20} {:>7.1} {:>5} {:>5} {:>5} {:>+7.0}",
+                rank + 1,
+                self.engines[idx].name,
+                standing.points(),
+                standing.wins,
+                standing.draws,
+                standing.losses,
+                standing.elo()
+            );
+        }
+    }
+}
+
+fn usage() -> ! {
+    eprintln!(
+        "Usage: tournament --engine depth=N[,name=NAME][,center=W][,king=B][,contempt=C][,variety=V][,temperature=T][,seed=S][,tc=TC][,st=SECONDS] --engine ... \
+         [--uci PATH[,name=NAME][,depth=N][,tc=TC][,st=SECONDS][,option.NAME=VALUE]] ... \
+         [--games N] [--tc BASE+INC] [--pgn FILE] [--gauntlet] \
+         [--sprt elo0=E0,elo1=E1,alpha=A,beta=B,max=N]"
+    );
+    std::process::exit(2);
+}
+
+fn main() -> Result<(), Box<dyn std::error::Error>> {
+    let mut engines: Vec<EngineConfig> = Vec::new();
+    let mut games_per_pairing = 2;
+    let mut time_control = TimeControl::from_str("60+1").unwrap();
+    let mut pgn_file = None;
+    let mut sprt = None;
+    let mut gauntlet = false;
+
+    let mut args = std::env::args().skip(1);
+    while let Some(arg) = args.next() {
+        let mut value = || args.next().unwrap_or_else(|| usage());
+        match arg.as_str() {
+            "--engine" => engines.push(value().parse()?),
+            "--uci" => engines.push(EngineConfig::parse_uci(&value())?),
+            "--gauntlet" => gauntlet = true,
+            "--games" => games_per_pairing = value().parse()?,
+            "--tc" => {
+                time_control = value()
+                    .parse()
+                    .map_err(|_| "Invalid time control, expected BASE+INC in seconds")?
+            }
+            "--pgn" => pgn_file = Some(File::create(value())?),
+            "--sprt" => sprt = Some(value().parse::<SprtParams>()?),
+            _ => usage(),
+        }
+    }
+    if engines.len() < 2 || (sprt.is_some() && engines.len() != 2) {
+        usage();
+    }
+
+    let mut runner = Runner {
+        standings: engines.iter().map(|_| Standing::default()).collect(),
+        engines,
+        time_control,
+        pgn_file,
+        round: 1,
+    };
+    match sprt {
+        Some(params) => runner.sprt(&params)?,
+        None if gauntlet => runner.gauntlet(games_per_pairing)?,
+        None => runner.round_robin(games_per_pairing)?,
+    }
+    runner.print_standings();
+    Ok(())
+}
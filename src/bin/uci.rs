@@ -1,17 +1,34 @@
-use std::{io::Stdin, mem, sync::mpsc::Sender};
+use std::{
+    io::Stdin,
+    mem,
+    path::Path,
+    sync::mpsc::Sender,
+    time::{Duration, Instant},
+};
 
 use chess::{
-    ai::AI,
-    game::{ChannelPlayer, ChessGame, Player},
-    logic::{Move, PieceColor},
+    ai::{mate_in, AI, MAX_SKILL},
+    book::{OpeningBook, MAX_BOOK_VARIETY},
+    game::{ChannelPlayer, ChessGame, PlayerAction},
+    logic::{ChessBoard, Move, PieceColor, Variant, START_FEN},
+    tt::{ReplacementPolicy, TranspositionTable, DEFAULT_HASH_MB, MAX_HASH_MB},
 };
+use strum::IntoEnumIterator;
+
+const MAX_THREADS: usize = 256;
 
 struct Uci {
-    white_channel: Sender<Move>,
-    black_channel: Sender<Move>,
+    white_channel: Sender<PlayerAction>,
+    black_channel: Sender<PlayerAction>,
     game: ChessGame,
     stdin: Stdin,
     ai: AI,
+    persistent_hash: bool,
+    variant: Variant,
+    own_book: bool,
+    book_file: String,
+    book_depth: Option<usize>,
+    book_variety: u32,
 }
 
 impl Uci {
@@ -20,21 +37,136 @@ impl Uci {
         let (black_channel, black_player) = ChannelPlayer::new();
 
         let game = ChessGame::new(Box::new(white_player), Box::new(black_player), || {});
+        let mut ai = AI::new();
+        ai.on_root_move = Some(Box::new(|board, depth, mv, number| {
+            println!(
+                "info depth {} currmove {} currmovenumber {}",
+                depth,
+                mv.to_uci(board),
+                number
+            );
+        }));
 
         Uci {
             white_channel,
             black_channel,
             game,
             stdin: std::io::stdin(),
-            ai: AI::new(),
+            ai,
+            persistent_hash: false,
+            variant: Variant::Standard,
+            own_book: false,
+            book_file: String::new(),
+            book_depth: None,
+            book_variety: MAX_BOOK_VARIETY,
         }
     }
 
     fn reset(&mut self, reset_ai: bool) {
         let mut old = Self::new();
         mem::swap(self, &mut old);
-        if !reset_ai {
-            self.ai = old.ai;
+        if reset_ai && !old.persistent_hash {
+            old.ai.tt.clear();
+        }
+        self.ai = old.ai;
+        self.persistent_hash = old.persistent_hash;
+        self.variant = old.variant;
+        self.own_book = old.own_book;
+        self.book_file = old.book_file;
+        self.book_depth = old.book_depth;
+        self.book_variety = old.book_variety;
+        *self.game.board.write().unwrap() = ChessBoard::with_variant(self.variant);
+    }
+
+    fn load_book(&mut self) {
+        if !self.own_book {
+            self.ai.book = None;
+            return;
+        }
+        let book = match self.book_file.as_str() {
+            "" => Ok(OpeningBook::load_learning()),
+            path => OpeningBook::load(Path::new(path)),
+        };
+        match book {
+            Ok(book) => self.ai.book = Some(book),
+            Err(err) => {
+                println!(
+                    "info string Could not load book {}: {}",
+                    self.book_file, err
+                );
+                self.ai.book = None;
+            }
+        }
+        self.apply_book_options();
+    }
+
+    fn apply_book_options(&mut self) {
+        if let Some(book) = &mut self.ai.book {
+            book.max_ply = self.book_depth;
+            book.variety = self.book_variety;
+        }
+    }
+
+    fn go_depth(&mut self, board: &ChessBoard, start: Instant) -> Move {
+        if let Some(mv) = self.ai.book_move(board) {
+            println!("info string book move {}", mv.to_uci(board));
+            return mv;
+        }
+        let depth = self.ai.depth;
+        self.ai.tt.new_search(board.piece_count());
+        let scores = self.ai.analyze(board, depth);
+        let best_move = self.ai.pick_move(board, &scores);
+        let score = scores
+            .iter()
+            .find(|(m, _)| *m == best_move)
+            .map_or(0.0, |(_, score)| *score);
+        println!(
+            "info depth {} score {} nodes {} time {} hashfull {} pv {}",
+            depth,
+            uci_score(score),
+            self.ai.nodes,
+            start.elapsed().as_millis(),
+            self.ai.tt.usage_permille(),
+            best_move.to_uci(board)
+        );
+        best_move
+    }
+
+    fn go_mate(
+        &mut self,
+        board: &ChessBoard,
+        moves: usize,
+        move_time: Option<u64>,
+        nodes: Option<usize>,
+        start: Instant,
+    ) -> Move {
+        let deadline = move_time.map(|ms| start + Duration::from_millis(ms));
+        match self.ai.find_mate(board, moves, deadline, nodes) {
+            Some((mate, line)) => {
+                let mut pv_board = board.clone();
+                let pv = line
+                    .iter()
+                    .map(|mv| {
+                        let uci = mv.to_uci(&pv_board);
+                        mv.perform(&mut pv_board);
+                        uci
+                    })
+                    .collect::<Vec<_>>();
+                println!(
+                    "info depth {} score mate {} nodes {} time {} pv {}",
+                    mate * 2 - 1,
+                    mate,
+                    self.ai.nodes,
+                    start.elapsed().as_millis(),
+                    pv.join(" ")
+                );
+                line[0]
+            }
+            None => {
+                println!("info string No mate in {} found", moves);
+                let scores = self.ai.analyze(board, 1);
+                self.ai.pick_move(board, &scores)
+            }
         }
     }
 
@@ -42,7 +174,9 @@ impl Uci {
         let mut input = String::new();
         loop {
             input.clear();
-            self.stdin.read_line(&mut input)?;
+            if self.stdin.read_line(&mut input)? == 0 {
+                break;
+            }
             let mut words = input.split_whitespace();
             let command = words.next().unwrap_or("");
 
@@ -50,6 +184,43 @@ impl Uci {
                 "uci" => {
                     println!("id name ChessAI");
                     println!("id author Leo Minton");
+                    println!("option name Variety type spin default 0 min 0 max 100");
+                    println!("option name Temperature type spin default 0 min 0 max 500");
+                    println!(
+                        "option name Skill Level type spin default {} min 0 max {}",
+                        MAX_SKILL, MAX_SKILL
+                    );
+                    println!(
+                        "option name Threads type spin default {} min 1 max {}",
+                        self.ai.threads(),
+                        MAX_THREADS
+                    );
+                    println!(
+                        "option name Hash type spin default {} min 1 max {}",
+                        DEFAULT_HASH_MB, MAX_HASH_MB
+                    );
+                    println!(
+                        "option name Hash Replacement type combo default {} var {} var {}",
+                        ReplacementPolicy::DepthPreferred.uci_name(),
+                        ReplacementPolicy::DepthPreferred.uci_name(),
+                        ReplacementPolicy::AlwaysReplace.uci_name()
+                    );
+                    println!("option name Persistent Hash type check default false");
+                    println!("option name OwnBook type check default false");
+                    println!("option name Book File type string default <empty>");
+                    println!("option name Book Depth type spin default 0 min 0 max 200");
+                    println!(
+                        "option name Book Variety type spin default {} min 0 max {}",
+                        MAX_BOOK_VARIETY, MAX_BOOK_VARIETY
+                    );
+                    println!("option name Seed type string default random");
+                    println!(
+                        "option name UCI_Variant type combo default {}{}",
+                        Variant::Standard.uci_name(),
+                        Variant::iter()
+                            .map(|variant| format!(" var {}", variant.uci_name()))
+                            .collect::<String>()
+                    );
                     println!("uciok");
                 }
                 "isready" => {
@@ -58,6 +229,107 @@ impl Uci {
                 "quit" => {
                     break;
                 }
+                "setoption" => {
+                    let rest = words.collect::<Vec<_>>().join(" ");
+                    let rest = rest.strip_prefix("name ").unwrap_or(&rest);
+                    let (name, value) = rest.split_once(" value ").unwrap_or((rest, ""));
+                    match name.trim().to_lowercase().as_str() {
+                        "hash" => match value.trim().parse::<usize>() {
+                            Ok(mb) => self.ai.tt.set_size_mb(mb),
+                            Err(_) => println!("info string Invalid value for Hash: {}", value),
+                        },
+                        "threads" => match value.trim().parse::<usize>() {
+                            Ok(threads) => {
+                                if let Err(err) = self.ai.set_threads(threads.clamp(1, MAX_THREADS))
+                                {
+                                    println!(
+                                        "info string Could not build search thread pool: {}",
+                                        err
+                                    );
+                                }
+                            }
+                            Err(_) => println!("info string Invalid value for Threads: {}", value),
+                        },
+                        "hash replacement" => match value.parse::<ReplacementPolicy>() {
+                            Ok(policy) => self.ai.tt.policy = policy,
+                            Err(_) => {
+                                println!(
+                                    "info string Invalid value for Hash Replacement: {}",
+                                    value
+                                )
+                            }
+                        },
+                        "ownbook" => {
+                            self.own_book = value.trim() == "true";
+                            self.load_book();
+                        }
+                        "book file" => {
+                            self.book_file = match value.trim() {
+                                "<empty>" => String::new(),
+                                path => path.to_string(),
+                            };
+                            self.load_book();
+                        }
+                        "book depth" => match value.trim().parse::<usize>() {
+                            Ok(plies) => {
+                                self.book_depth = (plies > 0).then_some(plies);
+                                self.apply_book_options();
+                            }
+                            Err(_) => {
+                                println!("info string Invalid value for Book Depth: {}", value)
+                            }
+                        },
+                        "book variety" => match value.trim().parse::<u32>() {
+                            Ok(variety) => {
+                                self.book_variety = variety.min(MAX_BOOK_VARIETY);
+                                self.apply_book_options();
+                            }
+                            Err(_) => {
+                                println!("info string Invalid value for Book Variety: {}", value)
+                            }
+                        },
+                        "persistent hash" => {
+                            self.persistent_hash = value.trim() == "true";
+                            if self.persistent_hash {
+                                let (capacity, policy) = (self.ai.tt.capacity(), self.ai.tt.policy);
+                                self.ai.tt = TranspositionTable::load_persistent();
+                                self.ai.tt.set_capacity(capacity);
+                                self.ai.tt.policy = policy;
+                            }
+                        }
+                        "seed" => match value.trim() {
+                            "" | "random" => self.ai.set_seed(None),
+                            value => match value.parse::<u64>() {
+                                Ok(seed) => self.ai.set_seed(Some(seed)),
+                                Err(_) => println!("info string Invalid value for Seed: {}", value),
+                            },
+                        },
+                        "uci_variant" => match value.trim().parse::<Variant>() {
+                            Ok(variant) => {
+                                self.variant = variant;
+                                self.reset(false);
+                            }
+                            Err(_) => println!("info string Unknown variant: {}", value),
+                        },
+                        "skill level" => match value.trim().parse::<u8>() {
+                            Ok(skill) => self.ai.skill = (skill < MAX_SKILL).then_some(skill),
+                            Err(_) => {
+                                println!("info string Invalid value for Skill Level: {}", value)
+                            }
+                        },
+                        "temperature" => match value.trim().parse::<f64>() {
+                            Ok(centipawns) => self.ai.temperature = centipawns.max(0.0) / 100.0,
+                            Err(_) => {
+                                println!("info string Invalid value for Temperature: {}", value)
+                            }
+                        },
+                        "variety" => match value.trim().parse::<f64>() {
+                            Ok(centipawns) => self.ai.variety = centipawns / 100.0,
+                            Err(_) => println!("info string Invalid value for Variety: {}", value),
+                        },
+                        _ => println!("info string Unknown option: {}", name),
+                    }
+                }
                 "ucinewgame" => {
                     self.reset(true);
                 }
@@ -71,11 +343,29 @@ impl Uci {
                                 board = self.game.board.write().unwrap();
                             }
                             "fen" => {
-                                let fen = words.next().unwrap_or("");
-                                board.set_from_fen(fen);
+                                let fen = words
+                                    .by_ref()
+                                    .take_while(|&word| word != "moves")
+                                    .collect::<Vec<_>>()
+                                    .join(" ");
+                                let mut parsed = board.clone();
+                                if let Err(err) = parsed.set_from_fen(&fen) {
+                                    println!("info string Invalid FEN: {}", err);
+                                    break;
+                                }
+                                if let Err(err) = parsed.validate() {
+                                    println!("info string Invalid position: {}", err);
+                                    break;
+                                }
+                                *board = parsed;
+                                for word in words.by_ref() {
+                                    if let Ok(mv) = Move::from_str(word, &board) {
+                                        mv.perform(&mut board);
+                                    }
+                                }
                             }
                             "moves" => {
-                                while let Some(word) = words.next() {
+                                for word in words.by_ref() {
                                     if let Ok(mv) = Move::from_str(word, &board) {
                                         mv.perform(&mut board);
                                     }
@@ -86,10 +376,13 @@ impl Uci {
                     }
                 }
                 "go" => {
-                    let mut wtime: usize = 0;
-                    let mut btime: usize = 0;
-                    let mut winc: usize = 0;
-                    let mut binc: usize = 0;
+                    let mut _wtime: usize = 0;
+                    let mut _btime: usize = 0;
+                    let mut _winc: usize = 0;
+                    let mut _binc: usize = 0;
+                    let mut mate: Option<usize> = None;
+                    let mut move_time: Option<u64> = None;
+                    let mut nodes: Option<usize> = None;
                     while let Some(command) = words.next() {
                         match command {
                             "searchmoves" => {
@@ -99,40 +392,198 @@ impl Uci {
                                 println!("Unimplemented: ponder");
                             }
                             "wtime" => {
-                                wtime = words.next().unwrap_or("0").parse().unwrap_or(0);
+                                _wtime = words.next().unwrap_or("0").parse().unwrap_or(0);
                             }
                             "btime" => {
-                                btime = words.next().unwrap_or("0").parse().unwrap_or(0);
+                                _btime = words.next().unwrap_or("0").parse().unwrap_or(0);
                             }
                             "winc" => {
-                                winc = words.next().unwrap_or("0").parse().unwrap_or(0);
+                                _winc = words.next().unwrap_or("0").parse().unwrap_or(0);
                             }
                             "binc" => {
-                                binc = words.next().unwrap_or("0").parse().unwrap_or(0);
+                                _binc = words.next().unwrap_or("0").parse().unwrap_or(0);
+                            }
+                            "mate" => {
+                                mate = words.next().and_then(|n| n.parse().ok());
+                            }
+                            "movetime" => {
+                                move_time = words.next().and_then(|ms| ms.parse().ok());
+                            }
+                            "nodes" => {
+                                nodes = words.next().and_then(|n| n.parse().ok());
                             }
                             _ => {}
                         }
                     }
-                    let best_move = self.ai.get_move(self.game.board.clone());
+                    let start = Instant::now();
+                    let board = self.game.board.read().unwrap().clone();
+                    let best_move = match mate {
+                        Some(moves) => self.go_mate(&board, moves, move_time, nodes, start),
+                        None => self.go_depth(&board, start),
+                    };
                     match self.game.board.read().unwrap().turn {
                         PieceColor::White => {
-                            self.white_channel.send(best_move.clone()).unwrap();
+                            self.white_channel
+                                .send(PlayerAction::Move(best_move))
+                                .unwrap();
                         }
                         PieceColor::Black => {
-                            self.black_channel.send(best_move.clone()).unwrap();
+                            self.black_channel
+                                .send(PlayerAction::Move(best_move))
+                                .unwrap();
                         }
                     }
-                    println!("bestmove {}", best_move.to_string());
+                    println!("bestmove {}", best_move.to_uci(&board));
+                }
+                "d" => {
+                    let board = self.game.board.read().unwrap();
+                    print_board(&board);
+                }
+                "eval" => {
+                    let board = self.game.board.read().unwrap();
+                    print_eval(&board, &self.ai);
+                }
+                "perft" => {
+                    let divide = words.clone().next() == Some("divide");
+                    if divide {
+                        words.next();
+                    }
+                    let depth = words.next().and_then(|d| d.parse().ok()).unwrap_or(1);
+                    let board = self.game.board.read().unwrap();
+                    perft(&board, depth, divide);
+                }
+                "bench" => {
+                    let depth = words.next().and_then(|d| d.parse().ok()).unwrap_or(3);
+                    bench(depth);
                 }
                 _ => {
                     println!("Unknown command: {}", command);
                 }
             }
         }
+        if self.persistent_hash {
+            self.ai.tt.save_persistent()?;
+        }
         Ok(())
     }
 }
 
+const BENCH_POSITIONS: &[&str] = &[
+    START_FEN,
+    "r3k2r/p1ppqpb1/bn2pnp1/3PN3/1p2P3/2N2Q1p/PPPBBPPP/R3K2R w KQkq - 0 1",
+    "r1bqkbnr/pppp1ppp/2n5/4p3/4P3/5N2/PPPP1PPP/RNBQKB1R w KQkq - 2 3",
+    "rnbqkb1r/pp1p1ppp/4pn2/2p5/2PP4/2N5/PP2PPPP/R1BQKBNR w KQkq - 0 4",
+    "r1bq1rk1/ppp2ppp/2np1n2/2b1p3/2B1P3/2NP1N2/PPP2PPP/R1BQ1RK1 w - - 0 7",
+    "8/2p5/3p4/KP5r/1R3p1k/8/4P1P1/8 w - - 0 1",
+    "4rrk1/pp3ppp/2p5/3q4/3P4/2Q2N2/PP3PPP/R4RK1 b - - 3 18",
+    "8/8/4k3/3p4/3P4/4K3/8/8 w - - 0 1",
+];
+
+fn print_board(board: &ChessBoard) {
+    println!();
+    print!("{}", board.render(false));
+    println!();
+    println!("Fen: {}", board.to_fen());
+    println!("Key: {:016X}", board.zobrist_key());
+    println!("Side to move: {}", board.turn.readable());
+    let checkers = board
+        .king_pos(board.turn)
+        .map(|king| board.attackers_to(king, board.turn.opposite()))
+        .unwrap_or_default()
+        .iter()
+        .map(|piece| board.square_name(piece.pos))
+        .collect::<Vec<_>>();
+    println!("Checkers: {}", checkers.join(" "));
+}
+
+fn print_eval(board: &ChessBoard, ai: &AI) {
+    let terms = AI::eval_terms(board, &ai.eval);
+    println!(
+        "{:>12} | {:>7} | {:>7} | {:>7}",
+        "Term", "White", "Black", "Total"
+    );
+    println!("{:-<13}+{:-<9}+{:-<9}+{:-<8}", "", "", "", "");
+    for (name, [white, black]) in terms.named() {
+        println!(
+            "{:>12} | {:>7.2} | {:>7.2} | {:>+7.2}",
+            name,
+            white,
+            black,
+            white - black
+        );
+    }
+    println!("{:-<13}+{:-<9}+{:-<9}+{:-<8}", "", "", "", "");
+    println!(
+        "{:>12} | {:>7} | {:>7} | {:>+7.2}",
+        "Total",
+        "",
+        "",
+        terms.score(PieceColor::White)
+    );
+    println!();
+    println!(
+        "Final evaluation: {:+.2} (white side), {:+.2} (side to move)",
+        terms.score(PieceColor::White),
+        terms.score(board.turn)
+    );
+}
+
+fn uci_score(score: f64) -> String {
+    match mate_in(score) {
+        Some(moves) => format!("mate {}", moves),
+        None => format!("cp {}", (score * 100.0).round() as i64),
+    }
+}
+
+fn perft(board: &ChessBoard, depth: usize, divide: bool) {
+    let start = Instant::now();
+    let nodes = if divide {
+        let mut moves = board.perft_divide(depth);
+        moves.sort_by_key(|(m, _)| m.to_uci(board));
+        for (m, count) in &moves {
+            println!("{}: {}", m.to_uci(board), count);
+        }
+        println!();
+        moves.iter().map(|(_, count)| count).sum()
+    } else {
+        board.perft(depth)
+    };
+    let elapsed = start.elapsed();
+    println!("Nodes searched  : {}", nodes);
+    println!("Total time (ms) : {}", elapsed.as_millis());
+    println!(
+        "Nodes/second    : {}",
+        (nodes as f64 / elapsed.as_secs_f64().max(0.001)) as u64
+    );
+}
+
+fn bench(depth: usize) {
+    let mut total_nodes = 0;
+    let start = Instant::now();
+    for (i, fen) in BENCH_POSITIONS.iter().enumerate() {
+        let mut board = ChessBoard::new();
+        board.set_from_fen(fen).unwrap();
+        let mut ai = AI::with_depth(depth);
+        let best_move = ai.best_move(&board, depth);
+        println!(
+            "Position {}/{}: bestmove {} nodes {}",
+            i + 1,
+            BENCH_POSITIONS.len(),
+            best_move.to_uci(&board),
+            ai.nodes
+        );
+        total_nodes += ai.nodes;
+    }
+    let elapsed = start.elapsed();
+    println!();
+    println!("Total time (ms) : {}", elapsed.as_millis());
+    println!("Nodes searched  : {}", total_nodes);
+    println!(
+        "Nodes/second    : {}",
+        (total_nodes as f64 / elapsed.as_secs_f64().max(0.001)) as u64
+    );
+}
+
 fn main() -> Result<(), Box<dyn std::error::Error>> {
     Uci::new().run()
 }
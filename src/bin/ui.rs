@@ -1,167 +1,3659 @@
-use chess::game::{ChannelPlayer, ChessGame};
+use chess::game::{ChannelPlayer, ChessGame, Player, PlayerAction};
 use std::{
-    collections::HashMap,
-    sync::{mpsc::Sender, Arc, RwLock},
+    collections::{HashMap, HashSet},
+    fs, io,
+    path::PathBuf,
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        mpsc::{self, Receiver, Sender},
+        Arc, Mutex, RwLock,
+    },
+    time::{Duration, Instant, SystemTime, UNIX_EPOCH},
 };
 use strum::IntoEnumIterator;
 
-use chess::ai::AI;
-use chess::logic::{ChessBoard, Move, MoveType, PieceColor, PieceType, WinState};
-use eframe::{
-    egui::{
-        self, Align2, Area, Color32, ColorImage, Context, Frame, Id, Modal, PointerButton, Pos2,
-        Rect, Sense, TextureHandle, TextureOptions, Ui, UiKind, Vec2,
-    },
-    CreationContext,
-};
-use include_dir::{include_dir, Dir};
+use chess::logic::{ChessBoard, Move, MoveType, PieceColor, PieceType, Variant, WinState};
+use chess::{
+    ai::{SearchStats, AI, MAX_SKILL},
+    analysis::{
+        analyze_move, format_score, summarize, win_percent, AnalysisSession, MoveAnalysis,
+        MoveClassification, SharedEngine, MAX_MULTI_PV,
+    },
+    assets,
+    book::OpeningBook,
+    clock::{format_duration, Clock, LowTimeWarning, TimeControl, TimeStage, TimingMethod},
+    config::{self, AppConfig, ConfirmMoves, KeyBindings},
+    engine::{Engine, EnginePlayer, SearchLimits},
+    fairy,
+    i18n::{tr, trf, Language},
+    import::{
+        lichess_analysis_url, parse_pasted, upload_to_lichess, GameSource, ImportError, PastedText,
+    },
+    legality::{self, IllegalMove},
+    movetree::{MoveTree, ROOT},
+    notation::{MoveNotation, SanLanguage},
+    pgn::{nag_symbol, PgnGame},
+    profile::{Profile, Profiles},
+    record::GameRecord,
+    render::{BoardRenderer, RenderOptions},
+    savegame::{self, SavedGame},
+    script::{self, ScriptPlayer},
+    settings::EngineSettings,
+    stats::SessionStats,
+    svg::Svg,
+    theme::{self, Theme},
+    tt::MAX_HASH_MB,
+    tutorial::{Tutorial, LESSONS},
+    uci_engine::UciEngine,
+};
+use eframe::{
+    egui::{
+        self, accesskit, Align, Align2, Area, Color32, ColorImage, Context, FontData,
+        FontDefinitions, FontFamily, Frame, Id, Layout, Modal, PointerButton, Pos2, Rect, RichText,
+        Sense, Stroke, Style, TextureHandle, TextureOptions, Ui, UiKind, Vec2, Visuals,
+    },
+    CreationContext,
+};
+
+const SIDE_PANEL_WIDTH: f32 = 220.0;
+const CAPTURED_ICON_SIZE: f32 = 20.0;
+const GIF_SIZE: u32 = 480;
+const PNG_SIZES: [u32; 4] = [256, 512, 1024, 2048];
+const ANALYSIS_DEPTH: usize = 3;
+const LIVE_ANALYSIS_DEPTH: usize = 6;
+const EVAL_BAR_HEIGHT: f32 = 12.0;
+const PV_ARROWS: usize = 3;
+const EVAL_GRAPH_HEIGHT: f32 = 80.0;
+const MIN_BOARD_ZOOM: f32 = 0.3;
+const MAX_BOARD_ZOOM: f32 = 3.0;
+const BOARD_ZOOM_STEP: f32 = 1.1;
+const MOVE_NAGS: &[u8] = &[1, 2, 3, 4, 5, 6, 7];
+const POSITION_NAGS: &[u8] = &[10, 13, 14, 15, 16, 17, 18, 19];
+const TIME_CONTROLS: &[&str] = &[
+    "180+2",
+    "300+0",
+    "300d3",
+    "600+5",
+    "900+10",
+    "40/5400+30:1800+30",
+    "1/86400",
+    "1/259200",
+];
+const SLOW_GAME_BASE: Duration = Duration::from_secs(600);
+const LOW_TIME_FLASH: Duration = Duration::from_millis(500);
+const SVG_EXPORT_SIZE: u32 = 256;
+const DEFAULT_ASSETS: &str = chess::profile::DEFAULT_PIECE_SET;
+
+#[derive(Clone, Debug, PartialEq)]
+struct MatchScore {
+    opponent: EngineSettings,
+    variant: Variant,
+    time_controls: Option<[TimeControl; 2]>,
+    human: f64,
+    engine: f64,
+}
+
+impl MatchScore {
+    fn new(
+        opponent: EngineSettings,
+        variant: Variant,
+        time_controls: Option<[TimeControl; 2]>,
+    ) -> Self {
+        Self {
+            opponent,
+            variant,
+            time_controls,
+            human: 0.0,
+            engine: 0.0,
+        }
+    }
+
+    fn same_opponent(&self, other: &MatchScore) -> bool {
+        self.opponent == other.opponent
+            && self.variant == other.variant
+            && self.time_controls == other.time_controls
+    }
+
+    fn record(&mut self, win_state: WinState, human_color: PieceColor) {
+        match win_state.winner() {
+            Some(color) if color == human_color => self.human += 1.0,
+            Some(_) => self.engine += 1.0,
+            None => {
+                self.human += 0.5;
+                self.engine += 0.5;
+            }
+        }
+    }
+
+    fn games(&self) -> usize {
+        (self.human + self.engine) as usize
+    }
+}
+
+fn format_age(age: Duration) -> String {
+    match age.as_secs() {
+        secs if secs < 60 => tr("moments").to_string(),
+        secs if secs < 3600 => trf("{} min", &[&(secs / 60)]),
+        secs if secs < 86400 => trf("{} h", &[&(secs / 3600)]),
+        secs => trf("{} d", &[&(secs / 86400)]),
+    }
+}
+
+fn format_points(points: f64) -> String {
+    match (points.trunc() as usize, points.fract() > 0.0) {
+        (0, true) => "½".to_string(),
+        (whole, true) => format!("{}½", whole),
+        (whole, false) => whole.to_string(),
+    }
+}
+
+#[derive(Clone, Copy)]
+struct BoardTheme {
+    dark_square: Color32,
+    light_square: Color32,
+    selected_square: Color32,
+    valid_move: Color32,
+    best_line: Color32,
+    hanging: Color32,
+    threatened: Color32,
+    premove: Color32,
+}
+
+fn to_color32(image::Rgba([r, g, b, a]): image::Rgba<u8>) -> Color32 {
+    Color32::from_rgba_premultiplied(r, g, b, a)
+}
+
+impl From<&Theme> for BoardTheme {
+    fn from(theme: &Theme) -> Self {
+        Self {
+            dark_square: to_color32(theme.dark_square),
+            light_square: to_color32(theme.light_square),
+            selected_square: to_color32(theme.selected_square),
+            valid_move: to_color32(theme.valid_move),
+            best_line: to_color32(theme.best_line),
+            hanging: to_color32(theme.hanging),
+            threatened: to_color32(theme.threatened),
+            premove: to_color32(theme.premove),
+        }
+    }
+}
+
+fn is_font_file(data: &[u8]) -> bool {
+    matches!(
+        data.get(..4),
+        Some([0, 1, 0, 0] | b"true" | b"OTTO" | b"ttcf")
+    )
+}
+
+fn theme_fonts(theme: &Theme) -> FontDefinitions {
+    let mut fonts = FontDefinitions::default();
+    for (family, path) in [
+        (FontFamily::Proportional, &theme.fonts.proportional),
+        (FontFamily::Monospace, &theme.fonts.monospace),
+    ] {
+        let Some(path) = path else {
+            continue;
+        };
+        match fs::read(path) {
+            Ok(data) if is_font_file(&data) => {
+                let name = path.display().to_string();
+                fonts
+                    .font_data
+                    .insert(name.clone(), Arc::new(FontData::from_owned(data)));
+                fonts.families.entry(family).or_default().insert(0, name);
+            }
+            Ok(_) => eprintln!(
+                "Ignoring {}: not a TrueType or OpenType font",
+                path.display()
+            ),
+            Err(err) => eprintln!("Could not load font {}: {}", path.display(), err),
+        }
+    }
+    fonts
+}
+
+fn theme_errors_text(errors: Vec<(PathBuf, theme::ThemeError)>) -> Vec<String> {
+    errors
+        .into_iter()
+        .map(|(path, err)| trf("Ignoring theme {}: {}", &[&path.display(), &err]))
+        .collect()
+}
+
+const LARGE_TEXT_SCALE: f32 = 1.3;
+
+fn to_color_image(image: &image::RgbaImage) -> ColorImage {
+    let size = [image.width() as _, image.height() as _];
+    ColorImage::from_rgba_unmultiplied(size, image.as_flat_samples().as_slice())
+}
+
+fn fairy_placeholder(color: PieceColor) -> image::RgbaImage {
+    let (fill, outline) = match color {
+        PieceColor::White => ([250, 250, 250, 255], [30, 30, 30, 255]),
+        PieceColor::Black => ([30, 30, 30, 255], [250, 250, 250, 255]),
+    };
+    image::RgbaImage::from_fn(64, 64, |x, y| {
+        let distance = ((x as f32 - 31.5).powi(2) + (y as f32 - 31.5).powi(2)).sqrt();
+        match distance {
+            d if d < 20.0 => image::Rgba(fill),
+            d if d < 23.0 => image::Rgba(outline),
+            _ => image::Rgba([0, 0, 0, 0]),
+        }
+    })
+}
+
+fn pv_text(
+    board: &ChessBoard,
+    pv: &[Move],
+    notation: MoveNotation,
+    language: SanLanguage,
+) -> String {
+    let mut board = board.clone();
+    let mut line = Vec::new();
+    for mv in pv {
+        if board.turn == PieceColor::White || line.is_empty() {
+            let dots = if board.turn == PieceColor::White {
+                "."
+            } else {
+                "..."
+            };
+            line.push(format!("{}{}", board.moves_made / 2 + 1, dots));
+        }
+        line.push(mv.notate(&board, notation, language));
+        mv.perform(&mut board);
+    }
+    line.join(" ")
+}
+
+fn to_rgba(color: Color32) -> image::Rgba<u8> {
+    image::Rgba(color.to_array())
+}
+
+fn high_contrast_visuals() -> Visuals {
+    let mut visuals = Visuals::dark();
+    visuals.override_text_color = Some(Color32::WHITE);
+    visuals.panel_fill = Color32::BLACK;
+    visuals.window_fill = Color32::BLACK;
+    visuals.extreme_bg_color = Color32::BLACK;
+    visuals.selection.bg_fill = Color32::from_rgb(0, 90, 200);
+    visuals.selection.stroke = Stroke::new(2.0, Color32::WHITE);
+    for (widget, stroke) in [
+        (&mut visuals.widgets.noninteractive, Color32::WHITE),
+        (&mut visuals.widgets.inactive, Color32::WHITE),
+        (&mut visuals.widgets.hovered, Color32::YELLOW),
+        (&mut visuals.widgets.active, Color32::YELLOW),
+    ] {
+        widget.bg_stroke = Stroke::new(2.0, stroke);
+        widget.fg_stroke = Stroke::new(2.0, Color32::WHITE);
+    }
+    visuals.widgets.inactive.weak_bg_fill = Color32::BLACK;
+    visuals.widgets.inactive.bg_fill = Color32::BLACK;
+    visuals
+}
+
+fn classification_color(classification: MoveClassification) -> Option<Color32> {
+    match classification {
+        MoveClassification::Inaccuracy => Some(Color32::from_rgb(230, 180, 40)),
+        MoveClassification::Mistake => Some(Color32::from_rgb(230, 120, 30)),
+        MoveClassification::Blunder => Some(Color32::from_rgb(220, 40, 40)),
+        _ => None,
+    }
+}
+
+fn skill_label(skill: Option<u8>) -> String {
+    match skill {
+        Some(skill) => trf("Level {}", &[&skill]),
+        None => tr("Full strength").to_string(),
+    }
+}
+
+fn time_control_label(time_control: Option<&TimeControl>) -> String {
+    let Some(time_control) = time_control else {
+        return tr("Untimed").to_string();
+    };
+    if time_control.is_per_move() && time_control.base.as_secs() % 86400 == 0 {
+        return trf("{} d/move", &[&(time_control.base.as_secs() / 86400)]);
+    }
+    let periods = std::iter::once((time_control.moves, time_control.base))
+        .chain(
+            time_control
+                .stages
+                .iter()
+                .map(|stage| (stage.moves, stage.time)),
+        )
+        .map(|(moves, time)| {
+            let minutes = time.as_secs_f64() / 60.0;
+            match moves {
+                Some(moves) => format!("{}/{}", moves, minutes),
+                None => minutes.to_string(),
+            }
+        })
+        .collect::<Vec<_>>()
+        .join(" → ");
+    let seconds = time_control.increment.as_secs_f64();
+    match time_control.method {
+        TimingMethod::Increment => format!("{}+{}", periods, seconds),
+        TimingMethod::SimpleDelay => trf("{} (delay {} s)", &[&periods, &seconds]),
+        TimingMethod::Bronstein => trf("{} (Bronstein {} s)", &[&periods, &seconds]),
+    }
+}
+
+fn time_control_options(ui: &mut Ui, selected: &mut Option<TimeControl>) {
+    ui.selectable_value(selected, None, tr("Untimed"));
+    for time_control in TIME_CONTROLS.iter().filter_map(|spec| spec.parse().ok()) {
+        let label = time_control_label(Some(&time_control));
+        ui.selectable_value(selected, Some(time_control), label);
+    }
+}
+
+fn binding_pressed(input: &egui::InputState, binding: &str) -> bool {
+    binding
+        .split(',')
+        .filter_map(|name| egui::Key::from_name(name.trim()))
+        .any(|key| input.key_pressed(key))
+}
+
+fn binding_label(binding: &str) -> String {
+    let name = binding.split(',').next().unwrap_or_default().trim();
+    egui::Key::from_name(name).map_or(name.to_string(), |key| key.symbol_or_name().to_string())
+}
+
+fn request_attention(context: &Context) {
+    context.send_viewport_cmd(egui::ViewportCommand::RequestUserAttention(
+        egui::UserAttentionType::Critical,
+    ));
+}
+
+struct ChessApp {
+    images: HashMap<(PieceType, PieceColor), TextureHandle>,
+    piece_svgs: HashMap<(PieceType, PieceColor), Svg>,
+    asset_errors: Vec<String>,
+    piece_raster_size: u32,
+    board: Arc<RwLock<ChessBoard>>,
+    selected_piece: Option<(usize, usize)>,
+    valid_moves: Vec<Move>,
+    win_state: Option<WinState>,
+    human_color: PieceColor,
+    hot_seat: bool,
+    hot_seat_game: bool,
+    auto_flip: bool,
+    hot_seat_confirm: bool,
+    flipped: bool,
+    show_threats: bool,
+    tutorial: Option<Tutorial>,
+    zen_mode: bool,
+    board_zoom: f32,
+    key_bindings: KeyBindings,
+    match_score: Option<MatchScore>,
+    stats: SessionStats,
+    show_stats: bool,
+    profiles: Profiles,
+    show_profiles: bool,
+    piece_set: String,
+    global_piece_set: Option<String>,
+    save_as: Option<String>,
+    autoplay: Option<Instant>,
+    autoplay_seconds: f32,
+    restart_modal_closed: bool,
+    promoting_piece: Option<(usize, usize)>,
+    white_channel: Option<Sender<PlayerAction>>,
+    black_channel: Option<Sender<PlayerAction>>,
+    game_thread: Option<std::thread::JoinHandle<Option<WinState>>>,
+    game_cancel: Arc<AtomicBool>,
+    ponder: bool,
+    confirm_moves: ConfirmMoves,
+    explain_illegal_moves: bool,
+    rejected_move: Option<((usize, usize), IllegalMove)>,
+    san_language: SanLanguage,
+    move_notation: MoveNotation,
+    localized_pgn_export: bool,
+    staged_move: Option<Move>,
+    premoves: bool,
+    premove: Option<Move>,
+    draw_offered_at: Option<usize>,
+    low_time_warnings: HashMap<TimeControl, LowTimeWarning>,
+    low_time_warned: HashSet<PieceColor>,
+    attention_alerts: bool,
+    start_board: ChessBoard,
+    moves: Arc<RwLock<Vec<Move>>>,
+    move_times: Arc<RwLock<Vec<Duration>>>,
+    san_moves: Vec<String>,
+    tree: MoveTree,
+    time_control: Option<TimeControl>,
+    opponent_time_control: Option<TimeControl>,
+    custom_time_control: TimeControl,
+    variant: Variant,
+    clock: Option<Arc<RwLock<Clock>>>,
+    correspondence: bool,
+    renderer: BoardRenderer,
+    export_status: Option<String>,
+    png_size: u32,
+    png_options: RenderOptions,
+    png_highlight: bool,
+    announcement: String,
+    high_contrast: bool,
+    large_controls: bool,
+    base_style: Option<Arc<Style>>,
+    applied_style: Option<(bool, bool, String)>,
+    themes: Vec<Theme>,
+    theme_errors: Vec<String>,
+    selected_theme: Theme,
+    board_theme: BoardTheme,
+    high_contrast_theme: BoardTheme,
+    engine_settings: EngineSettings,
+    show_engine_settings: bool,
+    search_stats: Arc<RwLock<Option<SearchStats>>>,
+    show_search_inspector: bool,
+    ai_stop: Arc<AtomicBool>,
+    ai_thinking_since: Option<Instant>,
+    analysis: Arc<RwLock<Vec<MoveAnalysis>>>,
+    analysis_stop: Arc<AtomicBool>,
+    analysis_started: bool,
+    selected_move: Option<usize>,
+    selected_node: Option<usize>,
+    live_analysis: Option<AnalysisSession>,
+    live_analysis_engine: Option<PathBuf>,
+    external_analysis: Option<(PathBuf, Option<SharedEngine>)>,
+    engine_status: Option<String>,
+    engine_error: Arc<RwLock<Option<String>>>,
+    opponent_start: Option<(Receiver<io::Result<UciEngine>>, Option<SavedGame>)>,
+    started_opponent: Option<io::Result<UciEngine>>,
+    multi_pv: usize,
+    imported: bool,
+    show_import: bool,
+    import_text: String,
+    import_status: Option<String>,
+    import_result: Option<Receiver<Result<PgnGame, ImportError>>>,
+    lichess_upload: Option<Receiver<Result<String, ImportError>>>,
+    language: Option<Language>,
+    window_size: Option<[f32; 2]>,
+    saved_config: AppConfig,
+}
+
+impl ChessApp {
+    fn new(cc: &CreationContext, config: AppConfig) -> Self {
+        let (themes, theme_errors) = theme::themes();
+        let selected_theme = themes
+            .iter()
+            .find(|theme| theme.name == config.theme)
+            .cloned()
+            .unwrap_or_default();
+        let mut app = Self {
+            images: HashMap::new(),
+            piece_svgs: HashMap::new(),
+            asset_errors: Vec::new(),
+            piece_raster_size: 0,
+            board: Arc::new(RwLock::new(ChessBoard::new())),
+            selected_piece: None,
+            valid_moves: Vec::new(),
+            win_state: None,
+            human_color: PieceColor::White,
+            hot_seat: false,
+            hot_seat_game: false,
+            auto_flip: true,
+            hot_seat_confirm: false,
+            flipped: false,
+            show_threats: false,
+            tutorial: None,
+            zen_mode: false,
+            board_zoom: config.board_zoom.clamp(MIN_BOARD_ZOOM, MAX_BOARD_ZOOM),
+            key_bindings: config.key_bindings.clone(),
+            match_score: None,
+            stats: SessionStats::load_saved(),
+            show_stats: false,
+            profiles: Profiles::load_saved(),
+            show_profiles: false,
+            piece_set: DEFAULT_ASSETS.to_string(),
+            global_piece_set: config.piece_set.clone(),
+            save_as: None,
+            autoplay: None,
+            autoplay_seconds: 1.0,
+            restart_modal_closed: false,
+            promoting_piece: None,
+            white_channel: None,
+            black_channel: None,
+            game_thread: None,
+            game_cancel: Arc::new(AtomicBool::new(false)),
+            ponder: false,
+            confirm_moves: config.confirm_moves,
+            explain_illegal_moves: true,
+            rejected_move: None,
+            san_language: config.san_language,
+            move_notation: config.move_notation,
+            localized_pgn_export: config.localized_pgn_export,
+            staged_move: None,
+            premoves: false,
+            premove: None,
+            draw_offered_at: None,
+            low_time_warnings: config.low_time_warnings.clone(),
+            low_time_warned: HashSet::new(),
+            attention_alerts: config.attention_alerts,
+            start_board: ChessBoard::new(),
+            moves: Arc::new(RwLock::new(Vec::new())),
+            move_times: Arc::new(RwLock::new(Vec::new())),
+            san_moves: Vec::new(),
+            tree: MoveTree::new(ChessBoard::new()),
+            time_control: None,
+            opponent_time_control: None,
+            custom_time_control: "40/5400+30:1800+30".parse().unwrap(),
+            variant: Variant::Standard,
+            clock: None,
+            correspondence: false,
+            renderer: BoardRenderer::new(
+                Theme::default().light_square,
+                Theme::default().dark_square,
+            ),
+            export_status: None,
+            png_size: 512,
+            png_options: RenderOptions::default(),
+            png_highlight: true,
+            announcement: String::new(),
+            high_contrast: config.high_contrast,
+            large_controls: config.large_controls,
+            base_style: None,
+            applied_style: None,
+            themes,
+            theme_errors: theme_errors_text(theme_errors),
+            board_theme: BoardTheme::from(&selected_theme),
+            selected_theme,
+            high_contrast_theme: BoardTheme::from(&Theme::high_contrast()),
+            engine_settings: config.engine.clone(),
+            show_engine_settings: false,
+            search_stats: Arc::new(RwLock::new(None)),
+            ai_stop: Arc::new(AtomicBool::new(false)),
+            ai_thinking_since: None,
+            show_search_inspector: false,
+            analysis: Arc::new(RwLock::new(Vec::new())),
+            analysis_stop: Arc::new(AtomicBool::new(false)),
+            analysis_started: false,
+            selected_move: None,
+            selected_node: None,
+            live_analysis: None,
+            live_analysis_engine: None,
+            external_analysis: None,
+            engine_status: None,
+            engine_error: Arc::new(RwLock::new(None)),
+            opponent_start: None,
+            started_opponent: None,
+            multi_pv: 3,
+            imported: false,
+            show_import: false,
+            import_text: String::new(),
+            import_status: None,
+            import_result: None,
+            lichess_upload: None,
+            language: config.language,
+            window_size: config.window_size,
+            saved_config: config,
+        };
+        app.apply_profile(&cc.egui_ctx);
+        let saved = app.load_saved_game();
+        app.start_game(&cc.egui_ctx, saved);
+        app
+    }
+
+    fn reset(&mut self, context: &Context) {
+        self.start_game(context, None);
+    }
+
+    fn rematch(&mut self, context: &Context) {
+        self.human_color = self.human_color.opposite();
+        self.reset(context);
+        self.restart_modal_closed = true;
+    }
+
+    fn stop_game(&mut self) {
+        self.game_cancel.store(true, Ordering::Relaxed);
+        self.ai_stop.store(true, Ordering::Relaxed);
+        if let Some(thread) = self.game_thread.take() {
+            let _ = thread.join();
+        }
+    }
+
+    fn clear_game(&mut self) {
+        self.autoplay = None;
+        self.staged_move = None;
+        self.premove = None;
+        self.draw_offered_at = None;
+        self.low_time_warned.clear();
+        self.rejected_move = None;
+        self.selected_piece = None;
+        self.valid_moves.clear();
+        self.win_state = None;
+        self.analysis_stop.store(true, Ordering::Relaxed);
+        self.analysis_stop = Arc::new(AtomicBool::new(false));
+        self.analysis = Arc::new(RwLock::new(Vec::new()));
+        self.analysis_started = false;
+        self.selected_move = None;
+        self.selected_node = None;
+        self.live_analysis = None;
+        self.san_moves.clear();
+        self.imported = false;
+    }
+
+    fn load_pgn(&mut self, pgn: &PgnGame) {
+        self.stop_game();
+        self.clear_game();
+        let record = GameRecord::from_pgn(pgn);
+        self.white_channel = None;
+        self.black_channel = None;
+        self.board = Arc::new(RwLock::new(record.final_board()));
+        self.start_board = record.start_board.clone();
+        self.moves = Arc::new(RwLock::new(record.move_list()));
+        self.move_times = Arc::new(RwLock::new(record.move_times()));
+        self.tree = MoveTree::from_pgn(pgn);
+        self.clock = None;
+        self.correspondence = false;
+        self.imported = true;
+        self.win_state = record.result;
+        self.restart_modal_closed = true;
+    }
+
+    fn start_from_position(&mut self, context: &Context, board: ChessBoard) {
+        let clock = self.time_controls().map(Clock::with_time_controls);
+        let setup = SavedGame::new(GameRecord::new(board), clock.as_ref());
+        self.start_game(context, Some(setup));
+        self.correspondence = false;
+    }
+
+    fn time_controls(&self) -> Option<[TimeControl; 2]> {
+        let human = self.time_control.clone()?;
+        let opponent = self
+            .opponent_time_control
+            .clone()
+            .unwrap_or_else(|| human.clone());
+        Some(match self.human_color {
+            PieceColor::White => [human, opponent],
+            PieceColor::Black => [opponent, human],
+        })
+    }
+
+    fn handle_paste(&mut self, context: &Context) {
+        if context.wants_keyboard_input() {
+            return;
+        }
+        let pasted = context.input(|input| {
+            input.events.iter().find_map(|event| match event {
+                egui::Event::Paste(text) => Some(text.clone()),
+                _ => None,
+            })
+        });
+        let Some(text) = pasted else {
+            return;
+        };
+        if self.game_thread.is_some() {
+            self.export_status = Some(tr("Finish the current game before pasting").to_string());
+            return;
+        }
+        match parse_pasted(&text) {
+            Ok(PastedText::Position(board)) => {
+                self.start_from_position(context, board);
+                self.export_status = Some(tr("Set up the pasted position").to_string());
+            }
+            Ok(PastedText::Game(pgn)) => {
+                self.load_pgn(&pgn);
+                self.export_status = Some(tr("Loaded the pasted game").to_string());
+            }
+            Err(err) => self.export_status = Some(trf("Could not paste: {}", &[&err])),
+        }
+    }
+
+    fn reviewing(&self) -> bool {
+        self.win_state.is_some() || self.imported
+    }
+
+    fn script_opponent(&mut self) -> Option<ScriptPlayer> {
+        let path = self
+            .engine_settings
+            .opponent_engine
+            .clone()
+            .filter(|path| script::is_script(path))?;
+        match ScriptPlayer::load(&path) {
+            Ok(player) => {
+                self.engine_status = None;
+                Some(player)
+            }
+            Err(err) => {
+                self.engine_status = Some(trf(
+                    "Could not start {}: {}; playing the built-in AI",
+                    &[&path.display(), &err],
+                ));
+                None
+            }
+        }
+    }
+
+    fn external_opponent_path(&self) -> Option<PathBuf> {
+        self.engine_settings
+            .opponent_engine
+            .clone()
+            .filter(|path| !script::is_script(path))
+    }
+
+    fn launch_external_opponent(
+        &mut self,
+        context: &Context,
+        path: PathBuf,
+        saved: Option<SavedGame>,
+    ) {
+        let (tx, rx) = mpsc::channel();
+        let context = context.clone();
+        self.engine_status = Some(trf("Starting {}...", &[&path.display()]));
+        std::thread::spawn(move || {
+            let start = || -> io::Result<UciEngine> {
+                let mut engine = UciEngine::spawn(&path)?;
+                engine.new_game()?;
+                Ok(engine)
+            };
+            let _ = tx.send(start());
+            context.request_repaint();
+        });
+        self.opponent_start = Some((rx, saved));
+    }
+
+    fn poll_opponent_start(&mut self, context: &Context) {
+        let Some(result) = self
+            .opponent_start
+            .as_ref()
+            .and_then(|(rx, _)| rx.try_recv().ok())
+        else {
+            return;
+        };
+        let (_, saved) = self.opponent_start.take().unwrap();
+        self.started_opponent = Some(result);
+        self.start_game(context, saved);
+    }
+
+    fn external_opponent(&mut self) -> Option<EnginePlayer> {
+        let path = self.external_opponent_path()?;
+        match self.started_opponent.take()? {
+            Ok(engine) => {
+                self.engine_status = None;
+                let limits = SearchLimits {
+                    depth: Some(self.engine_settings.depth.max(1)),
+                    move_time: self.engine_settings.move_time,
+                    ..SearchLimits::default()
+                };
+                Some(EnginePlayer::new(Box::new(engine), limits))
+            }
+            Err(err) => {
+                self.engine_status = Some(trf(
+                    "Could not start {}: {}; playing the built-in AI",
+                    &[&path.display(), &err],
+                ));
+                None
+            }
+        }
+    }
+
+    fn external_analysis_engine(&mut self) -> Option<SharedEngine> {
+        let path = self.engine_settings.analysis_engine.clone()?;
+        if let Some((loaded, engine)) = &self.external_analysis {
+            if *loaded == path {
+                return engine.clone();
+            }
+        }
+        let engine = match UciEngine::spawn(&path) {
+            Ok(engine) => {
+                self.engine_status = None;
+                Some(Arc::new(Mutex::new(Box::new(engine) as Box<dyn Engine>)))
+            }
+            Err(err) => {
+                self.engine_status = Some(trf(
+                    "Could not start {}: {}; analyzing with the built-in AI",
+                    &[&path.display(), &err],
+                ));
+                None
+            }
+        };
+        self.external_analysis = Some((path, engine.clone()));
+        engine
+    }
+
+    fn start_game(&mut self, context: &Context, saved: Option<SavedGame>) {
+        let hot_seat = saved.as_ref().map_or(self.hot_seat, |saved| saved.hot_seat);
+        self.opponent_start = None;
+        if !hot_seat && self.started_opponent.is_none() {
+            if let Some(path) = self.external_opponent_path() {
+                self.stop_game();
+                self.launch_external_opponent(context, path, saved);
+                return;
+            }
+        }
+        let context = context.clone();
+        self.stop_game();
+        self.clear_game();
+        if let Some(saved) = &saved {
+            self.human_color = saved.human_color;
+        }
+        self.hot_seat_game = hot_seat;
+        let clock = match &saved {
+            Some(saved) => saved.clock(),
+            None => self.time_controls().map(Clock::with_time_controls),
+        };
+        self.clock = clock.map(|clock| Arc::new(RwLock::new(clock)));
+
+        let mut book = OpeningBook::load_learning();
+        let mut ai = AI::new();
+        ai.book = Some(book.clone());
+        ai.ponder = self.ponder;
+        if let Err(err) = self.engine_settings.apply(&mut ai) {
+            self.engine_status = Some(trf("Could not build search thread pool: {}", &[&err]));
+        }
+        self.search_stats = ai.stats.clone();
+        self.ai_stop = ai.stop.clone();
+        self.engine_error = Arc::new(RwLock::new(None));
+        let (external, script) = if self.hot_seat_game {
+            (None, None)
+        } else {
+            (self.external_opponent(), self.script_opponent())
+        };
+        let engine_player: Box<dyn Player> = match (external, script) {
+            (Some(mut player), _) => {
+                player.clock = self.clock.clone();
+                self.search_stats = Arc::new(RwLock::new(None));
+                self.ai_stop = player.stop.clone();
+                self.engine_error = player.error.clone();
+                Box::new(player)
+            }
+            (None, Some(player)) => {
+                self.search_stats = Arc::new(RwLock::new(None));
+                self.ai_stop = Arc::new(AtomicBool::new(false));
+                self.engine_error = player.error.clone();
+                Box::new(player)
+            }
+            (None, None) => {
+                ai.clock = self.clock.clone();
+                Box::new(ai)
+            }
+        };
+
+        let opponent = MatchScore::new(
+            self.engine_settings.clone(),
+            saved
+                .as_ref()
+                .map_or(self.variant, |saved| saved.record.start_board.variant),
+            saved.as_ref().map_or(self.time_controls(), |saved| {
+                saved.record.time_controls.clone()
+            }),
+        );
+        if self.hot_seat_game {
+            self.match_score = None;
+        } else if !self
+            .match_score
+            .as_ref()
+            .is_some_and(|score| score.same_opponent(&opponent))
+        {
+            self.match_score = Some(opponent);
+        }
+
+        let (channel, player) = ChannelPlayer::new();
+        let (white, black): (Box<dyn Player>, Box<dyn Player>) = match self.human_color {
+            _ if self.hot_seat_game => {
+                let (black_channel, black_player) = ChannelPlayer::new();
+                self.white_channel = Some(channel);
+                self.black_channel = Some(black_channel);
+                (Box::new(player), Box::new(black_player))
+            }
+            PieceColor::White => {
+                self.white_channel = Some(channel);
+                self.black_channel = None;
+                (Box::new(player), engine_player)
+            }
+            PieceColor::Black => {
+                self.white_channel = None;
+                self.black_channel = Some(channel);
+                (engine_player, Box::new(player))
+            }
+        };
+        let repaint = context.clone();
+        let mut game = ChessGame::new(white, black, move || {
+            context.request_repaint();
+        });
+        match &saved {
+            Some(saved) => {
+                game.resume(saved.record.start_board.clone(), &saved.record.move_list());
+                *game.move_times.write().unwrap() = saved.record.move_times();
+            }
+            None => game.resume(ChessBoard::with_variant(self.variant), &[]),
+        }
+        self.board = game.board.clone();
+        self.game_cancel = game.cancel.clone();
+        self.start_board = self.board.read().unwrap().clone();
+        self.tree = MoveTree::new(self.start_board.clone());
+        if let Some(saved) = &saved {
+            self.tree.extend_main_line(&saved.record.move_list());
+            for (node, recorded) in self.tree.main_line().into_iter().zip(&saved.record.moves) {
+                let node = self.tree.node_mut(node);
+                node.comment = recorded.comment.clone();
+                node.nags = recorded.nags.clone();
+            }
+        }
+        self.moves = game.moves.clone();
+        self.move_times = game.move_times.clone();
+        game.clock = self.clock.clone();
+        self.correspondence = saved.is_some();
+        self.game_thread = Some(std::thread::spawn(move || {
+            let win_state = game.play();
+            repaint.request_repaint();
+            let win_state = win_state?;
+            book.learn(&game.start_board, &game.moves.read().unwrap(), win_state);
+            if let Err(err) = book.save_learning() {
+                eprintln!("Could not save book learning: {}", err);
+            }
+            Some(win_state)
+        }));
+    }
+
+    fn channel(&self, color: PieceColor) -> Option<Sender<PlayerAction>> {
+        match color {
+            PieceColor::White => self.white_channel.clone(),
+            PieceColor::Black => self.black_channel.clone(),
+        }
+    }
+
+    fn apply_profile(&mut self, context: &Context) {
+        let profile = self.profiles.active().cloned();
+        if let Some(profile) = &profile {
+            self.human_color = profile.color;
+            self.time_control = profile.time_control.clone();
+            self.high_contrast = profile.high_contrast;
+        }
+        self.refresh_piece_set(context);
+    }
+
+    fn refresh_piece_set(&mut self, context: &Context) {
+        let piece_set = self
+            .profiles
+            .active()
+            .map(|profile| profile.piece_set.clone())
+            .filter(|set| set != DEFAULT_ASSETS)
+            .or_else(|| self.global_piece_set.clone())
+            .or_else(|| self.selected_theme.piece_set.clone())
+            .unwrap_or_else(|| DEFAULT_ASSETS.to_string());
+        if self.images.is_empty() || piece_set != self.piece_set {
+            self.piece_set = piece_set;
+            self.load_assets(context);
+        }
+    }
+
+    fn load_assets(&mut self, context: &Context) {
+        self.piece_svgs.clear();
+        self.piece_raster_size = SVG_EXPORT_SIZE;
+        let mut errors = Vec::new();
+        for piece in PieceType::iter() {
+            for color in PieceColor::iter() {
+                let svg = assets::piece_svg(&self.piece_set, piece, color, &mut errors);
+                let image = svg
+                    .as_ref()
+                    .and_then(|svg| svg.render(SVG_EXPORT_SIZE, SVG_EXPORT_SIZE))
+                    .or_else(|| assets::piece_image(&self.piece_set, piece, color));
+                let Some(image) = image else {
+                    panic!(
+                        "Could not find asset file: {}",
+                        assets::piece_file_name(piece, color, "png")
+                    );
+                };
+                if let Some(svg) = svg {
+                    self.piece_svgs.insert((piece, color), svg);
+                }
+                self.renderer.set_piece_image(piece, color, image.clone());
+                let image = to_color_image(&image);
+                self.images.insert(
+                    (piece, color),
+                    context.load_texture("image", image, TextureOptions::default()),
+                );
+            }
+        }
+        self.asset_errors = errors
+            .into_iter()
+            .map(|(file, err)| trf("Ignoring {}: {}", &[&file, &err]))
+            .collect();
+        for piece in fairy::piece_types() {
+            let PieceType::Fairy(index) = piece else {
+                continue;
+            };
+            for color in PieceColor::iter() {
+                let image = fairy::piece(index)
+                    .and_then(|fairy| fairy.image_path(color))
+                    .and_then(|path| fs::read(path).ok())
+                    .and_then(|data| image::load_from_memory(&data).ok())
+                    .map(|image| image.to_rgba8())
+                    .unwrap_or_else(|| fairy_placeholder(color));
+                self.renderer.set_piece_image(piece, color, image.clone());
+                let image = to_color_image(&image);
+                self.images.insert(
+                    (piece, color),
+                    context.load_texture("image", image, TextureOptions::default()),
+                );
+            }
+        }
+    }
+
+    fn rasterize_pieces(&mut self, size: u32) {
+        if self.piece_svgs.is_empty() || size == self.piece_raster_size {
+            return;
+        }
+        self.piece_raster_size = size;
+        for (key, svg) in &self.piece_svgs {
+            let (Some(image), Some(texture)) = (svg.render(size, size), self.images.get_mut(key))
+            else {
+                continue;
+            };
+            texture.set(to_color_image(&image), TextureOptions::default());
+        }
+    }
+
+    fn get_image(&self, piece: PieceType, color: PieceColor) -> &TextureHandle {
+        self.images.get(&(piece, color)).unwrap()
+    }
+
+    fn update_clock(&mut self, context: &Context) {
+        let Some(clock) = &self.clock else {
+            return;
+        };
+        if self.win_state.is_some() {
+            clock.write().unwrap().stop();
+            return;
+        }
+        context.request_repaint_after(Duration::from_millis(100));
+        let clock = clock.read().unwrap().clone();
+        for color in PieceColor::iter() {
+            let warning = self.low_time_warning(clock.time_control(color));
+            if self.channel(color).is_some()
+                && clock.is_low(color, warning.threshold)
+                && self.low_time_warned.insert(color)
+                && warning.alert
+                && self.attention_alerts
+            {
+                request_attention(context);
+            }
+        }
+    }
+
+    fn low_time_warning(&self, time_control: &TimeControl) -> LowTimeWarning {
+        self.low_time_warnings
+            .get(time_control)
+            .copied()
+            .unwrap_or_else(|| LowTimeWarning::for_time_control(time_control))
+    }
+
+    fn premoves_enabled(&self) -> bool {
+        self.premoves
+            || self.clock.as_ref().is_some_and(|clock| {
+                let clock = clock.read().unwrap();
+                let warning = self.low_time_warning(clock.time_control(self.human_color));
+                warning.premoves && clock.is_low(self.human_color, warning.threshold)
+            })
+    }
+
+    fn can_premove(&self, board: &ChessBoard) -> bool {
+        self.tutorial.is_none()
+            && !self.reviewing()
+            && !self.hot_seat_game
+            && self.selected_move.is_none()
+            && board.turn != self.human_color
+            && self.channel(self.human_color).is_some()
+            && self.premoves_enabled()
+    }
+
+    fn play_premove(&mut self) {
+        if self.win_state.is_some() {
+            self.premove = None;
+        }
+        if self.tutorial.is_some() || self.reviewing() || self.selected_move.is_some() {
+            return;
+        }
+        let board = self.board.read().unwrap().clone();
+        if board.turn != self.human_color {
+            return;
+        }
+        if let Some(piece) = self.selected_piece.and_then(|pos| board.piece_at(pos)) {
+            self.valid_moves = piece.legal_moves(&board).collect();
+        }
+        let Some(premove) = self.premove.take() else {
+            return;
+        };
+        let legal = board
+            .piece_at(premove.original)
+            .filter(|piece| piece.color == board.turn)
+            .is_some_and(|piece| piece.legal_moves(&board).any(|mv| mv == premove));
+        if legal {
+            self.send_move(premove);
+        }
+    }
+
+    fn update_ai_status(&mut self, context: &Context) {
+        let thinking = self.win_state.is_none()
+            && !self.imported
+            && self
+                .game_thread
+                .as_ref()
+                .is_some_and(|thread| !thread.is_finished())
+            && !self.hot_seat_game
+            && self.board.read().unwrap().turn != self.human_color;
+        if !thinking {
+            self.ai_thinking_since = None;
+            return;
+        }
+        self.ai_thinking_since.get_or_insert_with(Instant::now);
+        context.request_repaint_after(Duration::from_millis(100));
+    }
+
+    fn ai_status(&mut self, ui: &mut Ui) {
+        let Some(since) = self.ai_thinking_since else {
+            return;
+        };
+        ui.horizontal(|ui| {
+            ui.spinner();
+            ui.label(trf(
+                "AI thinking... {}s",
+                &[&format!("{:.1}", since.elapsed().as_secs_f64())],
+            ));
+            if ui
+                .button(tr("Move now"))
+                .on_hover_text(tr("Play the best move found so far"))
+                .clicked()
+            {
+                self.ai_stop.store(true, Ordering::Relaxed);
+            }
+        });
+    }
+
+    fn game_record(&self) -> GameRecord {
+        let time_controls = self
+            .clock
+            .as_ref()
+            .map(|clock| clock.read().unwrap().time_controls().clone());
+        let mut record = GameRecord::from_moves(
+            self.start_board.clone(),
+            &self.moves.read().unwrap(),
+            &self.move_times.read().unwrap(),
+            time_controls,
+        );
+        for (recorded, node) in record.moves.iter_mut().zip(self.tree.main_line()) {
+            let node = self.tree.node(node);
+            recorded.comment = node.comment.clone();
+            recorded.nags = node.nags.clone();
+        }
+        record.result = self.win_state;
+        record
+    }
+
+    fn saved_game(&self) -> SavedGame {
+        let clock = self
+            .clock
+            .as_ref()
+            .map(|clock| clock.read().unwrap().clone());
+        let mut saved = SavedGame::new(self.game_record(), clock.as_ref());
+        saved.set_players(self.human_color, self.hot_seat_game);
+        saved
+    }
+
+    fn load_saved_game(&mut self) -> Option<SavedGame> {
+        SavedGame::load_saved().unwrap_or_else(|err| {
+            self.export_status = Some(trf("Could not load the saved game: {}", &[&err]));
+            None
+        })
+    }
+
+    fn save_game(&mut self) {
+        match self.saved_game().save_saved() {
+            Ok(()) => self.correspondence = true,
+            Err(err) => eprintln!("Could not save game: {}", err),
+        }
+    }
+
+    fn export_gif(&mut self) {
+        let Some(dir) = config::export_dir() else {
+            self.export_status = Some(tr("No export directory available").to_string());
+            return;
+        };
+        let timestamp = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map_or(0, |time| time.as_secs());
+        let path = dir.join(format!("game-{}.gif", timestamp));
+        let moves = self.moves.read().unwrap().clone();
+        self.export_status = Some(
+            match self
+                .renderer
+                .export_gif(&path, &self.start_board, &moves, GIF_SIZE)
+            {
+                Ok(()) => trf("Saved {}", &[&path.display()]),
+                Err(err) => trf("Could not export GIF: {}", &[&err]),
+            },
+        );
+    }
+
+    fn game_pgn(&self) -> PgnGame {
+        let mut pgn = self.saved_game().record.to_pgn();
+        for (pgn_move, tree_move) in pgn.moves.iter_mut().zip(self.tree.to_pgn_moves()) {
+            pgn_move.variations = tree_move.variations;
+        }
+        pgn.set_tag("Event", "Casual game");
+        pgn
+    }
+
+    fn open_position_in_lichess(&mut self, context: &Context) {
+        let board = self
+            .viewed_board()
+            .unwrap_or_else(|| self.board.read().unwrap().clone());
+        match lichess_analysis_url(&board) {
+            Some(url) => context.open_url(egui::OpenUrl::new_tab(url)),
+            None => {
+                self.export_status = Some(trf(
+                    "Could not open in lichess: {}",
+                    &[&ImportError::UnsupportedVariant],
+                ))
+            }
+        }
+    }
+
+    fn open_game_in_lichess(&mut self, context: &Context) {
+        let pgn = self.game_pgn();
+        let (tx, rx) = mpsc::channel();
+        let context = context.clone();
+        std::thread::spawn(move || {
+            let _ = tx.send(upload_to_lichess(&pgn));
+            context.request_repaint();
+        });
+        self.lichess_upload = Some(rx);
+        self.export_status = Some(tr("Uploading to lichess...").to_string());
+    }
+
+    fn poll_engine_error(&mut self) {
+        if let Some(error) = self.engine_error.write().unwrap().take() {
+            self.engine_status = Some(error);
+            self.show_engine_settings = true;
+        }
+    }
+
+    fn poll_lichess_upload(&mut self, context: &Context) {
+        let Some(result) = self
+            .lichess_upload
+            .as_ref()
+            .and_then(|rx| rx.try_recv().ok())
+        else {
+            return;
+        };
+        self.lichess_upload = None;
+        match result {
+            Ok(url) => {
+                self.export_status = Some(trf("Opened {}", &[&url]));
+                context.open_url(egui::OpenUrl::new_tab(url));
+            }
+            Err(err) => self.export_status = Some(trf("Could not open in lichess: {}", &[&err])),
+        }
+    }
+
+    fn export_pgn(&mut self) {
+        let Some(dir) = config::export_dir() else {
+            self.export_status = Some(tr("No export directory available").to_string());
+            return;
+        };
+        let timestamp = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map_or(0, |time| time.as_secs());
+        let path = dir.join(format!("game-{}.pgn", timestamp));
+        let pgn = self.game_pgn();
+        // Figurines are not valid PGN, so they are only used on screen.
+        let language = if self.localized_pgn_export && self.san_language != SanLanguage::Figurine {
+            self.san_language
+        } else {
+            SanLanguage::English
+        };
+        let written =
+            fs::create_dir_all(&dir).and_then(|_| fs::write(&path, pgn.to_string_in(language)));
+        self.export_status = Some(match written {
+            Ok(()) => trf("Saved {}", &[&path.display()]),
+            Err(err) => trf("Could not export PGN: {}", &[&err]),
+        });
+    }
+
+    fn export_png(&mut self) {
+        let Some(dir) = config::export_dir() else {
+            self.export_status = Some(tr("No export directory available").to_string());
+            return;
+        };
+        let timestamp = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map_or(0, |time| time.as_secs());
+        let path = dir.join(format!("position-{}.png", timestamp));
+        let board = self.board.read().unwrap().clone();
+        let options = RenderOptions {
+            last_move: self
+                .moves
+                .read()
+                .unwrap()
+                .last()
+                .copied()
+                .filter(|_| self.png_highlight),
+            ..self.png_options
+        };
+        self.export_status = Some(
+            match self
+                .renderer
+                .export_png(&path, &board, self.png_size, options)
+            {
+                Ok(()) => trf("Saved {}", &[&path.display()]),
+                Err(err) => trf("Could not export image: {}", &[&err]),
+            },
+        );
+    }
+
+    fn start_analysis(&mut self, context: &Context) {
+        if self.analysis_started || !self.reviewing() {
+            return;
+        }
+        self.analysis_started = true;
+        let context = context.clone();
+        let record = self.game_record();
+        let mut board = record.start_board.clone();
+        let moves = record.move_list();
+        let analysis = self.analysis.clone();
+        let stop = self.analysis_stop.clone();
+        let threads = self.engine_settings.threads;
+        std::thread::spawn(move || {
+            let mut ai = AI::with_depth(ANALYSIS_DEPTH);
+            if let Err(err) = ai.set_threads(threads) {
+                eprintln!("Could not build search thread pool: {}", err);
+            }
+            ai.stop = stop.clone();
+            for mv in moves {
+                let result = analyze_move(&mut ai, &board, mv, ANALYSIS_DEPTH);
+                if stop.load(Ordering::Relaxed) {
+                    return;
+                }
+                analysis.write().unwrap().push(result);
+                context.request_repaint();
+                mv.perform(&mut board);
+            }
+        });
+    }
+
+    fn analysis_panel(&self, ui: &mut Ui) {
+        if !self.analysis_started {
+            return;
+        }
+        let analysis = self.analysis.read().unwrap();
+        if analysis.len() < self.san_moves.len() {
+            ui.label(trf(
+                "Analyzing move {}/{}...",
+                &[&(analysis.len() + 1), &self.san_moves.len()],
+            ));
+        } else {
+            Self::game_summary(ui, &analysis);
+        }
+        let Some(selected) = self.selected_move.and_then(|i| analysis.get(i)) else {
+            return;
+        };
+        let mut board = self.start_board.clone();
+        for mv in self
+            .moves
+            .read()
+            .unwrap()
+            .iter()
+            .take(self.selected_move.unwrap())
+        {
+            mv.perform(&mut board);
+        }
+        ui.label(format!(
+            "{} ({})",
+            tr(selected.classification.readable()),
+            format_score(selected.white_score())
+        ));
+        if selected.played != selected.best {
+            ui.label(trf(
+                "Best was {} ({})",
+                &[
+                    &selected
+                        .best
+                        .notate(&board, self.move_notation, self.san_language),
+                    &format_score(selected.white_best_score()),
+                ],
+            ));
+        }
+    }
+
+    fn game_summary(ui: &mut Ui, analysis: &[MoveAnalysis]) {
+        let colors = [PieceColor::White, PieceColor::Black];
+        let [white, black] = colors.map(|color| summarize(analysis, color));
+        egui::Grid::new("Game summary")
+            .num_columns(3)
+            .striped(true)
+            .show(ui, |ui| {
+                ui.label("");
+                for color in colors {
+                    ui.label(RichText::new(tr(color.readable())).strong());
+                }
+                ui.end_row();
+                let rows = [
+                    (
+                        "Accuracy",
+                        [white, black].map(|s| format!("{:.1}%", s.accuracy)),
+                    ),
+                    (
+                        "Avg. loss",
+                        [white, black].map(|s| format!("{:.0} cp", s.average_loss * 100.0)),
+                    ),
+                    (
+                        "Inaccuracies",
+                        [white, black].map(|s| s.inaccuracies.to_string()),
+                    ),
+                    ("Mistakes", [white, black].map(|s| s.mistakes.to_string())),
+                    ("Blunders", [white, black].map(|s| s.blunders.to_string())),
+                ];
+                for (name, values) in rows {
+                    ui.label(tr(name));
+                    for value in values {
+                        ui.label(value);
+                    }
+                    ui.end_row();
+                }
+            });
+    }
+
+    fn finish_correspondence(&mut self) {
+        if self.correspondence && self.win_state.is_some() {
+            self.correspondence = false;
+            if let Err(err) = SavedGame::remove_saved() {
+                eprintln!("Could not remove saved game: {}", err);
+            }
+        }
+    }
+
+    fn update_move_list(&mut self) {
+        let moves = self.moves.read().unwrap();
+        if moves.len() == self.san_moves.len() {
+            return;
+        }
+        self.tree.extend_main_line(&moves);
+        let mut board = self.start_board.clone();
+        self.san_moves.clear();
+        self.announcement.clear();
+        for (i, mv) in moves.iter().enumerate() {
+            self.san_moves
+                .push(mv.notate(&board, self.move_notation, self.san_language));
+            if i + 1 == moves.len() {
+                self.announcement = format!("{}: {}", board.turn.readable(), mv.describe(&board));
+            }
+            mv.perform(&mut board);
+        }
+    }
+
+    fn theme(&self) -> BoardTheme {
+        if self.high_contrast {
+            self.high_contrast_theme
+        } else {
+            self.board_theme
+        }
+    }
+
+    fn select_theme(&mut self, context: &Context, theme: Theme) {
+        self.board_theme = BoardTheme::from(&theme);
+        self.selected_theme = theme;
+        self.applied_style = None;
+        self.refresh_piece_set(context);
+    }
+
+    fn current_config(&self) -> AppConfig {
+        AppConfig {
+            language: self.language,
+            theme: self.selected_theme.name.clone(),
+            piece_set: self.global_piece_set.clone(),
+            high_contrast: self.high_contrast,
+            large_controls: self.large_controls,
+            board_zoom: self.board_zoom,
+            window_size: self.saved_config.window_size,
+            move_notation: self.move_notation,
+            san_language: self.san_language,
+            localized_pgn_export: self.localized_pgn_export,
+            confirm_moves: self.confirm_moves,
+            attention_alerts: self.attention_alerts,
+            low_time_warnings: self.low_time_warnings.clone(),
+            key_bindings: self.key_bindings.clone(),
+            engine: self.engine_settings.clone(),
+        }
+    }
+
+    // The window size changes continuously while resizing, so it is only
+    // written on exit.
+    fn save_config(&mut self, exiting: bool) {
+        let mut config = self.current_config();
+        if exiting {
+            config.window_size = self.window_size.or(config.window_size);
+        }
+        if config == self.saved_config {
+            return;
+        }
+        if let Err(err) = config.save_saved() {
+            eprintln!("Could not save settings: {}", err);
+        }
+        self.saved_config = config;
+    }
+
+    fn apply_accessibility(&mut self, context: &Context) {
+        let settings = (
+            self.high_contrast,
+            self.large_controls,
+            self.selected_theme.name.clone(),
+        );
+        if self.applied_style.as_ref() == Some(&settings) {
+            return;
+        }
+        let base = self
+            .base_style
+            .get_or_insert_with(|| context.style())
+            .clone();
+        let mut style = (*base).clone();
+        if self.high_contrast {
+            style.visuals = high_contrast_visuals();
+        } else if let Some(background) = self.selected_theme.background {
+            let image::Rgba([r, g, b, _]) = background;
+            let luminance = 0.299 * r as f32 + 0.587 * g as f32 + 0.114 * b as f32;
+            style.visuals = if luminance < 128.0 {
+                Visuals::dark()
+            } else {
+                Visuals::light()
+            };
+            style.visuals.panel_fill = to_color32(background);
+            style.visuals.window_fill = to_color32(background);
+        }
+        context.set_fonts(theme_fonts(&self.selected_theme));
+        if let Some(size) = self.selected_theme.fonts.size {
+            let body = style
+                .text_styles
+                .get(&egui::TextStyle::Body)
+                .map_or(size, |font| font.size);
+            for font in style.text_styles.values_mut() {
+                font.size *= size / body;
+            }
+        }
+        if self.large_controls {
+            for font in style.text_styles.values_mut() {
+                font.size *= LARGE_TEXT_SCALE;
+            }
+            style.spacing.interact_size = Vec2::new(48.0, 36.0);
+            style.spacing.button_padding = Vec2::new(12.0, 8.0);
+            style.spacing.icon_width *= LARGE_TEXT_SCALE;
+            style.spacing.item_spacing *= LARGE_TEXT_SCALE;
+        }
+        context.set_style(style);
+        let theme = self.theme();
+        self.renderer.light_square = to_rgba(theme.light_square);
+        self.renderer.dark_square = to_rgba(theme.dark_square);
+        self.applied_style = Some(settings);
+    }
+
+    fn tutorial_panel(&mut self, ui: &mut Ui) {
+        let Some(tutorial) = &mut self.tutorial else {
+            return;
+        };
+        let lesson = tutorial.current_lesson();
+        ui.heading(trf(
+            "Lesson {}/{}: {}",
+            &[&(tutorial.lesson + 1), &LESSONS.len(), &tr(lesson.title)],
+        ));
+        ui.label(tr(lesson.intro));
+        ui.separator();
+        match tutorial.current_step() {
+            Some(step) => {
+                ui.label(RichText::new(tr(step.prompt)).strong());
+                if tutorial.mistake {
+                    ui.colored_label(Color32::RED, tr("Not quite, try again."));
+                }
+            }
+            None => {
+                ui.label(RichText::new(tr("Well done!")).strong());
+            }
+        }
+        ui.add_space(8.0);
+        let mut lesson = None;
+        ui.horizontal(|ui| {
+            if ui
+                .add_enabled(
+                    tutorial.lesson > 0,
+                    egui::Button::new(tr("Previous lesson")),
+                )
+                .clicked()
+            {
+                lesson = Some(tutorial.lesson - 1);
+            }
+            if ui.button(tr("Restart lesson")).clicked() {
+                lesson = Some(tutorial.lesson);
+            }
+            if ui
+                .add_enabled(
+                    tutorial.lesson + 1 < LESSONS.len(),
+                    egui::Button::new(tr("Next lesson")),
+                )
+                .clicked()
+            {
+                lesson = Some(tutorial.lesson + 1);
+            }
+        });
+        if let Some(lesson) = lesson {
+            tutorial.start_lesson(lesson);
+        }
+        let exit = ui.button(tr("Exit tutorial")).clicked();
+        if lesson.is_some() || exit {
+            self.selected_piece = None;
+            self.promoting_piece = None;
+            self.valid_moves.clear();
+        }
+        if exit {
+            self.tutorial = None;
+        }
+    }
+
+    fn side_panel(&mut self, ui: &mut Ui) {
+        self.update_move_list();
+        if self.tutorial.is_some() {
+            self.tutorial_panel(ui);
+            return;
+        }
+        let turn = self.board.read().unwrap().turn;
+        let status = match self.win_state {
+            Some(win_state) => match win_state.winner() {
+                Some(color) => trf(
+                    "{} wins by {}",
+                    &[&tr(color.readable()), &tr(win_state.reason())],
+                ),
+                None => trf("Draw by {}", &[&tr(win_state.reason())]),
+            },
+            None if self.imported => tr("Imported game").to_string(),
+            None => trf("{}'s turn", &[&tr(turn.readable())]),
+        };
+        ui.heading(status);
+        self.staged_move_prompt(ui);
+        self.ai_status(ui);
+        if let Some(score) = self.match_score.as_ref().filter(|score| score.games() > 0) {
+            ui.label(trf(
+                "Match: you {}–{} engine",
+                &[&format_points(score.human), &format_points(score.engine)],
+            ));
+        }
+        if !self.announcement.is_empty() {
+            ui.label(&self.announcement);
+        }
+        ui.separator();
+        self.clocks(ui);
+        ui.separator();
+        self.captured_pieces(ui);
+        ui.separator();
+
+        ui.with_layout(Layout::bottom_up(Align::Min), |ui| {
+            ui.add_space(4.0);
+            if self.hot_seat || self.hot_seat_game {
+                ui.checkbox(&mut self.auto_flip, tr("Flip board after each move"));
+                ui.checkbox(
+                    &mut self.hot_seat_confirm,
+                    tr("Confirm moves before handing over"),
+                );
+            }
+            ui.checkbox(
+                &mut self.hot_seat,
+                tr("Two players on this computer (next game)"),
+            );
+            ui.checkbox(&mut self.ponder, tr("AI thinks on your time (next game)"));
+            ui.checkbox(&mut self.explain_illegal_moves, tr("Explain illegal moves"));
+            ui.checkbox(&mut self.attention_alerts, tr("Window alerts"))
+                .on_hover_text(tr("Ask for attention when the clock runs low"));
+            ui.checkbox(&mut self.show_threats, tr("Show hanging pieces and threats"))
+                .on_hover_text(tr(
+                    "Marks your undefended pieces that are attacked and the squares the last move attacks",
+                ));
+            ui.add_enabled(
+                !matches!(
+                    self.san_language,
+                    SanLanguage::English | SanLanguage::Figurine
+                ),
+                egui::Checkbox::new(
+                    &mut self.localized_pgn_export,
+                    tr("Use piece letters in PGN export"),
+                ),
+            )
+            .on_disabled_hover_text(tr("PGN files need piece letters, not figurines"));
+            let mut interface_language = Language::current();
+            egui::ComboBox::from_label(tr("Language"))
+                .selected_text(interface_language.readable())
+                .show_ui(ui, |ui| {
+                    for option in Language::iter() {
+                        ui.selectable_value(&mut interface_language, option, option.readable());
+                    }
+                });
+            if interface_language != Language::current() {
+                interface_language.set_current();
+                self.language = Some(interface_language);
+            }
+            let (notation, language) = (self.move_notation, self.san_language);
+            egui::ComboBox::from_label(tr("Move notation"))
+                .selected_text(self.move_notation.readable())
+                .show_ui(ui, |ui| {
+                    for option in MoveNotation::iter() {
+                        ui.selectable_value(&mut self.move_notation, option, option.readable());
+                    }
+                });
+            egui::ComboBox::from_label(tr("Piece letters"))
+                .selected_text(self.san_language.readable())
+                .show_ui(ui, |ui| {
+                    for option in SanLanguage::iter() {
+                        ui.selectable_value(&mut self.san_language, option, option.readable());
+                    }
+                });
+            if self.move_notation != notation || self.san_language != language {
+                self.san_moves.clear();
+            }
+            egui::ComboBox::from_label(tr("Confirm moves"))
+                .selected_text(tr(self.confirm_moves.readable()))
+                .show_ui(ui, |ui| {
+                    for option in ConfirmMoves::iter() {
+                        ui.selectable_value(&mut self.confirm_moves, option, tr(option.readable()));
+                    }
+                });
+            egui::ComboBox::from_label(tr("Time control (next game)"))
+                .selected_text(time_control_label(self.time_control.as_ref()))
+                .show_ui(ui, |ui| time_control_options(ui, &mut self.time_control));
+            if self.time_control.is_some() {
+                egui::ComboBox::from_label(tr("Opponent clock (next game)"))
+                    .selected_text(match &self.opponent_time_control {
+                        Some(time_control) => time_control_label(Some(time_control)),
+                        None => tr("Same as yours").to_string(),
+                    })
+                    .show_ui(ui, |ui| {
+                        ui.selectable_value(
+                            &mut self.opponent_time_control,
+                            None,
+                            tr("Same as yours"),
+                        );
+                        for time_control in TIME_CONTROLS.iter().filter_map(|spec| spec.parse().ok())
+                        {
+                            let label = time_control_label(Some(&time_control));
+                            ui.selectable_value(
+                                &mut self.opponent_time_control,
+                                Some(time_control),
+                                label,
+                            );
+                        }
+                    })
+                    .response
+                    .on_hover_text(tr("Give one side more time than the other"));
+            }
+            self.custom_time_control(ui);
+            self.low_time_settings(ui);
+            self.variant_picker(ui, tr("Variant (next game)"));
+            ui.horizontal(|ui| {
+                self.profile_picker(ui);
+                if ui.button(tr("Edit")).clicked() {
+                    self.show_profiles = !self.show_profiles;
+                }
+            });
+            ui.horizontal(|ui| {
+                if ui
+                    .add_enabled(self.reviewing(), egui::Button::new(tr("New game")))
+                    .clicked()
+                {
+                    self.reset(ui.ctx());
+                    self.restart_modal_closed = true;
+                }
+                if ui
+                    .add_enabled(
+                        self.win_state.is_some() && !self.imported,
+                        egui::Button::new(tr("Rematch")),
+                    )
+                    .on_hover_text(tr("Play again with colors swapped"))
+                    .clicked()
+                {
+                    self.rematch(ui.ctx());
+                }
+                if ui
+                    .add_enabled(!self.reviewing(), egui::Button::new(tr("Save game")))
+                    .on_hover_text(tr("Save this game to resume it after a restart"))
+                    .clicked()
+                {
+                    self.save_game();
+                }
+                if ui
+                    .add_enabled(
+                        !self.san_moves.is_empty(),
+                        egui::Button::new(tr("Save game as...")),
+                    )
+                    .on_hover_text(tr("Save this game under a name"))
+                    .clicked()
+                {
+                    self.save_as = Some(String::new());
+                }
+                if self.reviewing() && ui.button(tr("Resume saved")).clicked() {
+                    if let Some(saved) = self.load_saved_game() {
+                        self.start_game(ui.ctx(), Some(saved));
+                        self.restart_modal_closed = true;
+                    }
+                }
+            });
+            self.game_actions(ui);
+            ui.add_enabled_ui(self.reviewing(), |ui| {
+                ui.menu_button(tr("Recent games"), |ui| self.recent_games_menu(ui))
+                    .response
+                    .on_disabled_hover_text(tr("Finish the current game to open another one"));
+            });
+            if ui
+                .add_enabled(self.reviewing(), egui::Button::new(tr("Export GIF")))
+                .on_hover_text(tr("Export the finished game as an animated GIF"))
+                .clicked()
+            {
+                self.export_gif();
+            }
+            if ui
+                .add_enabled(
+                    !self.san_moves.is_empty(),
+                    egui::Button::new(tr("Export PGN")),
+                )
+                .on_hover_text(tr("Export the game with move times as PGN"))
+                .clicked()
+            {
+                self.export_pgn();
+            }
+            ui.menu_button(tr("Open in lichess"), |ui| {
+                if ui.button(tr("Current position")).clicked() {
+                    self.open_position_in_lichess(ui.ctx());
+                    ui.close_menu();
+                }
+                let idle = self.lichess_upload.is_none();
+                if ui
+                    .add_enabled(
+                        idle && !self.san_moves.is_empty(),
+                        egui::Button::new(tr("Whole game")),
+                    )
+                    .clicked()
+                {
+                    self.open_game_in_lichess(ui.ctx());
+                    ui.close_menu();
+                }
+            });
+            if ui
+                .add_enabled(self.reviewing(), egui::Button::new(tr("Import game")))
+                .on_hover_text(tr("Load a lichess or chess.com game for analysis"))
+                .clicked()
+            {
+                self.show_import = !self.show_import;
+            }
+            if ui.button(tr("Engine settings")).clicked() {
+                self.show_engine_settings = !self.show_engine_settings;
+            }
+            if ui.button(tr("Statistics")).clicked() {
+                self.show_stats = !self.show_stats;
+            }
+            if ui
+                .add_enabled(
+                    self.reviewing() || self.clock.is_none(),
+                    egui::Button::new(tr("Rules tutorial")),
+                )
+                .on_hover_text(tr("Learn how the pieces move"))
+                .on_disabled_hover_text(tr("Finish the timed game to open the tutorial"))
+                .clicked()
+            {
+                self.tutorial = Some(Tutorial::new());
+                self.selected_piece = None;
+                self.valid_moves.clear();
+            }
+            if ui
+                .button(tr("Search inspector"))
+                .on_hover_text(tr("Show what the engine looked at in its last search"))
+                .clicked()
+            {
+                self.show_search_inspector = !self.show_search_inspector;
+            }
+            ui.collapsing(tr("View"), |ui| {
+                ui.add(
+                    egui::Slider::new(&mut self.board_zoom, MIN_BOARD_ZOOM..=MAX_BOARD_ZOOM)
+                        .text(tr("Board zoom"))
+                        .fixed_decimals(1),
+                )
+                .on_hover_text(trf(
+                    "{} and {} zoom, {} resets",
+                    &[
+                        &binding_label(&self.key_bindings.zoom_in),
+                        &binding_label(&self.key_bindings.zoom_out),
+                        &binding_label(&self.key_bindings.reset_zoom),
+                    ],
+                ));
+                if ui
+                    .button(tr("Zen mode"))
+                    .on_hover_text(trf(
+                        "Fullscreen board without panels ({})",
+                        &[&binding_label(&self.key_bindings.zen_mode)],
+                    ))
+                    .clicked()
+                {
+                    self.set_zen_mode(ui.ctx(), true);
+                }
+            });
+            ui.collapsing(tr("Theme"), |ui| {
+                let mut selected = None;
+                egui::ComboBox::from_id_salt("Board theme")
+                    .selected_text(&self.selected_theme.name)
+                    .show_ui(ui, |ui| {
+                        for theme in &self.themes {
+                            if ui
+                                .selectable_label(theme.name == self.selected_theme.name, &theme.name)
+                                .clicked()
+                            {
+                                selected = Some(theme.clone());
+                            }
+                        }
+                    });
+                let reload = ui.button(tr("Reload themes"));
+                let reload = match theme::user_themes_dir() {
+                    Some(dir) => reload.on_hover_text(trf(
+                        "Add custom themes as .toml files in {}",
+                        &[&dir.display()],
+                    )),
+                    None => reload,
+                };
+                if reload.clicked() {
+                    let (themes, errors) = theme::themes();
+                    self.themes = themes;
+                    self.theme_errors = theme_errors_text(errors);
+                    selected = self
+                        .themes
+                        .iter()
+                        .find(|theme| theme.name == self.selected_theme.name)
+                        .cloned()
+                        .or_else(|| Some(Theme::default()));
+                }
+                for error in &self.theme_errors {
+                    ui.colored_label(Color32::RED, error);
+                }
+                if let Some(theme) = selected {
+                    self.select_theme(ui.ctx(), theme);
+                }
+                let piece_set = self.global_piece_set.clone();
+                egui::ComboBox::from_label(tr("Piece set"))
+                    .selected_text(piece_set.as_deref().unwrap_or(tr("From theme")))
+                    .show_ui(ui, |ui| {
+                        ui.selectable_value(&mut self.global_piece_set, None, tr("From theme"));
+                        for set in assets::piece_sets() {
+                            ui.selectable_value(
+                                &mut self.global_piece_set,
+                                Some(set.clone()),
+                                set,
+                            );
+                        }
+                    });
+                if self.global_piece_set != piece_set {
+                    self.refresh_piece_set(ui.ctx());
+                }
+                for error in &self.asset_errors {
+                    ui.colored_label(Color32::RED, error);
+                }
+            });
+            ui.collapsing(tr("Accessibility"), |ui| {
+                ui.checkbox(&mut self.high_contrast, tr("High contrast"));
+                ui.checkbox(&mut self.large_controls, tr("Large controls"));
+            });
+            ui.collapsing(tr("Export position as image"), |ui| {
+                egui::ComboBox::from_label(tr("Resolution"))
+                    .selected_text(format!("{0}x{0}", self.png_size))
+                    .show_ui(ui, |ui| {
+                        for size in PNG_SIZES {
+                            ui.selectable_value(&mut self.png_size, size, format!("{0}x{0}", size));
+                        }
+                    });
+                ui.checkbox(&mut self.png_options.coordinates, tr("Coordinates"));
+                ui.checkbox(&mut self.png_highlight, tr("Highlight last move"));
+                if ui.button(tr("Export PNG")).clicked() {
+                    self.export_png();
+                }
+            });
+            if let Some(status) = &self.export_status {
+                ui.label(status);
+            }
+            if self.correspondence {
+                ui.label(tr("Saved game: progress is kept on exit"));
+            }
+            ui.separator();
+
+            ui.with_layout(Layout::top_down(Align::Min), |ui| {
+                ui.label(RichText::new(tr("Moves")).strong());
+                self.analysis_panel(ui);
+                self.live_analysis_panel(ui);
+                self.step_through_moves(ui);
+                self.annotation_editor(ui);
+                self.move_list(ui);
+            });
+        });
+    }
+
+    fn import_window(&mut self, context: &Context) {
+        if let Some(result) = self
+            .import_result
+            .as_ref()
+            .and_then(|rx| rx.try_recv().ok())
+        {
+            self.import_result = None;
+            match result {
+                Ok(pgn) => {
+                    self.load_pgn(&pgn);
+                    self.import_status = None;
+                    self.show_import = false;
+                }
+                Err(err) => self.import_status = Some(trf("Could not import game: {}", &[&err])),
+            }
+        }
+        let mut import = false;
+        egui::Window::new(tr("Import game"))
+            .open(&mut self.show_import)
+            .resizable(false)
+            .show(context, |ui| {
+                ui.label(tr("lichess or chess.com game link or ID"));
+                let response = ui.text_edit_singleline(&mut self.import_text);
+                let submitted =
+                    response.lost_focus() && ui.input(|input| input.key_pressed(egui::Key::Enter));
+                let idle = self.import_result.is_none();
+                if ui
+                    .add_enabled(idle, egui::Button::new(tr("Import")))
+                    .clicked()
+                    || submitted
+                {
+                    import = idle;
+                }
+                if !idle {
+                    ui.label(tr("Downloading..."));
+                } else if let Some(status) = &self.import_status {
+                    ui.label(status);
+                }
+            });
+        if !import {
+            return;
+        }
+        match self.import_text.parse::<GameSource>() {
+            Ok(source) => {
+                let (tx, rx) = mpsc::channel();
+                let context = context.clone();
+                std::thread::spawn(move || {
+                    let _ = tx.send(source.fetch());
+                    context.request_repaint();
+                });
+                self.import_result = Some(rx);
+                self.import_status = None;
+            }
+            Err(err) => self.import_status = Some(trf("Could not import game: {}", &[&err])),
+        }
+    }
+
+    fn engine_settings_window(&mut self, context: &Context) {
+        let mut settings = self.engine_settings.clone();
+        egui::Window::new(tr("Engine settings"))
+            .open(&mut self.show_engine_settings)
+            .resizable(false)
+            .show(context, |ui| {
+                egui::Grid::new("Engine settings grid")
+                    .num_columns(2)
+                    .show(ui, |ui| {
+                        ui.label(tr("Search depth"));
+                        ui.add(egui::DragValue::new(&mut settings.depth).range(1..=12));
+                        ui.end_row();
+
+                        ui.label(tr("Time per move"));
+                        ui.horizontal(|ui| {
+                            let mut limited = settings.move_time.is_some();
+                            if ui.checkbox(&mut limited, tr("Limit")).changed() {
+                                settings.move_time = limited.then_some(Duration::from_secs(2));
+                            }
+                            if let Some(move_time) = &mut settings.move_time {
+                                let mut secs = move_time.as_secs_f64();
+                                let response = ui.add(
+                                    egui::DragValue::new(&mut secs)
+                                        .range(0.1..=600.0)
+                                        .speed(0.1)
+                                        .suffix(" s"),
+                                );
+                                if response.changed() {
+                                    *move_time = Duration::from_millis((secs * 1000.0) as u64);
+                                }
+                            }
+                        });
+                        ui.end_row();
+
+                        ui.label(tr("Threads"));
+                        ui.add(egui::DragValue::new(&mut settings.threads).range(0..=256))
+                            .on_hover_text(tr("0 uses all cores; applies to the next game"));
+                        ui.end_row();
+
+                        ui.label(tr("Hash size"));
+                        ui.add(
+                            egui::DragValue::new(&mut settings.hash_mb)
+                                .range(1..=MAX_HASH_MB)
+                                .suffix(" MB"),
+                        )
+                        .on_hover_text(tr("Memory for remembering searched positions"));
+                        ui.end_row();
+
+                        ui.label(tr("Opening book"));
+                        ui.checkbox(&mut settings.use_book, tr("Use book"));
+                        ui.end_row();
+
+                        ui.label(tr("Contempt"));
+                        ui.add(
+                            egui::Slider::new(&mut settings.contempt, -1.0..=1.0)
+                                .suffix(format!(" {}", tr("pawns"))),
+                        );
+                        ui.end_row();
+
+                        ui.label(tr("Difficulty"));
+                        egui::ComboBox::from_id_salt("Difficulty")
+                            .selected_text(skill_label(settings.skill))
+                            .show_ui(ui, |ui| {
+                                ui.selectable_value(&mut settings.skill, None, skill_label(None));
+                                for skill in (0..MAX_SKILL).rev() {
+                                    ui.selectable_value(
+                                        &mut settings.skill,
+                                        Some(skill),
+                                        skill_label(Some(skill)),
+                                    );
+                                }
+                            })
+                            .response
+                            .on_hover_text(tr(
+                                "Lower levels overlook moves the way weaker players do",
+                            ));
+                        ui.end_row();
+
+                        ui.label(tr("Temperature"));
+                        ui.add(
+                            egui::Slider::new(&mut settings.temperature, 0.0..=2.0)
+                                .suffix(format!(" {}", tr("pawns"))),
+                        )
+                        .on_hover_text(tr(
+                            "Above zero, picks moves at random weighted by their score",
+                        ));
+                        ui.end_row();
+
+                        for (label, path) in [
+                            ("Opponent engine", &mut settings.opponent_engine),
+                            ("Analysis engine", &mut settings.analysis_engine),
+                        ] {
+                            ui.label(tr(label));
+                            let mut text = path
+                                .as_ref()
+                                .map_or(String::new(), |path| path.display().to_string());
+                            let response = ui
+                                .add(
+                                    egui::TextEdit::singleline(&mut text).hint_text(tr("Built-in")),
+                                )
+                                .on_hover_text(tr(
+                                    "Path to a UCI engine or .rhai bot script; leave empty for the built-in AI",
+                                ));
+                            if response.changed() {
+                                let text = text.trim();
+                                *path = (!text.is_empty()).then(|| PathBuf::from(text));
+                            }
+                            ui.end_row();
+                        }
+
+                        ui.label(tr("Random seed"));
+                        ui.horizontal(|ui| {
+                            let mut fixed = settings.seed.is_some();
+                            if ui
+                                .checkbox(&mut fixed, tr("Fixed"))
+                                .on_hover_text(tr("Makes book and move choices repeat exactly"))
+                                .changed()
+                            {
+                                settings.seed = fixed.then_some(0);
+                            }
+                            if let Some(seed) = &mut settings.seed {
+                                ui.add(egui::DragValue::new(seed));
+                            }
+                        });
+                        ui.end_row();
+                    });
+                ui.separator();
+                ui.label(tr("Changes apply to the next game."));
+                if let Some(status) = &self.engine_status {
+                    ui.colored_label(Color32::RED, status);
+                }
+            });
+        if settings != self.engine_settings {
+            self.engine_settings = settings;
+        }
+    }
+
+    fn search_inspector_window(&mut self, context: &Context) {
+        let stats = self.search_stats.read().unwrap().clone();
+        egui::Window::new(tr("Search inspector"))
+            .open(&mut self.show_search_inspector)
+            .default_width(420.0)
+            .show(context, |ui| {
+                let Some(stats) = stats else {
+                    ui.label(tr("The engine has not searched yet."));
+                    return;
+                };
+                egui::Grid::new("Search inspector summary")
+                    .num_columns(2)
+                    .show(ui, |ui| {
+                        ui.label(tr("Position"));
+                        ui.label(stats.board.to_fen());
+                        ui.end_row();
+
+                        ui.label(tr("Depth"));
+                        ui.label(stats.depth.to_string());
+                        ui.end_row();
+
+                        ui.label(tr("Nodes"));
+                        ui.label(trf(
+                            "{} in {} s ({} nodes/s)",
+                            &[
+                                &stats.nodes,
+                                &format!("{:.2}", stats.elapsed.as_secs_f64()),
+                                &format!("{:.0}", stats.nodes_per_second()),
+                            ],
+                        ));
+                        ui.end_row();
+
+                        ui.label(tr("TT hits"));
+                        ui.label(format!(
+                            "{}/{} ({:.1}%)",
+                            stats.tt_hits,
+                            stats.tt_probes,
+                            stats.tt_hit_rate() * 100.0
+                        ));
+                        ui.end_row();
+
+                        ui.label(tr("TT entries"));
+                        ui.label(trf(
+                            "{}/{} ({}% full)",
+                            &[
+                                &stats.tt_entries,
+                                &stats.tt_capacity,
+                                &format!(
+                                    "{:.1}",
+                                    stats.tt_entries as f64 * 100.0 / stats.tt_capacity as f64
+                                ),
+                            ],
+                        ));
+                        ui.end_row();
+                    });
+                ui.separator();
+                egui::ScrollArea::vertical().show(ui, |ui| {
+                    egui::Grid::new("Search inspector root moves")
+                        .num_columns(4)
+                        .striped(true)
+                        .show(ui, |ui| {
+                            ui.strong(tr("Move"));
+                            ui.strong(tr("Score"));
+                            ui.strong(tr("Nodes"));
+                            ui.strong(tr("Principal variation"));
+                            ui.end_row();
+                            for root in &stats.root_moves {
+                                ui.label(root.mv.notate(
+                                    &stats.board,
+                                    self.move_notation,
+                                    self.san_language,
+                                ));
+                                ui.label(format_score(root.score));
+                                ui.label(root.nodes.to_string());
+                                ui.label(pv_text(
+                                    &stats.board,
+                                    &root.pv,
+                                    self.move_notation,
+                                    self.san_language,
+                                ));
+                                ui.end_row();
+                            }
+                        });
+                });
+            });
+    }
+
+    fn clocks(&self, ui: &mut Ui) {
+        let Some(clock) = &self.clock else {
+            ui.label(tr("Untimed game"));
+            if let Some(profile) = self
+                .profiles
+                .active()
+                .filter(|_| !self.imported && !self.hot_seat_game)
+            {
+                ui.label(trf(
+                    "{} plays {}",
+                    &[&profile.name, &tr(self.human_color.readable())],
+                ));
+            }
+            return;
+        };
+        let clock = clock.read().unwrap().clone();
+        let labels = [
+            self.player_label(PieceColor::Black),
+            self.player_label(PieceColor::White),
+        ];
+        let label_width = labels
+            .iter()
+            .map(|label| label.chars().count())
+            .max()
+            .unwrap_or(0);
+        for (color, label) in [PieceColor::Black, PieceColor::White]
+            .into_iter()
+            .zip(labels)
+        {
+            let remaining = clock.remaining(color);
+            let mut text = RichText::new(format!(
+                "{:<width$}{:>10}",
+                label,
+                format_duration(remaining),
+                width = label_width.max(6)
+            ))
+            .monospace()
+            .size(20.0);
+            if clock.running() == Some(color) {
+                text = text.strong();
+            }
+            let warning = self.low_time_warning(clock.time_control(color));
+            let flash = clock.running() == Some(color)
+                && clock.is_low(color, warning.threshold)
+                && (remaining.as_millis() / LOW_TIME_FLASH.as_millis()).is_multiple_of(2);
+            if remaining.is_zero() || flash {
+                text = text.color(Color32::RED);
+            }
+            ui.label(text);
+        }
+    }
+
+    fn custom_time_control(&mut self, ui: &mut Ui) {
+        egui::CollapsingHeader::new(tr("Custom time control")).show(ui, |ui| {
+            let custom = &mut self.custom_time_control;
+            let mut minutes = custom.base.as_secs_f64() / 60.0;
+            ui.add(
+                egui::DragValue::new(&mut minutes)
+                    .range(0.5..=600.0)
+                    .speed(0.5)
+                    .suffix(" min"),
+            );
+            custom.base = Duration::from_secs_f64(minutes * 60.0);
+            egui::ComboBox::from_id_salt("Timing method")
+                .selected_text(tr(custom.method.readable()))
+                .show_ui(ui, |ui| {
+                    for method in [
+                        TimingMethod::Increment,
+                        TimingMethod::SimpleDelay,
+                        TimingMethod::Bronstein,
+                    ] {
+                        ui.selectable_value(&mut custom.method, method, tr(method.readable()));
+                    }
+                });
+            let mut seconds = custom.increment.as_secs_f64();
+            ui.add(
+                egui::DragValue::new(&mut seconds)
+                    .range(0.0..=180.0)
+                    .suffix(" s"),
+            );
+            custom.increment = Duration::from_secs_f64(seconds);
+            let mut staged = custom.moves.is_some();
+            ui.checkbox(&mut staged, tr("Add time after a number of moves"));
+            match (staged, custom.moves) {
+                (true, None) => {
+                    custom.moves = Some(40);
+                    custom.stages = vec![TimeStage {
+                        moves: None,
+                        time: Duration::from_secs(30 * 60),
+                    }];
+                }
+                (false, Some(_)) => {
+                    custom.moves = None;
+                    custom.stages.clear();
+                }
+                _ => {}
+            }
+            if let (Some(moves), Some(stage)) = (&mut custom.moves, custom.stages.first_mut()) {
+                ui.horizontal(|ui| {
+                    ui.label(tr("After move"));
+                    ui.add(egui::DragValue::new(moves).range(1..=200));
+                    let mut minutes = stage.time.as_secs_f64() / 60.0;
+                    ui.label(tr("add"));
+                    ui.add(
+                        egui::DragValue::new(&mut minutes)
+                            .range(0.0..=600.0)
+                            .speed(0.5)
+                            .suffix(" min"),
+                    );
+                    stage.time = Duration::from_secs_f64(minutes * 60.0);
+                });
+            }
+            ui.horizontal(|ui| {
+                if ui.button(tr("Use for the next game")).clicked() {
+                    self.time_control = Some(self.custom_time_control.clone());
+                }
+                if ui.button(tr("Use for the opponent")).clicked() {
+                    self.opponent_time_control = Some(self.custom_time_control.clone());
+                }
+            });
+        });
+    }
+
+    fn low_time_settings(&mut self, ui: &mut Ui) {
+        ui.checkbox(&mut self.premoves, tr("Premoves"))
+            .on_hover_text(tr("Queue a move while the opponent is thinking"));
+        let Some(time_control) = self.time_control.clone() else {
+            return;
+        };
+        let mut warning = self.low_time_warning(&time_control);
+        egui::CollapsingHeader::new(trf(
+            "Low-time warning ({})",
+            &[&time_control_label(Some(&time_control))],
+        ))
+        .id_salt("Low-time warning")
+        .show(ui, |ui| {
+            let mut seconds = warning.threshold.as_secs_f32();
+            ui.add(
+                egui::Slider::new(&mut seconds, 5.0..=120.0)
+                    .step_by(5.0)
+                    .suffix(" s")
+                    .text(tr("Threshold")),
+            );
+            warning.threshold = Duration::from_secs_f32(seconds);
+            ui.checkbox(&mut warning.alert, tr("Window alert"));
+            ui.checkbox(
+                &mut warning.premoves,
+                tr("Enable premoves when low on time"),
+            );
+        });
+        if warning != self.low_time_warning(&time_control) {
+            self.low_time_warnings.insert(time_control, warning);
+        }
+    }
+
+    fn profile_picker(&mut self, ui: &mut Ui) {
+        let active = self.profiles.active.clone();
+        let mut selected = active.clone();
+        egui::ComboBox::from_label(tr("Profile"))
+            .selected_text(selected.as_deref().unwrap_or(tr("None")))
+            .show_ui(ui, |ui| {
+                ui.selectable_value(&mut selected, None, tr("None"));
+                for profile in &self.profiles.profiles {
+                    ui.selectable_value(&mut selected, Some(profile.name.clone()), &profile.name);
+                }
+            });
+        if selected != active {
+            self.profiles.active = selected;
+            if let Err(err) = self.profiles.save_saved() {
+                eprintln!("Could not save profiles: {}", err);
+            }
+            self.apply_profile(ui.ctx());
+        }
+    }
+
+    fn profiles_window(&mut self, context: &Context) {
+        let mut profiles = self.profiles.clone();
+        let active_index = profiles
+            .active
+            .as_ref()
+            .and_then(|name| profiles.profiles.iter().position(|p| &p.name == name));
+        let piece_sets = assets::piece_sets();
+        let user_sets_dir = assets::user_piece_sets_dir();
+        let mut removed = None;
+        egui::Window::new(tr("Profiles"))
+            .open(&mut self.show_profiles)
+            .resizable(false)
+            .show(context, |ui| {
+                for (i, profile) in profiles.profiles.iter_mut().enumerate() {
+                    egui::CollapsingHeader::new(profile.name.clone())
+                        .id_salt(("Profile", i))
+                        .show(ui, |ui| {
+                            egui::Grid::new(("Profile grid", i))
+                                .num_columns(2)
+                                .show(ui, |ui| {
+                                    ui.label(tr("Name"));
+                                    ui.text_edit_singleline(&mut profile.name);
+                                    ui.end_row();
+
+                                    ui.label(tr("Color"));
+                                    ui.horizontal(|ui| {
+                                        for color in PieceColor::iter() {
+                                            ui.selectable_value(
+                                                &mut profile.color,
+                                                color,
+                                                tr(color.readable()),
+                                            );
+                                        }
+                                    });
+                                    ui.end_row();
+
+                                    ui.label(tr("Time control"));
+                                    egui::ComboBox::from_id_salt(("Profile time control", i))
+                                        .selected_text(time_control_label(
+                                            profile.time_control.as_ref(),
+                                        ))
+                                        .show_ui(ui, |ui| {
+                                            time_control_options(ui, &mut profile.time_control)
+                                        });
+                                    ui.end_row();
+
+                                    ui.label(tr("Theme"));
+                                    ui.checkbox(&mut profile.high_contrast, tr("High contrast"));
+                                    ui.end_row();
+
+                                    ui.label(tr("Piece set"));
+                                    let combo =
+                                        egui::ComboBox::from_id_salt(("Profile piece set", i))
+                                            .selected_text(&profile.piece_set)
+                                            .show_ui(ui, |ui| {
+                                                for set in &piece_sets {
+                                                    ui.selectable_value(
+                                                        &mut profile.piece_set,
+                                                        set.clone(),
+                                                        set,
+                                                    );
+                                                }
+                                            });
+                                    if let Some(dir) = &user_sets_dir {
+                                        combo.response.on_hover_text(trf(
+                                            "Add custom piece sets as folders in {}",
+                                            &[&dir.display()],
+                                        ));
+                                    }
+                                    ui.end_row();
+                                });
+                            if ui.button(tr("Delete profile")).clicked() {
+                                removed = Some(i);
+                            }
+                        });
+                }
+                if ui.button(tr("Add profile")).clicked() {
+                    let name = (1..)
+                        .map(|n| trf("Player {}", &[&n]))
+                        .find(|name| profiles.get(name).is_none())
+                        .unwrap();
+                    profiles.profiles.push(Profile::new(&name));
+                }
+            });
+        if let Some(i) = removed {
+            profiles.profiles.remove(i);
+        }
+        profiles.active = active_index.filter(|&i| removed != Some(i)).and_then(|i| {
+            let i = if removed.is_some_and(|removed| removed < i) {
+                i - 1
+            } else {
+                i
+            };
+            profiles.profiles.get(i).map(|profile| profile.name.clone())
+        });
+        if profiles != self.profiles {
+            let active_changed = profiles.active() != self.profiles.active();
+            self.profiles = profiles;
+            if let Err(err) = self.profiles.save_saved() {
+                eprintln!("Could not save profiles: {}", err);
+            }
+            if active_changed {
+                self.apply_profile(context);
+            }
+        }
+    }
+
+    fn player_label(&self, color: PieceColor) -> String {
+        match self.profiles.active() {
+            Some(profile) if color == self.human_color && !self.imported && !self.hot_seat_game => {
+                profile.name.clone()
+            }
+            _ => color.readable().to_string(),
+        }
+    }
+
+    fn variant_picker(&mut self, ui: &mut Ui, label: &str) {
+        egui::ComboBox::from_label(label)
+            .selected_text(tr(self.variant.readable()))
+            .show_ui(ui, |ui| {
+                for variant in Variant::iter() {
+                    ui.selectable_value(&mut self.variant, variant, tr(variant.readable()));
+                }
+            });
+    }
+
+    fn captured_pieces(&self, ui: &mut Ui) {
+        let board = self.board.read().unwrap();
+        for color in [PieceColor::Black, PieceColor::White] {
+            let captured = color.opposite();
+            ui.horizontal(|ui| {
+                ui.label(format!("{}:", tr(color.readable())));
+                for piece_type in PieceType::all().rev() {
+                    let count = |board: &ChessBoard| {
+                        board
+                            .pieces
+                            .iter()
+                            .flatten()
+                            .filter(|p| p.color == captured && p.piece_type == piece_type)
+                            .count()
+                    };
+                    for _ in count(&board)..count(&self.start_board) {
+                        ui.add(
+                            egui::Image::new(self.get_image(piece_type, captured))
+                                .fit_to_exact_size(Vec2::splat(CAPTURED_ICON_SIZE)),
+                        );
+                    }
+                }
+            });
+        }
+    }
+
+    fn select_move(&mut self, index: Option<usize>) {
+        self.selected_move = index.filter(|&index| index < self.san_moves.len());
+        self.selected_node = None;
+        self.selected_piece = None;
+        self.promoting_piece = None;
+        self.valid_moves.clear();
+    }
+
+    fn select_node(&mut self, node: usize) {
+        let ply = self.tree.ply(node);
+        if node != ROOT && ply <= self.san_moves.len() && self.tree.is_main_line(node) {
+            self.select_move(Some(ply - 1));
+        } else {
+            self.select_move(None);
+            self.selected_node = Some(node);
+        }
+    }
+
+    fn current_node(&self) -> usize {
+        if let Some(node) = self.selected_node {
+            return node;
+        }
+        let index = self.selected_move.or(self.san_moves.len().checked_sub(1));
+        index
+            .and_then(|index| self.tree.main_line().get(index).copied())
+            .unwrap_or(ROOT)
+    }
+
+    fn play_move(&mut self, mv: Move) {
+        if let Some(tutorial) = &mut self.tutorial {
+            tutorial.play(mv);
+            return;
+        }
+        if self.reviewing() {
+            let node = self.tree.add_move(self.current_node(), mv);
+            self.select_node(node);
+            return;
+        }
+        if self.needs_confirmation() {
+            self.staged_move = Some(mv);
+        } else {
+            self.send_move(mv);
+        }
+        self.promoting_piece = None;
+        self.selected_piece = None;
+        self.valid_moves.clear();
+    }
+
+    fn send_move(&self, mv: Move) {
+        self.send_action(PlayerAction::Move(mv));
+    }
+
+    fn send_action(&self, action: PlayerAction) {
+        let turn = self.board.read().unwrap().turn;
+        if let Some(channel) = self.channel(turn) {
+            let _ = channel.send(action);
+        }
+    }
+
+    fn game_actions(&mut self, ui: &mut Ui) {
+        let moves = self.moves.read().unwrap().len();
+        let turn = self.board.read().unwrap().turn;
+        let on_move = !self.reviewing() && self.channel(turn).is_some();
+        let offered = self.draw_offered_at.is_some_and(|at| at + 1 >= moves);
+        ui.horizontal(|ui| {
+            if ui
+                .add_enabled(on_move, egui::Button::new(tr("Resign")))
+                .clicked()
+            {
+                self.send_action(PlayerAction::Resign);
+            }
+            if ui
+                .add_enabled(on_move && !offered, egui::Button::new(tr("Offer draw")))
+                .clicked()
+            {
+                self.draw_offered_at = Some(moves);
+                self.send_action(PlayerAction::OfferDraw);
+            }
+            let can_accept = self.hot_seat_game
+                && on_move
+                && self.draw_offered_at.is_some_and(|at| at + 1 == moves);
+            if can_accept {
+                if ui.button(tr("Accept draw")).clicked() {
+                    self.send_action(PlayerAction::AcceptDraw);
+                }
+            } else if offered && !self.reviewing() {
+                ui.label(tr("Draw offered"));
+            }
+        });
+    }
 
-const BOARD_SIZE: usize = 8;
-const DEFAULT_ASSETS: &str = "default";
-static ASSETS: Dir = include_dir!("$CARGO_MANIFEST_DIR/src/assets");
+    fn needs_confirmation(&self) -> bool {
+        if self.hot_seat_game && self.hot_seat_confirm {
+            return true;
+        }
+        match self.confirm_moves {
+            ConfirmMoves::Never => false,
+            ConfirmMoves::Always => true,
+            ConfirmMoves::SlowGames => self.clock.as_ref().is_none_or(|clock| {
+                clock.read().unwrap().time_control(self.human_color).base >= SLOW_GAME_BASE
+            }),
+        }
+    }
 
-const DARK_SQUARE: egui::Color32 = egui::Color32::from_rgb(181, 136, 99);
-const LIGHT_SQUARE: egui::Color32 = egui::Color32::from_rgb(240, 217, 181);
-const SELECTED_SQUARE: egui::Color32 = egui::Color32::from_rgba_premultiplied(115, 154, 222, 128);
-const VALID_MOVE: egui::Color32 = egui::Color32::from_rgba_premultiplied(81, 173, 94, 128);
+    fn staged_move_prompt(&mut self, ui: &mut Ui) {
+        if self.win_state.is_some() {
+            self.staged_move = None;
+        }
+        let Some(mv) = self.staged_move else {
+            return;
+        };
+        let san = mv.notate(
+            &self.board.read().unwrap(),
+            self.move_notation,
+            self.san_language,
+        );
+        let (enter, escape) = ui.input(|input| {
+            (
+                input.key_pressed(egui::Key::Enter),
+                input.key_pressed(egui::Key::Escape),
+            )
+        });
+        ui.horizontal(|ui| {
+            ui.label(RichText::new(trf("Play {}?", &[&san])).strong());
+            if ui.button(tr("Confirm")).clicked() || enter {
+                self.send_move(mv);
+                self.staged_move = None;
+            } else if ui.button(tr("Cancel")).clicked() || escape {
+                self.staged_move = None;
+            }
+        });
+    }
 
-fn load_image_from_memory(image_data: &[u8]) -> ColorImage {
-    let image = image::load_from_memory(image_data).expect("Failed to load image");
-    let size = [image.width() as _, image.height() as _];
-    let image_buffer = image.to_rgba8();
-    let pixels = image_buffer.as_flat_samples();
-    ColorImage::from_rgba_unmultiplied(size, pixels.as_slice())
-}
+    fn step_back(&mut self) {
+        match self.selected_node {
+            Some(node) => {
+                if let Some(parent) = self.tree.node(node).parent {
+                    self.select_node(parent);
+                }
+            }
+            None => {
+                let index = match self.selected_move {
+                    Some(index) => Some(index.saturating_sub(1)),
+                    None => self.san_moves.len().checked_sub(1),
+                };
+                self.select_move(index);
+            }
+        }
+    }
 
-struct ChessApp {
-    images: HashMap<(PieceType, PieceColor), TextureHandle>,
-    board: Arc<RwLock<ChessBoard>>,
-    selected_piece: Option<(usize, usize)>,
-    valid_moves: Vec<Move>,
-    win_state: Option<WinState>,
-    restart_modal_closed: bool,
-    promoting_piece: Option<(usize, usize)>,
-    white_channel: Option<Sender<Move>>,
-    black_channel: Option<Sender<Move>>,
-    game_thread: Option<std::thread::JoinHandle<WinState>>,
-}
+    fn step_forward(&mut self) -> bool {
+        match self.selected_node {
+            Some(node) => match self.tree.node(node).children.first() {
+                Some(&child) => {
+                    self.select_node(child);
+                    true
+                }
+                None => false,
+            },
+            None => {
+                let Some(index) = self.selected_move else {
+                    return false;
+                };
+                let next = Some(index + 1).filter(|&index| index < self.san_moves.len());
+                self.select_move(next);
+                next.is_some()
+            }
+        }
+    }
 
-impl ChessApp {
-    fn new(cc: &CreationContext) -> Self {
-        let mut app = Self {
-            images: HashMap::new(),
-            board: Arc::new(RwLock::new(ChessBoard::new())),
-            selected_piece: None,
-            valid_moves: Vec::new(),
-            win_state: None,
-            restart_modal_closed: false,
-            promoting_piece: None,
-            white_channel: None,
-            black_channel: None,
-            game_thread: None,
+    fn autoplay(&mut self, context: &Context) {
+        let Some(last_step) = self.autoplay else {
+            return;
         };
-        app.load_assets(cc);
-        app.reset(&cc.egui_ctx);
-        app
+        let interval = Duration::from_secs_f32(self.autoplay_seconds);
+        if last_step.elapsed() < interval {
+            context.request_repaint_after(interval - last_step.elapsed());
+            return;
+        }
+        if self.step_forward() {
+            self.autoplay = Some(Instant::now());
+            context.request_repaint_after(interval);
+        } else {
+            self.autoplay = None;
+        }
     }
 
-    fn reset(&mut self, context: &Context) {
+    fn step_through_moves(&mut self, ui: &mut Ui) {
+        let last = self.san_moves.len().checked_sub(1);
+        let (back, forward) = ui.input(|input| {
+            (
+                binding_pressed(input, &self.key_bindings.previous_move),
+                binding_pressed(input, &self.key_bindings.next_move),
+            )
+        });
+        ui.horizontal(|ui| {
+            if ui.button("<<").on_hover_text(tr("First move")).clicked() {
+                self.autoplay = None;
+                self.select_move(last.map(|_| 0));
+            }
+            if ui.button("<").on_hover_text(tr("Previous move")).clicked() || back {
+                self.autoplay = None;
+                self.step_back();
+            }
+            let (label, hover) = if self.autoplay.is_some() {
+                ("⏸", tr("Pause"))
+            } else {
+                ("▶", tr("Play through the game"))
+            };
+            if ui
+                .add_enabled(last.is_some(), egui::Button::new(label))
+                .on_hover_text(hover)
+                .clicked()
+                && self.autoplay.take().is_none()
+            {
+                if self.selected_move.is_none() && self.selected_node.is_none() {
+                    self.select_move(Some(0));
+                }
+                self.autoplay = Some(Instant::now());
+            }
+            if ui.button(">").on_hover_text(tr("Next move")).clicked() || forward {
+                self.autoplay = None;
+                self.step_forward();
+            }
+            if ui
+                .button(">>")
+                .on_hover_text(tr("Current position"))
+                .clicked()
+            {
+                self.autoplay = None;
+                self.select_move(None);
+            }
+            ui.add(
+                egui::DragValue::new(&mut self.autoplay_seconds)
+                    .range(0.2..=5.0)
+                    .speed(0.05)
+                    .fixed_decimals(1)
+                    .suffix(format!(" {}", tr("s/move"))),
+            )
+            .on_hover_text(tr("Playback speed"));
+        });
+    }
+
+    fn viewed_board(&self) -> Option<ChessBoard> {
+        if let Some(tutorial) = &self.tutorial {
+            return Some(tutorial.board.clone());
+        }
+        if let Some(node) = self.selected_node {
+            return Some(self.tree.board_at(node));
+        }
+        self.selected_move.map(|index| self.board_after_move(index))
+    }
+
+    fn previous_board(&self) -> Option<ChessBoard> {
+        if let Some(node) = self.selected_node {
+            return self
+                .tree
+                .node(node)
+                .parent
+                .map(|parent| self.tree.board_at(parent));
+        }
+        match self.selected_move.or(self.san_moves.len().checked_sub(1))? {
+            0 => Some(self.start_board.clone()),
+            index => Some(self.board_after_move(index - 1)),
+        }
+    }
+
+    fn board_after_move(&self, index: usize) -> ChessBoard {
+        let mut board = self.start_board.clone();
+        for mv in self.moves.read().unwrap().iter().take(index + 1) {
+            mv.perform(&mut board);
+        }
+        board
+    }
+
+    fn moves_text(&self, count: usize) -> String {
+        let first_ply = self.start_board.moves_made;
+        let mut text = String::new();
+        for (i, san) in self.san_moves.iter().take(count).enumerate() {
+            let ply = first_ply + i;
+            if ply.is_multiple_of(2) {
+                text.push_str(&format!("{}. ", ply / 2 + 1));
+            } else if i == 0 {
+                text.push_str(&format!("{}... ", ply / 2 + 1));
+            }
+            text.push_str(san);
+            text.push(' ');
+        }
+        text.trim_end().to_string()
+    }
+
+    fn move_context_menu(&self, ui: &mut Ui, index: usize) {
+        if ui.button(tr("Copy game")).clicked() {
+            ui.ctx().copy_text(self.moves_text(self.san_moves.len()));
+            ui.close_menu();
+        }
+        if ui.button(tr("Copy moves up to here")).clicked() {
+            ui.ctx().copy_text(self.moves_text(index + 1));
+            ui.close_menu();
+        }
+        if ui.button(tr("Copy FEN")).clicked() {
+            ui.ctx().copy_text(self.board_after_move(index).to_fen());
+            ui.close_menu();
+        }
+    }
+
+    fn update_live_analysis(&mut self, context: &Context) {
+        let viewed = self
+            .viewed_board()
+            .filter(|_| self.reviewing() && self.tutorial.is_none());
+        let Some(board) = viewed else {
+            self.live_analysis = None;
+            return;
+        };
+        if self.live_analysis.as_ref().is_some_and(|session| {
+            session.board == board && session.limits.multi_pv == self.multi_pv
+        }) && self.live_analysis_engine == self.engine_settings.analysis_engine
+        {
+            return;
+        }
+        self.live_analysis = None;
+        self.live_analysis_engine = self.engine_settings.analysis_engine.clone();
+        let (engine, limits) = match self.external_analysis_engine() {
+            Some(engine) => (engine, SearchLimits::default()),
+            None => {
+                let mut ai = AI::with_depth(LIVE_ANALYSIS_DEPTH);
+                if let Err(err) = ai.set_threads(self.engine_settings.threads) {
+                    self.engine_status =
+                        Some(trf("Could not build search thread pool: {}", &[&err]));
+                }
+                let engine: SharedEngine = Arc::new(Mutex::new(Box::new(ai)));
+                (engine, SearchLimits::depth(LIVE_ANALYSIS_DEPTH))
+            }
+        };
         let context = context.clone();
-        self.selected_piece = None;
-        self.valid_moves.clear();
-        self.win_state = None;
+        self.live_analysis = Some(AnalysisSession::start(
+            board,
+            engine,
+            limits.with_multi_pv(self.multi_pv),
+            move || context.request_repaint(),
+        ));
+    }
 
-        let (white_channel, player) = ChannelPlayer::new();
-        self.white_channel = Some(white_channel);
-        let game = ChessGame::new(Box::new(player), Box::new(AI::new()), move || {
-            context.request_repaint();
+    fn live_analysis_panel(&mut self, ui: &mut Ui) {
+        if self.live_analysis.is_none() {
+            return;
+        }
+        ui.horizontal(|ui| {
+            ui.label(tr("Lines"));
+            ui.add(egui::DragValue::new(&mut self.multi_pv).range(1..=MAX_MULTI_PV));
         });
-        self.board = game.board.clone();
-        self.game_thread = Some(game.create_game_thread());
+        let Some(session) = &self.live_analysis else {
+            return;
+        };
+        if let Some(error) = session.error() {
+            ui.colored_label(Color32::RED, error);
+            return;
+        }
+        let Some(eval) = session.latest() else {
+            ui.label(tr("Engine: thinking..."));
+            return;
+        };
+        let score = eval.white_score(&session.board);
+        ui.label(trf(
+            "Engine: {} (depth {})",
+            &[&format_score(score), &eval.depth],
+        ));
+        let (rect, _) = ui.allocate_exact_size(
+            Vec2::new(ui.available_width(), EVAL_BAR_HEIGHT),
+            Sense::hover(),
+        );
+        let white_share = (win_percent(score) / 100.0) as f32;
+        ui.painter().rect_filled(rect, 0.0, Color32::from_gray(40));
+        ui.painter().rect_filled(
+            Rect::from_min_size(
+                rect.min,
+                Vec2::new(rect.width() * white_share, rect.height()),
+            ),
+            0.0,
+            Color32::from_gray(235),
+        );
+        let mut clicked = None;
+        for line in &eval.lines {
+            let text = format!(
+                "{} {}",
+                format_score(line.white_score(&session.board)),
+                pv_text(
+                    &session.board,
+                    &line.pv,
+                    self.move_notation,
+                    self.san_language
+                )
+            );
+            if ui
+                .selectable_label(false, RichText::new(text).monospace())
+                .on_hover_text(tr("Play this line's first move"))
+                .clicked()
+            {
+                clicked = line.pv.first().copied();
+            }
+        }
+        if let Some(mv) = clicked {
+            self.play_move(mv);
+        }
     }
 
-    fn channel(&self, color: PieceColor) -> Option<Sender<Move>> {
-        match color {
-            PieceColor::White => self.white_channel.clone(),
-            PieceColor::Black => self.black_channel.clone(),
+    fn annotation_editor(&mut self, ui: &mut Ui) {
+        if self.selected_move.is_none() && self.selected_node.is_none() {
+            return;
+        }
+        let node = self.current_node();
+        if node == ROOT {
+            return;
         }
+        if self.selected_node.is_some() && ui.button(tr("Delete variation")).clicked() {
+            let start = self.tree.variation_start(node).unwrap_or(node);
+            let parent = self.tree.node(start).parent.unwrap_or(ROOT);
+            self.tree.remove(start);
+            self.select_node(parent);
+            return;
+        }
+        let node = self.tree.node_mut(node);
+        let mut comment = node.comment.clone().unwrap_or_default();
+        ui.horizontal(|ui| {
+            ui.label(tr("Comment"));
+            ui.text_edit_singleline(&mut comment);
+        });
+        let comment = comment.trim();
+        node.comment = (!comment.is_empty()).then(|| comment.to_string());
+
+        let nags = &mut node.nags;
+        ui.horizontal(|ui| {
+            for (label, group) in [("Move", MOVE_NAGS), ("Position", POSITION_NAGS)] {
+                let mut current = nags.iter().copied().find(|nag| group.contains(nag));
+                egui::ComboBox::from_label(tr(label))
+                    .width(48.0)
+                    .selected_text(current.and_then(nag_symbol).unwrap_or("-"))
+                    .show_ui(ui, |ui| {
+                        ui.selectable_value(&mut current, None, "-");
+                        for &nag in group {
+                            ui.selectable_value(&mut current, Some(nag), nag_symbol(nag).unwrap());
+                        }
+                    });
+                nags.retain(|nag| !group.contains(nag));
+                nags.extend(current);
+                nags.sort_unstable();
+            }
+        });
     }
 
-    fn load_assets(&mut self, cc: &CreationContext) {
-        for piece in PieceType::iter() {
-            for color in PieceColor::iter() {
-                let path = &format!(
-                    "{}/{}{}.png",
-                    DEFAULT_ASSETS,
-                    color,
-                    piece.to_string().to_uppercase()
-                );
-                if let Some(image) = ASSETS.get_file(path).and_then(|f| Some(f.contents())) {
-                    let image = load_image_from_memory(image);
-                    self.images.insert(
-                        (piece, color),
-                        cc.egui_ctx
-                            .load_texture("image", image, TextureOptions::default()),
-                    );
-                } else {
-                    panic!("Could not find asset file: {}", path);
+    fn move_list(&mut self, ui: &mut Ui) {
+        let analysis = self.analysis.clone();
+        let analysis = analysis.read().unwrap();
+        let move_times = self.move_times.clone();
+        let move_times = move_times.read().unwrap();
+        let main_line = self.tree.main_line();
+        let mut clicked = None;
+        let mut clicked_node = None;
+        let graph_space = if analysis.is_empty() {
+            0.0
+        } else {
+            EVAL_GRAPH_HEIGHT + ui.spacing().item_spacing.y
+        };
+        egui::ScrollArea::vertical()
+            .max_height(ui.available_height() - graph_space)
+            .auto_shrink(false)
+            .stick_to_bottom(true)
+            .show(ui, |ui| {
+                egui::Grid::new("Move list")
+                    .num_columns(3)
+                    .striped(true)
+                    .show(ui, |ui| {
+                        for (i, pair) in self.san_moves.chunks(2).enumerate() {
+                            ui.label(format!("{}.", i + 1));
+                            for (j, san) in pair.iter().enumerate() {
+                                let index = i * 2 + j;
+                                let time = move_times
+                                    .get(index)
+                                    .filter(|time| !time.is_zero())
+                                    .map_or(String::new(), |&time| {
+                                        format!(" {}", format_duration(time))
+                                    });
+                                let nags = main_line
+                                    .get(index)
+                                    .map_or(String::new(), |&node| self.nag_symbols(node));
+                                let mut text = RichText::new(format!("{}{}{}", san, nags, time));
+                                if let Some(classification) =
+                                    analysis.get(index).map(|a| a.classification)
+                                {
+                                    text = RichText::new(format!(
+                                        "{}{}{}{}",
+                                        san,
+                                        classification.glyph().unwrap_or(""),
+                                        nags,
+                                        time
+                                    ));
+                                    if let Some(color) = classification_color(classification) {
+                                        text = text.color(color);
+                                    }
+                                }
+                                let selected = self.selected_move == Some(index);
+                                let response = ui.selectable_label(selected, text);
+                                if response.clicked() {
+                                    clicked = Some((!selected).then_some(index));
+                                }
+                                response.context_menu(|ui| self.move_context_menu(ui, index));
+                            }
+                            ui.end_row();
+                            for &node in main_line.iter().skip(i * 2).take(pair.len()) {
+                                if let Some(comment) = &self.tree.node(node).comment {
+                                    ui.label("");
+                                    ui.label(RichText::new(comment).italics().weak());
+                                    ui.end_row();
+                                }
+                                let variations = self.tree.variations(node);
+                                if !variations.is_empty() {
+                                    ui.label("");
+                                    ui.horizontal_wrapped(|ui| {
+                                        for &variation in variations {
+                                            self.variation_line(ui, variation, &mut clicked_node);
+                                        }
+                                    });
+                                    ui.end_row();
+                                }
+                            }
+                        }
+                    });
+                if let Some(&next) = main_line.get(self.san_moves.len()) {
+                    ui.horizontal_wrapped(|ui| {
+                        self.variation_line(ui, next, &mut clicked_node);
+                    });
                 }
+            });
+        if let Some(index) = Self::eval_graph(ui, &analysis, self.selected_move) {
+            clicked = Some(Some(index));
+        }
+        if let Some(index) = clicked {
+            self.select_move(index);
+        }
+        if let Some(node) = clicked_node {
+            self.select_node(node);
+        }
+    }
+
+    fn node_text(&self, node: usize) -> String {
+        let move_node = self.tree.node(node);
+        match (move_node.mv, move_node.parent) {
+            (Some(mv), Some(parent)) if self.move_notation != MoveNotation::San => mv.notate(
+                &self.tree.board_at(parent),
+                self.move_notation,
+                self.san_language,
+            ),
+            _ => self.san_language.localize(&move_node.san),
+        }
+    }
+
+    fn nag_symbols(&self, node: usize) -> String {
+        self.tree
+            .node(node)
+            .nags
+            .iter()
+            .filter_map(|&nag| nag_symbol(nag))
+            .collect()
+    }
+
+    fn variation_line(&self, ui: &mut Ui, start: usize, clicked: &mut Option<usize>) {
+        ui.label(RichText::new("(").weak());
+        let mut show_number = true;
+        for node in std::iter::once(start).chain(self.tree.continuation(start)) {
+            let ply = self.tree.start_board.moves_made + self.tree.ply(node) - 1;
+            let number = if ply.is_multiple_of(2) {
+                format!("{}. ", ply / 2 + 1)
+            } else if show_number {
+                format!("{}... ", ply / 2 + 1)
+            } else {
+                String::new()
+            };
+            let move_node = self.tree.node(node);
+            let text = format!(
+                "{}{}{}",
+                number,
+                self.node_text(node),
+                self.nag_symbols(node)
+            );
+            let mut response = ui.selectable_label(
+                self.selected_node == Some(node),
+                RichText::new(text).small(),
+            );
+            if let Some(comment) = &move_node.comment {
+                response = response.on_hover_text(comment);
+            }
+            if response.clicked() {
+                *clicked = Some(node);
+            }
+            show_number = false;
+            for &variation in self.tree.variations(node) {
+                self.variation_line(ui, variation, clicked);
+                show_number = true;
             }
         }
+        ui.label(RichText::new(")").weak());
     }
 
-    fn get_image(&self, piece: PieceType, color: PieceColor) -> &TextureHandle {
-        self.images.get(&(piece, color)).unwrap()
+    fn eval_graph(
+        ui: &mut Ui,
+        analysis: &[MoveAnalysis],
+        selected: Option<usize>,
+    ) -> Option<usize> {
+        if analysis.is_empty() {
+            return None;
+        }
+        let (rect, response) = ui.allocate_exact_size(
+            Vec2::new(ui.available_width(), EVAL_GRAPH_HEIGHT),
+            Sense::click(),
+        );
+        let painter = ui.painter_at(rect);
+        painter.rect_filled(rect, 0.0, Color32::from_gray(40));
+        let step = rect.width() / analysis.len().max(2).saturating_sub(1) as f32;
+        let point = |index: usize, score: f64| {
+            let share = (win_percent(score) / 100.0) as f32;
+            Pos2::new(
+                rect.left() + index as f32 * step,
+                rect.bottom() - share * rect.height(),
+            )
+        };
+        let points = analysis
+            .iter()
+            .enumerate()
+            .map(|(i, a)| point(i, a.white_score()))
+            .collect::<Vec<_>>();
+        for pair in points.windows(2) {
+            painter.add(egui::Shape::convex_polygon(
+                vec![
+                    pair[0],
+                    pair[1],
+                    Pos2::new(pair[1].x, rect.bottom()),
+                    Pos2::new(pair[0].x, rect.bottom()),
+                ],
+                Color32::from_gray(220),
+                Stroke::NONE,
+            ));
+        }
+        painter.hline(
+            rect.x_range(),
+            rect.center().y,
+            Stroke::new(1.0, Color32::GRAY),
+        );
+        painter.add(egui::Shape::line(
+            points.clone(),
+            Stroke::new(1.5, Color32::from_rgb(40, 110, 200)),
+        ));
+        if let Some(&selected) = selected.and_then(|i| points.get(i)) {
+            painter.vline(selected.x, rect.y_range(), Stroke::new(1.0, Color32::RED));
+            painter.circle_filled(selected, 3.0, Color32::RED);
+        }
+        let hovered = response
+            .hover_pos()
+            .map(|pos| (((pos.x - rect.left()) / step).round() as usize).min(analysis.len() - 1));
+        if let Some(index) = hovered {
+            response.clone().on_hover_text(trf(
+                "Move {}: {}",
+                &[
+                    &(index / 2 + 1),
+                    &format_score(analysis[index].white_score()),
+                ],
+            ));
+        }
+        hovered.filter(|_| response.clicked())
+    }
+
+    fn record_result(&mut self, win_state: WinState) {
+        if let Err(err) = savegame::record_finished(&self.game_pgn()) {
+            eprintln!("Could not save finished game: {}", err);
+        }
+        let Some(score) = &mut self.match_score else {
+            return;
+        };
+        score.record(win_state, self.human_color);
+        self.stats.record(
+            &score.opponent.opponent_label(),
+            win_state,
+            self.human_color,
+        );
+        if let Err(err) = self.stats.save_saved() {
+            eprintln!("Could not save statistics: {}", err);
+        }
+    }
+
+    fn save_as_window(&mut self, context: &Context) {
+        let Some(name) = &mut self.save_as else {
+            return;
+        };
+        let mut open = true;
+        let mut save = false;
+        egui::Window::new(tr("Save game as"))
+            .open(&mut open)
+            .resizable(false)
+            .collapsible(false)
+            .show(context, |ui| {
+                let response = ui.text_edit_singleline(name);
+                let entered =
+                    response.lost_focus() && ui.input(|input| input.key_pressed(egui::Key::Enter));
+                save = (ui
+                    .add_enabled(!name.trim().is_empty(), egui::Button::new(tr("Save")))
+                    .clicked()
+                    || entered)
+                    && !name.trim().is_empty();
+            });
+        if save {
+            let name = self.save_as.take().unwrap_or_default();
+            self.export_status = Some(match savegame::save_slot(&name, &self.game_pgn()) {
+                Ok(()) => trf("Saved \"{}\"", &[&name.trim()]),
+                Err(err) => trf("Could not save game: {}", &[&err]),
+            });
+        } else if !open {
+            self.save_as = None;
+        }
+    }
+
+    fn recent_games_menu(&mut self, ui: &mut Ui) {
+        let games = savegame::recent_games();
+        if games.is_empty() {
+            ui.label(tr("No saved or finished games yet"));
+            return;
+        }
+        let now = SystemTime::now();
+        for game in games {
+            let Ok(pgn) = game.load() else {
+                continue;
+            };
+            let age = now
+                .duration_since(game.modified)
+                .map_or(String::new(), |age| {
+                    format!(" {}", trf("({} ago)", &[&format_age(age)]))
+                });
+            let label = match &game.slot {
+                Some(slot) => format!("{}{}", slot, age),
+                None => format!(
+                    "{} vs {}, {}{}",
+                    pgn.tag("White").unwrap_or("?"),
+                    pgn.tag("Black").unwrap_or("?"),
+                    pgn.tag("Result").unwrap_or("*"),
+                    age
+                ),
+            };
+            if ui.button(label).clicked() {
+                self.load_pgn(&pgn);
+                self.export_status = Some(trf("Opened {}", &[&game.path.display()]));
+                ui.close_menu();
+            }
+        }
+    }
+
+    fn stats_window(&mut self, context: &Context) {
+        let mut reset = false;
+        egui::Window::new(tr("Statistics"))
+            .open(&mut self.show_stats)
+            .show(context, |ui| {
+                if self.stats.records.is_empty() {
+                    ui.label(tr("No finished games yet."));
+                    return;
+                }
+                egui::Grid::new("Statistics")
+                    .num_columns(6)
+                    .striped(true)
+                    .show(ui, |ui| {
+                        for heading in ["Opponent", "Games", "Wins", "Losses", "Draws", "Score"] {
+                            ui.strong(tr(heading));
+                        }
+                        ui.end_row();
+                        let total = self.stats.total();
+                        for (opponent, record) in self
+                            .stats
+                            .records
+                            .iter()
+                            .chain([(&tr("Total").to_string(), &total)])
+                        {
+                            ui.label(opponent);
+                            ui.label(record.games().to_string());
+                            ui.label(record.wins.to_string());
+                            ui.label(record.losses.to_string());
+                            ui.label(record.draws.to_string());
+                            ui.label(format!("{:.0}%", record.score_percent()));
+                            ui.end_row();
+                        }
+                    });
+                reset = ui.button(tr("Reset statistics")).clicked();
+            });
+        if reset {
+            self.stats = SessionStats::new();
+            if let Err(err) = self.stats.save_saved() {
+                eprintln!("Could not save statistics: {}", err);
+            }
+        }
+    }
+
+    fn set_zen_mode(&mut self, context: &Context, zen_mode: bool) {
+        self.zen_mode = zen_mode;
+        context.send_viewport_cmd(egui::ViewportCommand::Fullscreen(zen_mode));
+    }
+
+    fn view_shortcuts(&mut self, context: &Context) {
+        if context.wants_keyboard_input() {
+            return;
+        }
+        let (toggle, exit, zoom_in, zoom_out, reset) = context.input(|input| {
+            let plain = input.modifiers.is_none();
+            let bindings = &self.key_bindings;
+            (
+                binding_pressed(input, &bindings.zen_mode),
+                input.key_pressed(egui::Key::Escape) && self.staged_move.is_none(),
+                plain && binding_pressed(input, &bindings.zoom_in),
+                plain && binding_pressed(input, &bindings.zoom_out),
+                plain && binding_pressed(input, &bindings.reset_zoom),
+            )
+        });
+        if toggle || (exit && self.zen_mode) {
+            self.set_zen_mode(context, !self.zen_mode);
+        }
+        if zoom_in {
+            self.board_zoom = (self.board_zoom * BOARD_ZOOM_STEP).min(MAX_BOARD_ZOOM);
+        }
+        if zoom_out {
+            self.board_zoom = (self.board_zoom / BOARD_ZOOM_STEP).max(MIN_BOARD_ZOOM);
+        }
+        if reset {
+            self.board_zoom = 1.0;
+        }
+    }
+
+    fn zen_controls(&mut self, context: &Context) {
+        Area::new(Id::new("Zen controls"))
+            .anchor(Align2::RIGHT_TOP, Vec2::new(-8.0, 8.0))
+            .show(context, |ui| {
+                Frame::popup(ui.style()).show(ui, |ui| {
+                    self.staged_move_prompt(ui);
+                    if ui
+                        .button(tr("Exit zen mode"))
+                        .on_hover_text(binding_label(&self.key_bindings.zen_mode))
+                        .clicked()
+                    {
+                        self.set_zen_mode(ui.ctx(), false);
+                    }
+                });
+            });
+    }
+
+    fn update_orientation(&mut self) {
+        self.flipped = !self.imported
+            && self.tutorial.is_none()
+            && if self.hot_seat_game {
+                self.auto_flip && self.board.read().unwrap().turn == PieceColor::Black
+            } else {
+                self.human_color == PieceColor::Black
+            };
+    }
+
+    fn view_square(&self, pos: (usize, usize), width: usize, height: usize) -> (usize, usize) {
+        if self.flipped {
+            (width - 1 - pos.0, height - 1 - pos.1)
+        } else {
+            pos
+        }
+    }
+
+    fn threat_hints(
+        &self,
+        painter: &egui::Painter,
+        board: &ChessBoard,
+        origin: Pos2,
+        square_size: f32,
+    ) {
+        let player = if self.hot_seat_game || self.reviewing() {
+            board.turn
+        } else {
+            self.human_color
+        };
+        let theme = self.theme();
+        let square = |pos| {
+            let view = self.view_square(pos, board.width, board.height);
+            Rect::from_min_size(
+                origin + Vec2::new(view.0 as f32, view.1 as f32) * square_size,
+                Vec2::splat(square_size),
+            )
+        };
+        for pos in board.hanging_pieces(player) {
+            painter.rect_filled(square(pos), 0.0, theme.hanging);
+        }
+        if board.turn != player || self.staged_move.is_some() {
+            return;
+        }
+        let Some(previous) = self.previous_board() else {
+            return;
+        };
+        for pos in board.newly_attacked(&previous, player.opposite()) {
+            painter.circle_filled(square(pos).center(), square_size * 0.1, theme.threatened);
+        }
+    }
+
+    fn best_line_arrows(
+        &self,
+        painter: &egui::Painter,
+        board: &ChessBoard,
+        origin: Pos2,
+        square_size: f32,
+    ) {
+        let Some(session) = self.live_analysis.as_ref().filter(|s| s.board == *board) else {
+            return;
+        };
+        let Some(eval) = session.latest() else {
+            return;
+        };
+        let center = |pos: (usize, usize)| {
+            let pos = self.view_square(pos, board.width, board.height);
+            origin + Vec2::new(pos.0 as f32 + 0.5, pos.1 as f32 + 0.5) * square_size
+        };
+        let color = self.theme().best_line;
+        let arrow = |mv: &Move, width: f32, alpha: f32| {
+            let start = center(mv.original);
+            painter.arrow(
+                start,
+                center(mv.target) - start,
+                Stroke::new(square_size * width, color.gamma_multiply(alpha)),
+            );
+        };
+        for mv in eval.lines.iter().skip(1).filter_map(|line| line.pv.first()) {
+            arrow(mv, 0.06, 0.35);
+        }
+        for (i, mv) in eval.best().pv.iter().take(PV_ARROWS).enumerate().rev() {
+            arrow(mv, 0.12 - 0.03 * i as f32, 0.9 - 0.25 * i as f32);
+        }
     }
 
     fn chessboard(&mut self, ui: &mut Ui) -> egui::Response {
         if self.game_thread.as_ref().is_some_and(|x| x.is_finished()) {
-            self.win_state = self
-                .win_state
-                .take()
-                .or(self.game_thread.take().unwrap().join().ok());
+            let result = self.game_thread.take().unwrap().join().ok().flatten();
+            self.win_state = self.win_state.take().or(result);
+            if let Some(win_state) = result {
+                self.record_result(win_state);
+            }
             self.restart_modal_closed = false;
         }
-        let mut size = ui.available_size_before_wrap();
-        size = Vec2::splat(size.x.min(size.y));
+        let (width, height) = {
+            let board = self.board.read().unwrap();
+            (board.width, board.height)
+        };
+        let available = ui.available_size_before_wrap();
+        let square_size =
+            (available.x / width as f32).min(available.y / height as f32) * self.board_zoom;
+        let size = Vec2::new(width as f32, height as f32) * square_size;
+        self.rasterize_pieces((square_size * ui.ctx().pixels_per_point()).round() as u32);
+        if self.zen_mode && size.y < available.y {
+            ui.add_space((available.y - size.y) / 2.0);
+        }
         let (response, painter) = ui.allocate_painter(size, Sense::click());
 
-        let square_size = size.x / BOARD_SIZE as f32;
+        let theme = self.theme();
+        let announcement = self.announcement.clone();
+        ui.ctx().accesskit_node_builder(response.id, |node| {
+            node.set_role(accesskit::Role::Canvas);
+            node.set_label(trf("Chess board. {}", &[&announcement]));
+            node.set_live(accesskit::Live::Polite);
+        });
 
-        for row in 0..BOARD_SIZE {
-            for col in 0..BOARD_SIZE {
+        for row in 0..height {
+            for col in 0..width {
                 let color = if (row + col) % 2 == 0 {
-                    DARK_SQUARE
+                    theme.dark_square
                 } else {
-                    LIGHT_SQUARE
+                    theme.light_square
                 };
 
+                let view = self.view_square((col, row), width, height);
                 let rect = egui::Rect::from_min_size(
                     response.rect.min
-                        + Vec2::new(col as f32 * square_size, row as f32 * square_size),
+                        + Vec2::new(view.0 as f32 * square_size, view.1 as f32 * square_size),
                     Vec2::splat(square_size),
                 );
                 painter.rect_filled(rect, 0.0, color);
                 if self.selected_piece.is_some_and(|p| p == (col, row)) {
-                    painter.rect_filled(rect, 0.0, SELECTED_SQUARE);
+                    painter.rect_filled(rect, 0.0, theme.selected_square);
                 }
             }
         }
 
         for valid_move in &self.valid_moves {
-            let pos =
-                Vec2::new(valid_move.target.0 as f32, valid_move.target.1 as f32) * square_size;
+            let target = self.view_square(valid_move.target, width, height);
+            let pos = Vec2::new(target.0 as f32, target.1 as f32) * square_size;
             let rect = Rect::from_min_size(response.rect.min + pos, Vec2::splat(square_size));
-            painter.rect_filled(rect, 0.0, VALID_MOVE);
+            painter.rect_filled(rect, 0.0, theme.valid_move);
         }
 
-        let board = self.board.read().unwrap();
+        let mut board = self
+            .viewed_board()
+            .unwrap_or_else(|| self.board.read().unwrap().clone());
+        if let Some(staged) = self.staged_move.filter(|_| self.selected_move.is_none()) {
+            for square in [staged.original, staged.target] {
+                let square = self.view_square(square, width, height);
+                let pos = Vec2::new(square.0 as f32, square.1 as f32) * square_size;
+                let rect = Rect::from_min_size(response.rect.min + pos, Vec2::splat(square_size));
+                painter.rect_filled(rect, 0.0, theme.selected_square);
+            }
+            staged.perform(&mut board);
+        }
+        if let Some(premove) = self.premove.filter(|_| self.selected_move.is_none()) {
+            for square in [premove.original, premove.target] {
+                let square = self.view_square(square, width, height);
+                let pos = Vec2::new(square.0 as f32, square.1 as f32) * square_size;
+                let rect = Rect::from_min_size(response.rect.min + pos, Vec2::splat(square_size));
+                painter.rect_filled(rect, 0.0, theme.premove);
+            }
+        }
+        if self.show_threats && self.tutorial.is_none() {
+            self.threat_hints(&painter, &board, response.rect.min, square_size);
+        }
         for piece in board.pieces.iter().filter_map(|x| x.as_ref()) {
-            let pos = Vec2::new(piece.pos.0 as f32, piece.pos.1 as f32) * square_size;
+            let view = self.view_square(piece.pos, width, height);
+            let pos = Vec2::new(view.0 as f32, view.1 as f32) * square_size;
             let rect = Rect::from_min_size(response.rect.min + pos, Vec2::splat(square_size));
 
             egui::Image::new(self.get_image(piece.piece_type, piece.color)).paint_at(ui, rect);
         }
+        self.best_line_arrows(&painter, &board, response.rect.min, square_size);
 
         if let Some(pos) = self.promoting_piece {
             let options = self
@@ -176,10 +3668,11 @@ impl ChessApp {
                     }
                 });
 
+            let view = self.view_square(pos, width, height);
             let target_square = Rect::from_min_size(
                 Pos2::new(
-                    pos.0 as f32 * square_size + response.rect.min.x,
-                    pos.1 as f32 * square_size + response.rect.min.y,
+                    view.0 as f32 * square_size + response.rect.min.x,
+                    view.1 as f32 * square_size + response.rect.min.y,
                 ),
                 Vec2::splat(square_size),
             );
@@ -203,9 +3696,9 @@ impl ChessApp {
 
                             styles.spacing.button_padding = Vec2::ZERO;
                             let color = if i % 2 == 0 {
-                                DARK_SQUARE
+                                theme.dark_square
                             } else {
-                                LIGHT_SQUARE
+                                theme.light_square
                             };
                             styles.visuals.widgets.inactive.weak_bg_fill = color;
                             styles.visuals.widgets.hovered.weak_bg_fill =
@@ -213,11 +3706,11 @@ impl ChessApp {
                             styles.visuals.widgets.active.weak_bg_fill =
                                 color.lerp_to_gamma(Color32::DARK_GRAY, 0.25);
                             let all_widget_stypes = [
-                                styles.visuals.widgets.inactive,
-                                styles.visuals.widgets.hovered,
-                                styles.visuals.widgets.active,
+                                &mut styles.visuals.widgets.inactive,
+                                &mut styles.visuals.widgets.hovered,
+                                &mut styles.visuals.widgets.active,
                             ];
-                            for mut style in all_widget_stypes {
+                            for style in all_widget_stypes {
                                 style.expansion = 0.0;
                             }
 
@@ -232,85 +3725,162 @@ impl ChessApp {
                     })
                 });
 
-            if let Some(mv) = selected_move {
-                if let Some(channel) = self.channel(board.turn) {
-                    channel.send(*mv).unwrap();
-                    self.promoting_piece = None;
-                    self.selected_piece = None;
-                    self.valid_moves.clear();
-                }
-            }
-        } else if self.win_state.is_none() && response.clicked_by(PointerButton::Primary) {
-            if let Some(channel) = self.channel(board.turn) {
-                let pos = response.interact_pointer_pos().unwrap();
-                let col = ((pos.x - response.rect.min.x) / square_size).floor() as usize;
-                let row = ((pos.y - response.rect.min.y) / square_size).floor() as usize;
-
-                if col < BOARD_SIZE && row < BOARD_SIZE {
-                    let target_pos = (col, row);
-                    if self.selected_piece.is_none() {
-                        if let Some(piece) = board.piece_at(target_pos) {
-                            if piece.color == board.turn {
-                                self.selected_piece = Some((col, row));
-                                self.valid_moves = piece.valid_moves(&board, false).collect();
-                            }
-                        }
-                    } else {
-                        if let Some(valid_move) =
-                            self.valid_moves.iter().find(|&m| m.target == target_pos)
-                        {
-                            if let MoveType::Promotion(_) = valid_move.move_type {
-                                self.promoting_piece = Some(valid_move.target);
-                            } else {
-                                channel.send(*valid_move).unwrap();
-                                self.selected_piece = None;
-                                self.valid_moves.clear();
-                            }
-                        } else {
+            if let Some(&mv) = selected_move {
+                self.play_move(mv);
+            }
+        } else if response.clicked_by(PointerButton::Secondary) && self.premove.is_some() {
+            self.premove = None;
+            self.selected_piece = None;
+            self.valid_moves.clear();
+        } else if (self.reviewing() || self.selected_move.is_none())
+            && self.staged_move.is_none()
+            && response.clicked_by(PointerButton::Primary)
+            && (self.reviewing()
+                || self.tutorial.is_some()
+                || self.channel(board.turn).is_some()
+                || self.can_premove(&board))
+        {
+            let pos = response.interact_pointer_pos().unwrap();
+            let col = ((pos.x - response.rect.min.x) / square_size).floor() as usize;
+            let row = ((pos.y - response.rect.min.y) / square_size).floor() as usize;
+            let premoving = self.can_premove(&board);
+            let mover = if premoving {
+                self.human_color
+            } else {
+                board.turn
+            };
+
+            self.rejected_move = None;
+            if col < width && row < height {
+                let target_pos = self.view_square((col, row), width, height);
+                if let Some(selected) = self.selected_piece {
+                    if let Some(valid_move) =
+                        self.valid_moves.iter().find(|&m| m.target == target_pos)
+                    {
+                        if premoving {
+                            let queen = MoveType::Promotion(PieceType::Queen);
+                            self.premove = self
+                                .valid_moves
+                                .iter()
+                                .find(|m| m.target == target_pos && m.move_type == queen)
+                                .or(Some(valid_move))
+                                .copied();
                             self.selected_piece = None;
                             self.valid_moves.clear();
+                        } else if let MoveType::Promotion(_) = valid_move.move_type {
+                            self.promoting_piece = Some(valid_move.target);
+                        } else {
+                            self.play_move(*valid_move);
+                        }
+                    } else {
+                        if let Some(tutorial) = &mut self.tutorial {
+                            tutorial.mistake = selected != target_pos;
+                        } else if selected != target_pos && !premoving {
+                            self.rejected_move = legality::explain(&board, selected, target_pos)
+                                .map(|reason| (target_pos, reason));
                         }
+                        self.selected_piece = None;
+                        self.valid_moves.clear();
+                    }
+                } else if let Some(tutorial) = &mut self.tutorial {
+                    self.valid_moves = tutorial.allowed_moves(target_pos);
+                    tutorial.mistake = self.valid_moves.is_empty();
+                    if !tutorial.mistake {
+                        self.selected_piece = Some(target_pos);
+                    }
+                } else if let Some(piece) = board.piece_at(target_pos) {
+                    if piece.color == mover {
+                        self.premove = None;
+                        self.selected_piece = Some(target_pos);
+                        self.valid_moves = piece.legal_moves(&board).collect();
+                    } else {
+                        self.rejected_move = Some((target_pos, IllegalMove::WrongTurn(board.turn)));
                     }
                 }
             }
         }
 
-        response
+        let hovered = response
+            .hover_pos()
+            .map(|pos| {
+                (
+                    ((pos.x - response.rect.min.x) / square_size).floor() as usize,
+                    ((pos.y - response.rect.min.y) / square_size).floor() as usize,
+                )
+            })
+            .filter(|&(col, row)| col < width && row < height)
+            .map(|square| self.view_square(square, width, height));
+        match self.rejected_move {
+            Some((square, reason)) if self.explain_illegal_moves && hovered == Some(square) => {
+                response.on_hover_text_at_pointer(reason.to_string())
+            }
+            _ => response,
+        }
     }
 }
 
 impl eframe::App for ChessApp {
     fn update(&mut self, ctx: &egui::Context, _frame: &mut eframe::Frame) {
+        self.apply_accessibility(ctx);
+        self.update_clock(ctx);
+        self.play_premove();
+        self.update_ai_status(ctx);
+        self.update_orientation();
+        self.autoplay(ctx);
+        self.engine_settings_window(ctx);
+        self.search_inspector_window(ctx);
+        self.stats_window(ctx);
+        self.profiles_window(ctx);
+        self.save_as_window(ctx);
+        self.import_window(ctx);
+        self.poll_lichess_upload(ctx);
+        self.poll_opponent_start(ctx);
+        self.poll_engine_error();
+        self.handle_paste(ctx);
+        self.view_shortcuts(ctx);
+        if self.zen_mode {
+            self.update_move_list();
+            self.zen_controls(ctx);
+        } else {
+            egui::SidePanel::right("Side panel")
+                .resizable(false)
+                .exact_width(SIDE_PANEL_WIDTH)
+                .show(ctx, |ui| self.side_panel(ui));
+        }
         egui::CentralPanel::default().show(ctx, |ui| {
-            ui.vertical_centered(|ui| {
-                {
-                    ui.heading(format!(
-                        "{}'s turn",
-                        self.board.read().unwrap().turn.readable()
-                    ));
-                }
+            egui::ScrollArea::both().auto_shrink(false).show(ui, |ui| {
+                ui.vertical_centered(|ui| {
+                    Frame::canvas(ui.style())
+                        .stroke((0_f32, Color32::TRANSPARENT))
+                        .fill(Color32::TRANSPARENT)
+                        .show(ui, |ui| self.chessboard(ui));
 
-                Frame::canvas(ui.style())
-                    .stroke((0_f32, Color32::TRANSPARENT))
-                    .fill(Color32::TRANSPARENT)
-                    .show(ui, |ui| self.chessboard(ui));
-
-                if !self.restart_modal_closed {
-                    if self.win_state.is_some() {
+                    if !self.restart_modal_closed && self.win_state.is_some() {
                         Modal::new(Id::new("Winner modal")).show(ui.ctx(), |ui| {
                             ui.set_min_width(200.0);
-                            match self.win_state.as_ref().unwrap() {
-                                WinState::Checkmate(color) => {
-                                    ui.heading(format!("{} wins!", color.readable()));
+                            match self.win_state.as_ref().unwrap().winner() {
+                                Some(color) => {
+                                    ui.heading(trf("{} wins!", &[&tr(color.readable())]));
                                 }
-                                WinState::Stalemate => {
-                                    ui.heading("Draw!");
+                                None => {
+                                    ui.heading(tr("Draw!"));
                                 }
                             }
+                            self.variant_picker(ui, tr("Variant"));
+                            self.profile_picker(ui);
                             let play_again_clicked = egui::Sides::new().show(
                                 ui,
-                                |ui| ui.button("Play again").clicked(),
-                                |ui| ui.button("Close").clicked(),
+                                |ui| {
+                                    if ui
+                                        .button(tr("Rematch"))
+                                        .on_hover_text(tr("Play again with colors swapped"))
+                                        .clicked()
+                                    {
+                                        self.rematch(ui.ctx());
+                                    }
+                                    ui.button(tr("Play again")).clicked()
+                                },
+                                |ui| ui.button(tr("Close")).clicked(),
                             );
 
                             if play_again_clicked.0 {
@@ -322,21 +3892,46 @@ impl eframe::App for ChessApp {
                             }
                         });
                     }
-                }
+                });
             });
         });
+        self.finish_correspondence();
+        self.start_analysis(ctx);
+        self.update_live_analysis(ctx);
+        if let Some(rect) = ctx
+            .input(|input| input.viewport().inner_rect)
+            .filter(|_| !self.zen_mode)
+        {
+            self.window_size = Some([rect.width(), rect.height()]);
+        }
+        self.save_config(false);
+    }
+
+    fn on_exit(&mut self, _gl: Option<&eframe::glow::Context>) {
+        if self.correspondence && self.win_state.is_none() {
+            self.save_game();
+        }
+        self.stop_game();
+        self.save_config(true);
     }
 }
 
 fn main() -> Result<(), eframe::Error> {
-    println!(
-        "Running with thread pool size {}",
-        rayon::current_num_threads()
-    );
-    let options = eframe::NativeOptions::default();
+    let config = AppConfig::load_saved().unwrap_or_else(|err| {
+        eprintln!("Ignoring the saved settings: {}", err);
+        AppConfig::default()
+    });
+    config
+        .language
+        .unwrap_or_else(Language::from_env)
+        .set_current();
+    let mut options = eframe::NativeOptions::default();
+    if let Some(size) = config.window_size {
+        options.viewport = options.viewport.with_inner_size(size);
+    }
     eframe::run_native(
         "Chess Game",
         options,
-        Box::new(|cc| Ok(Box::new(ChessApp::new(cc)))),
+        Box::new(|cc| Ok(Box::new(ChessApp::new(cc, config)))),
     )
 }
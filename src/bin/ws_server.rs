@@ -0,0 +1,340 @@
+use std::{
+    collections::HashMap,
+    io::ErrorKind,
+    net::{TcpListener, TcpStream},
+    str::FromStr,
+    sync::{
+        mpsc::{self, Receiver, Sender},
+        Arc, Mutex,
+    },
+    thread,
+    time::Duration,
+};
+
+use chess::{
+    clock::{Clock, TimeControl},
+    game::timeout_result,
+    logic::{ChessBoard, Move, PieceColor, WinState},
+};
+use tungstenite::{Error as WsError, Message, WebSocket};
+
+const DEFAULT_PORT: u16 = 9090;
+const TICK_INTERVAL: Duration = Duration::from_millis(500);
+const POLL_INTERVAL: Duration = Duration::from_millis(50);
+
+struct Room {
+    board: ChessBoard,
+    clock: Clock,
+    seats: [Option<usize>; 2],
+    clients: HashMap<usize, Sender<String>>,
+    result: Option<WinState>,
+}
+
+fn seat_index(color: PieceColor) -> usize {
+    match color {
+        PieceColor::White => 0,
+        PieceColor::Black => 1,
+    }
+}
+
+impl Room {
+    fn new(time_control: TimeControl) -> Self {
+        Self {
+            board: ChessBoard::new(),
+            clock: Clock::new(time_control),
+            seats: [None; 2],
+            clients: HashMap::new(),
+            result: None,
+        }
+    }
+
+    fn broadcast(&mut self, message: &str) {
+        self.clients
+            .retain(|_, client| client.send(message.to_string()).is_ok());
+    }
+
+    fn send(&self, id: usize, message: &str) {
+        if let Some(client) = self.clients.get(&id) {
+            let _ = client.send(message.to_string());
+        }
+    }
+
+    fn clock_message(&self) -> String {
+        format!(
+            "clock {} {} {}",
+            self.clock.remaining(PieceColor::White).as_millis(),
+            self.clock.remaining(PieceColor::Black).as_millis(),
+            self.clock
+                .running()
+                .map_or("-".to_string(), |c| c.to_string())
+        )
+    }
+
+    fn seats_message(&self) -> String {
+        let seat =
+            |color| self.seats[seat_index(color)].map_or("open".to_string(), |id| id.to_string());
+        format!(
+            "seats {} {}",
+            seat(PieceColor::White),
+            seat(PieceColor::Black)
+        )
+    }
+
+    fn state_messages(&self) -> Vec<String> {
+        let mut messages = vec![
+            format!("board {}", self.board.to_fen()),
+            self.seats_message(),
+            self.clock_message(),
+        ];
+        if let Some(result) = self.result {
+            messages.push(format!("result {} {}", result.result(), result.reason()));
+        }
+        messages
+    }
+
+    fn finish(&mut self, result: WinState) {
+        self.clock.stop();
+        self.result = Some(result);
+        self.broadcast(&format!("result {} {}", result.result(), result.reason()));
+    }
+
+    fn flag(&mut self, color: PieceColor) {
+        self.finish(timeout_result(&self.board, color));
+    }
+
+    fn tick(&mut self) {
+        if self.result.is_some() {
+            return;
+        }
+        let Some(running) = self.clock.running() else {
+            return;
+        };
+        if self.clock.is_flagged(running) {
+            self.flag(running);
+        }
+        let clock = self.clock_message();
+        self.broadcast(&clock);
+    }
+
+    fn join(&mut self, id: usize, color: PieceColor) {
+        if self.seats[seat_index(color.opposite())] == Some(id) {
+            let other = color.opposite().readable();
+            return self.send(id, &format!("error already seated as {}", other));
+        }
+        let seat = &mut self.seats[seat_index(color)];
+        match seat {
+            Some(owner) if *owner != id => {
+                self.send(id, &format!("error {} seat is taken", color.readable()));
+                return;
+            }
+            _ => *seat = Some(id),
+        }
+        let seats = self.seats_message();
+        self.broadcast(&seats);
+        if self.seats.iter().all(Option::is_some)
+            && self.clock.running().is_none()
+            && self.result.is_none()
+        {
+            self.clock.start(self.board.turn);
+            let clock = self.clock_message();
+            self.broadcast(&clock);
+        }
+    }
+
+    fn play(&mut self, id: usize, text: &str) {
+        if self.result.is_some() {
+            return self.send(id, "error game is over");
+        }
+        let turn = self.board.turn;
+        if self.seats[seat_index(turn)] != Some(id) {
+            return self.send(id, "error not your turn");
+        }
+        if self.clock.running().is_none() {
+            return self.send(id, "error waiting for an opponent");
+        }
+        let Ok(mv) = Move::parse(text, &self.board) else {
+            return self.send(id, &format!("error illegal move {}", text));
+        };
+        let uci = mv.to_uci(&self.board);
+        let san = mv.to_san(&self.board);
+        if !self.clock.stop() {
+            return self.flag(turn);
+        }
+        mv.perform(&mut self.board);
+        self.broadcast(&format!("move {} {}", uci, san));
+        let board = format!("board {}", self.board.to_fen());
+        self.broadcast(&board);
+        match self.board.win_state() {
+            Some(result) => self.finish(result),
+            None => {
+                self.clock.start(self.board.turn);
+                let clock = self.clock_message();
+                self.broadcast(&clock);
+            }
+        }
+    }
+
+    fn new_game(&mut self, id: usize) {
+        if self.result.is_none() {
+            return self.send(id, "error game is still in progress");
+        }
+        if !self.seats.contains(&Some(id)) {
+            return self.send(id, "error only players can start a new game");
+        }
+        self.board = ChessBoard::new();
+        self.clock = Clock::with_time_controls(self.clock.time_controls().clone());
+        self.result = None;
+        self.seats.swap(0, 1);
+        for message in self.state_messages() {
+            self.broadcast(&message);
+        }
+        if self.seats.iter().all(Option::is_some) {
+            self.clock.start(self.board.turn);
+        }
+    }
+
+    fn leave(&mut self, id: usize) {
+        self.clients.remove(&id);
+        for color in [PieceColor::White, PieceColor::Black] {
+            if self.seats[seat_index(color)] == Some(id) {
+                self.seats[seat_index(color)] = None;
+                if self.result.is_none() && self.clock.running().is_some() {
+                    self.finish(WinState::Resignation(color.opposite()));
+                }
+            }
+        }
+        let seats = self.seats_message();
+        self.broadcast(&seats);
+    }
+
+    fn handle(&mut self, id: usize, line: &str) {
+        let mut words = line.split_whitespace();
+        match words.next() {
+            Some("join") => match words.next() {
+                Some("white") => self.join(id, PieceColor::White),
+                Some("black") => self.join(id, PieceColor::Black),
+                _ => self.send(id, "error expected join white|black"),
+            },
+            Some("move") => match words.next() {
+                Some(mv) => self.play(id, mv),
+                None => self.send(id, "error expected move MOVE"),
+            },
+            Some("resign") => {
+                let seat = [PieceColor::White, PieceColor::Black]
+                    .into_iter()
+                    .find(|&color| self.seats[seat_index(color)] == Some(id));
+                match seat {
+                    Some(color) if self.result.is_none() => {
+                        self.finish(WinState::Resignation(color.opposite()))
+                    }
+                    _ => self.send(id, "error nothing to resign"),
+                }
+            }
+            Some("new") => self.new_game(id),
+            Some("state") => {
+                for message in self.state_messages() {
+                    self.send(id, &message);
+                }
+            }
+            Some(command) => self.send(id, &format!("error unknown command {}", command)),
+            None => {}
+        }
+    }
+}
+
+fn serve_client(
+    room: Arc<Mutex<Room>>,
+    id: usize,
+    mut socket: WebSocket<TcpStream>,
+    outgoing: Receiver<String>,
+) {
+    loop {
+        match socket.read() {
+            Ok(Message::Text(text)) => {
+                for line in text.lines() {
+                    room.lock().unwrap().handle(id, line);
+                }
+            }
+            Ok(Message::Close(_)) => break,
+            Ok(_) => {}
+            Err(WsError::Io(err))
+                if matches!(err.kind(), ErrorKind::WouldBlock | ErrorKind::TimedOut) => {}
+            Err(_) => break,
+        }
+        let mut failed = false;
+        while let Ok(message) = outgoing.try_recv() {
+            if socket.send(Message::text(message)).is_err() {
+                failed = true;
+                break;
+            }
+        }
+        if failed {
+            break;
+        }
+    }
+    room.lock().unwrap().leave(id);
+}
+
+fn usage() -> ! {
+    eprintln!("Usage: ws_server [--host HOST] [--port PORT] [--tc BASE+INC]");
+    std::process::exit(2);
+}
+
+fn main() -> Result<(), Box<dyn std::error::Error>> {
+    let mut host = "127.0.0.1".to_string();
+    let mut port = DEFAULT_PORT;
+    let mut time_control = TimeControl::from_str("300+0").unwrap();
+    let mut args = std::env::args().skip(1);
+    while let Some(arg) = args.next() {
+        let mut value = || args.next().unwrap_or_else(|| usage());
+        match arg.as_str() {
+            "--host" => host = value(),
+            "--port" => port = value().parse()?,
+            "--tc" => {
+                time_control = value()
+                    .parse()
+                    .map_err(|_| "Invalid time control, expected BASE+INC in seconds")?
+            }
+            _ => usage(),
+        }
+    }
+
+    let room = Arc::new(Mutex::new(Room::new(time_control)));
+    let ticker = room.clone();
+    thread::spawn(move || loop {
+        thread::sleep(TICK_INTERVAL);
+        ticker.lock().unwrap().tick();
+    });
+
+    let listener = TcpListener::bind((host.as_str(), port))?;
+    println!("Listening on ws://{}:{}", host, port);
+    for (id, stream) in listener.incoming().enumerate() {
+        let Ok(stream) = stream else {
+            continue;
+        };
+        let room = room.clone();
+        thread::spawn(move || {
+            let Ok(socket) = tungstenite::accept(stream) else {
+                return;
+            };
+            if socket
+                .get_ref()
+                .set_read_timeout(Some(POLL_INTERVAL))
+                .is_err()
+            {
+                return;
+            }
+            let (sender, receiver) = mpsc::channel();
+            {
+                let mut room = room.lock().unwrap();
+                let _ = sender.send(format!("welcome {}", id));
+                for message in room.state_messages() {
+                    let _ = sender.send(message);
+                }
+                room.clients.insert(id, sender);
+            }
+            serve_client(room, id, socket, receiver);
+        });
+    }
+    Ok(())
+}
@@ -0,0 +1,172 @@
+use std::{collections::HashMap, fs, io, path::Path};
+
+use rand::Rng;
+
+use crate::{
+    config,
+    logic::{ChessBoard, Move, WinState},
+};
+
+pub const LEARNING_FILE: &str = "book_learning.txt";
+const DEFAULT_WEIGHT: u32 = 100;
+const WIN_REWARD: u32 = 20;
+const LOSS_PENALTY: u32 = 20;
+const LEARNING_PLIES: usize = 20;
+pub const MAX_BOOK_VARIETY: u32 = 100;
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct BookEntry {
+    pub mv: String,
+    pub weight: u32,
+}
+
+#[derive(Clone, Debug)]
+pub struct OpeningBook {
+    entries: HashMap<u64, Vec<BookEntry>>,
+    pub max_ply: Option<usize>,
+    pub variety: u32,
+}
+
+impl Default for OpeningBook {
+    fn default() -> Self {
+        Self {
+            entries: HashMap::new(),
+            max_ply: None,
+            variety: MAX_BOOK_VARIETY,
+        }
+    }
+}
+
+impl OpeningBook {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn load(path: &Path) -> io::Result<Self> {
+        let mut book = Self::new();
+        for line in fs::read_to_string(path)?.lines() {
+            let mut fields = line.split_whitespace();
+            let (Some(key), Some(mv), Some(weight)) = (fields.next(), fields.next(), fields.next())
+            else {
+                continue;
+            };
+            let (Ok(key), Ok(weight)) = (u64::from_str_radix(key, 16), weight.parse()) else {
+                continue;
+            };
+            book.entries.entry(key).or_default().push(BookEntry {
+                mv: mv.to_string(),
+                weight,
+            });
+        }
+        Ok(book)
+    }
+
+    pub fn save(&self, path: &Path) -> io::Result<()> {
+        if let Some(parent) = path.parent() {
+            fs::create_dir_all(parent)?;
+        }
+        let mut keys = self.entries.keys().collect::<Vec<_>>();
+        keys.sort();
+        let mut out = String::new();
+        for key in keys {
+            for entry in &self.entries[key] {
+                out.push_str(&format!("{:016x} {} {}\n", key, entry.mv, entry.weight));
+            }
+        }
+        fs::write(path, out)
+    }
+
+    pub fn load_learning() -> Self {
+        config::config_file(LEARNING_FILE)
+            .and_then(|path| Self::load(&path).ok())
+            .unwrap_or_default()
+    }
+
+    pub fn save_learning(&self) -> io::Result<()> {
+        match config::config_file(LEARNING_FILE) {
+            Some(path) => self.save(&path),
+            None => Ok(()),
+        }
+    }
+
+    pub fn len(&self) -> usize {
+        self.entries.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.entries.is_empty()
+    }
+
+    pub fn entries(&self, board: &ChessBoard) -> &[BookEntry] {
+        self.entries
+            .get(&board.zobrist_key())
+            .map_or(&[], Vec::as_slice)
+    }
+
+    pub fn pick(&self, board: &ChessBoard, rng: &mut impl Rng) -> Option<Move> {
+        if self
+            .max_ply
+            .is_some_and(|max_ply| board.moves_made >= max_ply)
+        {
+            return None;
+        }
+        let candidates = self
+            .entries(board)
+            .iter()
+            .filter(|entry| entry.weight >= DEFAULT_WEIGHT)
+            .filter_map(|entry| {
+                let mv = Move::from_str(&entry.mv, board).ok()?;
+                mv.is_legal(board).then_some((mv, entry.weight))
+            })
+            .collect::<Vec<_>>();
+        if self.variety == 0 {
+            return candidates
+                .iter()
+                .max_by_key(|(_, weight)| *weight)
+                .map(|(mv, _)| *mv);
+        }
+        let sharpness = MAX_BOOK_VARIETY as f64 / self.variety.min(MAX_BOOK_VARIETY) as f64;
+        let weights = candidates
+            .iter()
+            .map(|(_, weight)| (*weight as f64 / DEFAULT_WEIGHT as f64).powf(sharpness))
+            .collect::<Vec<_>>();
+        let total = weights.iter().sum::<f64>();
+        if total <= 0.0 {
+            return None;
+        }
+        let mut choice = rng.random_range(0.0..total);
+        for ((mv, _), weight) in candidates.into_iter().zip(weights) {
+            if choice < weight {
+                return Some(mv);
+            }
+            choice -= weight;
+        }
+        None
+    }
+
+    pub fn learn(&mut self, start: &ChessBoard, moves: &[Move], result: WinState) {
+        let mut board = start.clone();
+        for mv in moves.iter().take(LEARNING_PLIES) {
+            let mover = board.turn;
+            let entries = self.entries.entry(board.zobrist_key()).or_default();
+            let uci = mv.to_uci(&board);
+            let index = match entries.iter().position(|entry| entry.mv == uci) {
+                Some(index) => index,
+                None => {
+                    entries.push(BookEntry {
+                        mv: uci,
+                        weight: DEFAULT_WEIGHT,
+                    });
+                    entries.len() - 1
+                }
+            };
+            let entry = &mut entries[index];
+            match result.winner() {
+                Some(winner) if winner == mover => entry.weight += WIN_REWARD,
+                Some(_) => entry.weight = entry.weight.saturating_sub(LOSS_PENALTY).max(1),
+                None => {}
+            }
+            mv.perform(&mut board);
+        }
+    }
+}
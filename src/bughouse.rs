@@ -0,0 +1,270 @@
+use std::{
+    collections::HashSet,
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        mpsc, Arc, RwLock,
+    },
+    thread,
+    time::Duration,
+};
+
+use crate::{
+    ai::AI,
+    game::{Player, PlayerAction},
+    logic::{ChessBoard, Move, MoveType, PieceColor, PieceType, WinState, PIECE_TYPE_COUNT},
+};
+
+const WAIT_INTERVAL: Duration = Duration::from_millis(50);
+const DROP_SEARCH_DEPTH: usize = 1;
+
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Hash)]
+pub struct Pocket([u8; PIECE_TYPE_COUNT]);
+
+impl Pocket {
+    pub fn add(&mut self, piece_type: PieceType) {
+        self.0[piece_type.index()] += 1;
+    }
+
+    pub fn take(&mut self, piece_type: PieceType) -> bool {
+        let count = &mut self.0[piece_type.index()];
+        if *count == 0 {
+            return false;
+        }
+        *count -= 1;
+        true
+    }
+
+    pub fn count(&self, piece_type: PieceType) -> u8 {
+        self.0[piece_type.index()]
+    }
+
+    pub fn piece_types(&self) -> Vec<PieceType> {
+        PieceType::all()
+            .filter(|&piece_type| self.count(piece_type) > 0)
+            .collect()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.0.iter().all(|&count| count == 0)
+    }
+}
+
+pub type Pockets = [[Pocket; 2]; 2];
+
+pub fn team(board: usize, color: PieceColor) -> usize {
+    board ^ color as usize
+}
+
+pub fn partner(board: usize, color: PieceColor) -> (usize, PieceColor) {
+    (1 - board, color.opposite())
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub struct BughouseResult {
+    pub board: usize,
+    pub win_state: WinState,
+}
+
+impl BughouseResult {
+    pub fn winning_team(&self) -> Option<usize> {
+        self.win_state.winner().map(|color| team(self.board, color))
+    }
+}
+
+pub struct BughouseGame {
+    pub boards: [Arc<RwLock<ChessBoard>>; 2],
+    pub moves: [Arc<RwLock<Vec<Move>>>; 2],
+    pub pockets: Arc<RwLock<Pockets>>,
+    pub players: [[Box<dyn Player>; 2]; 2],
+    pub on_update_func: Arc<dyn Fn() + Send + Sync + 'static>,
+}
+
+impl BughouseGame {
+    pub fn new(
+        players: [[Box<dyn Player>; 2]; 2],
+        pockets: Arc<RwLock<Pockets>>,
+        on_update_func: impl Fn() + Send + Sync + 'static,
+    ) -> Self {
+        Self {
+            boards: [
+                Arc::new(RwLock::new(ChessBoard::new())),
+                Arc::new(RwLock::new(ChessBoard::new())),
+            ],
+            moves: [
+                Arc::new(RwLock::new(Vec::new())),
+                Arc::new(RwLock::new(Vec::new())),
+            ],
+            pockets,
+            players,
+            on_update_func: Arc::new(on_update_func),
+        }
+    }
+
+    pub fn create_game_thread(self) -> thread::JoinHandle<Option<BughouseResult>> {
+        thread::spawn(move || self.play())
+    }
+
+    pub fn play(self) -> Option<BughouseResult> {
+        let finished = Arc::new(AtomicBool::new(false));
+        let (result_tx, result_rx) = mpsc::channel();
+        for (index, mut players) in self.players.into_iter().enumerate() {
+            for player in &mut players {
+                player.set_cancel(finished.clone());
+            }
+            let mut linked = LinkedBoard {
+                index,
+                board: self.boards[index].clone(),
+                moves: self.moves[index].clone(),
+                pockets: self.pockets.clone(),
+                finished: finished.clone(),
+                promoted: HashSet::new(),
+                on_update_func: self.on_update_func.clone(),
+            };
+            let result_tx = result_tx.clone();
+            thread::spawn(move || {
+                if let Some(result) = linked.play(players) {
+                    let _ = result_tx.send(result);
+                }
+            });
+        }
+        drop(result_tx);
+        let result = result_rx.recv().ok();
+        finished.store(true, Ordering::Relaxed);
+        result
+    }
+}
+
+struct LinkedBoard {
+    index: usize,
+    board: Arc<RwLock<ChessBoard>>,
+    moves: Arc<RwLock<Vec<Move>>>,
+    pockets: Arc<RwLock<Pockets>>,
+    finished: Arc<AtomicBool>,
+    // Squares holding promoted pieces, which go to the partner's pocket as pawns when captured.
+    promoted: HashSet<(usize, usize)>,
+    on_update_func: Arc<dyn Fn() + Send + Sync + 'static>,
+}
+
+impl LinkedBoard {
+    fn play(&mut self, mut players: [Box<dyn Player>; 2]) -> Option<BughouseResult> {
+        loop {
+            if self.finished.load(Ordering::Relaxed) {
+                return None;
+            }
+            let turn = self.board.read().unwrap().turn;
+            if !self.can_move() {
+                let board = self.board.read().unwrap();
+                if board.is_in_check(turn) {
+                    return Some(BughouseResult {
+                        board: self.index,
+                        win_state: WinState::Checkmate(turn.opposite()),
+                    });
+                }
+                drop(board);
+                thread::sleep(WAIT_INTERVAL);
+                continue;
+            }
+            let player = &mut players[turn as usize];
+            let mv = match player.get_action(self.board.clone(), false) {
+                PlayerAction::Move(mv) => mv,
+                PlayerAction::Resign => {
+                    return Some(BughouseResult {
+                        board: self.index,
+                        win_state: WinState::Resignation(turn.opposite()),
+                    })
+                }
+                PlayerAction::OfferDraw | PlayerAction::AcceptDraw => {
+                    player.get_move(self.board.clone())
+                }
+                PlayerAction::Abort => {
+                    self.finished.store(true, Ordering::Relaxed);
+                    return None;
+                }
+            };
+            if self.finished.load(Ordering::Relaxed) {
+                return None;
+            }
+            self.apply(mv);
+        }
+    }
+
+    fn can_move(&self) -> bool {
+        let board = self.board.read().unwrap();
+        let pocket = self.pockets.read().unwrap()[self.index][board.turn as usize];
+        board.has_legal_move() || !board.legal_drops(&pocket.piece_types()).is_empty()
+    }
+
+    fn apply(&mut self, mv: Move) {
+        let mut board = self.board.write().unwrap();
+        let turn = board.turn;
+        if let MoveType::Drop(piece_type) = mv.move_type {
+            let mut pockets = self.pockets.write().unwrap();
+            if !mv.is_legal(&board) || !pockets[self.index][turn as usize].take(piece_type) {
+                return;
+            }
+        } else if !board.legal_move_list().contains(&mv) {
+            return;
+        }
+        let captured = match mv.move_type {
+            MoveType::EnPassant => Some(PieceType::Pawn),
+            MoveType::Drop(_) => None,
+            _ if self.promoted.remove(&mv.target) => Some(PieceType::Pawn),
+            _ => board.piece_at(mv.target).map(|piece| piece.piece_type),
+        };
+        if matches!(mv.move_type, MoveType::Promotion(_)) || self.promoted.remove(&mv.original) {
+            self.promoted.insert(mv.target);
+        }
+        if let Some(piece_type) = captured {
+            let (partner_board, partner_color) = partner(self.index, turn);
+            self.pockets.write().unwrap()[partner_board][partner_color as usize].add(piece_type);
+        }
+        mv.perform(&mut board);
+        self.moves.write().unwrap().push(mv);
+        (self.on_update_func)();
+    }
+}
+
+pub struct PocketAI {
+    pub ai: AI,
+    pub board: usize,
+    pub pockets: Arc<RwLock<Pockets>>,
+}
+
+impl PocketAI {
+    pub fn new(ai: AI, board: usize, pockets: Arc<RwLock<Pockets>>) -> Self {
+        Self { ai, board, pockets }
+    }
+
+    fn drop_score(&mut self, board: &ChessBoard, drop: Move) -> f64 {
+        let mut child = board.clone();
+        drop.perform(&mut child);
+        match self.ai.analyze(&child, DROP_SEARCH_DEPTH).first() {
+            Some(&(_, score)) => -score,
+            None if child.is_in_check(child.turn) => f64::INFINITY,
+            None => 0.0,
+        }
+    }
+}
+
+impl Player for PocketAI {
+    fn get_move(&mut self, board: Arc<RwLock<ChessBoard>>) -> Move {
+        let board = board.read().unwrap().clone();
+        let pocket = self.pockets.read().unwrap()[self.board][board.turn as usize];
+        let mut best = if board.has_legal_move() {
+            self.ai.think(&board).first().copied()
+        } else {
+            None
+        };
+        for drop in board.legal_drops(&pocket.piece_types()) {
+            let score = self.drop_score(&board, drop);
+            if best.is_none_or(|(_, best_score)| score > best_score) {
+                best = Some((drop, score));
+            }
+        }
+        best.expect("Board should always have valid moves").0
+    }
+
+    fn set_cancel(&mut self, cancel: Arc<AtomicBool>) {
+        self.ai.set_cancel(cancel);
+    }
+}
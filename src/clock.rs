@@ -0,0 +1,405 @@
+use std::{
+    fmt::Display,
+    str::FromStr,
+    time::{Duration, Instant},
+};
+
+use crate::logic::PieceColor;
+
+const SECONDS_PER_DAY: Duration = Duration::from_secs(24 * 60 * 60);
+
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Hash)]
+pub enum TimingMethod {
+    #[default]
+    Increment,
+    SimpleDelay,
+    Bronstein,
+}
+
+impl TimingMethod {
+    pub fn readable(&self) -> &'static str {
+        match self {
+            TimingMethod::Increment => "Fischer increment",
+            TimingMethod::SimpleDelay => "Simple delay",
+            TimingMethod::Bronstein => "Bronstein delay",
+        }
+    }
+
+    fn suffix(&self) -> char {
+        match self {
+            TimingMethod::Increment => '+',
+            TimingMethod::SimpleDelay => 'd',
+            TimingMethod::Bronstein => 'b',
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub struct TimeStage {
+    pub moves: Option<u32>,
+    pub time: Duration,
+}
+
+#[derive(Clone, Debug, PartialEq, Eq, Hash)]
+pub struct TimeControl {
+    pub base: Duration,
+    pub increment: Duration,
+    pub method: TimingMethod,
+    pub moves: Option<u32>,
+    pub stages: Vec<TimeStage>,
+}
+
+impl TimeControl {
+    pub fn new(base: Duration, increment: Duration) -> Self {
+        Self {
+            base,
+            increment,
+            method: TimingMethod::Increment,
+            moves: None,
+            stages: Vec::new(),
+        }
+    }
+
+    pub fn days_per_move(days: u32) -> Self {
+        Self {
+            moves: Some(1),
+            ..Self::new(SECONDS_PER_DAY * days, Duration::ZERO)
+        }
+    }
+
+    pub fn is_per_move(&self) -> bool {
+        self.moves == Some(1) && self.stages.is_empty()
+    }
+
+    pub fn after_move(&self, remaining: Duration, spent: Duration, moves_made: u32) -> Duration {
+        let charged = match self.method {
+            TimingMethod::SimpleDelay => spent.saturating_sub(self.increment),
+            TimingMethod::Increment | TimingMethod::Bronstein => spent,
+        };
+        let Some(left) = remaining
+            .checked_sub(charged)
+            .filter(|left| !left.is_zero())
+        else {
+            return Duration::ZERO;
+        };
+        if self.is_per_move() {
+            return self.base;
+        }
+        let bonus = match self.method {
+            TimingMethod::Increment => self.increment,
+            TimingMethod::SimpleDelay => Duration::ZERO,
+            TimingMethod::Bronstein => spent.min(self.increment),
+        };
+        left + bonus + self.stage_bonus(moves_made)
+    }
+
+    pub fn spent(&self, previous: Duration, remaining: Duration, moves_made: u32) -> Duration {
+        if self.is_per_move() {
+            return previous.saturating_sub(remaining);
+        }
+        let credit = previous + self.stage_bonus(moves_made);
+        match self.method {
+            TimingMethod::Increment => (credit + self.increment).saturating_sub(remaining),
+            TimingMethod::SimpleDelay | TimingMethod::Bronstein => {
+                match credit.saturating_sub(remaining) {
+                    lost if lost.is_zero() => lost,
+                    lost => lost + self.increment,
+                }
+            }
+        }
+    }
+
+    pub fn moves_to_go(&self, moves_made: u32) -> Option<u32> {
+        self.next_stage(moves_made)
+            .map(|(boundary, _)| boundary - moves_made)
+    }
+
+    fn stage_bonus(&self, moves_made: u32) -> Duration {
+        moves_made
+            .checked_sub(1)
+            .and_then(|before| self.next_stage(before))
+            .filter(|&(boundary, _)| boundary == moves_made)
+            .map_or(Duration::ZERO, |(_, time)| time)
+    }
+
+    fn next_stage(&self, moves_made: u32) -> Option<(u32, Duration)> {
+        let mut boundary = 0;
+        let mut period = (self.moves, self.base);
+        let mut stages = self.stages.iter();
+        loop {
+            boundary += period.0.filter(|&moves| moves > 0)?;
+            let next = stages
+                .next()
+                .map_or(period, |stage| (stage.moves, stage.time));
+            if boundary > moves_made {
+                return Some((boundary, next.1));
+            }
+            period = next;
+        }
+    }
+}
+
+impl Display for TimeControl {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let periods = std::iter::once((self.moves, self.base))
+            .chain(self.stages.iter().map(|stage| (stage.moves, stage.time)));
+        for (i, (moves, time)) in periods.enumerate() {
+            if i > 0 {
+                write!(f, ":")?;
+            }
+            if let Some(moves) = moves {
+                write!(f, "{}/", moves)?;
+            }
+            write!(f, "{}", time.as_secs_f64())?;
+            if !self.increment.is_zero() || self.method != TimingMethod::Increment {
+                write!(
+                    f,
+                    "{}{}",
+                    self.method.suffix(),
+                    self.increment.as_secs_f64()
+                )?;
+            }
+        }
+        Ok(())
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct LowTimeWarning {
+    pub threshold: Duration,
+    pub alert: bool,
+    pub premoves: bool,
+}
+
+impl LowTimeWarning {
+    pub fn for_time_control(time_control: &TimeControl) -> Self {
+        Self {
+            threshold: (time_control.base / 10)
+                .clamp(Duration::from_secs(10), Duration::from_secs(60)),
+            alert: true,
+            premoves: time_control.base < Duration::from_secs(600),
+        }
+    }
+}
+
+#[derive(Debug, PartialEq, Eq)]
+pub struct ParseTimeControlError;
+
+impl FromStr for TimeControl {
+    type Err = ParseTimeControlError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let seconds = |s: &str| {
+            s.parse::<f64>()
+                .ok()
+                .filter(|secs| secs.is_finite() && *secs >= 0.0)
+                .map(Duration::from_secs_f64)
+                .ok_or(ParseTimeControlError)
+        };
+        let mut periods = Vec::new();
+        let mut timing = None;
+        for period in s.split(':') {
+            let (moves, rest) = match period.split_once('/') {
+                Some((moves, rest)) => (
+                    Some(
+                        moves
+                            .parse::<u32>()
+                            .ok()
+                            .filter(|&moves| moves > 0)
+                            .ok_or(ParseTimeControlError)?,
+                    ),
+                    rest,
+                ),
+                None => (None, period),
+            };
+            let (time, bonus) = match rest.find(['+', 'd', 'b']) {
+                Some(at) => {
+                    let method = match &rest[at..at + 1] {
+                        "+" => TimingMethod::Increment,
+                        "d" => TimingMethod::SimpleDelay,
+                        _ => TimingMethod::Bronstein,
+                    };
+                    (&rest[..at], Some((method, seconds(&rest[at + 1..])?)))
+                }
+                None => (rest, None),
+            };
+            let time = seconds(time)?;
+            if time.is_zero() && periods.is_empty() {
+                return Err(ParseTimeControlError);
+            }
+            if let Some(bonus) = bonus {
+                if timing.is_some_and(|timing| timing != bonus) {
+                    return Err(ParseTimeControlError);
+                }
+                timing = Some(bonus);
+            }
+            periods.push(TimeStage { moves, time });
+        }
+        let (method, increment) = timing.unwrap_or_default();
+        let first = periods.remove(0);
+        Ok(Self {
+            base: first.time,
+            increment,
+            method,
+            moves: first.moves,
+            stages: periods,
+        })
+    }
+}
+
+#[derive(Clone, Debug)]
+pub struct Clock {
+    time_controls: [TimeControl; 2],
+    remaining: [Duration; 2],
+    moves_made: [u32; 2],
+    running: Option<(PieceColor, Instant)>,
+    carried: Duration,
+}
+
+impl Clock {
+    pub fn new(time_control: TimeControl) -> Self {
+        Self::with_time_controls([time_control.clone(), time_control])
+    }
+
+    pub fn with_time_controls(time_controls: [TimeControl; 2]) -> Self {
+        Self {
+            remaining: [time_controls[0].base, time_controls[1].base],
+            moves_made: [0; 2],
+            time_controls,
+            running: None,
+            carried: Duration::ZERO,
+        }
+    }
+
+    pub fn time_control(&self, color: PieceColor) -> &TimeControl {
+        &self.time_controls[Self::idx(color)]
+    }
+
+    pub fn time_controls(&self) -> &[TimeControl; 2] {
+        &self.time_controls
+    }
+
+    pub fn has_odds(&self) -> bool {
+        self.time_controls[0] != self.time_controls[1]
+    }
+
+    fn idx(color: PieceColor) -> usize {
+        match color {
+            PieceColor::White => 0,
+            PieceColor::Black => 1,
+        }
+    }
+
+    pub fn start(&mut self, color: PieceColor) {
+        self.resume(color, Duration::ZERO);
+    }
+
+    pub fn resume(&mut self, color: PieceColor, elapsed: Duration) {
+        self.stop();
+        self.running = Some((color, Instant::now()));
+        self.carried = elapsed;
+    }
+
+    pub fn stop(&mut self) -> bool {
+        let Some((color, started)) = self.running.take() else {
+            return true;
+        };
+        let elapsed = self.carried + started.elapsed();
+        self.carried = Duration::ZERO;
+        let idx = Self::idx(color);
+        self.moves_made[idx] += 1;
+        self.remaining[idx] =
+            self.time_controls[idx].after_move(self.remaining[idx], elapsed, self.moves_made[idx]);
+        !self.remaining[idx].is_zero()
+    }
+
+    pub fn running(&self) -> Option<PieceColor> {
+        self.running.map(|(color, _)| color)
+    }
+
+    pub fn remaining(&self, color: PieceColor) -> Duration {
+        let remaining = self.remaining[Self::idx(color)];
+        match self.running {
+            Some((running, started)) if running == color => {
+                let mut elapsed = self.carried + started.elapsed();
+                let time_control = self.time_control(color);
+                if time_control.method == TimingMethod::SimpleDelay {
+                    elapsed = elapsed.saturating_sub(time_control.increment);
+                }
+                remaining.saturating_sub(elapsed)
+            }
+            _ => remaining,
+        }
+    }
+
+    pub fn moves_made(&self, color: PieceColor) -> u32 {
+        self.moves_made[Self::idx(color)]
+    }
+
+    pub fn set_moves_made(&mut self, color: PieceColor, moves_made: u32) {
+        self.moves_made[Self::idx(color)] = moves_made;
+    }
+
+    pub fn moves_to_go(&self, color: PieceColor) -> Option<u32> {
+        self.time_control(color).moves_to_go(self.moves_made(color))
+    }
+
+    pub fn set_remaining(&mut self, color: PieceColor, remaining: Duration) {
+        self.remaining[Self::idx(color)] = remaining;
+        if let Some((running, _)) = self.running {
+            if running == color {
+                self.running = Some((color, Instant::now()));
+                self.carried = Duration::ZERO;
+            }
+        }
+    }
+
+    pub fn is_flagged(&self, color: PieceColor) -> bool {
+        self.remaining(color).is_zero()
+    }
+
+    pub fn is_low(&self, color: PieceColor, threshold: Duration) -> bool {
+        let remaining = self.remaining(color);
+        !remaining.is_zero() && remaining <= threshold
+    }
+}
+
+pub fn format_duration(duration: Duration) -> String {
+    let secs = duration.as_secs();
+    if secs >= SECONDS_PER_DAY.as_secs() {
+        format!(
+            "{}d {}:{:02}",
+            secs / SECONDS_PER_DAY.as_secs(),
+            secs / 3600 % 24,
+            secs / 60 % 60
+        )
+    } else if secs >= 3600 {
+        format!("{}:{:02}:{:02}", secs / 3600, secs / 60 % 60, secs % 60)
+    } else if secs >= 20 {
+        format!("{}:{:02}", secs / 60, secs % 60)
+    } else {
+        format!(
+            "{}:{:02}.{}",
+            secs / 60,
+            secs % 60,
+            duration.subsec_millis() / 100
+        )
+    }
+}
+
+pub fn format_pgn_clock(duration: Duration) -> String {
+    let secs = duration.as_secs();
+    format!("{}:{:02}:{:02}", secs / 3600, secs / 60 % 60, secs % 60)
+}
+
+pub fn parse_pgn_clock(s: &str) -> Option<Duration> {
+    let mut secs = 0.0;
+    for part in s.trim().split(':') {
+        let value = part.parse::<f64>().ok()?;
+        if !value.is_finite() || value < 0.0 {
+            return None;
+        }
+        secs = secs * 60.0 + value;
+    }
+    Some(Duration::from_secs_f64(secs))
+}
@@ -0,0 +1,395 @@
+use std::{
+    collections::HashMap,
+    env,
+    fmt::Display,
+    fs, io,
+    path::{Path, PathBuf},
+    time::Duration,
+};
+
+use strum::IntoEnumIterator;
+use strum_macros::EnumIter;
+use toml_edit::{value, Array, DocumentMut, Item, Table};
+
+use crate::{
+    clock::{LowTimeWarning, TimeControl},
+    i18n::Language,
+    notation::{MoveNotation, SanLanguage},
+    settings::EngineSettings,
+    theme::DEFAULT_THEME,
+};
+
+const APP_DIR: &str = "chess-rs";
+const EXPORT_DIR: &str = "exports";
+pub const APP_CONFIG_FILE: &str = "config.toml";
+
+pub fn config_dir() -> Option<PathBuf> {
+    let base = if cfg!(windows) {
+        env::var_os("APPDATA").map(PathBuf::from)
+    } else if cfg!(target_os = "macos") {
+        env::var_os("HOME").map(|home| PathBuf::from(home).join("Library/Application Support"))
+    } else {
+        env::var_os("XDG_CONFIG_HOME")
+            .map(PathBuf::from)
+            .or_else(|| env::var_os("HOME").map(|home| PathBuf::from(home).join(".config")))
+    };
+    base.map(|base| base.join(APP_DIR))
+}
+
+pub fn config_file(name: &str) -> Option<PathBuf> {
+    config_dir().map(|dir| dir.join(name))
+}
+
+pub fn export_dir() -> Option<PathBuf> {
+    config_file(EXPORT_DIR)
+}
+
+#[derive(Debug)]
+pub enum ConfigError {
+    Io(io::Error),
+    InvalidToml(String),
+}
+
+impl Display for ConfigError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            ConfigError::Io(err) => write!(f, "{}", err),
+            ConfigError::InvalidToml(err) => write!(f, "invalid TOML: {}", err),
+        }
+    }
+}
+
+impl std::error::Error for ConfigError {}
+
+impl From<io::Error> for ConfigError {
+    fn from(err: io::Error) -> Self {
+        ConfigError::Io(err)
+    }
+}
+
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, EnumIter)]
+pub enum ConfirmMoves {
+    Never,
+    #[default]
+    SlowGames,
+    Always,
+}
+
+impl ConfirmMoves {
+    pub fn readable(&self) -> &'static str {
+        match self {
+            ConfirmMoves::Never => "Never",
+            ConfirmMoves::SlowGames => "Untimed and 10+ min games",
+            ConfirmMoves::Always => "Always",
+        }
+    }
+
+    pub fn code(&self) -> &'static str {
+        match self {
+            ConfirmMoves::Never => "never",
+            ConfirmMoves::SlowGames => "slow_games",
+            ConfirmMoves::Always => "always",
+        }
+    }
+}
+
+// Each binding is a comma-separated list of egui key names.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct KeyBindings {
+    pub zen_mode: String,
+    pub zoom_in: String,
+    pub zoom_out: String,
+    pub reset_zoom: String,
+    pub previous_move: String,
+    pub next_move: String,
+}
+
+impl Default for KeyBindings {
+    fn default() -> Self {
+        Self {
+            zen_mode: "F11".to_string(),
+            zoom_in: "Plus, Equals".to_string(),
+            zoom_out: "Minus".to_string(),
+            reset_zoom: "0".to_string(),
+            previous_move: "Left".to_string(),
+            next_move: "Right".to_string(),
+        }
+    }
+}
+
+impl KeyBindings {
+    fn fields(&mut self) -> [(&'static str, &mut String); 6] {
+        [
+            ("zen_mode", &mut self.zen_mode),
+            ("zoom_in", &mut self.zoom_in),
+            ("zoom_out", &mut self.zoom_out),
+            ("reset_zoom", &mut self.reset_zoom),
+            ("previous_move", &mut self.previous_move),
+            ("next_move", &mut self.next_move),
+        ]
+    }
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct AppConfig {
+    pub language: Option<Language>,
+    pub theme: String,
+    pub piece_set: Option<String>,
+    pub high_contrast: bool,
+    pub large_controls: bool,
+    pub board_zoom: f32,
+    pub window_size: Option<[f32; 2]>,
+    pub move_notation: MoveNotation,
+    pub san_language: SanLanguage,
+    pub localized_pgn_export: bool,
+    pub confirm_moves: ConfirmMoves,
+    pub attention_alerts: bool,
+    pub low_time_warnings: HashMap<TimeControl, LowTimeWarning>,
+    pub key_bindings: KeyBindings,
+    pub engine: EngineSettings,
+}
+
+impl Default for AppConfig {
+    fn default() -> Self {
+        Self {
+            language: None,
+            theme: DEFAULT_THEME.to_string(),
+            piece_set: None,
+            high_contrast: false,
+            large_controls: false,
+            board_zoom: 1.0,
+            window_size: None,
+            move_notation: MoveNotation::default(),
+            san_language: SanLanguage::default(),
+            localized_pgn_export: false,
+            confirm_moves: ConfirmMoves::default(),
+            attention_alerts: true,
+            low_time_warnings: HashMap::new(),
+            key_bindings: KeyBindings::default(),
+            engine: EngineSettings::default(),
+        }
+    }
+}
+
+fn number(item: &Item) -> Option<f64> {
+    item.as_float()
+        .or_else(|| item.as_integer().map(|number| number as f64))
+}
+
+fn plain_value(item: &Item) -> Option<String> {
+    item.as_str()
+        .map(str::to_string)
+        .or_else(|| item.as_integer().map(|number| number.to_string()))
+        .or_else(|| item.as_float().map(|number| number.to_string()))
+        .or_else(|| item.as_bool().map(|flag| flag.to_string()))
+}
+
+fn typed_value(text: &str) -> Item {
+    if let Ok(number) = text.parse::<i64>() {
+        value(number)
+    } else if let Ok(flag) = text.parse::<bool>() {
+        value(flag)
+    } else if let Ok(number) = text.parse::<f64>() {
+        value(number)
+    } else {
+        value(text)
+    }
+}
+
+impl AppConfig {
+    // Unknown keys and invalid values are ignored so that a hand-edited file
+    // never stops the app from starting.
+    pub fn parse(text: &str) -> Result<Self, ConfigError> {
+        let doc = text
+            .parse::<DocumentMut>()
+            .map_err(|err| ConfigError::InvalidToml(err.to_string()))?;
+        let mut config = Self::default();
+        let get = |section: &str, key: &str| doc.get(section).and_then(|table| table.get(key));
+        let flag = |section: &str, key: &str, default: bool| {
+            get(section, key).and_then(Item::as_bool).unwrap_or(default)
+        };
+        let text = |section: &str, key: &str| get(section, key).and_then(Item::as_str);
+
+        config.language = text("interface", "language").and_then(|code| code.parse().ok());
+        if let Some(theme) = text("interface", "theme") {
+            config.theme = theme.to_string();
+        }
+        config.piece_set = text("interface", "piece_set").map(str::to_string);
+        config.high_contrast = flag("interface", "high_contrast", config.high_contrast);
+        config.large_controls = flag("interface", "large_controls", config.large_controls);
+        if let Some(zoom) = get("interface", "board_zoom").and_then(number) {
+            config.board_zoom = zoom as f32;
+        }
+        config.window_size = get("interface", "window_size")
+            .and_then(Item::as_array)
+            .and_then(|size| {
+                let mut size = size.iter().map(|item| {
+                    item.as_float()
+                        .or_else(|| item.as_integer().map(|number| number as f64))
+                });
+                Some([size.next()?? as f32, size.next()?? as f32])
+            })
+            .filter(|size| size.iter().all(|&length| length > 0.0));
+
+        if let Some(notation) = text("notation", "moves")
+            .and_then(|code| MoveNotation::iter().find(|notation| notation.code() == code))
+        {
+            config.move_notation = notation;
+        }
+        if let Some(language) = text("notation", "piece_letters").and_then(|code| code.parse().ok())
+        {
+            config.san_language = language;
+        }
+        config.localized_pgn_export = flag(
+            "notation",
+            "localized_pgn_export",
+            config.localized_pgn_export,
+        );
+
+        if let Some(confirm) = text("play", "confirm_moves")
+            .and_then(|code| ConfirmMoves::iter().find(|confirm| confirm.code() == code))
+        {
+            config.confirm_moves = confirm;
+        }
+        config.attention_alerts = flag(
+            "play",
+            "attention_alerts",
+            flag("play", "sounds", config.attention_alerts),
+        );
+
+        if let Some(warnings) = doc.get("low_time_warnings").and_then(Item::as_table_like) {
+            for (time_control, warning) in warnings.iter() {
+                let Ok(time_control) = time_control.parse::<TimeControl>() else {
+                    continue;
+                };
+                let mut parsed = LowTimeWarning::for_time_control(&time_control);
+                if let Some(threshold) = warning
+                    .get("threshold")
+                    .and_then(number)
+                    .filter(|seconds| *seconds > 0.0)
+                {
+                    parsed.threshold = Duration::from_secs_f64(threshold);
+                }
+                parsed.alert = warning
+                    .get("alert")
+                    .or_else(|| warning.get("sound"))
+                    .and_then(Item::as_bool)
+                    .unwrap_or(parsed.alert);
+                parsed.premoves = warning
+                    .get("premoves")
+                    .and_then(Item::as_bool)
+                    .unwrap_or(parsed.premoves);
+                config.low_time_warnings.insert(time_control, parsed);
+            }
+        }
+
+        for (key, binding) in config.key_bindings.fields() {
+            if let Some(keys) = text("keys", key) {
+                *binding = keys.to_string();
+            }
+        }
+
+        if let Some(engine) = doc.get("engine").and_then(Item::as_table_like) {
+            for (key, item) in engine.iter() {
+                if let Some(text) = plain_value(item) {
+                    config.engine.set(key, &text);
+                }
+            }
+        }
+        Ok(config)
+    }
+
+    pub fn to_toml(&self) -> String {
+        let mut doc = DocumentMut::new();
+
+        let mut interface = Table::new();
+        if let Some(language) = self.language {
+            interface.insert("language", value(language.code()));
+        }
+        interface.insert("theme", value(&self.theme));
+        if let Some(piece_set) = &self.piece_set {
+            interface.insert("piece_set", value(piece_set));
+        }
+        interface.insert("high_contrast", value(self.high_contrast));
+        interface.insert("large_controls", value(self.large_controls));
+        interface.insert("board_zoom", value(self.board_zoom as f64));
+        if let Some(size) = self.window_size {
+            interface.insert(
+                "window_size",
+                value(size.iter().map(|&length| length as f64).collect::<Array>()),
+            );
+        }
+        doc.insert("interface", Item::Table(interface));
+
+        let mut notation = Table::new();
+        notation.insert("moves", value(self.move_notation.code()));
+        notation.insert("piece_letters", value(self.san_language.code()));
+        notation.insert("localized_pgn_export", value(self.localized_pgn_export));
+        doc.insert("notation", Item::Table(notation));
+
+        let mut play = Table::new();
+        play.insert("confirm_moves", value(self.confirm_moves.code()));
+        play.insert("attention_alerts", value(self.attention_alerts));
+        doc.insert("play", Item::Table(play));
+
+        if !self.low_time_warnings.is_empty() {
+            let mut warnings = Table::new();
+            warnings.set_implicit(true);
+            let mut sorted = self.low_time_warnings.iter().collect::<Vec<_>>();
+            sorted.sort_by_key(|(time_control, _)| time_control.to_string());
+            for (time_control, warning) in sorted {
+                let mut table = Table::new();
+                table.insert("threshold", value(warning.threshold.as_secs_f64()));
+                table.insert("alert", value(warning.alert));
+                table.insert("premoves", value(warning.premoves));
+                warnings.insert(&time_control.to_string(), Item::Table(table));
+            }
+            doc.insert("low_time_warnings", Item::Table(warnings));
+        }
+
+        let mut keys = Table::new();
+        for (key, binding) in self.key_bindings.clone().fields() {
+            keys.insert(key, value(binding.as_str()));
+        }
+        doc.insert("keys", Item::Table(keys));
+
+        let mut engine = Table::new();
+        for (key, text) in self.engine.entries() {
+            engine.insert(key, typed_value(&text));
+        }
+        doc.insert("engine", Item::Table(engine));
+
+        doc.to_string()
+    }
+
+    pub fn load(path: &Path) -> Result<Self, ConfigError> {
+        Self::parse(&fs::read_to_string(path)?)
+    }
+
+    pub fn save(&self, path: &Path) -> io::Result<()> {
+        if let Some(parent) = path.parent() {
+            fs::create_dir_all(parent)?;
+        }
+        fs::write(path, self.to_toml())
+    }
+
+    pub fn load_saved() -> Result<Self, ConfigError> {
+        let Some(path) = config_file(APP_CONFIG_FILE) else {
+            return Ok(Self::default());
+        };
+        match Self::load(&path) {
+            Err(ConfigError::Io(err)) if err.kind() == io::ErrorKind::NotFound => Ok(Self {
+                engine: EngineSettings::load_legacy().unwrap_or_default(),
+                ..Self::default()
+            }),
+            result => result,
+        }
+    }
+
+    pub fn save_saved(&self) -> io::Result<()> {
+        match config_file(APP_CONFIG_FILE) {
+            Some(path) => self.save(&path),
+            None => Ok(()),
+        }
+    }
+}
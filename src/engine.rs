@@ -0,0 +1,160 @@
+use std::{
+    io,
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        Arc, RwLock,
+    },
+    time::Duration,
+};
+
+use crate::{
+    analysis::LiveEval,
+    clock::Clock,
+    game::{Player, PlayerAction},
+    logic::{ChessBoard, Move},
+};
+
+const DEFAULT_MOVES_TO_GO: u32 = 30;
+
+#[derive(Clone, Debug, Default)]
+pub struct SearchLimits {
+    pub depth: Option<usize>,
+    pub move_time: Option<Duration>,
+    pub clock: Option<Clock>,
+    pub multi_pv: usize,
+}
+
+impl SearchLimits {
+    pub fn depth(depth: usize) -> Self {
+        Self {
+            depth: Some(depth),
+            ..Self::default()
+        }
+    }
+
+    pub fn move_time(move_time: Duration) -> Self {
+        Self {
+            move_time: Some(move_time),
+            ..Self::default()
+        }
+    }
+
+    pub fn clock(clock: &Clock) -> Self {
+        Self {
+            clock: Some(clock.clone()),
+            ..Self::default()
+        }
+    }
+
+    pub fn with_multi_pv(mut self, multi_pv: usize) -> Self {
+        self.multi_pv = multi_pv;
+        self
+    }
+
+    pub fn lines(&self) -> usize {
+        self.multi_pv.max(1)
+    }
+
+    pub fn time_budget(&self, board: &ChessBoard) -> Option<Duration> {
+        if self.move_time.is_some() {
+            return self.move_time;
+        }
+        let clock = self.clock.as_ref()?;
+        let remaining = clock.remaining(board.turn);
+        let moves_to_go = clock
+            .moves_to_go(board.turn)
+            .unwrap_or(DEFAULT_MOVES_TO_GO)
+            .max(1);
+        let budget = remaining / moves_to_go + clock.time_control(board.turn).increment;
+        Some(budget.min(remaining / 2))
+    }
+}
+
+pub trait Engine: Send {
+    fn name(&self) -> String;
+
+    fn new_game(&mut self) -> io::Result<()> {
+        Ok(())
+    }
+
+    fn set_option(&mut self, _name: &str, _value: &str) -> io::Result<()> {
+        Ok(())
+    }
+
+    fn set_position(&mut self, board: &ChessBoard) -> io::Result<()>;
+
+    fn search(
+        &mut self,
+        limits: &SearchLimits,
+        on_info: &mut dyn FnMut(&LiveEval),
+    ) -> io::Result<Move>;
+
+    fn set_stop(&mut self, stop: Arc<AtomicBool>);
+
+    // Ends the search like the stop flag, but is owned by the game.
+    fn set_cancel(&mut self, cancel: Arc<AtomicBool>);
+}
+
+pub struct EnginePlayer {
+    pub engine: Box<dyn Engine>,
+    pub limits: SearchLimits,
+    pub clock: Option<Arc<RwLock<Clock>>>,
+    pub stop: Arc<AtomicBool>,
+    // The last failure, kept for the caller since the game only sees a resignation.
+    pub error: Arc<RwLock<Option<String>>>,
+    cancel: Arc<AtomicBool>,
+}
+
+impl EnginePlayer {
+    pub fn new(mut engine: Box<dyn Engine>, limits: SearchLimits) -> Self {
+        let stop = Arc::new(AtomicBool::new(false));
+        engine.set_stop(stop.clone());
+        Self {
+            engine,
+            limits,
+            clock: None,
+            stop,
+            error: Arc::new(RwLock::new(None)),
+            cancel: Arc::new(AtomicBool::new(false)),
+        }
+    }
+}
+
+impl Player for EnginePlayer {
+    fn get_move(&mut self, board: Arc<RwLock<ChessBoard>>) -> Move {
+        match self.get_action(board, false) {
+            PlayerAction::Move(mv) => mv,
+            _ => panic!("The engine did not return a move"),
+        }
+    }
+
+    fn get_action(&mut self, board: Arc<RwLock<ChessBoard>>, _draw_offered: bool) -> PlayerAction {
+        self.stop.store(false, Ordering::Relaxed);
+        let board = board.read().unwrap().clone();
+        let limits = match &self.clock {
+            Some(clock) => SearchLimits {
+                clock: Some(clock.read().unwrap().clone()),
+                ..self.limits.clone()
+            },
+            None => self.limits.clone(),
+        };
+        let result = self
+            .engine
+            .set_position(&board)
+            .and_then(|_| self.engine.search(&limits, &mut |_| {}));
+        match result {
+            _ if self.cancel.load(Ordering::Relaxed) => PlayerAction::Abort,
+            Ok(mv) => PlayerAction::Move(mv),
+            Err(err) => {
+                *self.error.write().unwrap() =
+                    Some(format!("{} failed: {}", self.engine.name(), err));
+                PlayerAction::Resign
+            }
+        }
+    }
+
+    fn set_cancel(&mut self, cancel: Arc<AtomicBool>) {
+        self.engine.set_cancel(cancel.clone());
+        self.cancel = cancel;
+    }
+}
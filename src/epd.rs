@@ -0,0 +1,246 @@
+use std::{fmt::Display, str::FromStr};
+
+use crate::logic::{ChessBoard, Move};
+
+#[derive(Clone, Debug)]
+pub struct EpdRecord {
+    pub board: ChessBoard,
+    pub id: Option<String>,
+    pub best_moves: Vec<Move>,
+    pub avoid_moves: Vec<Move>,
+    pub centipawns: Option<i32>,
+    pub pv: Vec<Move>,
+    pub other: Vec<(String, Vec<String>)>,
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct EpdError {
+    pub line: usize,
+    pub message: String,
+}
+
+impl Display for EpdError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "line {}: {}", self.line, self.message)
+    }
+}
+
+impl std::error::Error for EpdError {}
+
+impl EpdRecord {
+    pub fn new(board: ChessBoard) -> Self {
+        Self {
+            board,
+            id: None,
+            best_moves: Vec::new(),
+            avoid_moves: Vec::new(),
+            centipawns: None,
+            pv: Vec::new(),
+            other: Vec::new(),
+        }
+    }
+
+    pub fn operation(&self, opcode: &str) -> Option<&[String]> {
+        self.other
+            .iter()
+            .find(|(name, _)| name == opcode)
+            .map(|(_, operands)| operands.as_slice())
+    }
+
+    pub fn parse_all(text: &str) -> Result<Vec<EpdRecord>, EpdError> {
+        text.lines()
+            .enumerate()
+            .filter(|(_, line)| !line.trim().is_empty())
+            .map(|(i, line)| {
+                parse_record(line).map_err(|message| EpdError {
+                    line: i + 1,
+                    message,
+                })
+            })
+            .collect()
+    }
+}
+
+impl FromStr for EpdRecord {
+    type Err = EpdError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        parse_record(s).map_err(|message| EpdError { line: 1, message })
+    }
+}
+
+fn parse_record(text: &str) -> Result<EpdRecord, String> {
+    let mut rest = text.trim_start();
+    let mut fields = Vec::new();
+    for _ in 0..4 {
+        let end = rest.find(char::is_whitespace).unwrap_or(rest.len());
+        if end == 0 {
+            return Err("expected four position fields".to_string());
+        }
+        fields.push(&rest[..end]);
+        rest = rest[end..].trim_start();
+    }
+    let operations = parse_operations(rest)?;
+    let number = |opcode: &str, default: &str| {
+        operations
+            .iter()
+            .find(|(name, _)| name == opcode)
+            .and_then(|(_, operands)| operands.first())
+            .map_or(default.to_string(), String::clone)
+    };
+    let mut board = ChessBoard::new();
+    board
+        .set_from_fen(&format!(
+            "{} {} {}",
+            fields.join(" "),
+            number("hmvc", "0"),
+            number("fmvn", "1")
+        ))
+        .map_err(|err| format!("invalid position: {}", err))?;
+
+    let mut record = EpdRecord::new(board);
+    for (opcode, operands) in operations {
+        match opcode.as_str() {
+            "id" => record.id = operands.into_iter().next(),
+            "bm" => record.best_moves = parse_moves(&record.board, &opcode, &operands)?,
+            "am" => record.avoid_moves = parse_moves(&record.board, &opcode, &operands)?,
+            "ce" => {
+                record.centipawns = Some(
+                    operands
+                        .first()
+                        .and_then(|ce| ce.parse().ok())
+                        .ok_or("invalid ce operand")?,
+                )
+            }
+            "pv" => {
+                let mut board = record.board.clone();
+                for operand in &operands {
+                    let mv = parse_move(&board, operand)
+                        .ok_or_else(|| format!("illegal move in pv: {}", operand))?;
+                    mv.perform(&mut board);
+                    record.pv.push(mv);
+                }
+            }
+            _ => record.other.push((opcode, operands)),
+        }
+    }
+    Ok(record)
+}
+
+fn parse_operations(text: &str) -> Result<Vec<(String, Vec<String>)>, String> {
+    let mut operations = Vec::new();
+    let mut tokens = Vec::new();
+    let mut token = String::new();
+    let mut quoted = false;
+    let mut finish_operation = |tokens: &mut Vec<String>| {
+        if !tokens.is_empty() {
+            let opcode = tokens.remove(0);
+            operations.push((opcode, std::mem::take(tokens)));
+        }
+    };
+    for c in text.chars() {
+        match c {
+            '"' if quoted => {
+                tokens.push(std::mem::take(&mut token));
+                quoted = false;
+            }
+            _ if quoted => token.push(c),
+            '"' | ';' => {
+                if !token.is_empty() {
+                    tokens.push(std::mem::take(&mut token));
+                }
+                if c == ';' {
+                    finish_operation(&mut tokens);
+                } else {
+                    quoted = true;
+                }
+            }
+            c if c.is_whitespace() => {
+                if !token.is_empty() {
+                    tokens.push(std::mem::take(&mut token));
+                }
+            }
+            _ => token.push(c),
+        }
+    }
+    if quoted {
+        return Err("unterminated string".to_string());
+    }
+    if !token.is_empty() {
+        tokens.push(token);
+    }
+    finish_operation(&mut tokens);
+    Ok(operations)
+}
+
+fn parse_move(board: &ChessBoard, s: &str) -> Option<Move> {
+    Move::from_san(s, board)
+        .or_else(|_| Move::from_str(s, board))
+        .ok()
+        .filter(|mv| mv.is_legal(board))
+}
+
+fn parse_moves(board: &ChessBoard, opcode: &str, operands: &[String]) -> Result<Vec<Move>, String> {
+    operands
+        .iter()
+        .map(|operand| {
+            parse_move(board, operand)
+                .ok_or_else(|| format!("illegal move in {}: {}", opcode, operand))
+        })
+        .collect()
+}
+
+fn quote(operand: &str) -> String {
+    if operand.is_empty() || operand.contains(|c: char| c.is_whitespace() || c == ';') {
+        format!("\"{}\"", operand)
+    } else {
+        operand.to_string()
+    }
+}
+
+impl Display for EpdRecord {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let fen = self.board.to_fen();
+        write!(
+            f,
+            "{}",
+            fen.split_whitespace().take(4).collect::<Vec<_>>().join(" ")
+        )?;
+        let san = |moves: &[Move]| {
+            moves
+                .iter()
+                .map(|mv| mv.to_san(&self.board))
+                .collect::<Vec<_>>()
+                .join(" ")
+        };
+        if !self.best_moves.is_empty() {
+            write!(f, " bm {};", san(&self.best_moves))?;
+        }
+        if !self.avoid_moves.is_empty() {
+            write!(f, " am {};", san(&self.avoid_moves))?;
+        }
+        if let Some(centipawns) = self.centipawns {
+            write!(f, " ce {};", centipawns)?;
+        }
+        if !self.pv.is_empty() {
+            let mut board = self.board.clone();
+            let mut line = Vec::new();
+            for mv in &self.pv {
+                line.push(mv.to_san(&board));
+                mv.perform(&mut board);
+            }
+            write!(f, " pv {};", line.join(" "))?;
+        }
+        if let Some(id) = &self.id {
+            write!(f, " id \"{}\";", id)?;
+        }
+        for (opcode, operands) in &self.other {
+            write!(f, " {}", opcode)?;
+            for operand in operands {
+                write!(f, " {}", quote(operand))?;
+            }
+            write!(f, ";")?;
+        }
+        Ok(())
+    }
+}
@@ -0,0 +1,348 @@
+use alloc::{
+    string::{String, ToString},
+    vec::Vec,
+};
+use core::str::FromStr;
+#[cfg(not(feature = "std"))]
+use core::{
+    ptr,
+    sync::atomic::{AtomicPtr, Ordering},
+};
+#[cfg(feature = "std")]
+use std::{fs, io, path::Path, sync::OnceLock};
+
+#[cfg(feature = "std")]
+use crate::config;
+use crate::logic::{ChessBoard, Move, MoveList, MoveType, PieceColor, PieceType};
+
+pub const PIECES_FILE: &str = "pieces.cfg";
+pub const MAX_FAIRY_PIECES: usize = 8;
+const RESERVED_LETTERS: &str = "kqrbnp";
+
+#[cfg(feature = "std")]
+static PIECES: OnceLock<Vec<FairyPiece>> = OnceLock::new();
+#[cfg(not(feature = "std"))]
+static PIECES: AtomicPtr<Vec<FairyPiece>> = AtomicPtr::new(ptr::null_mut());
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum MoveMode {
+    Any,
+    MoveOnly,
+    CaptureOnly,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub struct MoveRule {
+    pub step: (isize, isize),
+    pub range: usize,
+    pub mode: MoveMode,
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct MovementSpec(pub Vec<MoveRule>);
+
+#[derive(Debug, PartialEq, Eq)]
+pub struct ParseMovementError;
+
+fn atom_steps(atom: char) -> Option<Vec<(isize, isize)>> {
+    let (a, b) = match atom {
+        'W' => (1, 0),
+        'F' => (1, 1),
+        'D' => (2, 0),
+        'N' => (2, 1),
+        'A' => (2, 2),
+        'H' => (3, 0),
+        'C' => (3, 1),
+        'Z' => (3, 2),
+        'G' => (3, 3),
+        _ => return None,
+    };
+    let mut steps = Vec::new();
+    for (x, y) in [(a, b), (b, a)] {
+        for (sx, sy) in [(1, 1), (1, -1), (-1, 1), (-1, -1)] {
+            let step = (x * sx, y * sy);
+            if !steps.contains(&step) {
+                steps.push(step);
+            }
+        }
+    }
+    Some(steps)
+}
+
+fn compound_atoms(c: char) -> Option<(&'static [char], bool)> {
+    match c {
+        'K' => Some((&['W', 'F'], false)),
+        'Q' => Some((&['W', 'F'], true)),
+        'R' => Some((&['W'], true)),
+        'B' => Some((&['F'], true)),
+        _ => atom_steps(c).map(|_| (&[][..], false)),
+    }
+}
+
+impl FromStr for MovementSpec {
+    type Err = ParseMovementError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let mut rules = Vec::new();
+        let mut chars = s.chars().filter(|c| !c.is_whitespace()).peekable();
+        let (mut mode, mut forward, mut backward) = (MoveMode::Any, false, false);
+        while let Some(c) = chars.next() {
+            match c {
+                'm' => mode = MoveMode::MoveOnly,
+                'c' => mode = MoveMode::CaptureOnly,
+                'f' => forward = true,
+                'b' => backward = true,
+                _ => {
+                    let (compound, rider) = compound_atoms(c).ok_or(ParseMovementError)?;
+                    let atoms = if compound.is_empty() {
+                        &[c][..]
+                    } else {
+                        compound
+                    };
+                    let mut digits = String::new();
+                    while let Some(digit) = chars.next_if(|c| c.is_ascii_digit()) {
+                        digits.push(digit);
+                    }
+                    let range = if !digits.is_empty() {
+                        digits.parse().map_err(|_| ParseMovementError)?
+                    } else if rider || (compound.is_empty() && chars.next_if_eq(&c).is_some()) {
+                        0
+                    } else {
+                        1
+                    };
+                    for &atom in atoms {
+                        rules.extend(
+                            atom_steps(atom)
+                                .unwrap_or_default()
+                                .into_iter()
+                                .filter(|&(_, dy)| {
+                                    (!forward && !backward)
+                                        || (forward && dy < 0)
+                                        || (backward && dy > 0)
+                                })
+                                .map(|step| MoveRule { step, range, mode }),
+                        );
+                    }
+                    (mode, forward, backward) = (MoveMode::Any, false, false);
+                }
+            }
+        }
+        if rules.is_empty() {
+            return Err(ParseMovementError);
+        }
+        Ok(MovementSpec(rules))
+    }
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct FairyPiece {
+    pub name: String,
+    pub letter: char,
+    pub value: f64,
+    pub image: Option<String>,
+    pub movement: MovementSpec,
+    san: String,
+}
+
+impl FairyPiece {
+    pub fn new(name: &str, letter: char, movement: MovementSpec) -> Self {
+        Self {
+            name: name.to_string(),
+            letter: letter.to_ascii_lowercase(),
+            value: 3.0,
+            image: None,
+            movement,
+            san: letter.to_ascii_uppercase().to_string(),
+        }
+    }
+
+    pub fn san_letter(&self) -> &str {
+        &self.san
+    }
+
+    pub fn image_path(&self, color: PieceColor) -> Option<String> {
+        self.image
+            .as_ref()
+            .map(|path| path.replace("{color}", &color.to_string()))
+    }
+
+    fn targets(
+        &self,
+        board: &ChessBoard,
+        pos: (usize, usize),
+        color: PieceColor,
+    ) -> Vec<((usize, usize), MoveMode, bool)> {
+        let flip = if color == PieceColor::White { 1 } else { -1 };
+        let mut targets = Vec::new();
+        for rule in &self.movement.0 {
+            let step = (rule.step.0, rule.step.1 * flip);
+            let mut steps = 0;
+            let mut target = board.offset(pos, step);
+            while let Some(square) = target {
+                let occupied = board.piece_at(square).is_some();
+                targets.push((square, rule.mode, occupied));
+                steps += 1;
+                if occupied || (rule.range != 0 && steps >= rule.range) {
+                    break;
+                }
+                target = board.offset(square, step);
+            }
+        }
+        targets
+    }
+
+    pub fn generate_moves_into(
+        &self,
+        board: &ChessBoard,
+        pos: (usize, usize),
+        color: PieceColor,
+        moves: &mut MoveList,
+    ) {
+        for (target, mode, occupied) in self.targets(board, pos, color) {
+            let allowed = match mode {
+                MoveMode::Any => true,
+                MoveMode::MoveOnly => !occupied,
+                MoveMode::CaptureOnly => occupied,
+            };
+            if allowed {
+                moves.push(Move::new(pos, target, MoveType::Normal));
+            }
+        }
+    }
+
+    pub fn attacks(
+        &self,
+        board: &ChessBoard,
+        pos: (usize, usize),
+        color: PieceColor,
+        target: (usize, usize),
+    ) -> bool {
+        self.targets(board, pos, color)
+            .into_iter()
+            .any(|(square, mode, _)| square == target && mode != MoveMode::MoveOnly)
+    }
+}
+
+struct Section {
+    name: String,
+    letter: Option<char>,
+    movement: Option<MovementSpec>,
+    value: f64,
+    image: Option<String>,
+}
+
+impl Section {
+    fn finish(self, pieces: &mut Vec<FairyPiece>) {
+        let (Some(letter), Some(movement)) = (self.letter, self.movement) else {
+            return;
+        };
+        let letter = letter.to_ascii_lowercase();
+        if !letter.is_ascii_alphabetic()
+            || RESERVED_LETTERS.contains(letter)
+            || pieces.iter().any(|piece| piece.letter == letter)
+            || pieces.len() >= MAX_FAIRY_PIECES
+        {
+            return;
+        }
+        let mut piece = FairyPiece::new(&self.name, letter, movement);
+        piece.value = self.value;
+        piece.image = self.image;
+        pieces.push(piece);
+    }
+}
+
+pub fn parse(text: &str) -> Vec<FairyPiece> {
+    let mut pieces = Vec::new();
+    let mut current: Option<Section> = None;
+    for line in text.lines().map(str::trim) {
+        if let Some(name) = line.strip_prefix('[').and_then(|l| l.strip_suffix(']')) {
+            if let Some(section) = current.take() {
+                section.finish(&mut pieces);
+            }
+            current = Some(Section {
+                name: name.trim().to_string(),
+                letter: None,
+                movement: None,
+                value: 3.0,
+                image: None,
+            });
+            continue;
+        }
+        let (Some((key, value)), Some(section)) = (line.split_once('='), current.as_mut()) else {
+            continue;
+        };
+        let value = value.trim();
+        match key.trim() {
+            "name" => section.name = value.to_string(),
+            "letter" => section.letter = value.chars().next(),
+            "moves" => section.movement = value.parse().ok(),
+            "value" => section.value = value.parse().unwrap_or(section.value),
+            "image" => section.image = Some(value.to_string()),
+            _ => {}
+        }
+    }
+    if let Some(section) = current {
+        section.finish(&mut pieces);
+    }
+    pieces
+}
+
+#[cfg(feature = "std")]
+pub fn load(path: &Path) -> io::Result<Vec<FairyPiece>> {
+    Ok(parse(&fs::read_to_string(path)?))
+}
+
+#[cfg(feature = "std")]
+pub fn install(pieces: Vec<FairyPiece>) -> bool {
+    PIECES.set(pieces).is_ok()
+}
+
+#[cfg(feature = "std")]
+pub fn pieces() -> &'static [FairyPiece] {
+    PIECES.get_or_init(|| {
+        config::config_file(PIECES_FILE)
+            .and_then(|path| load(&path).ok())
+            .unwrap_or_default()
+    })
+}
+
+// Without std there is no config file to read, so the pieces stay empty
+// unless installed. The leaked Vec lives for the rest of the program, like
+// the OnceLock above.
+#[cfg(not(feature = "std"))]
+pub fn install(pieces: Vec<FairyPiece>) -> bool {
+    let pieces = alloc::boxed::Box::into_raw(alloc::boxed::Box::new(pieces));
+    let installed = PIECES
+        .compare_exchange(ptr::null_mut(), pieces, Ordering::AcqRel, Ordering::Acquire)
+        .is_ok();
+    if !installed {
+        drop(unsafe { alloc::boxed::Box::from_raw(pieces) });
+    }
+    installed
+}
+
+#[cfg(not(feature = "std"))]
+pub fn pieces() -> &'static [FairyPiece] {
+    let pieces = PIECES.load(Ordering::Acquire);
+    if pieces.is_null() {
+        &[]
+    } else {
+        unsafe { &*pieces }
+    }
+}
+
+pub fn piece(index: u8) -> Option<&'static FairyPiece> {
+    pieces().get(index as usize)
+}
+
+pub fn piece_types() -> impl DoubleEndedIterator<Item = PieceType> {
+    (0..pieces().len() as u8).map(PieceType::Fairy)
+}
+
+pub fn by_letter(letter: char) -> Option<PieceType> {
+    let letter = letter.to_ascii_lowercase();
+    pieces()
+        .iter()
+        .position(|piece| piece.letter == letter)
+        .map(|index| PieceType::Fairy(index as u8))
+}
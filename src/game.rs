@@ -1,53 +1,184 @@
-use std::sync::{
-    mpsc::{self, Receiver, Sender},
-    Arc, RwLock,
+use std::{
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        mpsc::{self, Receiver, RecvTimeoutError, Sender},
+        Arc, RwLock,
+    },
+    thread,
+    time::{Duration, Instant},
 };
 
-use crate::logic::{ChessBoard, PieceColor, WinState};
+use crate::{
+    clock::Clock,
+    logic::{ChessBoard, Move, PieceColor, WinState},
+};
+
+const CANCEL_POLL_INTERVAL: Duration = Duration::from_millis(50);
+
+pub fn timeout_result(board: &ChessBoard, flagged: PieceColor) -> WinState {
+    if board.has_mating_material(flagged.opposite()) {
+        WinState::Timeout(flagged.opposite())
+    } else {
+        WinState::InsufficientMaterial
+    }
+}
 
 pub struct ChessGame {
     pub board: Arc<RwLock<ChessBoard>>,
+    pub start_board: ChessBoard,
+    pub moves: Arc<RwLock<Vec<Move>>>,
+    pub move_times: Arc<RwLock<Vec<Duration>>>,
     pub white_player: Box<dyn Player>,
     pub black_player: Box<dyn Player>,
     pub on_update_func: Box<dyn Fn() + Send + 'static>,
+    pub cancel: Arc<AtomicBool>,
+    pub clock: Option<Arc<RwLock<Clock>>>,
 }
 
 impl ChessGame {
     pub fn new(
-        white_player: Box<dyn Player>,
-        black_player: Box<dyn Player>,
+        mut white_player: Box<dyn Player>,
+        mut black_player: Box<dyn Player>,
         on_update_func: impl Fn() + Send + 'static,
     ) -> Self {
+        let cancel = Arc::new(AtomicBool::new(false));
+        white_player.set_cancel(cancel.clone());
+        black_player.set_cancel(cancel.clone());
         Self {
             board: Arc::new(RwLock::new(ChessBoard::new())),
+            start_board: ChessBoard::new(),
+            moves: Arc::new(RwLock::new(Vec::new())),
+            move_times: Arc::new(RwLock::new(Vec::new())),
             white_player,
             black_player,
             on_update_func: Box::new(on_update_func),
+            cancel,
+            clock: None,
         }
     }
 
-    pub fn create_game_thread(mut self) -> std::thread::JoinHandle<WinState> {
+    pub fn resume(&mut self, start_board: ChessBoard, moves: &[Move]) {
+        let mut board = start_board.clone();
+        for mv in moves {
+            mv.perform(&mut board);
+        }
+        *self.board.write().unwrap() = board;
+        *self.moves.write().unwrap() = moves.to_vec();
+        *self.move_times.write().unwrap() = vec![Duration::ZERO; moves.len()];
+        self.start_board = start_board;
+    }
+
+    pub fn create_game_thread(mut self) -> std::thread::JoinHandle<Option<WinState>> {
         std::thread::spawn(move || self.play())
     }
 
-    pub fn play(&mut self) -> WinState {
+    pub fn cancel(&self) {
+        self.cancel.store(true, Ordering::Relaxed);
+    }
+
+    pub fn play(&mut self) -> Option<WinState> {
+        let Some(clock) = self.clock.clone() else {
+            return self.play_moves();
+        };
+        let done = AtomicBool::new(false);
+        let cancel = self.cancel.clone();
+        thread::scope(|scope| {
+            scope.spawn(|| Self::watch_clock(&clock, &cancel, &done));
+            let result = self.play_moves();
+            done.store(true, Ordering::Relaxed);
+            result
+        })
+    }
+
+    fn watch_clock(clock: &RwLock<Clock>, cancel: &AtomicBool, done: &AtomicBool) {
+        while !done.load(Ordering::Relaxed) {
+            let clock = clock.read().unwrap();
+            if clock.running().is_some_and(|color| clock.is_flagged(color)) {
+                cancel.store(true, Ordering::Relaxed);
+                return;
+            }
+            drop(clock);
+            thread::sleep(CANCEL_POLL_INTERVAL);
+        }
+    }
+
+    fn flag_fall(&self) -> Option<WinState> {
+        let clock = self.clock.as_ref()?.read().unwrap();
+        let flagged = clock.running().filter(|&color| clock.is_flagged(color))?;
+        Some(timeout_result(&self.board.read().unwrap(), flagged))
+    }
+
+    fn play_moves(&mut self) -> Option<WinState> {
+        let mut draw_offer: Option<PieceColor> = None;
+        if self.moves.read().unwrap().is_empty() {
+            self.start_board = self.board.read().unwrap().clone();
+        }
+        if let Some(win_state) = self.board.read().unwrap().win_state() {
+            return Some(win_state);
+        }
         loop {
-            let current_player = {
+            if let Some(clock) = &self.clock {
+                let turn = self.board.read().unwrap().turn;
+                let mut clock = clock.write().unwrap();
+                if clock.running() != Some(turn) {
+                    clock.start(turn);
+                }
+            }
+            if let Some(win_state) = self.flag_fall() {
+                return Some(win_state);
+            }
+            if self.cancel.load(Ordering::Relaxed) {
+                return None;
+            }
+            let turn = {
                 let board = self.board.read().unwrap();
                 board.turn
             };
+            let draw_offered = draw_offer == Some(turn.opposite());
+            let started = Instant::now();
             let new_ref = self.board.clone();
-            let current_player = self.get_player(current_player);
-            let chess_move = current_player.get_move(new_ref);
+            let current_player = self.get_player(turn);
+            let chess_move = match current_player.get_action(new_ref.clone(), draw_offered) {
+                PlayerAction::Move(chess_move) => chess_move,
+                PlayerAction::OfferDraw if draw_offer != Some(turn) => {
+                    draw_offer = Some(turn);
+                    continue;
+                }
+                PlayerAction::AcceptDraw if draw_offered => return Some(WinState::DrawAgreement),
+                // A repeated offer or an accept with nothing to accept is no action at all, so
+                // ask for a move instead of letting a player that keeps sending one stall the game.
+                PlayerAction::OfferDraw | PlayerAction::AcceptDraw => {
+                    current_player.get_move(new_ref)
+                }
+                PlayerAction::Resign => return Some(WinState::Resignation(turn.opposite())),
+                PlayerAction::Abort => return self.flag_fall(),
+            };
+            if let Some(win_state) = self.flag_fall() {
+                return Some(win_state);
+            }
+            if self.cancel.load(Ordering::Relaxed) {
+                return None;
+            }
+            if draw_offered {
+                draw_offer = None;
+            }
+
+            if let Some(clock) = &self.clock {
+                if !clock.write().unwrap().stop() {
+                    return Some(timeout_result(&self.board.read().unwrap(), turn));
+                }
+            }
 
             let mut board = self.board.write().unwrap();
 
             chess_move.perform(&mut board);
+            self.moves.write().unwrap().push(chess_move);
+            self.move_times.write().unwrap().push(started.elapsed());
 
             (self.on_update_func)();
 
             if let Some(win_state) = board.win_state() {
-                return win_state;
+                return Some(win_state);
             }
         }
     }
@@ -60,25 +191,62 @@ impl ChessGame {
     }
 }
 
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum PlayerAction {
+    Move(Move),
+    OfferDraw,
+    AcceptDraw,
+    Resign,
+    Abort,
+}
+
 pub trait Player: Send {
-    fn get_move(&mut self, board: Arc<RwLock<ChessBoard>>) -> crate::logic::Move;
+    fn get_move(&mut self, board: Arc<RwLock<ChessBoard>>) -> Move;
+
+    fn get_action(&mut self, board: Arc<RwLock<ChessBoard>>, _draw_offered: bool) -> PlayerAction {
+        PlayerAction::Move(self.get_move(board))
+    }
+
+    fn set_cancel(&mut self, _cancel: Arc<AtomicBool>) {}
 }
 
 pub struct ChannelPlayer {
-    pub move_channel: Receiver<crate::logic::Move>,
+    pub action_channel: Receiver<PlayerAction>,
+    cancel: Arc<AtomicBool>,
 }
 
 impl ChannelPlayer {
-    pub fn new() -> (Sender<crate::logic::Move>, Self) {
+    pub fn new() -> (Sender<PlayerAction>, Self) {
         let (tx, rx) = mpsc::channel();
-        (tx, Self { move_channel: rx })
+        (
+            tx,
+            Self {
+                action_channel: rx,
+                cancel: Arc::new(AtomicBool::new(false)),
+            },
+        )
     }
 }
 
 impl Player for ChannelPlayer {
-    fn get_move(&mut self, _board: Arc<RwLock<ChessBoard>>) -> crate::logic::Move {
-        self.move_channel.recv().unwrap_or_else(|_| {
-            std::process::exit(0);
-        })
+    fn get_move(&mut self, board: Arc<RwLock<ChessBoard>>) -> Move {
+        match self.get_action(board, false) {
+            PlayerAction::Move(mv) => mv,
+            _ => panic!("The game was cancelled while waiting for a move"),
+        }
+    }
+
+    fn get_action(&mut self, _board: Arc<RwLock<ChessBoard>>, _draw_offered: bool) -> PlayerAction {
+        loop {
+            match self.action_channel.recv_timeout(CANCEL_POLL_INTERVAL) {
+                Ok(action) => return action,
+                Err(RecvTimeoutError::Timeout) if !self.cancel.load(Ordering::Relaxed) => {}
+                Err(_) => return PlayerAction::Abort,
+            }
+        }
+    }
+
+    fn set_cancel(&mut self, cancel: Arc<AtomicBool>) {
+        self.cancel = cancel;
     }
 }
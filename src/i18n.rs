@@ -0,0 +1,123 @@
+use std::{
+    collections::HashMap,
+    fmt::Display,
+    str::FromStr,
+    sync::{
+        atomic::{AtomicUsize, Ordering},
+        OnceLock,
+    },
+};
+
+use strum::IntoEnumIterator;
+use strum_macros::EnumIter;
+
+static CURRENT: AtomicUsize = AtomicUsize::new(0);
+static CATALOGS: OnceLock<HashMap<Language, HashMap<&'static str, &'static str>>> = OnceLock::new();
+
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Hash, EnumIter)]
+pub enum Language {
+    #[default]
+    English,
+    German,
+    Spanish,
+}
+
+#[derive(Debug, PartialEq, Eq)]
+pub struct ParseLanguageError;
+
+impl FromStr for Language {
+    type Err = ParseLanguageError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let code = s.split(['_', '-', '.']).next().unwrap_or_default();
+        Language::iter()
+            .find(|language| {
+                language.code().eq_ignore_ascii_case(code)
+                    || language.readable().eq_ignore_ascii_case(s)
+            })
+            .ok_or(ParseLanguageError)
+    }
+}
+
+impl Language {
+    pub fn code(&self) -> &'static str {
+        match self {
+            Language::English => "en",
+            Language::German => "de",
+            Language::Spanish => "es",
+        }
+    }
+
+    pub fn readable(&self) -> &'static str {
+        match self {
+            Language::English => "English",
+            Language::German => "Deutsch",
+            Language::Spanish => "Español",
+        }
+    }
+
+    fn catalog_source(&self) -> &'static str {
+        match self {
+            Language::English => "",
+            Language::German => include_str!("i18n/de.txt"),
+            Language::Spanish => include_str!("i18n/es.txt"),
+        }
+    }
+
+    pub fn from_env() -> Self {
+        ["LC_ALL", "LC_MESSAGES", "LANG"]
+            .iter()
+            .filter_map(|name| std::env::var(name).ok())
+            .find(|value| !value.is_empty())
+            .and_then(|value| value.parse().ok())
+            .unwrap_or_default()
+    }
+
+    pub fn current() -> Self {
+        Language::iter()
+            .nth(CURRENT.load(Ordering::Relaxed))
+            .unwrap_or_default()
+    }
+
+    pub fn set_current(self) {
+        CURRENT.store(self as usize, Ordering::Relaxed);
+    }
+
+    pub fn translate<'a>(&self, text: &'a str) -> &'a str {
+        let catalogs = CATALOGS.get_or_init(|| {
+            Language::iter()
+                .map(|language| (language, parse_catalog(language.catalog_source())))
+                .collect()
+        });
+        catalogs
+            .get(self)
+            .and_then(|catalog| catalog.get(text))
+            .copied()
+            .unwrap_or(text)
+    }
+}
+
+fn parse_catalog(source: &'static str) -> HashMap<&'static str, &'static str> {
+    source
+        .lines()
+        .filter(|line| !line.starts_with('#'))
+        .filter_map(|line| line.split_once(" = "))
+        .map(|(text, translation)| (text.trim(), translation.trim()))
+        .collect()
+}
+
+pub fn tr(text: &str) -> &str {
+    Language::current().translate(text)
+}
+
+pub fn trf(template: &str, args: &[&dyn Display]) -> String {
+    let mut parts = tr(template).split("{}");
+    let mut out = parts.next().unwrap_or_default().to_string();
+    for (i, part) in parts.enumerate() {
+        if let Some(arg) = args.get(i) {
+            out.push_str(&arg.to_string());
+        }
+        out.push_str(part);
+    }
+    out
+}
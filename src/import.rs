@@ -0,0 +1,280 @@
+use std::{fmt::Display, str::FromStr};
+
+use serde_json::Value;
+
+use crate::{
+    epd::EpdRecord,
+    logic::{ChessBoard, FenError, PositionError, Variant},
+    pgn::{PgnError, PgnGame},
+    record::{GameRecord, ReplayError},
+};
+
+const LICHESS_EXPORT_URL: &str = "https://lichess.org/game/export";
+const LICHESS_IMPORT_URL: &str = "https://lichess.org/api/import";
+const LICHESS_ANALYSIS_URL: &str = "https://lichess.org/analysis";
+const CHESS_COM_CALLBACK_URL: &str = "https://www.chess.com/callback";
+const CHESS_COM_ARCHIVE_URL: &str = "https://api.chess.com/pub/player";
+const USER_AGENT: &str = concat!("chess-rs/", env!("CARGO_PKG_VERSION"));
+const LICHESS_ID_LENGTH: usize = 8;
+const LIVE_ARCHIVE_MONTHS: usize = 2;
+const DAILY_ARCHIVE_MONTHS: usize = 12;
+
+#[derive(Clone, Debug, PartialEq, Eq, Hash)]
+pub enum GameSource {
+    Lichess(String),
+    ChessCom { id: String, daily: bool },
+}
+
+#[derive(Debug)]
+pub enum ImportError {
+    UnknownSource,
+    NotFound,
+    Network(String),
+    InvalidResponse,
+    InvalidPgn(PgnError),
+    UnsupportedVariant,
+    InvalidFen(FenError),
+    InvalidPosition(PositionError),
+    InvalidGame(ReplayError),
+    UnrecognizedText,
+}
+
+impl Display for ImportError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            ImportError::UnknownSource => write!(f, "not a lichess or chess.com game link"),
+            ImportError::NotFound => write!(f, "game not found"),
+            ImportError::Network(err) => write!(f, "network error: {}", err),
+            ImportError::InvalidResponse => write!(f, "unexpected response from the server"),
+            ImportError::InvalidPgn(err) => write!(f, "invalid PGN: {}", err),
+            ImportError::UnsupportedVariant => write!(f, "lichess does not support this variant"),
+            ImportError::InvalidFen(err) => write!(f, "invalid FEN: {}", err),
+            ImportError::InvalidPosition(err) => write!(f, "invalid position: {}", err),
+            ImportError::InvalidGame(err) => write!(f, "game does not replay: {}", err),
+            ImportError::UnrecognizedText => write!(f, "text is neither a FEN nor a PGN"),
+        }
+    }
+}
+
+impl std::error::Error for ImportError {}
+
+#[derive(Clone, Debug)]
+pub enum PastedText {
+    Position(ChessBoard),
+    Game(PgnGame),
+}
+
+fn looks_like_fen(text: &str) -> bool {
+    let mut fields = text.split_whitespace();
+    let placement = fields.next().unwrap_or_default();
+    placement.contains('/')
+        && placement
+            .chars()
+            .all(|c| c == '/' || c.is_ascii_alphanumeric())
+        && fields.next().is_none_or(|turn| turn == "w" || turn == "b")
+}
+
+pub fn parse_pasted(text: &str) -> Result<PastedText, ImportError> {
+    let text = text.trim();
+    if !text.contains('\n') && looks_like_fen(text) {
+        let mut board = ChessBoard::new();
+        if let Err(err) = board.set_from_fen(text) {
+            board = text
+                .parse::<EpdRecord>()
+                .map_err(|_| ImportError::InvalidFen(err))?
+                .board;
+        }
+        board.validate().map_err(ImportError::InvalidPosition)?;
+        return Ok(PastedText::Position(board));
+    }
+    let game = PgnGame::parse_all(text)
+        .map_err(ImportError::InvalidPgn)?
+        .into_iter()
+        .next()
+        .filter(|game| !game.moves.is_empty() || game.tag("FEN").is_some())
+        .ok_or(ImportError::UnrecognizedText)?;
+    game.try_starting_board()
+        .map_err(ImportError::InvalidFen)?
+        .validate()
+        .map_err(ImportError::InvalidPosition)?;
+    Ok(PastedText::Game(game))
+}
+
+fn lichess_id(s: &str) -> Option<String> {
+    let valid = s.chars().all(|c| c.is_ascii_alphanumeric())
+        && (s.len() == LICHESS_ID_LENGTH || s.len() == LICHESS_ID_LENGTH + 4);
+    valid.then(|| s[..LICHESS_ID_LENGTH].to_string())
+}
+
+fn chess_com_id(s: &str) -> Option<String> {
+    (!s.is_empty() && s.chars().all(|c| c.is_ascii_digit())).then(|| s.to_string())
+}
+
+impl FromStr for GameSource {
+    type Err = ImportError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let s = s.trim();
+        let rest = s.split_once("://").map_or(s, |(_, rest)| rest);
+        let rest = rest.split(['?', '#']).next().unwrap_or_default();
+        let Some((host, path)) = rest.split_once('/') else {
+            if let Some(id) = chess_com_id(rest) {
+                return Ok(GameSource::ChessCom { id, daily: false });
+            }
+            return lichess_id(rest)
+                .map(GameSource::Lichess)
+                .ok_or(ImportError::UnknownSource);
+        };
+        let segments = path
+            .split('/')
+            .filter(|s| !s.is_empty())
+            .collect::<Vec<_>>();
+        match host.trim_start_matches("www.") {
+            "lichess.org" => segments
+                .first()
+                .and_then(|id| lichess_id(id))
+                .map(GameSource::Lichess),
+            "chess.com" => segments
+                .iter()
+                .rev()
+                .find_map(|segment| chess_com_id(segment))
+                .map(|id| GameSource::ChessCom {
+                    id,
+                    daily: segments.contains(&"daily"),
+                }),
+            _ => None,
+        }
+        .ok_or(ImportError::UnknownSource)
+    }
+}
+
+impl GameSource {
+    pub fn fetch(&self) -> Result<PgnGame, ImportError> {
+        let text = match self {
+            GameSource::Lichess(id) => get(&format!(
+                "{}/{}?clocks=true&evals=false",
+                LICHESS_EXPORT_URL, id
+            ))?,
+            GameSource::ChessCom { id, daily } => chess_com_pgn(id, *daily)?,
+        };
+        let pgn = PgnGame::parse_all(&text)
+            .map_err(ImportError::InvalidPgn)?
+            .into_iter()
+            .next()
+            .ok_or(ImportError::NotFound)?;
+        GameRecord::from_pgn(&pgn)
+            .replay()
+            .map_err(ImportError::InvalidGame)?;
+        Ok(pgn)
+    }
+}
+
+fn request_error(err: ureq::Error) -> ImportError {
+    match err {
+        ureq::Error::StatusCode(404) => ImportError::NotFound,
+        err => ImportError::Network(err.to_string()),
+    }
+}
+
+fn get(url: &str) -> Result<String, ImportError> {
+    ureq::get(url)
+        .header("User-Agent", USER_AGENT)
+        .call()
+        .map_err(request_error)?
+        .into_body()
+        .read_to_string()
+        .map_err(request_error)
+}
+
+fn get_json(url: &str) -> Result<Value, ImportError> {
+    serde_json::from_str(&get(url)?).map_err(|_| ImportError::InvalidResponse)
+}
+
+fn lichess_variant(board: &ChessBoard) -> Option<&'static str> {
+    if board.has_fairy_pieces() {
+        return None;
+    }
+    match board.variant {
+        Variant::Standard => Some("standard"),
+        Variant::RacingKings => Some("racingKings"),
+        Variant::Gardner => None,
+    }
+}
+
+pub fn lichess_analysis_url(board: &ChessBoard) -> Option<String> {
+    Some(format!(
+        "{}/{}/{}",
+        LICHESS_ANALYSIS_URL,
+        lichess_variant(board)?,
+        board.to_fen().replace(' ', "_")
+    ))
+}
+
+pub fn upload_to_lichess(pgn: &PgnGame) -> Result<String, ImportError> {
+    if lichess_variant(&pgn.starting_board()).is_none() {
+        return Err(ImportError::UnsupportedVariant);
+    }
+    let text = ureq::post(LICHESS_IMPORT_URL)
+        .header("User-Agent", USER_AGENT)
+        .header("Accept", "application/json")
+        .send_form([("pgn", pgn.to_string())])
+        .map_err(request_error)?
+        .into_body()
+        .read_to_string()
+        .map_err(request_error)?;
+    let response: Value = serde_json::from_str(&text).map_err(|_| ImportError::InvalidResponse)?;
+    response["url"]
+        .as_str()
+        .map(str::to_string)
+        .ok_or(ImportError::InvalidResponse)
+}
+
+fn chess_com_pgn(id: &str, daily: bool) -> Result<String, ImportError> {
+    let kind = if daily { "daily" } else { "live" };
+    let game = get_json(&format!("{}/{}/game/{}", CHESS_COM_CALLBACK_URL, kind, id))?;
+    let headers = &game["game"]["pgnHeaders"];
+    let (Some(white), Some(date)) = (headers["White"].as_str(), headers["Date"].as_str()) else {
+        return Err(ImportError::InvalidResponse);
+    };
+    let mut date = date.split('.').map(|part| part.parse::<usize>());
+    let (Some(Ok(mut year)), Some(Ok(mut month))) = (date.next(), date.next()) else {
+        return Err(ImportError::InvalidResponse);
+    };
+    let months = if daily {
+        DAILY_ARCHIVE_MONTHS
+    } else {
+        LIVE_ARCHIVE_MONTHS
+    };
+    let suffix = format!("/{}", id);
+    for _ in 0..months {
+        let archive = match get_json(&format!(
+            "{}/{}/games/{}/{:02}",
+            CHESS_COM_ARCHIVE_URL,
+            white.to_lowercase(),
+            year,
+            month
+        )) {
+            Err(ImportError::NotFound) => break,
+            archive => archive?,
+        };
+        let pgn = archive["games"]
+            .as_array()
+            .into_iter()
+            .flatten()
+            .find(|game| {
+                game["url"]
+                    .as_str()
+                    .is_some_and(|url| url.ends_with(&suffix))
+            })
+            .and_then(|game| game["pgn"].as_str());
+        if let Some(pgn) = pgn {
+            return Ok(pgn.to_string());
+        }
+        (year, month) = if month == 12 {
+            (year + 1, 1)
+        } else {
+            (year, month + 1)
+        };
+    }
+    Err(ImportError::NotFound)
+}
@@ -0,0 +1,148 @@
+use std::fmt::Display;
+
+use crate::logic::{ChessBoard, ChessPiece, PieceColor, PieceType, Variant};
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum IllegalMove {
+    NoPiece,
+    WrongTurn(PieceColor),
+    OwnPiece,
+    InvalidMovement(PieceType),
+    PathBlocked,
+    PawnCaptureOnly,
+    CastlingRightsLost,
+    CastleOutOfCheck,
+    CastleThroughCheck,
+    InCheck,
+    Pinned,
+    KingIntoCheck,
+    LeavesKingInCheck,
+    GivesCheck,
+}
+
+impl Display for IllegalMove {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            IllegalMove::NoPiece => write!(f, "There is no piece on that square"),
+            IllegalMove::WrongTurn(color) => write!(f, "It is {}'s turn", color.readable()),
+            IllegalMove::OwnPiece => write!(f, "You cannot capture your own piece"),
+            IllegalMove::InvalidMovement(piece_type) => write!(
+                f,
+                "A {} cannot move like that",
+                piece_type.readable().to_lowercase()
+            ),
+            IllegalMove::PathBlocked => write!(f, "Another piece is in the way"),
+            IllegalMove::PawnCaptureOnly => {
+                write!(f, "A pawn only moves diagonally when capturing")
+            }
+            IllegalMove::CastlingRightsLost => {
+                write!(f, "You cannot castle once the king or that rook has moved")
+            }
+            IllegalMove::CastleOutOfCheck => write!(f, "You cannot castle while in check"),
+            IllegalMove::CastleThroughCheck => {
+                write!(f, "You cannot castle through an attacked square")
+            }
+            IllegalMove::InCheck => write!(f, "Your king is in check and this does not stop it"),
+            IllegalMove::Pinned => write!(f, "That piece is pinned to your king"),
+            IllegalMove::KingIntoCheck => write!(f, "Your king would be in check there"),
+            IllegalMove::LeavesKingInCheck => write!(f, "That would leave your king in check"),
+            IllegalMove::GivesCheck => write!(f, "Giving check is not allowed in Racing Kings"),
+        }
+    }
+}
+
+pub fn explain(
+    board: &ChessBoard,
+    from: (usize, usize),
+    to: (usize, usize),
+) -> Option<IllegalMove> {
+    let Some(piece) = board.piece_at(from) else {
+        return Some(IllegalMove::NoPiece);
+    };
+    if piece.color != board.turn {
+        return Some(IllegalMove::WrongTurn(board.turn));
+    }
+    if piece.legal_moves(board).any(|mv| mv.target == to) {
+        return None;
+    }
+    if board
+        .piece_at(to)
+        .is_some_and(|target| target.color == piece.color)
+    {
+        return Some(IllegalMove::OwnPiece);
+    }
+    let Some(mv) = piece.pseudo_legal_moves(board).find(|mv| mv.target == to) else {
+        return Some(unreachable_reason(board, piece, to));
+    };
+    let mut after = board.clone();
+    mv.perform(&mut after);
+    Some(
+        if board.variant == Variant::RacingKings && after.is_in_check(piece.color.opposite()) {
+            IllegalMove::GivesCheck
+        } else if piece.piece_type == PieceType::King {
+            IllegalMove::KingIntoCheck
+        } else if board.is_in_check(piece.color) {
+            IllegalMove::InCheck
+        } else if board.is_pinned(from) {
+            IllegalMove::Pinned
+        } else {
+            IllegalMove::LeavesKingInCheck
+        },
+    )
+}
+
+fn unreachable_reason(board: &ChessBoard, piece: &ChessPiece, to: (usize, usize)) -> IllegalMove {
+    let dx = to.0 as isize - piece.pos.0 as isize;
+    let dy = to.1 as isize - piece.pos.1 as isize;
+    let straight = (dx == 0) != (dy == 0);
+    let diagonal = dx != 0 && dx.abs() == dy.abs();
+    let aligned = match piece.piece_type {
+        PieceType::Queen => straight || diagonal,
+        PieceType::Rook => straight,
+        PieceType::Bishop => diagonal,
+        PieceType::King if dy == 0 && dx.abs() == 2 => {
+            return castling_reason(board, piece, dx.signum());
+        }
+        PieceType::Pawn => {
+            let forward = match piece.color {
+                PieceColor::White => -1,
+                PieceColor::Black => 1,
+            };
+            if dx.abs() == 1 && dy == forward {
+                return IllegalMove::PawnCaptureOnly;
+            }
+            dx == 0
+                && (dy == forward
+                    || (dy == 2 * forward
+                        && piece.first_move_at.is_none()
+                        && board.variant.pawn_double_step()))
+        }
+        _ => false,
+    };
+    if aligned {
+        IllegalMove::PathBlocked
+    } else {
+        IllegalMove::InvalidMovement(piece.piece_type)
+    }
+}
+
+fn castling_reason(board: &ChessBoard, king: &ChessPiece, direction: isize) -> IllegalMove {
+    let rook = board.pieces.iter().flatten().find(|p| {
+        p.piece_type == PieceType::Rook
+            && p.color == king.color
+            && p.first_move_at.is_none()
+            && p.pos.1 == king.pos.1
+            && (p.pos.0 as isize - king.pos.0 as isize).signum() == direction
+    });
+    let Some(rook) = rook.filter(|_| king.first_move_at.is_none()) else {
+        return IllegalMove::CastlingRightsLost;
+    };
+    let (start, end) = (king.pos.0.min(rook.pos.0), king.pos.0.max(rook.pos.0));
+    if (start + 1..end).any(|col| board.piece_at((col, king.pos.1)).is_some()) {
+        IllegalMove::PathBlocked
+    } else if board.is_in_check(king.color) {
+        IllegalMove::CastleOutOfCheck
+    } else {
+        IllegalMove::CastleThroughCheck
+    }
+}
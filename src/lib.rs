@@ -1,3 +1,68 @@
+#![cfg_attr(not(feature = "std"), no_std)]
+
+extern crate alloc;
+
+#[cfg(feature = "std")]
 pub mod ai;
+#[cfg(feature = "std")]
+pub mod analysis;
+#[cfg(feature = "std")]
+pub mod assets;
+#[cfg(feature = "std")]
+pub mod book;
+#[cfg(feature = "std")]
+pub mod bughouse;
+#[cfg(feature = "std")]
+pub mod clock;
+#[cfg(feature = "std")]
+pub mod config;
+#[cfg(feature = "std")]
+pub mod engine;
+#[cfg(feature = "std")]
+pub mod epd;
+pub mod fairy;
+#[cfg(feature = "std")]
 pub mod game;
+#[cfg(feature = "std")]
+pub mod i18n;
+#[cfg(feature = "std")]
+pub mod import;
+#[cfg(feature = "std")]
+pub mod legality;
 pub mod logic;
+#[cfg(feature = "std")]
+pub mod movepick;
+#[cfg(feature = "std")]
+pub mod movetree;
+#[cfg(feature = "std")]
+pub mod notation;
+mod parallel;
+#[cfg(feature = "std")]
+pub mod pgn;
+#[cfg(feature = "std")]
+pub mod profile;
+#[cfg(feature = "python")]
+pub mod python;
+#[cfg(feature = "std")]
+pub mod record;
+#[cfg(feature = "std")]
+pub mod render;
+#[cfg(feature = "std")]
+pub mod savegame;
+#[cfg(feature = "std")]
+pub mod script;
+#[cfg(feature = "std")]
+pub mod settings;
+#[cfg(feature = "std")]
+pub mod stats;
+#[cfg(feature = "std")]
+pub mod svg;
+#[cfg(feature = "std")]
+pub mod theme;
+#[cfg(feature = "std")]
+pub mod tt;
+#[cfg(feature = "std")]
+pub mod tutorial;
+#[cfg(feature = "std")]
+pub mod uci_engine;
+pub mod zobrist;
@@ -1,11 +1,65 @@
-use rayon::iter::{IntoParallelRefIterator, ParallelIterator};
-use std::{
+use alloc::{
+    format,
+    string::{String, ToString},
+    vec,
+    vec::Vec,
+};
+use core::{
     fmt::{Debug, Display},
+    ops::{Deref, DerefMut},
     str::FromStr,
 };
 use strum::IntoEnumIterator;
 use strum_macros::EnumIter;
 
+use crate::{
+    fairy,
+    parallel::{IntoParallelRefIterator, ParallelIterator},
+    zobrist,
+};
+
+const KNIGHT_OFFSETS: [(isize, isize); 8] = [
+    (2, 1),
+    (2, -1),
+    (-2, 1),
+    (-2, -1),
+    (1, 2),
+    (1, -2),
+    (-1, 2),
+    (-1, -2),
+];
+const KING_OFFSETS: [(isize, isize); 8] = [
+    (-1, -1),
+    (-1, 0),
+    (-1, 1),
+    (0, -1),
+    (0, 1),
+    (1, -1),
+    (1, 0),
+    (1, 1),
+];
+const ROOK_DIRECTIONS: [(isize, isize); 4] = [(-1, 0), (1, 0), (0, -1), (0, 1)];
+const BISHOP_DIRECTIONS: [(isize, isize); 4] = [(-1, -1), (-1, 1), (1, -1), (1, 1)];
+
+type Pin = ((usize, usize), (isize, isize));
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+enum MoveKind {
+    All,
+    Tactical,
+    Quiet,
+}
+
+impl MoveKind {
+    fn includes(&self, board: &ChessBoard, m: &Move) -> bool {
+        match self {
+            MoveKind::All => true,
+            MoveKind::Tactical => m.is_capture(board) || m.is_promotion(),
+            MoveKind::Quiet => !m.is_capture(board) && !m.is_promotion(),
+        }
+    }
+}
+
 #[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, EnumIter)]
 pub enum PieceType {
     King,
@@ -14,20 +68,50 @@ pub enum PieceType {
     Bishop,
     Knight,
     Pawn,
+    #[strum(disabled)]
+    Fairy(u8),
 }
 
+pub const PIECE_TYPE_COUNT: usize = 6 + fairy::MAX_FAIRY_PIECES;
+
 impl PieceType {
     pub fn promotable_to(&self) -> bool {
+        !matches!(
+            self,
+            PieceType::Pawn | PieceType::King | PieceType::Fairy(_)
+        )
+    }
+
+    pub fn readable(&self) -> &'static str {
         match self {
-            PieceType::Pawn => false,
-            PieceType::King => false,
-            _ => true,
+            PieceType::King => "King",
+            PieceType::Queen => "Queen",
+            PieceType::Rook => "Rook",
+            PieceType::Bishop => "Bishop",
+            PieceType::Knight => "Knight",
+            PieceType::Pawn => "Pawn",
+            PieceType::Fairy(index) => fairy::piece(*index).map_or("Fairy piece", |p| &p.name),
         }
     }
+
+    pub fn all() -> impl DoubleEndedIterator<Item = PieceType> {
+        PieceType::iter().chain(fairy::piece_types())
+    }
+
+    pub fn index(&self) -> usize {
+        match self {
+            PieceType::Fairy(index) => 6 + *index as usize,
+            _ => PieceType::iter().position(|p| p == *self).unwrap(),
+        }
+    }
+
+    pub fn from_index(index: usize) -> Option<PieceType> {
+        PieceType::all().nth(index)
+    }
 }
 
 impl Display for PieceType {
-    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
         match self {
             PieceType::King => write!(f, "k"),
             PieceType::Queen => write!(f, "q"),
@@ -35,6 +119,9 @@ impl Display for PieceType {
             PieceType::Bishop => write!(f, "b"),
             PieceType::Knight => write!(f, "n"),
             PieceType::Pawn => write!(f, "p"),
+            PieceType::Fairy(index) => {
+                write!(f, "{}", fairy::piece(*index).map_or('?', |p| p.letter))
+            }
         }
     }
 }
@@ -56,7 +143,7 @@ impl FromStr for PieceType {
             'b' => Ok(PieceType::Bishop),
             'n' => Ok(PieceType::Knight),
             'p' => Ok(PieceType::Pawn),
-            _ => Err(ParsePieceError),
+            c => fairy::by_letter(c).ok_or(ParsePieceError),
         }
     }
 }
@@ -83,7 +170,7 @@ impl PieceColor {
 }
 
 impl Display for PieceColor {
-    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
         match self {
             PieceColor::White => write!(f, "w"),
             PieceColor::Black => write!(f, "b"),
@@ -109,44 +196,94 @@ impl ChessPiece {
         }
     }
 
+    pub fn to_char(&self) -> char {
+        let c = self.piece_type.to_string().chars().next().unwrap();
+        match self.color {
+            PieceColor::White => c.to_ascii_uppercase(),
+            PieceColor::Black => c,
+        }
+    }
+
+    pub fn to_unicode(&self) -> char {
+        match (self.color, self.piece_type) {
+            (PieceColor::White, PieceType::King) => '♔',
+            (PieceColor::White, PieceType::Queen) => '♕',
+            (PieceColor::White, PieceType::Rook) => '♖',
+            (PieceColor::White, PieceType::Bishop) => '♗',
+            (PieceColor::White, PieceType::Knight) => '♘',
+            (PieceColor::White, PieceType::Pawn) => '♙',
+            (PieceColor::Black, PieceType::King) => '♚',
+            (PieceColor::Black, PieceType::Queen) => '♛',
+            (PieceColor::Black, PieceType::Rook) => '♜',
+            (PieceColor::Black, PieceType::Bishop) => '♝',
+            (PieceColor::Black, PieceType::Knight) => '♞',
+            (PieceColor::Black, PieceType::Pawn) => '♟',
+            (_, PieceType::Fairy(_)) => self.to_char(),
+        }
+    }
+
     pub fn move_to(mut self, target: (usize, usize), first_move_at: usize, board: &mut ChessBoard) {
         self.pos = target;
         self.first_move_at = Some(first_move_at);
-        board.pieces[ChessBoard::pos_to_idx(target)] = Some(self);
+        let idx = board.pos_to_idx(target);
+        board.pieces[idx] = Some(self);
     }
 
     fn add_in_dir(
         dir: (isize, isize),
         pos: (usize, usize),
         board: &ChessBoard,
-        moves: &mut Vec<Move>,
+        moves: &mut MoveList,
     ) {
-        let mut target = (pos.0 as isize + dir.0, pos.1 as isize + dir.1);
-        while (0..8).contains(&(target.0 as usize)) && (0..8).contains(&(target.1 as usize)) {
-            moves.push(Move::new(
-                pos,
-                (target.0 as usize, target.1 as usize),
-                MoveType::Normal,
-            ));
-            if board
-                .piece_at((target.0 as usize, target.1 as usize))
-                .is_some()
-            {
+        let mut target = board.offset(pos, dir);
+        while let Some(square) = target {
+            moves.push(Move::new(pos, square, MoveType::Normal));
+            if board.piece_at(square).is_some() {
                 break;
             }
-            target = (target.0 + dir.0, target.1 + dir.1);
+            target = board.offset(square, dir);
         }
     }
 
-    pub fn valid_moves<'a>(
-        &self,
-        board: &'a ChessBoard,
-        ignore_check: bool,
-    ) -> impl Iterator<Item = Move> + 'a {
-        let mut moves = Vec::with_capacity(64);
+    pub fn pseudo_legal_moves(&self, board: &ChessBoard) -> impl Iterator<Item = Move> {
+        let mut moves = MoveList::new();
+        self.generate_moves_into(board, MoveKind::All, &mut moves);
+        moves
+            .iter()
+            .copied()
+            .filter(|m| m.is_pseudo_legal(board))
+            .collect::<Vec<_>>()
+            .into_iter()
+    }
+
+    pub fn legal_moves(&self, board: &ChessBoard) -> impl Iterator<Item = Move> {
+        let pins = board.pins(self.color);
+        let in_check = board.is_in_check(self.color);
+        self.legal_moves_given(board, &pins, in_check).into_iter()
+    }
+
+    fn legal_moves_given(&self, board: &ChessBoard, pins: &[Pin], in_check: bool) -> Vec<Move> {
+        let mut moves = MoveList::new();
+        self.generate_moves_into(board, MoveKind::All, &mut moves);
+        moves
+            .iter()
+            .copied()
+            .filter(|m| m.is_pseudo_legal(board) && m.is_legal_given(board, pins, in_check))
+            .collect()
+    }
+
+    // Moves of other kinds may still be generated, callers filter them out.
+    fn generate_moves_into(&self, board: &ChessBoard, kind: MoveKind, moves: &mut MoveList) {
+        let tactical = kind != MoveKind::Quiet;
+        let quiet = kind != MoveKind::Tactical;
         match self.piece_type {
+            PieceType::Fairy(index) => {
+                if let Some(piece) = fairy::piece(index) {
+                    piece.generate_moves_into(board, self.pos, self.color, moves);
+                }
+            }
             PieceType::King => {
-                if !ignore_check && !board.is_in_check(self.color) && self.first_move_at.is_none() {
+                if quiet && self.first_move_at.is_none() && !board.is_in_check(self.color) {
                     for rook in board.pieces.iter().filter_map(|p| {
                         if p.as_ref().is_some_and(|p| {
                             p.piece_type == PieceType::Rook
@@ -169,7 +306,6 @@ impl ChessPiece {
                         }) && !board.is_pos_attacked(
                             ((self.pos.0 as isize + direction) as usize, self.pos.1),
                             self.color.opposite(),
-                            true,
                         ) {
                             moves.push(Move::new_with_isize(
                                 self.pos,
@@ -215,7 +351,13 @@ impl ChessPiece {
                     _ => unreachable!(),
                 };
                 for &dir in &directions {
-                    Self::add_in_dir(dir, self.pos, board, &mut moves);
+                    if kind == MoveKind::Tactical {
+                        if let Some(target) = board.first_occupied(self.pos, dir) {
+                            moves.push(Move::new(self.pos, target, MoveType::Normal));
+                        }
+                    } else {
+                        Self::add_in_dir(dir, self.pos, board, moves);
+                    }
                 }
             }
             PieceType::Knight => {
@@ -245,62 +387,159 @@ impl ChessPiece {
                 let target_row = (self.pos.1 as isize + direction) as usize;
 
                 if board.piece_at((self.pos.0, target_row)).is_none() {
-                    if target_row == 0 || target_row == 7 {
-                        moves.extend(PieceType::iter().filter(|p| p.promotable_to()).map(
-                            |piece| {
-                                Move::new(
-                                    self.pos,
-                                    (self.pos.0, target_row),
-                                    MoveType::Promotion(piece),
-                                )
-                            },
-                        ));
-                    } else {
+                    if board.is_last_row(target_row) {
+                        if tactical {
+                            moves.extend(PieceType::iter().filter(|p| p.promotable_to()).map(
+                                |piece| {
+                                    Move::new(
+                                        self.pos,
+                                        (self.pos.0, target_row),
+                                        MoveType::Promotion(piece),
+                                    )
+                                },
+                            ));
+                        }
+                    } else if quiet {
                         moves.push(Move::new(
                             self.pos,
                             (self.pos.0, target_row),
                             MoveType::Normal,
                         ));
                     }
-                    if self.first_move_at.is_none() {
-                        let double_target_row = (self.pos.1 as isize + 2 * direction) as usize;
-                        if board.piece_at((self.pos.0, double_target_row)).is_none() {
-                            moves.push(Move::new(
-                                self.pos,
-                                (self.pos.0, double_target_row),
-                                MoveType::Normal,
-                            ));
+                    if quiet && self.first_move_at.is_none() && board.variant.pawn_double_step() {
+                        if let Some(double_target) = board.offset(self.pos, (0, 2 * direction)) {
+                            if board.piece_at(double_target).is_none() {
+                                moves.push(Move::new(self.pos, double_target, MoveType::Normal));
+                            }
                         }
                     }
                 }
 
-                for &(dx, dy) in &[(-1, direction), (1, direction)] {
-                    let target = (self.pos.0 as isize + dx, self.pos.1 as isize + dy);
-                    if (0..8).contains(&target.0) && (0..8).contains(&target.1) {
-                        if let Some(target_piece) =
-                            board.piece_at((target.0 as usize, target.1 as usize))
-                        {
-                            if target_piece.color != self.color {
-                                moves.push(Move::new(
-                                    self.pos,
-                                    (target.0 as usize, target.1 as usize),
-                                    MoveType::Normal,
+                if !tactical {
+                    return;
+                }
+                for dir in [(-1, direction), (1, direction)] {
+                    if let Some(target) = board.offset(self.pos, dir) {
+                        if let Some(target_piece) = board.piece_at(target) {
+                            if target_piece.color != self.color && board.is_last_row(target.1) {
+                                moves.extend(PieceType::iter().filter(|p| p.promotable_to()).map(
+                                    |piece| Move::new(self.pos, target, MoveType::Promotion(piece)),
                                 ));
+                            } else if target_piece.color != self.color {
+                                moves.push(Move::new(self.pos, target, MoveType::Normal));
                             }
+                        } else if board.en_passant == Some(target) && board.turn == self.color {
+                            moves.push(Move::new(self.pos, target, MoveType::EnPassant));
                         }
                     }
                 }
             }
         }
-        moves
-            .into_iter()
-            .filter(move |m| m.is_valid(board, ignore_check))
     }
 }
 
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Hash, EnumIter)]
+pub enum Variant {
+    #[default]
+    Standard,
+    RacingKings,
+    Gardner,
+}
+
+impl Variant {
+    pub fn start_fen(&self) -> &'static str {
+        match self {
+            Variant::Standard => START_FEN,
+            Variant::RacingKings => RACING_KINGS_FEN,
+            Variant::Gardner => GARDNER_FEN,
+        }
+    }
+
+    pub fn pawn_double_step(&self) -> bool {
+        *self != Variant::Gardner
+    }
+
+    pub fn readable(&self) -> &'static str {
+        match self {
+            Variant::Standard => "Standard",
+            Variant::RacingKings => "Racing Kings",
+            Variant::Gardner => "Gardner minichess",
+        }
+    }
+
+    pub fn uci_name(&self) -> &'static str {
+        match self {
+            Variant::Standard => "chess",
+            Variant::RacingKings => "racingkings",
+            Variant::Gardner => "gardner",
+        }
+    }
+}
+
+#[derive(Debug, PartialEq, Eq)]
+pub struct ParseVariantError;
+
+impl FromStr for Variant {
+    type Err = ParseVariantError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let name = s.replace([' ', '-', '_'], "").to_lowercase();
+        Variant::iter()
+            .find(|variant| {
+                name == variant.uci_name()
+                    || name == variant.readable().replace(' ', "").to_lowercase()
+            })
+            .ok_or(ParseVariantError)
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
 pub enum WinState {
     Checkmate(PieceColor),
     Stalemate,
+    FiftyMoveRule,
+    Repetition,
+    InsufficientMaterial,
+    Timeout(PieceColor),
+    Resignation(PieceColor),
+    DrawAgreement,
+    RaceWon(PieceColor),
+    RaceDrawn,
+}
+
+impl WinState {
+    pub fn winner(&self) -> Option<PieceColor> {
+        match self {
+            WinState::Checkmate(color)
+            | WinState::Timeout(color)
+            | WinState::Resignation(color)
+            | WinState::RaceWon(color) => Some(*color),
+            _ => None,
+        }
+    }
+
+    pub fn result(&self) -> &'static str {
+        match self.winner() {
+            Some(PieceColor::White) => "1-0",
+            Some(PieceColor::Black) => "0-1",
+            None => "1/2-1/2",
+        }
+    }
+
+    pub fn reason(&self) -> &'static str {
+        match self {
+            WinState::Checkmate(_) => "checkmate",
+            WinState::Stalemate => "stalemate",
+            WinState::FiftyMoveRule => "fifty-move rule",
+            WinState::Repetition => "threefold repetition",
+            WinState::InsufficientMaterial => "insufficient material",
+            WinState::Timeout(_) => "timeout",
+            WinState::Resignation(_) => "resignation",
+            WinState::DrawAgreement => "agreement",
+            WinState::RaceWon(_) => "reaching the eighth rank",
+            WinState::RaceDrawn => "both kings reaching the eighth rank",
+        }
+    }
 }
 
 #[derive(Copy, Clone, Debug, PartialEq, Eq, Hash)]
@@ -313,39 +552,252 @@ pub enum MoveType {
     },
     EnPassant,
     Promotion(PieceType),
+    Drop(PieceType),
 }
 
-#[derive(Copy, Clone, PartialEq, Eq, Hash)]
-pub struct Move {
-    pub original: (usize, usize),
-    pub target: (usize, usize),
-    pub move_type: MoveType,
+#[derive(Debug, PartialEq, Eq)]
+pub struct ParseMoveError;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum FenErrorKind {
+    MissingPlacement,
+    TooManyRows,
+    RowTooWide,
+    RowWidthMismatch,
+    InvalidPiece(char),
+    InvalidTurn,
+    InvalidCastling(char),
+    InvalidEnPassant,
+    InvalidNumber,
+    TrailingFields,
 }
 
-impl ToString for Move {
-    fn to_string(&self) -> String {
-        match self.move_type {
-            MoveType::Normal | MoveType::EnPassant | MoveType::Castling { .. } => format!(
-                "{}{}",
-                pos_to_notation(self.original),
-                pos_to_notation(self.target)
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct FenError {
+    pub index: usize,
+    pub kind: FenErrorKind,
+}
+
+impl FenError {
+    fn new(index: usize, kind: FenErrorKind) -> Self {
+        Self { index, kind }
+    }
+}
+
+impl Display for FenError {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        match self.kind {
+            FenErrorKind::MissingPlacement => write!(f, "missing piece placement"),
+            FenErrorKind::TooManyRows => {
+                write!(f, "more than {} ranks", MAX_BOARD_SIZE)
+            }
+            FenErrorKind::RowTooWide => write!(f, "rank is too long"),
+            FenErrorKind::RowWidthMismatch => {
+                write!(f, "rank length differs from the first rank")
+            }
+            FenErrorKind::InvalidPiece(c) => write!(f, "unknown piece '{}'", c),
+            FenErrorKind::InvalidTurn => write!(f, "side to move must be 'w' or 'b'"),
+            FenErrorKind::InvalidCastling(c) => write!(f, "invalid castling right '{}'", c),
+            FenErrorKind::InvalidEnPassant => write!(f, "invalid en passant square"),
+            FenErrorKind::InvalidNumber => write!(f, "invalid move number"),
+            FenErrorKind::TrailingFields => write!(f, "unexpected extra field"),
+        }?;
+        write!(f, " at character {}", self.index + 1)
+    }
+}
+
+impl core::error::Error for FenError {}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum PositionError {
+    KingCount(PieceColor, usize),
+    PawnOnBackRank(String),
+    OpponentInCheck(PieceColor),
+    InvalidCastlingRights(PieceColor),
+}
+
+impl Display for PositionError {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        match self {
+            PositionError::KingCount(color, 0) => write!(f, "{} has no king", color.readable()),
+            PositionError::KingCount(color, count) => {
+                write!(f, "{} has {} kings", color.readable(), count)
+            }
+            PositionError::PawnOnBackRank(square) => {
+                write!(f, "pawn on {} is on a back rank", square)
+            }
+            PositionError::OpponentInCheck(color) => write!(
+                f,
+                "{} is in check but it is not their move",
+                color.readable()
             ),
-            MoveType::Promotion(piece_type) => format!(
-                "{}{}{}",
-                pos_to_notation(self.original),
-                pos_to_notation(self.target),
-                piece_type
+            PositionError::InvalidCastlingRights(color) => write!(
+                f,
+                "{} can castle without its king and rook on their starting squares",
+                color.readable()
             ),
         }
     }
 }
 
-impl Debug for Move {
-    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
-        write!(f, "{}", self.to_string())
+impl core::error::Error for PositionError {}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum BuildError {
+    InvalidSize(usize, usize),
+    OutOfBounds((usize, usize)),
+    InvalidCastling(char),
+    InvalidEnPassant((usize, usize)),
+    InvalidPosition(PositionError),
+}
+
+impl Display for BuildError {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        match self {
+            BuildError::InvalidSize(width, height) => {
+                write!(f, "a {}x{} board is not supported", width, height)
+            }
+            BuildError::OutOfBounds(pos) => write!(f, "square {:?} is off the board", pos),
+            BuildError::InvalidCastling(c) => write!(f, "invalid castling right '{}'", c),
+            BuildError::InvalidEnPassant(pos) => {
+                write!(f, "square {:?} is not a valid en passant square", pos)
+            }
+            BuildError::InvalidPosition(err) => write!(f, "{}", err),
+        }
+    }
+}
+
+impl core::error::Error for BuildError {}
+
+pub const MAX_MOVES: usize = 256;
+const PARALLEL_PERFT_DEPTH: usize = 3;
+
+// Moves live in the fixed array until it is full and then spill onto the heap,
+// which only happens for pathological positions and very large boards.
+#[derive(Clone)]
+pub struct MoveList {
+    moves: [Move; MAX_MOVES],
+    len: usize,
+    spilled: Vec<Move>,
+}
+
+impl Default for MoveList {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl MoveList {
+    pub fn new() -> Self {
+        Self {
+            moves: [Move::new((0, 0), (0, 0), MoveType::Normal); MAX_MOVES],
+            len: 0,
+            spilled: Vec::new(),
+        }
+    }
+
+    fn is_spilled(&self) -> bool {
+        self.len > MAX_MOVES
+    }
+
+    fn unspill(&mut self) {
+        if !self.is_spilled() && !self.spilled.is_empty() {
+            self.moves[..self.len].copy_from_slice(&self.spilled);
+            self.spilled.clear();
+        }
+    }
+
+    pub fn push(&mut self, m: Move) {
+        if self.len < MAX_MOVES {
+            self.moves[self.len] = m;
+        } else {
+            if self.len == MAX_MOVES {
+                self.spilled.extend_from_slice(&self.moves);
+            }
+            self.spilled.push(m);
+        }
+        self.len += 1;
+    }
+
+    pub fn pop(&mut self) -> Option<Move> {
+        if self.len == 0 {
+            return None;
+        }
+        let m = self[self.len - 1];
+        if self.is_spilled() {
+            self.spilled.pop();
+        }
+        self.len -= 1;
+        self.unspill();
+        Some(m)
+    }
+
+    pub fn clear(&mut self) {
+        self.len = 0;
+        self.spilled.clear();
+    }
+
+    pub fn retain(&mut self, mut keep: impl FnMut(&Move) -> bool) {
+        if self.is_spilled() {
+            self.spilled.retain(keep);
+            self.len = self.spilled.len();
+            self.unspill();
+            return;
+        }
+        let mut kept = 0;
+        for i in 0..self.len {
+            if keep(&self.moves[i]) {
+                self.moves[kept] = self.moves[i];
+                kept += 1;
+            }
+        }
+        self.len = kept;
+    }
+}
+
+impl Deref for MoveList {
+    type Target = [Move];
+
+    fn deref(&self) -> &[Move] {
+        if self.is_spilled() {
+            &self.spilled
+        } else {
+            &self.moves[..self.len]
+        }
+    }
+}
+
+impl DerefMut for MoveList {
+    fn deref_mut(&mut self) -> &mut [Move] {
+        if self.is_spilled() {
+            &mut self.spilled
+        } else {
+            &mut self.moves[..self.len]
+        }
+    }
+}
+
+impl Extend<Move> for MoveList {
+    fn extend<T: IntoIterator<Item = Move>>(&mut self, iter: T) {
+        for m in iter {
+            self.push(m);
+        }
+    }
+}
+
+impl Debug for MoveList {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        f.debug_list().entries(self.iter()).finish()
     }
 }
 
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Hash)]
+pub struct Move {
+    pub original: (usize, usize),
+    pub target: (usize, usize),
+    pub move_type: MoveType,
+}
+
 impl Move {
     pub fn new(original: (usize, usize), target: (usize, usize), move_type: MoveType) -> Self {
         Self {
@@ -355,34 +807,67 @@ impl Move {
         }
     }
 
-    pub fn from_str(s: &str, board: &ChessBoard) -> Result<Self, ()> {
-        match s.len() {
-            4 => {
-                let original = notation_to_pos(&s[0..2]).ok_or(())?;
-                let target = notation_to_pos(&s[2..4]).ok_or(())?;
-                let piece = board.piece_at(original).ok_or(())?;
-                if piece.piece_type == PieceType::King
-                    && (original.0 as isize - target.0 as isize).abs() == 2
-                {
-                    Ok(Move::new(
-                        original,
-                        target,
-                        MoveType::Castling {
-                            rook: (if target.0 < 4 { 0 } else { 7 }, target.1),
-                            direction: (target.0 as isize - original.0 as isize).signum(),
+    pub fn from_str(s: &str, board: &ChessBoard) -> Result<Self, ParseMoveError> {
+        if let Some((piece, target)) = s.split_once('@') {
+            let piece_type = PieceType::from_str(piece).map_err(|_| ParseMoveError)?;
+            let target = board.parse_square(target).ok_or(ParseMoveError)?;
+            return Ok(Move::new(target, target, MoveType::Drop(piece_type)));
+        }
+        let (original, rest) = split_square(s).ok_or(ParseMoveError)?;
+        let (target, promotion) = split_square(rest).ok_or(ParseMoveError)?;
+        let original = board.parse_square(original).ok_or(ParseMoveError)?;
+        let target = board.parse_square(target).ok_or(ParseMoveError)?;
+        if !promotion.is_empty() {
+            let piece_type = PieceType::from_str(promotion).map_err(|_| ParseMoveError)?;
+            return Ok(Move::new(original, target, MoveType::Promotion(piece_type)));
+        }
+        let piece = board.piece_at(original).ok_or(ParseMoveError)?;
+        if piece.piece_type == PieceType::King
+            && (original.0 as isize - target.0 as isize).abs() == 2
+        {
+            Ok(Move::new(
+                original,
+                target,
+                MoveType::Castling {
+                    rook: (
+                        if target.0 < board.width / 2 {
+                            0
+                        } else {
+                            board.width - 1
                         },
-                    ))
-                } else {
-                    Ok(Move::new(original, target, MoveType::Normal))
-                }
-            }
-            5 => {
-                let original = notation_to_pos(&s[0..2]).ok_or(())?;
-                let target = notation_to_pos(&s[2..4]).ok_or(())?;
-                let piece_type = PieceType::from_str(&s[4..5]).map_err(|_| ())?;
-                Ok(Move::new(original, target, MoveType::Promotion(piece_type)))
-            }
-            _ => Err(()),
+                        target.1,
+                    ),
+                    direction: (target.0 as isize - original.0 as isize).signum(),
+                },
+            ))
+        } else if piece.piece_type == PieceType::Pawn
+            && original.0 != target.0
+            && board.en_passant == Some(target)
+        {
+            Ok(Move::new(original, target, MoveType::EnPassant))
+        } else {
+            Ok(Move::new(original, target, MoveType::Normal))
+        }
+    }
+
+    pub fn to_uci(&self, board: &ChessBoard) -> String {
+        match self.move_type {
+            MoveType::Promotion(piece_type) => format!(
+                "{}{}{}",
+                board.square_name(self.original),
+                board.square_name(self.target),
+                piece_type
+            ),
+            MoveType::Drop(piece_type) => format!(
+                "{}@{}",
+                piece_type.to_string().to_uppercase(),
+                board.square_name(self.target)
+            ),
+            _ => format!(
+                "{}{}",
+                board.square_name(self.original),
+                board.square_name(self.target)
+            ),
         }
     }
 
@@ -405,37 +890,109 @@ impl Move {
         }
     }
 
-    pub fn is_valid(&self, board: &ChessBoard, ignore_check: bool) -> bool {
-        if self.target.0 >= 8 || self.target.1 >= 8 {
+    pub fn is_capture(&self, board: &ChessBoard) -> bool {
+        self.move_type == MoveType::EnPassant || board.piece_at(self.target).is_some()
+    }
+
+    pub fn is_promotion(&self) -> bool {
+        matches!(self.move_type, MoveType::Promotion(_))
+    }
+
+    pub fn is_drop(&self) -> bool {
+        matches!(self.move_type, MoveType::Drop(_))
+    }
+
+    pub fn is_pseudo_legal(&self, board: &ChessBoard) -> bool {
+        if self.target.0 >= board.width || self.target.1 >= board.height {
             return false;
         }
-        if let Some(piece) = board.piece_at(self.original) {
-            if let Some(target_piece) = board.piece_at(self.target) {
-                if piece.color == target_piece.color {
-                    return false;
-                }
-            }
-        } else {
+        if let MoveType::Drop(piece_type) = self.move_type {
+            return board.piece_at(self.target).is_none()
+                && piece_type != PieceType::King
+                && !(piece_type == PieceType::Pawn && board.is_last_row(self.target.1));
+        }
+        let Some(piece) = board.piece_at(self.original) else {
+            return false;
+        };
+        board
+            .piece_at(self.target)
+            .is_none_or(|target_piece| target_piece.color != piece.color)
+    }
+
+    pub fn is_legal(&self, board: &ChessBoard) -> bool {
+        if !self.is_pseudo_legal(board) {
             return false;
         }
-        if !ignore_check {
+        let color = match self.move_type {
+            MoveType::Drop(_) => board.turn,
+            _ => board.piece_at(self.original).unwrap().color,
+        };
+        self.is_legal_given(board, &board.pins(color), board.is_in_check(color))
+    }
+
+    fn is_legal_given(&self, board: &ChessBoard, pins: &[Pin], in_check: bool) -> bool {
+        if self.is_drop() {
+            if !in_check {
+                return true;
+            }
             let mut temp_board = board.clone();
-            if let Some(piece) = board.piece_at(self.original) {
-                self.perform(&mut temp_board);
-                if temp_board.is_in_check(piece.color) {
-                    return false;
-                }
+            self.perform(&mut temp_board);
+            return !temp_board.is_in_check(board.turn);
+        }
+        let piece = board.piece_at(self.original).unwrap();
+        if in_check
+            || piece.piece_type == PieceType::King
+            || board.variant == Variant::RacingKings
+            || board.has_fairy_pieces()
+            || !matches!(self.move_type, MoveType::Normal | MoveType::Promotion(_))
+        {
+            let mut temp_board = board.clone();
+            self.perform(&mut temp_board);
+            return temp_board.is_legal_after_move(piece.color);
+        }
+        match pins.iter().find(|(pos, _)| *pos == self.original) {
+            Some(&(_, (dx, dy))) => {
+                let move_dx = self.target.0 as isize - self.original.0 as isize;
+                let move_dy = self.target.1 as isize - self.original.1 as isize;
+                move_dx * dy == move_dy * dx
             }
+            None => true,
         }
-        true
     }
 
     pub fn perform(&self, board: &mut ChessBoard) {
         let moves_made = board.moves_made;
-        if let Some(mut piece) = board.pieces[ChessBoard::pos_to_idx(self.original)].take() {
+        if let MoveType::Drop(piece_type) = self.move_type {
+            let mut piece = ChessPiece::new(piece_type, self.target, board.turn);
+            if piece_type != PieceType::Pawn || self.target.1 != board.pawn_start_row(board.turn) {
+                piece.first_move_at = Some(moves_made);
+            }
+            let idx = board.pos_to_idx(self.target);
+            board.pieces[idx] = Some(piece);
+            board.halfmove_clock = 0;
+            board.history.clear();
+            board.en_passant = None;
+            board.turn = board.turn.opposite();
+            board.moves_made += 1;
+            return;
+        }
+        let irreversible = board.piece_at(self.target).is_some()
+            || board
+                .piece_at(self.original)
+                .is_some_and(|p| p.piece_type == PieceType::Pawn);
+        if irreversible {
+            board.halfmove_clock = 0;
+            board.history.clear();
+        } else {
+            board.halfmove_clock += 1;
+            board.history.push(board.zobrist_key());
+        }
+        let original = board.pos_to_idx(self.original);
+        if let Some(mut piece) = board.pieces[original].take() {
             match self.move_type {
                 MoveType::Castling { rook, direction } => {
-                    if let Some(rook_piece) = board.pieces[ChessBoard::pos_to_idx(rook)].take() {
+                    let rook = board.pos_to_idx(rook);
+                    if let Some(rook_piece) = board.pieces[rook].take() {
                         let target = ((self.target.0 as isize - direction) as usize, self.target.1);
                         rook_piece.move_to(target, moves_made, board);
                     }
@@ -444,11 +1001,14 @@ impl Move {
                     piece.piece_type = piece_type;
                 }
                 MoveType::EnPassant => {
-                    let target = (self.target.0, self.original.1);
-                    board.pieces[ChessBoard::pos_to_idx(target)] = None;
+                    let target = board.pos_to_idx((self.target.0, self.original.1));
+                    board.pieces[target] = None;
                 }
-                MoveType::Normal => {}
+                MoveType::Normal | MoveType::Drop(_) => {}
             }
+            board.en_passant = (piece.piece_type == PieceType::Pawn
+                && self.original.1.abs_diff(self.target.1) == 2)
+                .then_some((self.original.0, (self.original.1 + self.target.1) / 2));
             piece.move_to(self.target, moves_made, board);
         }
         board.turn = board.turn.opposite();
@@ -456,11 +1016,22 @@ impl Move {
     }
 }
 
+pub const START_FEN: &str = "rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq - 0 1";
+pub const RACING_KINGS_FEN: &str = "8/8/8/8/8/8/krbnNBRK/qrbnNBRQ w - - 0 1";
+pub const GARDNER_FEN: &str = "rnbqk/ppppp/5/PPPPP/RNBQK w - - 0 1";
+pub const MAX_BOARD_SIZE: usize = 16;
+
 #[derive(Clone, Debug, PartialEq, Eq, Hash)]
 pub struct ChessBoard {
-    pub pieces: [Option<ChessPiece>; 64],
+    pub pieces: Vec<Option<ChessPiece>>,
+    pub width: usize,
+    pub height: usize,
     pub turn: PieceColor,
+    pub en_passant: Option<(usize, usize)>,
     pub moves_made: usize,
+    pub halfmove_clock: usize,
+    pub history: Vec<u64>,
+    pub variant: Variant,
 }
 
 impl Default for ChessBoard {
@@ -472,121 +1043,1046 @@ impl Default for ChessBoard {
 impl ChessBoard {
     pub fn new() -> Self {
         let mut board = ChessBoard {
-            pieces: [const { None }; 64],
+            pieces: vec![None; 64],
+            width: 8,
+            height: 8,
             turn: PieceColor::White,
+            en_passant: None,
             moves_made: 0,
+            halfmove_clock: 0,
+            history: Vec::new(),
+            variant: Variant::Standard,
         };
         board.initialize_pieces();
         board
     }
 
-    fn pos_to_idx(pos: (usize, usize)) -> usize {
-        pos.0 + pos.1 * 8
+    pub fn with_variant(variant: Variant) -> Self {
+        let mut board = Self::new();
+        board.variant = variant;
+        board
+            .set_from_fen(variant.start_fen())
+            .expect("variant starting positions should be valid FENs");
+        board
     }
 
-    fn initialize_pieces(&mut self) {
-        self.set_from_fen("rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR")
-    }
-    pub fn set_from_fen(&mut self, fen: &str) {
-        let lines = fen.split('/');
-        let mut pos = (0, 0);
-        self.pieces = [const { None }; 64];
-        for line in lines {
-            for c in line.chars() {
-                match c {
-                    '1'..='8' => {
-                        let empty_squares = c.to_digit(10).unwrap() as usize;
-                        pos.0 += empty_squares;
-                    }
-                    c => {
-                        let piece_type = PieceType::from_str(&c.to_string()).unwrap();
-                        let color = if c.is_uppercase() {
-                            PieceColor::White
-                        } else {
-                            PieceColor::Black
-                        };
-                        self.pieces[Self::pos_to_idx(pos)] =
-                            Some(ChessPiece::new(piece_type, pos, color));
-                        pos.0 += 1;
-                    }
-                }
-            }
-            pos.0 = 0;
-            pos.1 += 1;
-            if pos.1 >= 8 {
-                break;
-            }
-        }
+    fn pos_to_idx(&self, pos: (usize, usize)) -> usize {
+        pos.0 + pos.1 * self.width
     }
 
-    pub fn piece_at(&self, pos: (usize, usize)) -> Option<&ChessPiece> {
-        self.pieces[Self::pos_to_idx(pos)].as_ref()
+    pub fn squares(&self) -> impl Iterator<Item = (usize, usize)> + '_ {
+        (0..self.height).flat_map(move |row| (0..self.width).map(move |col| (col, row)))
     }
 
-    pub fn piece_at_mut(&mut self, pos: (usize, usize)) -> Option<&mut ChessPiece> {
-        self.pieces[Self::pos_to_idx(pos)].as_mut()
+    pub fn in_bounds(&self, pos: (usize, usize)) -> bool {
+        pos.0 < self.width && pos.1 < self.height
     }
 
-    pub fn valid_moves<'a>(
-        &'a self,
-        ignore_check: bool,
-        color: PieceColor,
-    ) -> impl ParallelIterator<Item = Move> + 'a {
-        self.pieces
-            .par_iter()
-            .filter_map(move |piece| {
-                piece.as_ref().and_then(|piece| {
-                    if piece.color == color {
-                        Some(piece)
-                    } else {
-                        None
+    pub fn is_last_row(&self, row: usize) -> bool {
+        row == 0 || row == self.height - 1
+    }
+
+    pub fn home_row(&self, color: PieceColor) -> usize {
+        match color {
+            PieceColor::White => self.height - 1,
+            PieceColor::Black => 0,
+        }
+    }
+
+    pub fn pawn_start_row(&self, color: PieceColor) -> usize {
+        match color {
+            PieceColor::White => self.height.saturating_sub(2),
+            PieceColor::Black => 1,
+        }
+    }
+
+    pub fn square_name(&self, pos: (usize, usize)) -> String {
+        format!("{}{}", (b'a' + pos.0 as u8) as char, self.height - pos.1)
+    }
+
+    pub fn parse_square(&self, name: &str) -> Option<(usize, usize)> {
+        let mut chars = name.chars();
+        let file = chars.next().filter(char::is_ascii_lowercase)? as usize - 'a' as usize;
+        let rank = chars.as_str().parse::<usize>().ok()?;
+        let pos = (file, self.height.checked_sub(rank)?);
+        (rank > 0 && self.in_bounds(pos)).then_some(pos)
+    }
+
+    fn initialize_pieces(&mut self) {
+        self.set_from_fen(START_FEN)
+            .expect("starting position should be a valid FEN");
+    }
+
+    pub fn set_from_fen(&mut self, fen: &str) -> Result<(), FenError> {
+        let mut fields = fen_fields(fen).into_iter();
+        let Some((start, placement)) = fields.next() else {
+            return Err(FenError::new(0, FenErrorKind::MissingPlacement));
+        };
+        let lines = placement.split('/').collect::<Vec<_>>();
+        if lines.len() > MAX_BOARD_SIZE {
+            return Err(FenError::new(start, FenErrorKind::TooManyRows));
+        }
+        let width = fen_row_width(lines[0], start)?;
+        if width == 0 {
+            return Err(FenError::new(start, FenErrorKind::MissingPlacement));
+        }
+        if width > MAX_BOARD_SIZE {
+            return Err(FenError::new(start, FenErrorKind::RowTooWide));
+        }
+        let mut board = ChessBoard {
+            pieces: vec![None; width * lines.len()],
+            width,
+            height: lines.len(),
+            turn: PieceColor::White,
+            en_passant: None,
+            moves_made: 0,
+            halfmove_clock: 0,
+            history: Vec::new(),
+            variant: self.variant,
+        };
+        let mut offset = start;
+        for (row, line) in lines.into_iter().enumerate() {
+            let mut col = 0;
+            let mut empty_squares: usize = 0;
+            for (i, c) in line.char_indices() {
+                if let Some(digit) = c.to_digit(10) {
+                    empty_squares = empty_squares
+                        .checked_mul(10)
+                        .and_then(|n| n.checked_add(digit as usize))
+                        .filter(|&n| n <= width - col)
+                        .ok_or(FenError::new(offset + i, FenErrorKind::RowTooWide))?;
+                    continue;
+                }
+                col += core::mem::take(&mut empty_squares);
+                let piece_type = PieceType::from_str(&c.to_string())
+                    .map_err(|_| FenError::new(offset + i, FenErrorKind::InvalidPiece(c)))?;
+                if col >= width {
+                    return Err(FenError::new(offset + i, FenErrorKind::RowTooWide));
+                }
+                let color = if c.is_uppercase() {
+                    PieceColor::White
+                } else {
+                    PieceColor::Black
+                };
+                let mut piece = ChessPiece::new(piece_type, (col, row), color);
+                if piece_type == PieceType::Pawn && row != board.pawn_start_row(color) {
+                    piece.first_move_at = Some(0);
+                }
+                let idx = board.pos_to_idx((col, row));
+                board.pieces[idx] = Some(piece);
+                col += 1;
+            }
+            if col + empty_squares != width {
+                return Err(FenError::new(offset, FenErrorKind::RowWidthMismatch));
+            }
+            offset += line.len() + 1;
+        }
+
+        match fields.next() {
+            Some((_, "w")) | None => {}
+            Some((_, "b")) => board.turn = PieceColor::Black,
+            Some((offset, _)) => return Err(FenError::new(offset, FenErrorKind::InvalidTurn)),
+        }
+        match fields.next() {
+            Some((offset, castling)) => board.set_castling_rights(castling, offset)?,
+            None => {
+                board.clear_castling_rights();
+                for c in ['K', 'Q', 'k', 'q'] {
+                    board.grant_castling_right(c);
+                }
+            }
+        }
+        if let Some((offset, en_passant)) = fields.next() {
+            if en_passant != "-" {
+                board.en_passant = board
+                    .parse_square(en_passant)
+                    .filter(|&pos| board.is_valid_en_passant(pos));
+                if board.en_passant.is_none() {
+                    return Err(FenError::new(offset, FenErrorKind::InvalidEnPassant));
+                }
+            }
+        }
+        let mut number = |default| match fields.next() {
+            Some((offset, n)) => n
+                .parse::<usize>()
+                .map_err(|_| FenError::new(offset, FenErrorKind::InvalidNumber)),
+            None => Ok(default),
+        };
+        board.halfmove_clock = number(0)?;
+        let fullmove = number(1)?;
+        if let Some((offset, _)) = fields.next() {
+            return Err(FenError::new(offset, FenErrorKind::TrailingFields));
+        }
+        board.moves_made =
+            fullmove.saturating_sub(1) * 2 + (board.turn == PieceColor::Black) as usize;
+        *self = board;
+        Ok(())
+    }
+
+    fn set_castling_rights(&mut self, castling: &str, offset: usize) -> Result<(), FenError> {
+        self.clear_castling_rights();
+        if castling == "-" {
+            return Ok(());
+        }
+        for (i, c) in castling.char_indices() {
+            if !self.grant_castling_right(c) {
+                return Err(FenError::new(offset + i, FenErrorKind::InvalidCastling(c)));
+            }
+        }
+        Ok(())
+    }
+
+    fn clear_castling_rights(&mut self) {
+        for piece in self.pieces.iter_mut().flatten() {
+            if matches!(piece.piece_type, PieceType::King | PieceType::Rook) {
+                piece.first_move_at = Some(0);
+            }
+        }
+    }
+
+    fn grant_castling_right(&mut self, c: char) -> bool {
+        let color = if c.is_ascii_uppercase() {
+            PieceColor::White
+        } else {
+            PieceColor::Black
+        };
+        let back_row = self.home_row(color);
+        let Some(king) = self.king_pos(color).filter(|pos| pos.1 == back_row) else {
+            return false;
+        };
+        let rooks = self.back_rank_rooks(color);
+        let rook_col = match c.to_ascii_lowercase() {
+            'k' => rooks.iter().copied().filter(|&col| col > king.0).max(),
+            'q' => rooks.iter().copied().filter(|&col| col < king.0).min(),
+            file @ 'a'..='z' => {
+                Some(file as usize - 'a' as usize).filter(|col| rooks.contains(col))
+            }
+            _ => None,
+        };
+        let Some(rook_col) = rook_col.filter(|&col| col != king.0) else {
+            return false;
+        };
+        for pos in [king, (rook_col, back_row)] {
+            if let Some(piece) = self.piece_at_mut(pos) {
+                piece.first_move_at = None;
+            }
+        }
+        true
+    }
+
+    fn back_rank_rooks(&self, color: PieceColor) -> Vec<usize> {
+        let back_row = self.home_row(color);
+        (0..self.width)
+            .filter(|&col| {
+                self.piece_at((col, back_row))
+                    .is_some_and(|p| p.piece_type == PieceType::Rook && p.color == color)
+            })
+            .collect()
+    }
+
+    fn castling_field(&self, color: PieceColor) -> String {
+        let back_row = self.home_row(color);
+        let Some(king) = self
+            .king_pos(color)
+            .and_then(|pos| self.piece_at(pos))
+            .filter(|king| king.first_move_at.is_none() && king.pos.1 == back_row)
+        else {
+            return String::new();
+        };
+        let rooks = self.back_rank_rooks(color);
+        let outermost_kingside = rooks.iter().copied().filter(|&col| col > king.pos.0).max();
+        let outermost_queenside = rooks.iter().copied().filter(|&col| col < king.pos.0).min();
+        let mut field = String::new();
+        for &col in rooks.iter().rev() {
+            if self
+                .piece_at((col, back_row))
+                .is_some_and(|rook| rook.first_move_at.is_none())
+            {
+                let c = if Some(col) == outermost_kingside {
+                    'k'
+                } else if Some(col) == outermost_queenside {
+                    'q'
+                } else {
+                    (b'a' + col as u8) as char
+                };
+                field.push(match color {
+                    PieceColor::White => c.to_ascii_uppercase(),
+                    PieceColor::Black => c,
+                });
+            }
+        }
+        field
+    }
+
+    // The square behind a pawn of the side not to move that could just have
+    // made a double step.
+    fn is_valid_en_passant(&self, pos: (usize, usize)) -> bool {
+        let mover = self.turn.opposite();
+        let pawn_dir = match self.turn {
+            PieceColor::White => 1,
+            PieceColor::Black => -1,
+        };
+        self.in_bounds(pos)
+            && self.piece_at(pos).is_none()
+            && self.offset(pos, (0, -pawn_dir)).is_some_and(|origin| {
+                self.piece_at(origin).is_none() && origin.1 == self.pawn_start_row(mover)
+            })
+            && self
+                .offset(pos, (0, pawn_dir))
+                .and_then(|pawn| self.piece_at(pawn))
+                .is_some_and(|pawn| pawn.piece_type == PieceType::Pawn && pawn.color == mover)
+    }
+
+    // Only an en passant square that a pawn can capture on changes the position.
+    fn can_capture_en_passant(&self) -> bool {
+        let pawn_dir = match self.turn {
+            PieceColor::White => 1,
+            PieceColor::Black => -1,
+        };
+        self.en_passant.is_some_and(|target| {
+            [-1, 1].into_iter().any(|dx| {
+                self.offset(target, (dx, pawn_dir))
+                    .and_then(|pos| self.piece_at(pos))
+                    .is_some_and(|p| p.piece_type == PieceType::Pawn && p.color == self.turn)
+            })
+        })
+    }
+
+    pub fn to_fen(&self) -> String {
+        let mut fen = String::new();
+        for row in 0..self.height {
+            let mut empty = 0;
+            for col in 0..self.width {
+                match self.piece_at((col, row)) {
+                    Some(piece) => {
+                        if empty > 0 {
+                            fen.push_str(&empty.to_string());
+                            empty = 0;
+                        }
+                        fen.push(piece.to_char());
                     }
-                })
+                    None => empty += 1,
+                }
+            }
+            if empty > 0 {
+                fen.push_str(&empty.to_string());
+            }
+            if row + 1 < self.height {
+                fen.push('/');
+            }
+        }
+        let mut castling = self.castling_field(PieceColor::White)
+            + self.castling_field(PieceColor::Black).as_str();
+        if castling.is_empty() {
+            castling.push('-');
+        }
+        let en_passant = self
+            .en_passant
+            .map_or("-".to_string(), |pos| self.square_name(pos));
+        format!(
+            "{} {} {} {} {} {}",
+            fen,
+            self.turn,
+            castling,
+            en_passant,
+            self.halfmove_clock,
+            self.moves_made / 2 + 1
+        )
+    }
+
+    pub fn render(&self, unicode: bool) -> String {
+        let mut out = String::new();
+        for row in 0..self.height {
+            out.push_str(&format!("{:>2} ", self.height - row));
+            for col in 0..self.width {
+                let c = match self.piece_at((col, row)) {
+                    Some(piece) if unicode => piece.to_unicode(),
+                    Some(piece) => piece.to_char(),
+                    None => '.',
+                };
+                out.push(' ');
+                out.push(c);
+            }
+            out.push('\n');
+        }
+        out.push_str("   ");
+        for col in 0..self.width {
+            out.push(' ');
+            out.push((b'a' + col as u8) as char);
+        }
+        out.push('\n');
+        out
+    }
+
+    pub fn piece_count(&self) -> usize {
+        self.pieces.iter().flatten().count()
+    }
+
+    pub fn piece_at(&self, pos: (usize, usize)) -> Option<&ChessPiece> {
+        if !self.in_bounds(pos) {
+            return None;
+        }
+        self.pieces[self.pos_to_idx(pos)].as_ref()
+    }
+
+    pub fn king_pos(&self, color: PieceColor) -> Option<(usize, usize)> {
+        self.pieces
+            .iter()
+            .flatten()
+            .find(|p| p.piece_type == PieceType::King && p.color == color)
+            .map(|p| p.pos)
+    }
+
+    pub fn castling_rights(&self, color: PieceColor) -> (bool, bool) {
+        let Some(king) = self.king_pos(color).and_then(|pos| self.piece_at(pos)) else {
+            return (false, false);
+        };
+        if king.first_move_at.is_some() {
+            return (false, false);
+        }
+        self.pieces
+            .iter()
+            .flatten()
+            .filter(|p| {
+                p.piece_type == PieceType::Rook
+                    && p.color == color
+                    && p.first_move_at.is_none()
+                    && p.pos.1 == king.pos.1
+            })
+            .fold((false, false), |(kingside, queenside), rook| {
+                (
+                    kingside || rook.pos.0 > king.pos.0,
+                    queenside || rook.pos.0 < king.pos.0,
+                )
             })
-            .flat_map_iter(move |piece| piece.valid_moves(self, ignore_check))
     }
 
-    pub fn is_in_check(&self, color: PieceColor) -> bool {
-        self.valid_moves(true, color.opposite()).any(|m| {
-            self.piece_at(m.target)
-                .map_or(false, |p| p.piece_type == PieceType::King)
+    pub fn zobrist_key(&self) -> u64 {
+        let mut key = 0;
+        for piece in self.pieces.iter().flatten() {
+            let piece_idx = match piece.piece_type {
+                PieceType::Fairy(index) => {
+                    12 + piece.color as usize * fairy::MAX_FAIRY_PIECES + index as usize
+                }
+                piece_type => piece.color as usize * 6 + piece_type.index(),
+            };
+            key ^= zobrist::piece_key(piece_idx, self.pos_to_idx(piece.pos));
+        }
+        for (i, color) in PieceColor::iter().enumerate() {
+            let (kingside, queenside) = self.castling_rights(color);
+            if kingside {
+                key ^= zobrist::CASTLING_KEYS[i * 2];
+            }
+            if queenside {
+                key ^= zobrist::CASTLING_KEYS[i * 2 + 1];
+            }
+        }
+        if let Some(pos) = self.en_passant.filter(|_| self.can_capture_en_passant()) {
+            key ^= zobrist::EN_PASSANT_KEYS[pos.0];
+        }
+        if self.turn == PieceColor::Black {
+            key ^= zobrist::SIDE_KEY;
+        }
+        key
+    }
+
+    pub fn repetition_count(&self) -> usize {
+        let key = self.zobrist_key();
+        self.history.iter().filter(|&&k| k == key).count() + 1
+    }
+
+    pub fn has_mating_material(&self, color: PieceColor) -> bool {
+        let pieces = self.pieces.iter().flatten().filter(|p| p.color == color);
+        let mut minor_pieces = 0;
+        for piece in pieces {
+            match piece.piece_type {
+                PieceType::Queen | PieceType::Rook | PieceType::Pawn | PieceType::Fairy(_) => {
+                    return true
+                }
+                PieceType::Bishop | PieceType::Knight => minor_pieces += 1,
+                PieceType::King => {}
+            }
+        }
+        minor_pieces >= 2
+    }
+
+    pub fn piece_at_mut(&mut self, pos: (usize, usize)) -> Option<&mut ChessPiece> {
+        let idx = self.pos_to_idx(pos);
+        self.pieces[idx].as_mut()
+    }
+
+    fn pieces_of(&self, color: PieceColor) -> impl ParallelIterator<Item = &ChessPiece> + '_ {
+        self.pieces
+            .par_iter()
+            .filter_map(move |piece| piece.as_ref().filter(|piece| piece.color == color))
+    }
+
+    pub fn pseudo_legal_moves(&self, color: PieceColor) -> impl ParallelIterator<Item = Move> + '_ {
+        self.pieces_of(color)
+            .flat_map_iter(move |piece| piece.pseudo_legal_moves(self))
+    }
+
+    pub fn generate_moves_into(&self, moves: &mut MoveList) {
+        self.generate_kind_into(MoveKind::All, moves);
+    }
+
+    // Captures and promotions only.
+    pub fn generate_captures_into(&self, moves: &mut MoveList) {
+        self.generate_kind_into(MoveKind::Tactical, moves);
+    }
+
+    pub fn generate_quiets_into(&self, moves: &mut MoveList) {
+        self.generate_kind_into(MoveKind::Quiet, moves);
+    }
+
+    fn generate_kind_into(&self, kind: MoveKind, moves: &mut MoveList) {
+        let start = moves.len();
+        for piece in self.pieces.iter().flatten() {
+            if piece.color == self.turn {
+                piece.generate_moves_into(self, kind, moves);
+            }
+        }
+        let mut idx = 0;
+        moves.retain(|m| {
+            idx += 1;
+            idx <= start || (m.is_pseudo_legal(self) && kind.includes(self, m))
+        });
+    }
+
+    pub fn has_legal_move(&self) -> bool {
+        let mut moves = MoveList::new();
+        self.generate_moves_into(&mut moves);
+        let pins = self.pins(self.turn);
+        let in_check = self.is_in_check(self.turn);
+        moves
+            .iter()
+            .any(|m| m.is_legal_given(self, &pins, in_check))
+    }
+
+    pub fn legal_move_list(&self) -> MoveList {
+        let mut moves = MoveList::new();
+        self.generate_moves_into(&mut moves);
+        let pins = self.pins(self.turn);
+        let in_check = self.is_in_check(self.turn);
+        moves.retain(|m| m.is_legal_given(self, &pins, in_check));
+        moves
+    }
+
+    pub fn legal_drops(&self, piece_types: &[PieceType]) -> Vec<Move> {
+        let in_check = self.is_in_check(self.turn);
+        let mut drops = Vec::new();
+        for &piece_type in piece_types {
+            for target in self.squares() {
+                let m = Move::new(target, target, MoveType::Drop(piece_type));
+                if m.is_pseudo_legal(self) && m.is_legal_given(self, &[], in_check) {
+                    drops.push(m);
+                }
+            }
+        }
+        drops
+    }
+
+    pub fn perft(&self, depth: usize) -> u64 {
+        if depth == 0 {
+            return 1;
+        }
+        let moves = self.legal_move_list();
+        if depth == 1 {
+            return moves.len() as u64;
+        }
+        let count = |m: &Move| {
+            let mut board = self.clone();
+            m.perform(&mut board);
+            board.perft(depth - 1)
+        };
+        if depth >= PARALLEL_PERFT_DEPTH {
+            moves.par_iter().map(count).sum()
+        } else {
+            moves.iter().map(count).sum()
+        }
+    }
+
+    pub fn perft_divide(&self, depth: usize) -> Vec<(Move, u64)> {
+        self.legal_move_list()
+            .par_iter()
+            .map(|&m| {
+                let mut board = self.clone();
+                m.perform(&mut board);
+                (m, board.perft(depth.saturating_sub(1)))
+            })
+            .collect()
+    }
+
+    pub fn pseudo_legal_captures(
+        &self,
+        color: PieceColor,
+    ) -> impl ParallelIterator<Item = Move> + '_ {
+        self.pseudo_legal_moves(color)
+            .filter(move |m| m.is_capture(self) || m.is_promotion())
+    }
+
+    pub fn pseudo_legal_quiets(
+        &self,
+        color: PieceColor,
+    ) -> impl ParallelIterator<Item = Move> + '_ {
+        self.pseudo_legal_moves(color)
+            .filter(move |m| !m.is_capture(self) && !m.is_promotion())
+    }
+
+    pub fn legal_moves(&self, color: PieceColor) -> impl ParallelIterator<Item = Move> + '_ {
+        let pins = self.pins(color);
+        let in_check = self.is_in_check(color);
+        self.pieces_of(color)
+            .flat_map_iter(move |piece| piece.legal_moves_given(self, &pins, in_check))
+    }
+
+    fn pins(&self, color: PieceColor) -> Vec<Pin> {
+        let mut pins = Vec::new();
+        let Some(king) = self.king_pos(color) else {
+            return pins;
+        };
+        for (directions, slider) in [
+            (ROOK_DIRECTIONS, PieceType::Rook),
+            (BISHOP_DIRECTIONS, PieceType::Bishop),
+        ] {
+            for dir in directions {
+                let mut candidate = None;
+                let mut target = self.offset(king, dir);
+                while let Some(square) = target {
+                    if let Some(piece) = self.piece_at(square) {
+                        match candidate {
+                            None if piece.color == color => candidate = Some(square),
+                            None => break,
+                            Some(pinned) => {
+                                if piece.color != color
+                                    && (piece.piece_type == slider
+                                        || piece.piece_type == PieceType::Queen)
+                                {
+                                    pins.push((pinned, dir));
+                                }
+                                break;
+                            }
+                        }
+                    }
+                    target = self.offset(square, dir);
+                }
+            }
+        }
+        pins
+    }
+
+    pub fn pinned_pieces(&self, color: PieceColor) -> Vec<&ChessPiece> {
+        self.pins(color)
+            .into_iter()
+            .filter_map(|(pos, _)| self.piece_at(pos))
+            .collect()
+    }
+
+    pub fn is_pinned(&self, pos: (usize, usize)) -> bool {
+        self.piece_at(pos).is_some_and(|piece| {
+            self.pins(piece.color)
+                .iter()
+                .any(|&(pinned, _)| pinned == pos)
         })
     }
 
-    pub fn is_pos_attacked(
+    pub fn is_in_check(&self, color: PieceColor) -> bool {
+        self.king_pos(color)
+            .is_some_and(|king| self.is_pos_attacked(king, color.opposite()))
+    }
+
+    pub fn is_legal_after_move(&self, mover: PieceColor) -> bool {
+        !self.is_in_check(mover)
+            && (self.variant != Variant::RacingKings || !self.is_in_check(mover.opposite()))
+    }
+
+    pub fn offset(&self, pos: (usize, usize), (dx, dy): (isize, isize)) -> Option<(usize, usize)> {
+        let x = pos.0.checked_add_signed(dx)?;
+        let y = pos.1.checked_add_signed(dy)?;
+        self.in_bounds((x, y)).then_some((x, y))
+    }
+
+    fn first_occupied(&self, pos: (usize, usize), dir: (isize, isize)) -> Option<(usize, usize)> {
+        core::iter::successors(self.offset(pos, dir), |&square| self.offset(square, dir))
+            .find(|&square| self.piece_at(square).is_some())
+    }
+
+    fn attackers(
         &self,
         pos: (usize, usize),
-        attacking_color: PieceColor,
-        ignore_check: bool,
-    ) -> bool {
-        let moves = self.valid_moves(ignore_check, attacking_color);
-        return moves.any(|m| m.target == pos);
+        color: PieceColor,
+    ) -> impl Iterator<Item = &ChessPiece> + '_ {
+        const PAWN: &[PieceType] = &[PieceType::Pawn];
+        const KNIGHT: &[PieceType] = &[PieceType::Knight];
+        const KING: &[PieceType] = &[PieceType::King];
+        const ROOK_OR_QUEEN: &[PieceType] = &[PieceType::Rook, PieceType::Queen];
+        const BISHOP_OR_QUEEN: &[PieceType] = &[PieceType::Bishop, PieceType::Queen];
+
+        let pawn_row = match color {
+            PieceColor::White => 1,
+            PieceColor::Black => -1,
+        };
+        let pawns = [-1, 1]
+            .into_iter()
+            .map(move |dx| (self.offset(pos, (dx, pawn_row)), PAWN));
+        let knights = KNIGHT_OFFSETS
+            .into_iter()
+            .map(move |offset| (self.offset(pos, offset), KNIGHT));
+        let kings = KING_OFFSETS
+            .into_iter()
+            .map(move |offset| (self.offset(pos, offset), KING));
+        let rooks = ROOK_DIRECTIONS
+            .into_iter()
+            .map(move |dir| (self.first_occupied(pos, dir), ROOK_OR_QUEEN));
+        let bishops = BISHOP_DIRECTIONS
+            .into_iter()
+            .map(move |dir| (self.first_occupied(pos, dir), BISHOP_OR_QUEEN));
+        let fairies = self.pieces.iter().flatten().filter(move |piece| {
+            let PieceType::Fairy(index) = piece.piece_type else {
+                return false;
+            };
+            piece.color == color
+                && fairy::piece(index)
+                    .is_some_and(|fairy| fairy.attacks(self, piece.pos, color, pos))
+        });
+        pawns
+            .chain(knights)
+            .chain(kings)
+            .chain(rooks)
+            .chain(bishops)
+            .filter_map(move |(target, types)| {
+                let piece = self.piece_at(target?)?;
+                (piece.color == color && types.contains(&piece.piece_type)).then_some(piece)
+            })
+            .chain(fairies)
+    }
+
+    pub fn has_fairy_pieces(&self) -> bool {
+        !fairy::pieces().is_empty()
+            && self
+                .pieces
+                .iter()
+                .flatten()
+                .any(|piece| matches!(piece.piece_type, PieceType::Fairy(_)))
+    }
+
+    pub fn attackers_to(&self, pos: (usize, usize), color: PieceColor) -> Vec<&ChessPiece> {
+        self.attackers(pos, color).collect()
+    }
+
+    pub fn is_pos_attacked(&self, pos: (usize, usize), attacking_color: PieceColor) -> bool {
+        self.attackers(pos, attacking_color).next().is_some()
+    }
+
+    pub fn hanging_pieces(&self, color: PieceColor) -> Vec<(usize, usize)> {
+        self.pieces
+            .iter()
+            .flatten()
+            .filter(|piece| piece.color == color && piece.piece_type != PieceType::King)
+            .filter(|piece| {
+                self.is_pos_attacked(piece.pos, color.opposite())
+                    && !self.is_pos_attacked(piece.pos, color)
+            })
+            .map(|piece| piece.pos)
+            .collect()
+    }
+
+    pub fn newly_attacked(&self, previous: &ChessBoard, color: PieceColor) -> Vec<(usize, usize)> {
+        (0..self.height)
+            .flat_map(|row| (0..self.width).map(move |col| (col, row)))
+            .filter(|&pos| self.piece_at(pos).is_none_or(|piece| piece.color != color))
+            .filter(|&pos| {
+                self.is_pos_attacked(pos, color) && !previous.is_pos_attacked(pos, color)
+            })
+            .collect()
+    }
+
+    pub fn variant_result(&self) -> Option<WinState> {
+        if self.variant != Variant::RacingKings {
+            return None;
+        }
+        let on_goal = |color| self.king_pos(color).is_some_and(|pos| pos.1 == 0);
+        match (on_goal(PieceColor::White), on_goal(PieceColor::Black)) {
+            (true, true) => Some(WinState::RaceDrawn),
+            (false, true) => Some(WinState::RaceWon(PieceColor::Black)),
+            (true, false)
+                if self.turn == PieceColor::White || !self.can_reach_goal(PieceColor::Black) =>
+            {
+                Some(WinState::RaceWon(PieceColor::White))
+            }
+            _ => None,
+        }
+    }
+
+    fn can_reach_goal(&self, color: PieceColor) -> bool {
+        self.legal_move_list().iter().any(|m| {
+            m.target.1 == 0
+                && self.piece_at(m.original).is_some_and(|piece| {
+                    piece.piece_type == PieceType::King && piece.color == color
+                })
+        })
+    }
+
+    pub fn validate(&self) -> Result<(), PositionError> {
+        for color in PieceColor::iter() {
+            let kings = self
+                .pieces
+                .iter()
+                .flatten()
+                .filter(|p| p.piece_type == PieceType::King && p.color == color)
+                .count();
+            if kings != 1 {
+                return Err(PositionError::KingCount(color, kings));
+            }
+        }
+        if let Some(pawn) = self.pieces.iter().flatten().find(|p| {
+            p.piece_type == PieceType::Pawn && (p.pos.1 == 0 || self.is_last_row(p.pos.1))
+        }) {
+            return Err(PositionError::PawnOnBackRank(self.square_name(pawn.pos)));
+        }
+        if self.is_in_check(self.turn.opposite()) {
+            return Err(PositionError::OpponentInCheck(self.turn.opposite()));
+        }
+        for color in PieceColor::iter() {
+            let home_row = self.home_row(color);
+            let king_unmoved = self
+                .king_pos(color)
+                .and_then(|pos| self.piece_at(pos))
+                .is_some_and(|king| king.first_move_at.is_none() && king.pos.1 == home_row);
+            if self.pieces.iter().flatten().any(|p| {
+                p.piece_type == PieceType::Rook
+                    && p.color == color
+                    && p.first_move_at.is_none()
+                    && (!king_unmoved || p.pos.1 != home_row)
+            }) {
+                return Err(PositionError::InvalidCastlingRights(color));
+            }
+        }
+        Ok(())
     }
 
     pub fn win_state(&self) -> Option<WinState> {
-        if self.valid_moves(false, self.turn).all(|_| false) {
+        if let Some(win_state) = self.variant_result() {
+            return Some(win_state);
+        }
+        if !self.has_legal_move() {
             if self.is_in_check(self.turn) {
                 return Some(WinState::Checkmate(self.turn.opposite()));
             } else {
                 return Some(WinState::Stalemate);
             }
         }
+        if self.halfmove_clock >= 100 {
+            return Some(WinState::FiftyMoveRule);
+        }
+        if self.repetition_count() >= 3 {
+            return Some(WinState::Repetition);
+        }
+        if self.variant == Variant::Standard
+            && PieceColor::iter().all(|color| !self.has_mating_material(color))
+        {
+            return Some(WinState::InsufficientMaterial);
+        }
         None
     }
 }
 
-pub fn notation_to_pos(notation: &str) -> Option<(usize, usize)> {
-    if notation.len() != 2 {
-        return None;
+fn split_square(s: &str) -> Option<(&str, &str)> {
+    let digits = s
+        .get(1..)?
+        .find(|c: char| !c.is_ascii_digit())
+        .map_or(s.len(), |i| i + 1);
+    (digits > 1).then(|| s.split_at(digits))
+}
+
+fn fen_fields(fen: &str) -> Vec<(usize, &str)> {
+    let mut fields = Vec::new();
+    let mut start = None;
+    for (i, c) in fen.char_indices().chain([(fen.len(), ' ')]) {
+        match (c.is_whitespace(), start) {
+            (true, Some(field_start)) => {
+                fields.push((field_start, &fen[field_start..i]));
+                start = None;
+            }
+            (false, None) => start = Some(i),
+            _ => {}
+        }
+    }
+    fields
+}
+
+fn fen_row_width(row: &str, offset: usize) -> Result<usize, FenError> {
+    let mut width: usize = 0;
+    let mut empty_squares: usize = 0;
+    for (i, c) in row.char_indices() {
+        let too_wide = FenError::new(offset + i, FenErrorKind::RowTooWide);
+        match c.to_digit(10) {
+            Some(digit) => {
+                empty_squares = empty_squares
+                    .checked_mul(10)
+                    .and_then(|n| n.checked_add(digit as usize))
+                    .ok_or(too_wide)?
+            }
+            None => {
+                width = width
+                    .checked_add(core::mem::take(&mut empty_squares))
+                    .and_then(|n| n.checked_add(1))
+                    .ok_or(too_wide)?
+            }
+        }
     }
-    let chars: Vec<char> = notation.chars().collect();
-    let x = chars[0] as usize - 'a' as usize;
-    let y = 8 - chars[1].to_digit(10)? as usize;
-    Some((x, y))
+    width.checked_add(empty_squares).ok_or(FenError::new(
+        offset + row.len().saturating_sub(1),
+        FenErrorKind::RowTooWide,
+    ))
+}
+
+#[derive(Clone, Debug)]
+pub struct BoardBuilder {
+    width: usize,
+    height: usize,
+    variant: Variant,
+    pieces: Vec<ChessPiece>,
+    turn: PieceColor,
+    castling: Option<String>,
+    en_passant: Option<(usize, usize)>,
+    halfmove_clock: usize,
+    fullmove_number: usize,
 }
 
-pub fn pos_to_notation(pos: (usize, usize)) -> String {
-    let x = (pos.0 as u8 + b'a') as char;
-    let y = (8 - pos.1).to_string();
-    format!("{}{}", x, y)
+impl Default for BoardBuilder {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl BoardBuilder {
+    pub fn new() -> Self {
+        Self::with_size(8, 8)
+    }
+
+    pub fn with_size(width: usize, height: usize) -> Self {
+        Self {
+            width,
+            height,
+            variant: Variant::Standard,
+            pieces: Vec::new(),
+            turn: PieceColor::White,
+            castling: None,
+            en_passant: None,
+            halfmove_clock: 0,
+            fullmove_number: 1,
+        }
+    }
+
+    pub fn from_board(board: &ChessBoard) -> Self {
+        let mut builder = Self::with_size(board.width, board.height)
+            .variant(board.variant)
+            .turn(board.turn)
+            .halfmove_clock(board.halfmove_clock)
+            .fullmove_number(board.moves_made / 2 + 1)
+            .en_passant(board.en_passant);
+        builder.pieces = board.pieces.iter().flatten().cloned().collect();
+        builder.castling = Some(
+            board.castling_field(PieceColor::White)
+                + board.castling_field(PieceColor::Black).as_str(),
+        );
+        builder
+    }
+
+    pub fn variant(mut self, variant: Variant) -> Self {
+        self.variant = variant;
+        self
+    }
+
+    pub fn piece(mut self, pos: (usize, usize), color: PieceColor, piece_type: PieceType) -> Self {
+        self.pieces.retain(|piece| piece.pos != pos);
+        self.pieces.push(ChessPiece::new(piece_type, pos, color));
+        self
+    }
+
+    pub fn remove(mut self, pos: (usize, usize)) -> Self {
+        self.pieces.retain(|piece| piece.pos != pos);
+        self
+    }
+
+    pub fn turn(mut self, turn: PieceColor) -> Self {
+        self.turn = turn;
+        self
+    }
+
+    pub fn castling(mut self, castling: &str) -> Self {
+        self.castling = Some(castling.trim_matches('-').to_string());
+        self
+    }
+
+    pub fn en_passant(mut self, pos: Option<(usize, usize)>) -> Self {
+        self.en_passant = pos;
+        self
+    }
+
+    pub fn halfmove_clock(mut self, halfmove_clock: usize) -> Self {
+        self.halfmove_clock = halfmove_clock;
+        self
+    }
+
+    pub fn fullmove_number(mut self, fullmove_number: usize) -> Self {
+        self.fullmove_number = fullmove_number;
+        self
+    }
+
+    pub fn build(&self) -> Result<ChessBoard, BuildError> {
+        if !(1..=MAX_BOARD_SIZE).contains(&self.width)
+            || !(1..=MAX_BOARD_SIZE).contains(&self.height)
+        {
+            return Err(BuildError::InvalidSize(self.width, self.height));
+        }
+        let mut board = ChessBoard {
+            pieces: vec![None; self.width * self.height],
+            width: self.width,
+            height: self.height,
+            turn: self.turn,
+            en_passant: None,
+            moves_made: self.fullmove_number.saturating_sub(1) * 2
+                + (self.turn == PieceColor::Black) as usize,
+            halfmove_clock: self.halfmove_clock,
+            history: Vec::new(),
+            variant: self.variant,
+        };
+        for piece in &self.pieces {
+            if !board.in_bounds(piece.pos) {
+                return Err(BuildError::OutOfBounds(piece.pos));
+            }
+            let mut piece = piece.clone();
+            piece.first_move_at = (piece.piece_type == PieceType::Pawn
+                && piece.pos.1 != board.pawn_start_row(piece.color))
+            .then_some(0);
+            let idx = board.pos_to_idx(piece.pos);
+            board.pieces[idx] = Some(piece);
+        }
+
+        board.clear_castling_rights();
+        match &self.castling {
+            Some(castling) => {
+                if let Some(c) = castling.chars().find(|&c| !board.grant_castling_right(c)) {
+                    return Err(BuildError::InvalidCastling(c));
+                }
+            }
+            None => {
+                for c in ['K', 'Q', 'k', 'q'] {
+                    board.grant_castling_right(c);
+                }
+            }
+        }
+
+        if let Some(pos) = self.en_passant {
+            if !board.is_valid_en_passant(pos) {
+                return Err(BuildError::InvalidEnPassant(pos));
+            }
+            board.en_passant = Some(pos);
+        }
+
+        board.validate().map_err(BuildError::InvalidPosition)?;
+        Ok(board)
+    }
 }
@@ -0,0 +1,114 @@
+use crate::{
+    fairy,
+    logic::{ChessBoard, Move, MoveList, MoveType, PieceType},
+};
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+enum Stage {
+    TtMove,
+    Captures,
+    Killers,
+    Quiets,
+    Done,
+}
+
+pub struct MovePicker<'a> {
+    board: &'a ChessBoard,
+    tt_move: Option<Move>,
+    killers: [Option<Move>; 2],
+    stage: Stage,
+    moves: MoveList,
+}
+
+fn piece_value(piece_type: PieceType) -> i32 {
+    match piece_type {
+        PieceType::Pawn => 1,
+        PieceType::Knight | PieceType::Bishop => 3,
+        PieceType::Rook => 5,
+        PieceType::Queen => 9,
+        PieceType::King => 100,
+        PieceType::Fairy(index) => fairy::piece(index).map_or(3, |p| p.value.round() as i32),
+    }
+}
+
+fn mvv_lva(board: &ChessBoard, m: Move) -> i32 {
+    let victim = match m.move_type {
+        MoveType::Promotion(piece_type) => piece_value(piece_type),
+        MoveType::EnPassant => piece_value(PieceType::Pawn),
+        _ => board
+            .piece_at(m.target)
+            .map_or(0, |piece| piece_value(piece.piece_type)),
+    };
+    let attacker = board
+        .piece_at(m.original)
+        .map_or(0, |piece| piece_value(piece.piece_type));
+    victim * 10 - attacker
+}
+
+impl<'a> MovePicker<'a> {
+    pub fn new(board: &'a ChessBoard, tt_move: Option<Move>, killers: [Option<Move>; 2]) -> Self {
+        Self {
+            board,
+            tt_move: tt_move.filter(|&m| Self::is_available(board, m)),
+            killers,
+            stage: Stage::TtMove,
+            moves: MoveList::new(),
+        }
+    }
+
+    fn is_available(board: &ChessBoard, m: Move) -> bool {
+        board.piece_at(m.original).is_some_and(|piece| {
+            piece.color == board.turn && piece.pseudo_legal_moves(board).any(|other| other == m)
+        })
+    }
+
+    fn is_tactical(&self, m: Move) -> bool {
+        m.is_capture(self.board) || m.is_promotion()
+    }
+}
+
+impl Iterator for MovePicker<'_> {
+    type Item = Move;
+
+    fn next(&mut self) -> Option<Move> {
+        loop {
+            if let Some(m) = self.moves.pop() {
+                return Some(m);
+            }
+            match self.stage {
+                Stage::TtMove => {
+                    self.stage = Stage::Captures;
+                    if self.tt_move.is_some() {
+                        return self.tt_move;
+                    }
+                }
+                Stage::Captures => {
+                    self.stage = Stage::Killers;
+                    let (board, tt_move) = (self.board, self.tt_move);
+                    board.generate_captures_into(&mut self.moves);
+                    self.moves.retain(|&m| Some(m) != tt_move);
+                    self.moves.sort_by_key(|&m| mvv_lva(board, m));
+                }
+                Stage::Killers => {
+                    self.stage = Stage::Quiets;
+                    for m in self.killers.into_iter().rev().flatten() {
+                        if Some(m) != self.tt_move
+                            && !self.is_tactical(m)
+                            && Self::is_available(self.board, m)
+                        {
+                            self.moves.push(m);
+                        }
+                    }
+                }
+                Stage::Quiets => {
+                    self.stage = Stage::Done;
+                    let board = self.board;
+                    let skipped = [self.tt_move, self.killers[0], self.killers[1]];
+                    board.generate_quiets_into(&mut self.moves);
+                    self.moves.retain(|&m| !skipped.contains(&Some(m)));
+                }
+                Stage::Done => return None,
+            }
+        }
+    }
+}
@@ -0,0 +1,201 @@
+use crate::{
+    logic::{ChessBoard, Move},
+    pgn::{PgnGame, PgnMove},
+};
+
+pub const ROOT: usize = 0;
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct MoveNode {
+    pub mv: Option<Move>,
+    pub san: String,
+    pub parent: Option<usize>,
+    pub children: Vec<usize>,
+    pub comment: Option<String>,
+    pub nags: Vec<u8>,
+}
+
+#[derive(Clone, Debug)]
+pub struct MoveTree {
+    pub start_board: ChessBoard,
+    nodes: Vec<MoveNode>,
+}
+
+impl MoveTree {
+    pub fn new(start_board: ChessBoard) -> Self {
+        Self {
+            start_board,
+            nodes: vec![MoveNode {
+                mv: None,
+                san: String::new(),
+                parent: None,
+                children: Vec::new(),
+                comment: None,
+                nags: Vec::new(),
+            }],
+        }
+    }
+
+    pub fn from_moves(start_board: ChessBoard, moves: &[Move]) -> Self {
+        let mut tree = Self::new(start_board);
+        tree.extend_main_line(moves);
+        tree
+    }
+
+    pub fn from_pgn(pgn: &PgnGame) -> Self {
+        let mut tree = Self::new(pgn.starting_board());
+        tree.nodes[ROOT].comment = pgn.comment.clone();
+        tree.add_pgn_line(ROOT, &pgn.moves);
+        tree
+    }
+
+    fn add_pgn_line(&mut self, parent: usize, moves: &[PgnMove]) {
+        let mut node = parent;
+        for pgn_move in moves {
+            let previous = node;
+            node = self.add_move(previous, pgn_move.mv);
+            self.nodes[node].comment = pgn_move.comment.clone();
+            self.nodes[node].nags = pgn_move.nags.clone();
+            for variation in &pgn_move.variations {
+                self.add_pgn_line(previous, variation);
+            }
+        }
+    }
+
+    pub fn node(&self, id: usize) -> &MoveNode {
+        &self.nodes[id]
+    }
+
+    pub fn node_mut(&mut self, id: usize) -> &mut MoveNode {
+        &mut self.nodes[id]
+    }
+
+    pub fn add_move(&mut self, parent: usize, mv: Move) -> usize {
+        if let Some(&existing) = self.nodes[parent]
+            .children
+            .iter()
+            .find(|&&child| self.nodes[child].mv == Some(mv))
+        {
+            return existing;
+        }
+        let board = self.board_at(parent);
+        let id = self.nodes.len();
+        self.nodes.push(MoveNode {
+            mv: Some(mv),
+            san: mv.to_san(&board),
+            parent: Some(parent),
+            children: Vec::new(),
+            comment: None,
+            nags: Vec::new(),
+        });
+        self.nodes[parent].children.push(id);
+        id
+    }
+
+    pub fn extend_main_line(&mut self, moves: &[Move]) {
+        let mut node = ROOT;
+        for &mv in moves {
+            node = self.add_move(node, mv);
+        }
+    }
+
+    pub fn main_line(&self) -> Vec<usize> {
+        self.continuation(ROOT)
+    }
+
+    pub fn continuation(&self, id: usize) -> Vec<usize> {
+        let mut line = Vec::new();
+        let mut node = id;
+        while let Some(&child) = self.nodes[node].children.first() {
+            line.push(child);
+            node = child;
+        }
+        line
+    }
+
+    pub fn variations(&self, id: usize) -> &[usize] {
+        match self.nodes[id].parent {
+            Some(parent) if self.nodes[parent].children.first() == Some(&id) => {
+                &self.nodes[parent].children[1..]
+            }
+            _ => &[],
+        }
+    }
+
+    pub fn path(&self, id: usize) -> Vec<usize> {
+        let mut path = Vec::new();
+        let mut node = id;
+        while let Some(parent) = self.nodes[node].parent {
+            path.push(node);
+            node = parent;
+        }
+        path.reverse();
+        path
+    }
+
+    pub fn moves_to(&self, id: usize) -> Vec<Move> {
+        self.path(id)
+            .into_iter()
+            .filter_map(|node| self.nodes[node].mv)
+            .collect()
+    }
+
+    pub fn board_at(&self, id: usize) -> ChessBoard {
+        let mut board = self.start_board.clone();
+        for mv in self.moves_to(id) {
+            mv.perform(&mut board);
+        }
+        board
+    }
+
+    pub fn ply(&self, id: usize) -> usize {
+        self.path(id).len()
+    }
+
+    pub fn is_main_line(&self, id: usize) -> bool {
+        self.variation_start(id).is_none()
+    }
+
+    pub fn variation_start(&self, id: usize) -> Option<usize> {
+        let mut node = id;
+        while let Some(parent) = self.nodes[node].parent {
+            if self.nodes[parent].children.first() != Some(&node) {
+                return Some(node);
+            }
+            node = parent;
+        }
+        None
+    }
+
+    pub fn remove(&mut self, id: usize) {
+        if let Some(parent) = self.nodes[id].parent {
+            self.nodes[parent].children.retain(|&child| child != id);
+        }
+    }
+
+    pub fn to_pgn_moves(&self) -> Vec<PgnMove> {
+        self.pgn_line(self.nodes[ROOT].children.first().copied())
+    }
+
+    fn pgn_line(&self, first: Option<usize>) -> Vec<PgnMove> {
+        let mut line = Vec::new();
+        let mut next = first;
+        while let Some(id) = next {
+            let node = &self.nodes[id];
+            let Some(mv) = node.mv else {
+                break;
+            };
+            let mut pgn_move = PgnMove::new(mv);
+            pgn_move.comment = node.comment.clone();
+            pgn_move.nags = node.nags.clone();
+            pgn_move.variations = self
+                .variations(id)
+                .iter()
+                .map(|&variation| self.pgn_line(Some(variation)))
+                .collect();
+            line.push(pgn_move);
+            next = node.children.first().copied();
+        }
+        line
+    }
+}
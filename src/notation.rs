@@ -0,0 +1,400 @@
+use std::str::FromStr;
+
+use rayon::iter::ParallelIterator;
+use strum_macros::EnumIter;
+
+use crate::{
+    fairy,
+    logic::{ChessBoard, Move, MoveType, ParseMoveError, PieceType, WinState},
+};
+
+impl PieceType {
+    pub fn san_letter(&self) -> &'static str {
+        match self {
+            PieceType::King => "K",
+            PieceType::Queen => "Q",
+            PieceType::Rook => "R",
+            PieceType::Bishop => "B",
+            PieceType::Knight => "N",
+            PieceType::Pawn => "",
+            PieceType::Fairy(index) => fairy::piece(*index).map_or("?", |p| p.san_letter()),
+        }
+    }
+}
+
+const ENGLISH_LETTERS: [char; 5] = ['K', 'Q', 'R', 'B', 'N'];
+
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Hash, EnumIter)]
+pub enum SanLanguage {
+    #[default]
+    English,
+    German,
+    French,
+    Spanish,
+    Italian,
+    Dutch,
+    Figurine,
+}
+
+#[derive(Debug, PartialEq, Eq)]
+pub struct ParseSanLanguageError;
+
+impl FromStr for SanLanguage {
+    type Err = ParseSanLanguageError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_lowercase().as_str() {
+            "en" | "english" => Ok(SanLanguage::English),
+            "de" | "german" => Ok(SanLanguage::German),
+            "fr" | "french" => Ok(SanLanguage::French),
+            "es" | "spanish" => Ok(SanLanguage::Spanish),
+            "it" | "italian" => Ok(SanLanguage::Italian),
+            "nl" | "dutch" => Ok(SanLanguage::Dutch),
+            "fig" | "figurine" => Ok(SanLanguage::Figurine),
+            _ => Err(ParseSanLanguageError),
+        }
+    }
+}
+
+impl SanLanguage {
+    pub fn readable(&self) -> &'static str {
+        match self {
+            SanLanguage::English => "English",
+            SanLanguage::German => "German",
+            SanLanguage::French => "French",
+            SanLanguage::Spanish => "Spanish",
+            SanLanguage::Italian => "Italian",
+            SanLanguage::Dutch => "Dutch",
+            SanLanguage::Figurine => "Figurines",
+        }
+    }
+
+    pub fn code(&self) -> &'static str {
+        match self {
+            SanLanguage::English => "en",
+            SanLanguage::German => "de",
+            SanLanguage::French => "fr",
+            SanLanguage::Spanish => "es",
+            SanLanguage::Italian => "it",
+            SanLanguage::Dutch => "nl",
+            SanLanguage::Figurine => "fig",
+        }
+    }
+
+    pub fn letters(&self) -> [char; 5] {
+        match self {
+            SanLanguage::English => ENGLISH_LETTERS,
+            SanLanguage::German => ['K', 'D', 'T', 'L', 'S'],
+            SanLanguage::French => ['R', 'D', 'T', 'F', 'C'],
+            SanLanguage::Spanish | SanLanguage::Italian => ['R', 'D', 'T', 'A', 'C'],
+            SanLanguage::Dutch => ['K', 'D', 'T', 'L', 'P'],
+            SanLanguage::Figurine => ['♔', '♕', '♖', '♗', '♘'],
+        }
+    }
+
+    fn translate(san: &str, from: [char; 5], to: [char; 5]) -> String {
+        san.chars()
+            .map(|c| from.iter().position(|&l| l == c).map_or(c, |i| to[i]))
+            .collect()
+    }
+
+    pub fn localize(&self, san: &str) -> String {
+        Self::translate(san, ENGLISH_LETTERS, self.letters())
+    }
+
+    pub fn to_english(&self, san: &str) -> String {
+        Self::translate(san, self.letters(), ENGLISH_LETTERS)
+    }
+}
+
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Hash, EnumIter)]
+pub enum MoveNotation {
+    #[default]
+    San,
+    LongAlgebraic,
+    Uci,
+}
+
+impl MoveNotation {
+    pub fn readable(&self) -> &'static str {
+        match self {
+            MoveNotation::San => "Short algebraic (SAN)",
+            MoveNotation::LongAlgebraic => "Long algebraic",
+            MoveNotation::Uci => "Coordinates (UCI)",
+        }
+    }
+
+    pub fn code(&self) -> &'static str {
+        match self {
+            MoveNotation::San => "san",
+            MoveNotation::LongAlgebraic => "lan",
+            MoveNotation::Uci => "uci",
+        }
+    }
+}
+
+impl Move {
+    pub fn notate(
+        &self,
+        board: &ChessBoard,
+        notation: MoveNotation,
+        language: SanLanguage,
+    ) -> String {
+        match notation {
+            MoveNotation::San => self.to_san_in(board, language),
+            MoveNotation::LongAlgebraic => language.localize(&self.to_long_algebraic(board)),
+            MoveNotation::Uci => self.to_uci(board),
+        }
+    }
+
+    pub fn to_long_algebraic(&self, board: &ChessBoard) -> String {
+        let mut lan = match self.move_type {
+            MoveType::Drop(_) => self.to_uci(board),
+            MoveType::Castling { direction, .. } => {
+                if direction > 0 {
+                    "O-O".to_string()
+                } else {
+                    "O-O-O".to_string()
+                }
+            }
+            _ => {
+                let Some(piece) = board.piece_at(self.original) else {
+                    return self.to_uci(board);
+                };
+                let is_capture =
+                    board.piece_at(self.target).is_some() || self.move_type == MoveType::EnPassant;
+                let mut lan = piece.piece_type.san_letter().to_string();
+                lan.push_str(&board.square_name(self.original));
+                lan.push(if is_capture { 'x' } else { '-' });
+                lan.push_str(&board.square_name(self.target));
+                if let MoveType::Promotion(piece_type) = self.move_type {
+                    lan.push('=');
+                    lan.push_str(piece_type.san_letter());
+                }
+                lan
+            }
+        };
+        lan.push_str(self.check_suffix(board));
+        lan
+    }
+
+    fn check_suffix(&self, board: &ChessBoard) -> &'static str {
+        let mut after = board.clone();
+        self.perform(&mut after);
+        if !after.is_in_check(after.turn) {
+            ""
+        } else if let Some(WinState::Checkmate(_)) = after.win_state() {
+            "#"
+        } else {
+            "+"
+        }
+    }
+
+    pub fn to_san_in(&self, board: &ChessBoard, language: SanLanguage) -> String {
+        language.localize(&self.to_san(board))
+    }
+
+    pub fn parse_in(
+        s: &str,
+        board: &ChessBoard,
+        language: SanLanguage,
+    ) -> Result<Self, ParseMoveError> {
+        Self::parse(&language.to_english(s), board)
+    }
+
+    pub fn to_san(&self, board: &ChessBoard) -> String {
+        let mut san = match self.move_type {
+            MoveType::Drop(_) => self.to_uci(board),
+            MoveType::Castling { direction, .. } => {
+                if direction > 0 {
+                    "O-O".to_string()
+                } else {
+                    "O-O-O".to_string()
+                }
+            }
+            _ => {
+                let Some(piece) = board.piece_at(self.original) else {
+                    return self.to_uci(board);
+                };
+                let is_capture =
+                    board.piece_at(self.target).is_some() || self.move_type == MoveType::EnPassant;
+                let mut san = piece.piece_type.san_letter().to_string();
+
+                if piece.piece_type == PieceType::Pawn {
+                    if is_capture {
+                        san.push_str(&board.square_name(self.original)[0..1]);
+                    }
+                } else {
+                    let ambiguous = board
+                        .legal_moves(piece.color)
+                        .filter(|m| {
+                            m.target == self.target
+                                && m.original != self.original
+                                && board
+                                    .piece_at(m.original)
+                                    .is_some_and(|p| p.piece_type == piece.piece_type)
+                        })
+                        .collect::<Vec<_>>();
+                    if !ambiguous.is_empty() {
+                        let origin = board.square_name(self.original);
+                        if ambiguous.iter().all(|m| m.original.0 != self.original.0) {
+                            san.push_str(&origin[0..1]);
+                        } else if ambiguous.iter().all(|m| m.original.1 != self.original.1) {
+                            san.push_str(&origin[1..]);
+                        } else {
+                            san.push_str(&origin);
+                        }
+                    }
+                }
+
+                if is_capture {
+                    san.push('x');
+                }
+                san.push_str(&board.square_name(self.target));
+                if let MoveType::Promotion(piece_type) = self.move_type {
+                    san.push('=');
+                    san.push_str(piece_type.san_letter());
+                }
+                san
+            }
+        };
+        san.push_str(self.check_suffix(board));
+        san
+    }
+
+    pub fn describe(&self, board: &ChessBoard) -> String {
+        let mut text = match self.move_type {
+            MoveType::Drop(piece_type) => format!(
+                "{} dropped on {}",
+                piece_type.readable(),
+                board.square_name(self.target)
+            ),
+            MoveType::Castling { direction, .. } if direction > 0 => "Castles kingside".to_string(),
+            MoveType::Castling { .. } => "Castles queenside".to_string(),
+            _ => {
+                let Some(piece) = board.piece_at(self.original) else {
+                    return self.to_uci(board);
+                };
+                let captured = match self.move_type {
+                    MoveType::EnPassant => Some(PieceType::Pawn),
+                    _ => board.piece_at(self.target).map(|p| p.piece_type),
+                };
+                let mut text = format!(
+                    "{} from {}",
+                    piece.piece_type.readable(),
+                    board.square_name(self.original)
+                );
+                match captured {
+                    Some(captured) => text.push_str(&format!(
+                        " takes {} on {}",
+                        captured.readable().to_lowercase(),
+                        board.square_name(self.target)
+                    )),
+                    None => text.push_str(&format!(" to {}", board.square_name(self.target))),
+                }
+                if let MoveType::Promotion(piece_type) = self.move_type {
+                    text.push_str(&format!(
+                        ", promotes to {}",
+                        piece_type.readable().to_lowercase()
+                    ));
+                }
+                text
+            }
+        };
+
+        let mut after = board.clone();
+        self.perform(&mut after);
+        if after.is_in_check(after.turn) {
+            if let Some(WinState::Checkmate(_)) = after.win_state() {
+                text.push_str(", checkmate");
+            } else {
+                text.push_str(", check");
+            }
+        }
+        text
+    }
+
+    pub fn from_san(s: &str, board: &ChessBoard) -> Result<Self, ParseMoveError> {
+        let san = s.trim_end_matches(['+', '#', '!', '?']);
+        let legal_moves = board.legal_moves(board.turn).collect::<Vec<_>>();
+
+        if let Some(castle_direction) = match san {
+            "O-O" | "0-0" => Some(1),
+            "O-O-O" | "0-0-0" => Some(-1),
+            _ => None,
+        } {
+            return legal_moves
+                .into_iter()
+                .find(|m| {
+                    matches!(m.move_type, MoveType::Castling { direction, .. } if direction == castle_direction)
+                })
+                .ok_or(ParseMoveError);
+        }
+
+        let (san, promotion) = match san.split_once('=') {
+            Some((san, promotion)) => (
+                san,
+                Some(PieceType::from_str(promotion).map_err(|_| ParseMoveError)?),
+            ),
+            None => match san.chars().last() {
+                Some(c @ ('Q' | 'R' | 'B' | 'N')) => (
+                    &san[..san.len() - 1],
+                    Some(PieceType::from_str(&c.to_string()).map_err(|_| ParseMoveError)?),
+                ),
+                _ => (san, None),
+            },
+        };
+
+        let (piece_type, rest) = match san.chars().next() {
+            Some(c) if c.is_ascii_uppercase() => (
+                PieceType::from_str(&c.to_string()).map_err(|_| ParseMoveError)?,
+                &san[1..],
+            ),
+            Some(_) => (PieceType::Pawn, san),
+            None => return Err(ParseMoveError),
+        };
+
+        let rest = rest.replace('x', "");
+        let square_start = rest
+            .rfind(|c: char| c.is_ascii_lowercase())
+            .ok_or(ParseMoveError)?;
+        let target = board
+            .parse_square(&rest[square_start..])
+            .ok_or(ParseMoveError)?;
+        let disambiguation = &rest[..square_start];
+        let file = disambiguation
+            .chars()
+            .find(char::is_ascii_lowercase)
+            .map(|c| c as usize - 'a' as usize);
+        let rank = disambiguation
+            .trim_start_matches(|c: char| c.is_ascii_lowercase())
+            .parse::<usize>()
+            .ok()
+            .and_then(|r| board.height.checked_sub(r));
+
+        let mut candidates = legal_moves.into_iter().filter(|m| {
+            m.target == target
+                && board
+                    .piece_at(m.original)
+                    .is_some_and(|p| p.piece_type == piece_type)
+                && file.is_none_or(|file| m.original.0 == file)
+                && rank.is_none_or(|rank| m.original.1 == rank)
+                && match m.move_type {
+                    MoveType::Promotion(p) => promotion == Some(p),
+                    _ => promotion.is_none(),
+                }
+        });
+        match (candidates.next(), candidates.next()) {
+            (Some(m), None) => Ok(m),
+            _ => Err(ParseMoveError),
+        }
+    }
+
+    pub fn parse(s: &str, board: &ChessBoard) -> Result<Self, ParseMoveError> {
+        board
+            .legal_moves(board.turn)
+            .find_any(|m| m.to_uci(board) == s)
+            .ok_or(ParseMoveError)
+            .or_else(|_| Self::from_san(s, board))
+    }
+}
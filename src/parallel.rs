@@ -0,0 +1,38 @@
+#[cfg(feature = "std")]
+pub use rayon::iter::{IntoParallelRefIterator, ParallelIterator};
+
+#[cfg(not(feature = "std"))]
+pub use sequential::{IntoParallelRefIterator, ParallelIterator};
+
+// Without std there are no threads, so the rayon calls in move generation
+// fall back to plain iterators with the same method names.
+#[cfg(not(feature = "std"))]
+mod sequential {
+    use core::{iter::FlatMap, slice::Iter};
+
+    pub trait ParallelIterator: Iterator + Sized {
+        fn flat_map_iter<U, F>(self, f: F) -> FlatMap<Self, U, F>
+        where
+            U: IntoIterator,
+            F: FnMut(Self::Item) -> U,
+        {
+            self.flat_map(f)
+        }
+    }
+
+    impl<I: Iterator> ParallelIterator for I {}
+
+    pub trait IntoParallelRefIterator<'a> {
+        type Iter: Iterator;
+
+        fn par_iter(&'a self) -> Self::Iter;
+    }
+
+    impl<'a, T: 'a> IntoParallelRefIterator<'a> for [T] {
+        type Iter = Iter<'a, T>;
+
+        fn par_iter(&'a self) -> Self::Iter {
+            self.iter()
+        }
+    }
+}
@@ -0,0 +1,528 @@
+use std::{fmt::Display, iter::Peekable, str::Chars};
+
+use crate::{
+    logic::{ChessBoard, FenError, Move, PieceColor, Variant, WinState},
+    notation::SanLanguage,
+};
+
+const LINE_WIDTH: usize = 80;
+const RESULTS: [&str; 4] = ["1-0", "0-1", "1/2-1/2", "*"];
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct PgnMove {
+    pub mv: Move,
+    pub glyph: Option<String>,
+    pub nags: Vec<u8>,
+    pub comment: Option<String>,
+    pub variations: Vec<Vec<PgnMove>>,
+}
+
+impl PgnMove {
+    pub fn new(mv: Move) -> Self {
+        Self {
+            mv,
+            glyph: None,
+            nags: Vec::new(),
+            comment: None,
+            variations: Vec::new(),
+        }
+    }
+
+    pub fn add_comment(&mut self, comment: &str) {
+        self.comment = join_comments(self.comment.take(), comment);
+    }
+}
+
+fn push_line(
+    tokens: &mut Vec<String>,
+    moves: &[PgnMove],
+    mut board: ChessBoard,
+    language: SanLanguage,
+) {
+    let mut show_number = true;
+    for pgn_move in moves {
+        let move_number = board.moves_made / 2 + 1;
+        if board.turn == PieceColor::White {
+            tokens.push(format!("{}.", move_number));
+        } else if show_number {
+            tokens.push(format!("{}...", move_number));
+        }
+        let mut san = pgn_move.mv.to_san_in(&board, language);
+        if let Some(glyph) = &pgn_move.glyph {
+            san.push_str(glyph);
+        }
+        tokens.push(san);
+        tokens.extend(pgn_move.nags.iter().map(|nag| format!("${}", nag)));
+        show_number = false;
+        if let Some(comment) = &pgn_move.comment {
+            tokens.push(format!("{{{}}}", comment.replace('}', ")")));
+            show_number = true;
+        }
+        for variation in &pgn_move.variations {
+            let start = tokens.len();
+            push_line(tokens, variation, board.clone(), language);
+            if tokens.len() > start {
+                tokens[start].insert(0, '(');
+                tokens.last_mut().unwrap().push(')');
+                show_number = true;
+            }
+        }
+        pgn_move.mv.perform(&mut board);
+    }
+}
+
+fn join_comments(existing: Option<String>, comment: &str) -> Option<String> {
+    let comment = comment.trim();
+    match existing {
+        Some(existing) if !comment.is_empty() => Some(format!("{} {}", existing, comment)),
+        Some(existing) => Some(existing),
+        None if comment.is_empty() => None,
+        None => Some(comment.to_string()),
+    }
+}
+
+pub fn nag_symbol(nag: u8) -> Option<&'static str> {
+    Some(match nag {
+        1 => "!",
+        2 => "?",
+        3 => "!!",
+        4 => "??",
+        5 => "!?",
+        6 => "?!",
+        7 => "□",
+        10 => "=",
+        13 => "∞",
+        14 => "⩲",
+        15 => "⩱",
+        16 => "±",
+        17 => "∓",
+        18 => "+-",
+        19 => "-+",
+        _ => return None,
+    })
+}
+
+pub fn comment_command<'a>(comment: &'a str, name: &str) -> Option<&'a str> {
+    let start = comment.find(&format!("[%{} ", name))? + name.len() + 3;
+    let end = comment[start..].find(']')? + start;
+    Some(comment[start..end].trim())
+}
+
+pub fn strip_commands(comment: &str) -> Option<String> {
+    let mut text = String::new();
+    let mut rest = comment;
+    while let Some(start) = rest.find("[%") {
+        text.push_str(&rest[..start]);
+        rest = rest[start..]
+            .find(']')
+            .map_or("", |end| &rest[start + end + 1..]);
+    }
+    text.push_str(rest);
+    let text = text.split_whitespace().collect::<Vec<_>>().join(" ");
+    (!text.is_empty()).then_some(text)
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct PgnGame {
+    pub tags: Vec<(String, String)>,
+    pub comment: Option<String>,
+    pub moves: Vec<PgnMove>,
+    pub result: String,
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct PgnError {
+    pub line: usize,
+    pub message: String,
+}
+
+impl Display for PgnError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "line {}: {}", self.line, self.message)
+    }
+}
+
+impl std::error::Error for PgnError {}
+
+impl Default for PgnGame {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl PgnGame {
+    pub fn new() -> Self {
+        let tags = ["Event", "Site", "Date", "Round", "White", "Black", "Result"]
+            .iter()
+            .map(|name| {
+                let value = match *name {
+                    "Date" => "????.??.??",
+                    "Result" => "*",
+                    _ => "?",
+                };
+                (name.to_string(), value.to_string())
+            })
+            .collect();
+        Self {
+            tags,
+            comment: None,
+            moves: Vec::new(),
+            result: "*".to_string(),
+        }
+    }
+
+    pub fn tag(&self, name: &str) -> Option<&str> {
+        self.tags
+            .iter()
+            .find(|(tag, _)| tag == name)
+            .map(|(_, value)| value.as_str())
+    }
+
+    pub fn set_tag(&mut self, name: &str, value: impl Into<String>) {
+        let value = value.into();
+        if let Some((_, existing)) = self.tags.iter_mut().find(|(tag, _)| tag == name) {
+            *existing = value;
+        } else {
+            self.tags.push((name.to_string(), value));
+        }
+    }
+
+    pub fn set_result(&mut self, result: &str) {
+        self.result = result.to_string();
+        self.set_tag("Result", result);
+    }
+
+    pub fn starting_board(&self) -> ChessBoard {
+        self.try_starting_board()
+            .unwrap_or_else(|_| ChessBoard::with_variant(self.variant()))
+    }
+
+    pub fn try_starting_board(&self) -> Result<ChessBoard, FenError> {
+        let mut board = ChessBoard::with_variant(self.variant());
+        if let Some(fen) = self.tag("FEN") {
+            board.set_from_fen(fen)?;
+        }
+        Ok(board)
+    }
+
+    fn variant(&self) -> Variant {
+        self.tag("Variant")
+            .and_then(|variant| variant.parse().ok())
+            .unwrap_or_default()
+    }
+
+    pub fn final_board(&self) -> ChessBoard {
+        let mut board = self.starting_board();
+        for pgn_move in &self.moves {
+            pgn_move.mv.perform(&mut board);
+        }
+        board
+    }
+
+    pub fn win_state(&self) -> Option<WinState> {
+        if let Some(win_state) = self.final_board().win_state() {
+            return Some(win_state);
+        }
+        let winner = match self.result.as_str() {
+            "1-0" => PieceColor::White,
+            "0-1" => PieceColor::Black,
+            "1/2-1/2" => return Some(WinState::DrawAgreement),
+            _ => return None,
+        };
+        let on_time = self
+            .tag("Termination")
+            .is_some_and(|termination| termination.to_lowercase().contains("time"));
+        Some(if on_time {
+            WinState::Timeout(winner)
+        } else {
+            WinState::Resignation(winner)
+        })
+    }
+
+    pub fn movetext(&self) -> String {
+        self.movetext_in(SanLanguage::English)
+    }
+
+    pub fn movetext_in(&self, language: SanLanguage) -> String {
+        let mut tokens = Vec::with_capacity(self.moves.len() * 2 + 1);
+        if let Some(comment) = &self.comment {
+            tokens.push(format!("{{{}}}", comment.replace('}', ")")));
+        }
+        push_line(&mut tokens, &self.moves, self.starting_board(), language);
+        tokens.push(self.result.clone());
+
+        let mut text = String::new();
+        let mut line_len = 0;
+        for token in tokens {
+            if line_len > 0 && line_len + 1 + token.len() > LINE_WIDTH {
+                text.push('\n');
+                line_len = 0;
+            } else if line_len > 0 {
+                text.push(' ');
+                line_len += 1;
+            }
+            line_len += token.len();
+            text.push_str(&token);
+        }
+        text
+    }
+
+    pub fn parse_all(text: &str) -> Result<Vec<PgnGame>, PgnError> {
+        let mut games = Vec::new();
+        let mut game = PgnGame::new();
+        let mut lines: Vec<Line> = Vec::new();
+        let mut has_content = false;
+
+        let mut lexer = Lexer::new(text);
+        while let Some(token) = lexer.next_token()? {
+            let line = lexer.line;
+            match token {
+                Token::Tag(name, value) => {
+                    if !lines.is_empty() {
+                        game.moves = Line::finish(&mut lines);
+                        games.push(std::mem::take(&mut game));
+                    }
+                    game.set_tag(&name, value);
+                    has_content = true;
+                }
+                Token::OpenParen => {
+                    let Some(board) = lines.last().and_then(|l| l.previous.clone()) else {
+                        return Err(PgnError {
+                            line,
+                            message: "variation without a preceding move".to_string(),
+                        });
+                    };
+                    lines.push(Line {
+                        moves: Vec::new(),
+                        board,
+                        previous: None,
+                    });
+                }
+                Token::CloseParen => {
+                    if lines.len() < 2 {
+                        return Err(PgnError {
+                            line,
+                            message: "unmatched ')'".to_string(),
+                        });
+                    }
+                    Line::close(&mut lines);
+                }
+                Token::Comment(comment) => {
+                    let depth = lines.len();
+                    match lines.last_mut().and_then(|l| l.moves.last_mut()) {
+                        Some(pgn_move) => pgn_move.add_comment(&comment),
+                        None if depth <= 1 => {
+                            game.comment = join_comments(game.comment.take(), &comment);
+                            has_content = true;
+                        }
+                        None => {}
+                    }
+                }
+                Token::Nag(nag) => {
+                    if let Some(pgn_move) = lines.last_mut().and_then(|l| l.moves.last_mut()) {
+                        pgn_move.nags.push(nag);
+                    }
+                }
+                Token::Symbol(symbol) => {
+                    if RESULTS.contains(&symbol.as_str()) {
+                        game.moves = Line::finish(&mut lines);
+                        game.set_result(&symbol);
+                        games.push(std::mem::take(&mut game));
+                        has_content = false;
+                        continue;
+                    }
+                    let san = symbol.trim_start_matches(|c: char| c.is_ascii_digit() || c == '.');
+                    if san.is_empty() {
+                        continue;
+                    }
+                    if lines.is_empty() {
+                        let board = game.try_starting_board().map_err(|err| PgnError {
+                            line,
+                            message: format!("invalid FEN tag: {}", err),
+                        })?;
+                        lines.push(Line {
+                            moves: Vec::new(),
+                            board,
+                            previous: None,
+                        });
+                    }
+                    let current = lines.last_mut().unwrap();
+                    let glyph_start = san.find(['!', '?']).unwrap_or(san.len());
+                    let mv = Move::from_san(&san[..glyph_start], &current.board).map_err(|_| {
+                        PgnError {
+                            line,
+                            message: format!("illegal or ambiguous move: {}", san),
+                        }
+                    })?;
+                    let mut pgn_move = PgnMove::new(mv);
+                    if glyph_start < san.len() {
+                        pgn_move.glyph = Some(san[glyph_start..].to_string());
+                    }
+                    current.previous = Some(current.board.clone());
+                    mv.perform(&mut current.board);
+                    current.moves.push(pgn_move);
+                    has_content = true;
+                }
+            }
+        }
+        if has_content {
+            game.moves = Line::finish(&mut lines);
+            games.push(game);
+        }
+        Ok(games)
+    }
+}
+
+struct Line {
+    moves: Vec<PgnMove>,
+    board: ChessBoard,
+    previous: Option<ChessBoard>,
+}
+
+impl Line {
+    fn close(lines: &mut Vec<Line>) {
+        let variation = lines.pop().unwrap().moves;
+        let parent = lines.last_mut().and_then(|line| line.moves.last_mut());
+        if let (false, Some(parent)) = (variation.is_empty(), parent) {
+            parent.variations.push(variation);
+        }
+    }
+
+    fn finish(lines: &mut Vec<Line>) -> Vec<PgnMove> {
+        while lines.len() > 1 {
+            Line::close(lines);
+        }
+        lines.pop().map_or(Vec::new(), |line| line.moves)
+    }
+}
+
+impl PgnGame {
+    pub fn to_string_in(&self, language: SanLanguage) -> String {
+        let mut text = String::new();
+        for (name, value) in &self.tags {
+            text.push_str(&format!(
+                "[{} \"{}\"]\n",
+                name,
+                value.replace('\\', "\\\\").replace('"', "\\\"")
+            ));
+        }
+        text.push('\n');
+        text.push_str(&self.movetext_in(language));
+        text.push('\n');
+        text
+    }
+}
+
+impl Display for PgnGame {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", self.to_string_in(SanLanguage::English))
+    }
+}
+
+enum Token {
+    Tag(String, String),
+    Comment(String),
+    Nag(u8),
+    OpenParen,
+    CloseParen,
+    Symbol(String),
+}
+
+struct Lexer<'a> {
+    chars: Peekable<Chars<'a>>,
+    line: usize,
+}
+
+impl<'a> Lexer<'a> {
+    fn new(text: &'a str) -> Self {
+        Self {
+            chars: text.chars().peekable(),
+            line: 1,
+        }
+    }
+
+    fn error(&self, message: &str) -> PgnError {
+        PgnError {
+            line: self.line,
+            message: message.to_string(),
+        }
+    }
+
+    fn bump(&mut self) -> Option<char> {
+        let c = self.chars.next();
+        if c == Some('\n') {
+            self.line += 1;
+        }
+        c
+    }
+
+    fn read_while(&mut self, predicate: impl Fn(char) -> bool) -> String {
+        let mut out = String::new();
+        while let Some(&c) = self.chars.peek() {
+            if !predicate(c) {
+                break;
+            }
+            out.push(c);
+            self.bump();
+        }
+        out
+    }
+
+    fn next_token(&mut self) -> Result<Option<Token>, PgnError> {
+        loop {
+            let Some(c) = self.bump() else {
+                return Ok(None);
+            };
+            return Ok(Some(match c {
+                c if c.is_whitespace() => continue,
+                '%' => {
+                    self.read_while(|c| c != '\n');
+                    continue;
+                }
+                ';' => Token::Comment(self.read_while(|c| c != '\n')),
+                '{' => {
+                    let comment = self.read_while(|c| c != '}');
+                    if self.bump() != Some('}') {
+                        return Err(self.error("unterminated comment"));
+                    }
+                    Token::Comment(comment)
+                }
+                '(' => Token::OpenParen,
+                ')' => Token::CloseParen,
+                '$' => match self.read_while(|c| c.is_ascii_digit()).parse() {
+                    Ok(nag) => Token::Nag(nag),
+                    Err(_) => return Err(self.error("invalid NAG")),
+                },
+                '[' => {
+                    self.read_while(char::is_whitespace);
+                    let name = self.read_while(|c| !c.is_whitespace() && c != '"');
+                    self.read_while(char::is_whitespace);
+                    if self.bump() != Some('"') {
+                        return Err(self.error("expected tag value"));
+                    }
+                    let mut value = String::new();
+                    loop {
+                        match self.bump() {
+                            Some('\\') => value.extend(self.bump()),
+                            Some('"') => break,
+                            Some(c) => value.push(c),
+                            None => return Err(self.error("unterminated tag value")),
+                        }
+                    }
+                    self.read_while(|c| c != ']');
+                    if self.bump() != Some(']') {
+                        return Err(self.error("unterminated tag"));
+                    }
+                    Token::Tag(name, value)
+                }
+                c => {
+                    let mut symbol = c.to_string();
+                    symbol.push_str(
+                        &self.read_while(|c| !c.is_whitespace() && !"{}()[];$".contains(c)),
+                    );
+                    Token::Symbol(symbol)
+                }
+            }));
+        }
+    }
+}
@@ -0,0 +1,125 @@
+use std::{fs, io, path::Path};
+
+use crate::{clock::TimeControl, config, logic::PieceColor};
+
+pub const PROFILES_FILE: &str = "profiles.cfg";
+pub const DEFAULT_PIECE_SET: &str = "default";
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct Profile {
+    pub name: String,
+    pub color: PieceColor,
+    pub time_control: Option<TimeControl>,
+    pub high_contrast: bool,
+    pub piece_set: String,
+}
+
+impl Profile {
+    pub fn new(name: &str) -> Self {
+        Self {
+            name: name.to_string(),
+            color: PieceColor::White,
+            time_control: None,
+            high_contrast: false,
+            piece_set: DEFAULT_PIECE_SET.to_string(),
+        }
+    }
+}
+
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct Profiles {
+    pub profiles: Vec<Profile>,
+    pub active: Option<String>,
+}
+
+impl Profiles {
+    pub fn active(&self) -> Option<&Profile> {
+        let active = self.active.as_ref()?;
+        self.profiles.iter().find(|profile| &profile.name == active)
+    }
+
+    pub fn get(&self, name: &str) -> Option<&Profile> {
+        self.profiles.iter().find(|profile| profile.name == name)
+    }
+
+    pub fn parse(text: &str) -> Self {
+        let mut profiles = Self::default();
+        for line in text.lines() {
+            let line = line.trim();
+            if let Some(name) = line.strip_prefix('[').and_then(|l| l.strip_suffix(']')) {
+                profiles.profiles.push(Profile::new(name));
+                continue;
+            }
+            let Some((key, value)) = line.split_once('=') else {
+                continue;
+            };
+            let value = value.trim();
+            let Some(profile) = profiles.profiles.last_mut() else {
+                if key.trim() == "active" && !value.is_empty() {
+                    profiles.active = Some(value.to_string());
+                }
+                continue;
+            };
+            match key.trim() {
+                "color" => {
+                    profile.color = match value {
+                        "black" => PieceColor::Black,
+                        _ => PieceColor::White,
+                    }
+                }
+                "time_control" => profile.time_control = value.parse().ok(),
+                "high_contrast" => {
+                    profile.high_contrast = value.parse().unwrap_or(profile.high_contrast)
+                }
+                "piece_set" if !value.is_empty() => profile.piece_set = value.to_string(),
+                _ => {}
+            }
+        }
+        profiles
+    }
+
+    pub fn to_config(&self) -> String {
+        let mut out = String::new();
+        if let Some(active) = &self.active {
+            out.push_str(&format!("active = {}\n", active));
+        }
+        for profile in &self.profiles {
+            out.push_str(&format!(
+                "\n[{}]\ncolor = {}\ntime_control = {}\nhigh_contrast = {}\npiece_set = {}\n",
+                profile.name,
+                profile.color.readable().to_lowercase(),
+                profile
+                    .time_control
+                    .as_ref()
+                    .map_or("none".to_string(), |tc| tc.to_string()),
+                profile.high_contrast,
+                profile.piece_set
+            ));
+        }
+        out
+    }
+
+    pub fn load(path: &Path) -> io::Result<Self> {
+        Ok(Self::parse(&fs::read_to_string(path)?))
+    }
+
+    pub fn save(&self, path: &Path) -> io::Result<()> {
+        if let Some(parent) = path.parent() {
+            fs::create_dir_all(parent)?;
+        }
+        fs::write(path, self.to_config())
+    }
+
+    pub fn load_saved() -> Self {
+        config::config_file(PROFILES_FILE)
+            .and_then(|path| Self::load(&path).ok())
+            .unwrap_or_default()
+    }
+
+    pub fn save_saved(&self) -> io::Result<()> {
+        match config::config_file(PROFILES_FILE) {
+            Some(path) => self.save(&path),
+            None => Ok(()),
+        }
+    }
+}
@@ -0,0 +1,152 @@
+use pyo3::{exceptions::PyValueError, prelude::*};
+use rayon::iter::ParallelIterator;
+
+use crate::{
+    ai::AI,
+    logic::{ChessBoard, Move, PieceColor, Variant},
+};
+
+fn value_error(err: impl ToString) -> PyErr {
+    PyValueError::new_err(err.to_string())
+}
+
+#[pyclass(name = "ChessBoard", module = "chess")]
+#[derive(Clone)]
+pub struct PyChessBoard {
+    board: ChessBoard,
+}
+
+impl PyChessBoard {
+    fn legal(&self) -> Vec<Move> {
+        let mut moves = self.board.legal_moves(self.board.turn).collect::<Vec<_>>();
+        moves.sort_by_key(|mv| (mv.original, mv.target));
+        moves
+    }
+
+    fn parse_move(&self, text: &str) -> PyResult<Move> {
+        Move::parse(text, &self.board).map_err(|_| value_error(format!("illegal move: {}", text)))
+    }
+}
+
+#[pymethods]
+impl PyChessBoard {
+    #[new]
+    #[pyo3(signature = (fen = None, variant = None))]
+    fn new(fen: Option<&str>, variant: Option<&str>) -> PyResult<Self> {
+        let variant = match variant {
+            Some(variant) => variant
+                .parse::<Variant>()
+                .map_err(|_| value_error(format!("unknown variant: {}", variant)))?,
+            None => Variant::Standard,
+        };
+        let mut board = ChessBoard::with_variant(variant);
+        if let Some(fen) = fen {
+            board.set_from_fen(fen).map_err(value_error)?;
+            board.validate().map_err(value_error)?;
+        }
+        Ok(Self { board })
+    }
+
+    #[getter]
+    fn fen(&self) -> String {
+        self.board.to_fen()
+    }
+
+    #[getter]
+    fn turn(&self) -> &'static str {
+        match self.board.turn {
+            PieceColor::White => "white",
+            PieceColor::Black => "black",
+        }
+    }
+
+    #[getter]
+    fn ply(&self) -> usize {
+        self.board.moves_made
+    }
+
+    fn legal_moves(&self) -> Vec<String> {
+        self.legal()
+            .iter()
+            .map(|mv| mv.to_uci(&self.board))
+            .collect()
+    }
+
+    fn legal_moves_san(&self) -> Vec<String> {
+        self.legal()
+            .iter()
+            .map(|mv| mv.to_san(&self.board))
+            .collect()
+    }
+
+    fn san(&self, mv: &str) -> PyResult<String> {
+        Ok(self.parse_move(mv)?.to_san(&self.board))
+    }
+
+    fn uci(&self, mv: &str) -> PyResult<String> {
+        Ok(self.parse_move(mv)?.to_uci(&self.board))
+    }
+
+    fn push(&mut self, mv: &str) -> PyResult<()> {
+        self.parse_move(mv)?.perform(&mut self.board);
+        Ok(())
+    }
+
+    fn is_check(&self) -> bool {
+        self.board.is_in_check(self.board.turn)
+    }
+
+    fn is_game_over(&self) -> bool {
+        self.board.win_state().is_some()
+    }
+
+    fn result(&self) -> Option<(&'static str, &'static str)> {
+        self.board
+            .win_state()
+            .map(|win_state| (win_state.result(), win_state.reason()))
+    }
+
+    fn perft(&self, py: Python<'_>, depth: usize) -> u64 {
+        let board = self.board.clone();
+        py.allow_threads(move || board.perft(depth))
+    }
+
+    #[pyo3(signature = (depth = 4))]
+    fn best_move(&self, py: Python<'_>, depth: usize) -> PyResult<String> {
+        if !self.board.has_legal_move() {
+            return Err(value_error("no legal moves"));
+        }
+        let board = self.board.clone();
+        let mv = py.allow_threads(|| AI::with_depth(depth.max(1)).best_move(&board, depth.max(1)));
+        Ok(mv.to_uci(&self.board))
+    }
+
+    #[pyo3(signature = (depth = 4))]
+    fn analyze(&self, py: Python<'_>, depth: usize) -> Vec<(String, f64)> {
+        let board = self.board.clone();
+        let scores =
+            py.allow_threads(|| AI::with_depth(depth.max(1)).analyze(&board, depth.max(1)));
+        scores
+            .into_iter()
+            .map(|(mv, score)| (mv.to_uci(&self.board), score))
+            .collect()
+    }
+
+    fn copy(&self) -> Self {
+        self.clone()
+    }
+
+    fn __str__(&self) -> String {
+        self.board.render(false)
+    }
+
+    fn __repr__(&self) -> String {
+        format!("ChessBoard('{}')", self.board.to_fen())
+    }
+}
+
+#[pymodule]
+fn chess(module: &Bound<'_, PyModule>) -> PyResult<()> {
+    module.add_class::<PyChessBoard>()?;
+    Ok(())
+}
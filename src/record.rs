@@ -0,0 +1,252 @@
+use std::{fmt::Display, time::Duration};
+
+use rayon::iter::ParallelIterator;
+
+use crate::{
+    clock::{format_pgn_clock, parse_pgn_clock, TimeControl},
+    logic::{ChessBoard, Move, PieceColor, Variant, WinState},
+    pgn::{comment_command, strip_commands, PgnGame, PgnMove},
+};
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct RecordedMove {
+    pub mv: Move,
+    pub time: Duration,
+    pub clock: Option<Duration>,
+    pub comment: Option<String>,
+    pub nags: Vec<u8>,
+}
+
+impl RecordedMove {
+    pub fn new(mv: Move) -> Self {
+        Self {
+            mv,
+            time: Duration::ZERO,
+            clock: None,
+            comment: None,
+            nags: Vec::new(),
+        }
+    }
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum ReplayError {
+    IllegalMove { ply: usize, mv: String },
+    HashMismatch { expected: u64, actual: u64 },
+}
+
+impl Display for ReplayError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            ReplayError::IllegalMove { ply, mv } => {
+                write!(f, "illegal move {} at ply {}", mv, ply + 1)
+            }
+            ReplayError::HashMismatch { expected, actual } => write!(
+                f,
+                "final position hash {:016x} does not match the recorded {:016x}",
+                actual, expected
+            ),
+        }
+    }
+}
+
+impl std::error::Error for ReplayError {}
+
+#[derive(Clone, Debug)]
+pub struct GameRecord {
+    pub white: String,
+    pub black: String,
+    pub start_board: ChessBoard,
+    pub moves: Vec<RecordedMove>,
+    pub time_controls: Option<[TimeControl; 2]>,
+    pub result: Option<WinState>,
+}
+
+impl GameRecord {
+    pub fn new(start_board: ChessBoard) -> Self {
+        Self {
+            white: "?".to_string(),
+            black: "?".to_string(),
+            start_board,
+            moves: Vec::new(),
+            time_controls: None,
+            result: None,
+        }
+    }
+
+    pub fn from_moves(
+        start_board: ChessBoard,
+        moves: &[Move],
+        move_times: &[Duration],
+        time_controls: Option<[TimeControl; 2]>,
+    ) -> Self {
+        let mut record = Self::new(start_board);
+        record.time_controls = time_controls;
+        for (i, &mv) in moves.iter().enumerate() {
+            record.push(mv, move_times.get(i).copied().unwrap_or_default());
+        }
+        record
+    }
+
+    fn turn_at(&self, ply: usize) -> PieceColor {
+        if ply.is_multiple_of(2) {
+            self.start_board.turn
+        } else {
+            self.start_board.turn.opposite()
+        }
+    }
+
+    pub fn push(&mut self, mv: Move, time: Duration) {
+        let ply = self.moves.len();
+        let color = self.turn_at(ply);
+        let clock = self.time_controls.as_ref().map(|time_controls| {
+            let time_control = &time_controls[color as usize];
+            let own_moves = (0..ply).filter(|&i| self.turn_at(i) == color);
+            let previous = own_moves
+                .clone()
+                .next_back()
+                .and_then(|i| self.moves[i].clock)
+                .unwrap_or(time_control.base);
+            time_control.after_move(previous, time, own_moves.count() as u32 + 1)
+        });
+        self.moves.push(RecordedMove {
+            clock,
+            time,
+            ..RecordedMove::new(mv)
+        });
+    }
+
+    pub fn move_list(&self) -> Vec<Move> {
+        self.moves.iter().map(|recorded| recorded.mv).collect()
+    }
+
+    pub fn move_times(&self) -> Vec<Duration> {
+        self.moves.iter().map(|recorded| recorded.time).collect()
+    }
+
+    pub fn final_board(&self) -> ChessBoard {
+        let mut board = self.start_board.clone();
+        for recorded in &self.moves {
+            recorded.mv.perform(&mut board);
+        }
+        board
+    }
+
+    pub fn replay(&self) -> Result<ChessBoard, ReplayError> {
+        let mut board = self.start_board.clone();
+        for (ply, recorded) in self.moves.iter().enumerate() {
+            if !board
+                .legal_moves(board.turn)
+                .any(|legal| legal == recorded.mv)
+            {
+                return Err(ReplayError::IllegalMove {
+                    ply,
+                    mv: recorded.mv.to_uci(&board),
+                });
+            }
+            recorded.mv.perform(&mut board);
+        }
+        Ok(board)
+    }
+
+    pub fn verify(&self, expected_hash: Option<u64>) -> Result<ChessBoard, ReplayError> {
+        let board = self.replay()?;
+        let actual = board.zobrist_key();
+        match expected_hash {
+            Some(expected) if expected != actual => {
+                Err(ReplayError::HashMismatch { expected, actual })
+            }
+            _ => Ok(board),
+        }
+    }
+
+    pub fn to_pgn(&self) -> PgnGame {
+        let mut pgn = PgnGame::new();
+        pgn.set_tag("White", &self.white);
+        pgn.set_tag("Black", &self.black);
+        if self.start_board.variant != Variant::Standard {
+            pgn.set_tag("Variant", self.start_board.variant.readable());
+        }
+        if self.start_board != ChessBoard::with_variant(self.start_board.variant) {
+            pgn.set_tag("SetUp", "1");
+            pgn.set_tag("FEN", self.start_board.to_fen());
+        }
+        if let Some([white, black]) = &self.time_controls {
+            if white == black {
+                pgn.set_tag("TimeControl", white.to_string());
+            } else {
+                pgn.set_tag("WhiteTimeControl", white.to_string());
+                pgn.set_tag("BlackTimeControl", black.to_string());
+            }
+        }
+        if let Some(result) = self.result {
+            pgn.set_result(result.result());
+            pgn.set_tag("Termination", result.reason());
+        }
+        pgn.moves = self
+            .moves
+            .iter()
+            .map(|recorded| {
+                let mut pgn_move = PgnMove::new(recorded.mv);
+                if let Some(comment) = &recorded.comment {
+                    pgn_move.add_comment(comment);
+                }
+                pgn_move.add_comment(&match recorded.clock {
+                    Some(clock) => format!("[%clk {}]", format_pgn_clock(clock)),
+                    None => format!("[%emt {}]", format_pgn_clock(recorded.time)),
+                });
+                pgn_move.nags = recorded.nags.clone();
+                pgn_move
+            })
+            .collect();
+        pgn
+    }
+
+    pub fn time_controls_from_pgn(pgn: &PgnGame) -> Option<[TimeControl; 2]> {
+        let tag = |name| pgn.tag(name).and_then(|tc| tc.parse::<TimeControl>().ok());
+        match (tag("WhiteTimeControl"), tag("BlackTimeControl")) {
+            (Some(white), Some(black)) => Some([white, black]),
+            _ => tag("TimeControl").map(|tc| [tc.clone(), tc]),
+        }
+    }
+
+    pub fn from_pgn(pgn: &PgnGame) -> Self {
+        let mut record = Self::new(pgn.starting_board());
+        record.white = pgn.tag("White").unwrap_or("?").to_string();
+        record.black = pgn.tag("Black").unwrap_or("?").to_string();
+        record.time_controls = Self::time_controls_from_pgn(pgn);
+        record.result = pgn.win_state();
+        let command = |pgn_move: &PgnMove, name| {
+            pgn_move
+                .comment
+                .as_deref()
+                .and_then(|comment| comment_command(comment, name))
+                .and_then(parse_pgn_clock)
+        };
+        let mut previous = record
+            .time_controls
+            .as_ref()
+            .map(|tcs| tcs.clone().map(|tc| tc.base));
+        let mut moves_made = [0; 2];
+        for (ply, pgn_move) in pgn.moves.iter().enumerate() {
+            let turn = record.turn_at(ply) as usize;
+            moves_made[turn] += 1;
+            let clock = command(pgn_move, "clk");
+            let time = command(pgn_move, "emt").or_else(|| {
+                let time_control = &record.time_controls.as_ref()?[turn];
+                let previous = &mut previous.as_mut()?[turn];
+                let spent = time_control.spent(*previous, clock?, moves_made[turn]);
+                *previous = clock?;
+                Some(spent)
+            });
+            record.moves.push(RecordedMove {
+                time: time.unwrap_or(Duration::ZERO),
+                clock,
+                comment: pgn_move.comment.as_deref().and_then(strip_commands),
+                nags: pgn_move.nags.clone(),
+                ..RecordedMove::new(pgn_move.mv)
+            });
+        }
+        record
+    }
+}
@@ -0,0 +1,269 @@
+use std::{collections::HashMap, fs, io, path::Path, time::Duration};
+
+use image::{
+    codecs::gif::{GifEncoder, Repeat},
+    imageops::{self, FilterType},
+    Delay, Frame, ImageFormat, Rgba, RgbaImage,
+};
+
+use crate::logic::{ChessBoard, Move, PieceColor, PieceType};
+
+const FRAME_DELAY: Duration = Duration::from_millis(800);
+const FINAL_FRAME_DELAY: Duration = Duration::from_millis(3000);
+const HIGHLIGHT: Rgba<u8> = Rgba([205, 210, 106, 160]);
+const GLYPH_WIDTH: u32 = 3;
+const GLYPH_HEIGHT: u32 = 5;
+
+fn glyph(c: char) -> [&'static str; 5] {
+    match c {
+        'a' => ["...", ".##", "#.#", "#.#", ".##"],
+        'b' => ["#..", "##.", "#.#", "#.#", "##."],
+        'c' => ["...", ".##", "#..", "#..", ".##"],
+        'd' => ["..#", ".##", "#.#", "#.#", ".##"],
+        'e' => ["...", ".#.", "###", "#..", ".##"],
+        'f' => [".##", "#..", "##.", "#..", "#.."],
+        'g' => [".##", "#.#", ".##", "..#", "##."],
+        'h' => ["#..", "##.", "#.#", "#.#", "#.#"],
+        'i' => [".#.", "...", ".#.", ".#.", ".#."],
+        'j' => ["..#", "...", "..#", "#.#", ".#."],
+        'k' => ["#..", "#.#", "##.", "#.#", "#.#"],
+        'l' => ["##.", ".#.", ".#.", ".#.", "###"],
+        'm' => ["...", "#.#", "###", "#.#", "#.#"],
+        'n' => ["...", "##.", "#.#", "#.#", "#.#"],
+        'o' => ["...", ".#.", "#.#", "#.#", ".#."],
+        'p' => ["...", "##.", "#.#", "##.", "#.."],
+        '0' => ["###", "#.#", "#.#", "#.#", "###"],
+        '1' => [".#.", "##.", ".#.", ".#.", "###"],
+        '2' => ["##.", "..#", ".#.", "#..", "###"],
+        '3' => ["##.", "..#", ".#.", "..#", "##."],
+        '4' => ["#.#", "#.#", "###", "..#", "..#"],
+        '5' => ["###", "#..", "##.", "..#", "##."],
+        '6' => [".##", "#..", "###", "#.#", "###"],
+        '7' => ["###", "..#", ".#.", ".#.", ".#."],
+        '8' => ["###", "#.#", "###", "#.#", "###"],
+        '9' => ["###", "#.#", "###", "..#", "###"],
+        _ => ["..."; 5],
+    }
+}
+
+fn square_size(board: &ChessBoard, size: u32) -> u32 {
+    (size / board.width.max(board.height) as u32).max(1)
+}
+
+fn blend(base: Rgba<u8>, over: Rgba<u8>) -> Rgba<u8> {
+    let alpha = over[3] as u32;
+    let mix = |a: u8, b: u8| ((a as u32 * (255 - alpha) + b as u32 * alpha) / 255) as u8;
+    Rgba([
+        mix(base[0], over[0]),
+        mix(base[1], over[1]),
+        mix(base[2], over[2]),
+        base[3],
+    ])
+}
+
+fn draw_glyph(image: &mut RgbaImage, c: char, x: u32, y: u32, scale: u32, color: Rgba<u8>) {
+    for (dy, line) in glyph(c).iter().enumerate() {
+        for (dx, pixel) in line.chars().enumerate() {
+            if pixel != '#' {
+                continue;
+            }
+            for sy in 0..scale {
+                for sx in 0..scale {
+                    let (px, py) = (x + dx as u32 * scale + sx, y + dy as u32 * scale + sy);
+                    if px < image.width() && py < image.height() {
+                        image.put_pixel(px, py, color);
+                    }
+                }
+            }
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct RenderOptions {
+    pub coordinates: bool,
+    pub last_move: Option<Move>,
+}
+
+#[derive(Clone, Debug)]
+pub struct BoardRenderer {
+    pub light_square: Rgba<u8>,
+    pub dark_square: Rgba<u8>,
+    pub highlight: Rgba<u8>,
+    pieces: HashMap<(PieceType, PieceColor), RgbaImage>,
+}
+
+impl BoardRenderer {
+    pub fn new(light_square: Rgba<u8>, dark_square: Rgba<u8>) -> Self {
+        Self {
+            light_square,
+            dark_square,
+            highlight: HIGHLIGHT,
+            pieces: HashMap::new(),
+        }
+    }
+
+    pub fn set_piece_image(&mut self, piece_type: PieceType, color: PieceColor, image: RgbaImage) {
+        self.pieces.insert((piece_type, color), image);
+    }
+
+    fn scaled_pieces(&self, square_size: u32) -> HashMap<(PieceType, PieceColor), RgbaImage> {
+        self.pieces
+            .iter()
+            .map(|(&key, image)| {
+                let scaled =
+                    imageops::resize(image, square_size, square_size, FilterType::Triangle);
+                (key, scaled)
+            })
+            .collect()
+    }
+
+    fn square_color(&self, col: u32, row: u32) -> Rgba<u8> {
+        if (row + col).is_multiple_of(2) {
+            self.dark_square
+        } else {
+            self.light_square
+        }
+    }
+
+    fn draw(
+        &self,
+        board: &ChessBoard,
+        square_size: u32,
+        pieces: &HashMap<(PieceType, PieceColor), RgbaImage>,
+        options: RenderOptions,
+    ) -> RgbaImage {
+        let (width, height) = (board.width as u32, board.height as u32);
+        let highlighted = options
+            .last_move
+            .map(|mv| [mv.original, mv.target])
+            .unwrap_or_default();
+        let mut image = RgbaImage::from_fn(width * square_size, height * square_size, |x, y| {
+            let (col, row) = (x / square_size, y / square_size);
+            let color = self.square_color(col, row);
+            if options.last_move.is_some() && highlighted.contains(&(col as usize, row as usize)) {
+                blend(color, self.highlight)
+            } else {
+                color
+            }
+        });
+        if options.coordinates {
+            self.draw_coordinates(&mut image, board, square_size);
+        }
+        for piece in board.pieces.iter().flatten() {
+            if let Some(sprite) = pieces.get(&(piece.piece_type, piece.color)) {
+                imageops::overlay(
+                    &mut image,
+                    sprite,
+                    (piece.pos.0 as u32 * square_size) as i64,
+                    (piece.pos.1 as u32 * square_size) as i64,
+                );
+            }
+        }
+        image
+    }
+
+    fn draw_coordinates(&self, image: &mut RgbaImage, board: &ChessBoard, square_size: u32) {
+        let scale = (square_size / 16).max(1);
+        let margin = scale.max(square_size / 20);
+        let (width, height) = (board.width as u32, board.height as u32);
+        for row in 0..height {
+            let rank = (height - row).to_string();
+            for (i, c) in rank.chars().enumerate() {
+                draw_glyph(
+                    image,
+                    c,
+                    margin + i as u32 * (GLYPH_WIDTH + 1) * scale,
+                    row * square_size + margin,
+                    scale,
+                    self.square_color(1, row),
+                );
+            }
+        }
+        for col in 0..width {
+            let file = char::from(b'a' + col as u8);
+            draw_glyph(
+                image,
+                file,
+                (col + 1) * square_size - margin - GLYPH_WIDTH * scale,
+                height * square_size - margin - GLYPH_HEIGHT * scale,
+                scale,
+                self.square_color(col + 1, height - 1),
+            );
+        }
+    }
+
+    pub fn render(&self, board: &ChessBoard, size: u32, options: RenderOptions) -> RgbaImage {
+        let square_size = square_size(board, size);
+        self.draw(
+            board,
+            square_size,
+            &self.scaled_pieces(square_size),
+            options,
+        )
+    }
+
+    pub fn export_png(
+        &self,
+        path: &Path,
+        board: &ChessBoard,
+        size: u32,
+        options: RenderOptions,
+    ) -> io::Result<()> {
+        if let Some(parent) = path.parent() {
+            fs::create_dir_all(parent)?;
+        }
+        self.render(board, size, options)
+            .save_with_format(path, ImageFormat::Png)
+            .map_err(io::Error::other)
+    }
+
+    pub fn render_game(
+        &self,
+        start_board: &ChessBoard,
+        moves: &[Move],
+        size: u32,
+    ) -> Vec<RgbaImage> {
+        let square_size = square_size(start_board, size);
+        let pieces = self.scaled_pieces(square_size);
+        let mut board = start_board.clone();
+        let mut frames = vec![self.draw(&board, square_size, &pieces, RenderOptions::default())];
+        for &mv in moves {
+            mv.perform(&mut board);
+            let options = RenderOptions {
+                coordinates: false,
+                last_move: Some(mv),
+            };
+            frames.push(self.draw(&board, square_size, &pieces, options));
+        }
+        frames
+    }
+
+    pub fn export_gif(
+        &self,
+        path: &Path,
+        start_board: &ChessBoard,
+        moves: &[Move],
+        size: u32,
+    ) -> io::Result<()> {
+        if let Some(parent) = path.parent() {
+            fs::create_dir_all(parent)?;
+        }
+        let images = self.render_game(start_board, moves, size);
+        let last = images.len() - 1;
+        let frames = images.into_iter().enumerate().map(|(i, image)| {
+            let delay = if i == last {
+                FINAL_FRAME_DELAY
+            } else {
+                FRAME_DELAY
+            };
+            Frame::from_parts(image, 0, 0, Delay::from_saturating_duration(delay))
+        });
+        let mut encoder =
+            GifEncoder::new_with_speed(io::BufWriter::new(fs::File::create(path)?), 10);
+        encoder
+            .set_repeat(Repeat::Infinite)
+            .map_err(io::Error::other)?;
+        encoder.encode_frames(frames).map_err(io::Error::other)
+    }
+}
@@ -0,0 +1,294 @@
+use std::{
+    cmp::Reverse,
+    fs, io,
+    path::{Path, PathBuf},
+    time::{Duration, SystemTime, UNIX_EPOCH},
+};
+
+use crate::{
+    clock::Clock,
+    config,
+    logic::{ChessBoard, PieceColor},
+    pgn::PgnGame,
+    record::GameRecord,
+};
+
+pub const SAVE_FILE: &str = "correspondence.pgn";
+const SLOTS_DIR: &str = "saves";
+const FINISHED_DIR: &str = "finished";
+pub const MAX_RECENT_GAMES: usize = 10;
+const HUMAN_PLAYER: &str = "Player";
+const ENGINE_PLAYER: &str = "Computer";
+const POSITION_HASH_TAG: &str = "PositionHash";
+
+#[derive(Clone, Debug)]
+pub struct SavedGame {
+    pub record: GameRecord,
+    pub remaining: [Duration; 2],
+    pub saved_at: Option<SystemTime>,
+    pub human_color: PieceColor,
+    pub hot_seat: bool,
+}
+
+impl SavedGame {
+    pub fn new(mut record: GameRecord, clock: Option<&Clock>) -> Self {
+        if let Some(clock) = clock {
+            record.time_controls = Some(clock.time_controls().clone());
+        }
+        let mut saved = Self {
+            record,
+            remaining: clock.map_or([Duration::ZERO; 2], |clock| {
+                [
+                    clock.remaining(PieceColor::White),
+                    clock.remaining(PieceColor::Black),
+                ]
+            }),
+            saved_at: clock.map(|_| SystemTime::now()),
+            human_color: PieceColor::White,
+            hot_seat: false,
+        };
+        saved.set_players(PieceColor::White, false);
+        saved
+    }
+
+    pub fn set_players(&mut self, human_color: PieceColor, hot_seat: bool) {
+        self.human_color = human_color;
+        self.hot_seat = hot_seat;
+        let (white, black) = match human_color {
+            _ if hot_seat => (HUMAN_PLAYER, HUMAN_PLAYER),
+            PieceColor::White => (HUMAN_PLAYER, ENGINE_PLAYER),
+            PieceColor::Black => (ENGINE_PLAYER, HUMAN_PLAYER),
+        };
+        self.record.white = white.to_string();
+        self.record.black = black.to_string();
+    }
+
+    pub fn final_board(&self) -> ChessBoard {
+        self.record.final_board()
+    }
+
+    pub fn clock(&self) -> Option<Clock> {
+        let mut clock = Clock::with_time_controls(self.record.time_controls.clone()?);
+        clock.set_remaining(PieceColor::White, self.remaining[0]);
+        clock.set_remaining(PieceColor::Black, self.remaining[1]);
+        let moves = self.record.moves.len() as u32;
+        let first_moves = moves.div_ceil(2);
+        let (white, black) = match self.record.start_board.turn {
+            PieceColor::White => (first_moves, moves - first_moves),
+            PieceColor::Black => (moves - first_moves, first_moves),
+        };
+        clock.set_moves_made(PieceColor::White, white);
+        clock.set_moves_made(PieceColor::Black, black);
+        let turn = self.final_board().turn;
+        if let Some(saved_at) = self
+            .saved_at
+            .filter(|_| clock.time_control(turn).is_per_move())
+        {
+            let elapsed = SystemTime::now()
+                .duration_since(saved_at)
+                .unwrap_or_default();
+            clock.resume(turn, elapsed);
+        }
+        Some(clock)
+    }
+
+    pub fn to_pgn(&self) -> PgnGame {
+        let mut pgn = self.record.to_pgn();
+        pgn.set_tag("Event", "Correspondence game");
+        if self.record.time_controls.is_some() {
+            pgn.set_tag(
+                "WhiteClock",
+                format!("{:.3}", self.remaining[0].as_secs_f64()),
+            );
+            pgn.set_tag(
+                "BlackClock",
+                format!("{:.3}", self.remaining[1].as_secs_f64()),
+            );
+        }
+        if let Some(saved_at) = self.saved_at {
+            let secs = saved_at
+                .duration_since(UNIX_EPOCH)
+                .unwrap_or_default()
+                .as_secs();
+            pgn.set_tag("SavedAt", secs.to_string());
+        }
+        let final_board = self.final_board();
+        pgn.set_tag("Turn", final_board.turn.readable());
+        pgn.set_tag(
+            POSITION_HASH_TAG,
+            format!("{:016x}", final_board.zobrist_key()),
+        );
+        pgn
+    }
+
+    pub fn from_pgn(pgn: &PgnGame) -> Self {
+        let clock_tag = |name| {
+            pgn.tag(name)
+                .and_then(|value| value.parse::<f64>().ok())
+                .filter(|secs| secs.is_finite() && *secs >= 0.0)
+                .map_or(Duration::ZERO, Duration::from_secs_f64)
+        };
+        let record = GameRecord::from_pgn(pgn);
+        Self {
+            human_color: if record.black == HUMAN_PLAYER {
+                PieceColor::Black
+            } else {
+                PieceColor::White
+            },
+            hot_seat: record.white == HUMAN_PLAYER && record.black == HUMAN_PLAYER,
+            record,
+            remaining: [clock_tag("WhiteClock"), clock_tag("BlackClock")],
+            saved_at: pgn
+                .tag("SavedAt")
+                .and_then(|secs| secs.parse().ok())
+                .map(|secs| UNIX_EPOCH + Duration::from_secs(secs)),
+        }
+    }
+
+    pub fn save(&self, path: &Path) -> io::Result<()> {
+        if let Some(parent) = path.parent() {
+            fs::create_dir_all(parent)?;
+        }
+        fs::write(path, self.to_pgn().to_string())
+    }
+
+    pub fn load(path: &Path) -> io::Result<Self> {
+        let text = fs::read_to_string(path)?;
+        let games = PgnGame::parse_all(&text)
+            .map_err(|err| io::Error::new(io::ErrorKind::InvalidData, err))?;
+        let Some(pgn) = games.first() else {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidData,
+                "no game in save file",
+            ));
+        };
+        let saved = Self::from_pgn(pgn);
+        saved
+            .record
+            .verify(position_hash(pgn))
+            .map_err(|err| io::Error::new(io::ErrorKind::InvalidData, err))?;
+        Ok(saved)
+    }
+
+    pub fn load_saved() -> io::Result<Option<Self>> {
+        match config::config_file(SAVE_FILE).filter(|path| path.exists()) {
+            Some(path) => Self::load(&path).map(Some),
+            None => Ok(None),
+        }
+    }
+
+    pub fn save_saved(&self) -> io::Result<()> {
+        match config::config_file(SAVE_FILE) {
+            Some(path) => self.save(&path),
+            None => Ok(()),
+        }
+    }
+
+    pub fn remove_saved() -> io::Result<()> {
+        match config::config_file(SAVE_FILE) {
+            Some(path) if path.exists() => fs::remove_file(path),
+            _ => Ok(()),
+        }
+    }
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct RecentGame {
+    pub path: PathBuf,
+    pub slot: Option<String>,
+    pub modified: SystemTime,
+}
+
+impl RecentGame {
+    pub fn load(&self) -> io::Result<PgnGame> {
+        let text = fs::read_to_string(&self.path)?;
+        let pgn = PgnGame::parse_all(&text)
+            .map_err(|err| io::Error::new(io::ErrorKind::InvalidData, err))?
+            .into_iter()
+            .next()
+            .ok_or_else(|| io::Error::new(io::ErrorKind::InvalidData, "no game in file"))?;
+        GameRecord::from_pgn(&pgn)
+            .verify(position_hash(&pgn))
+            .map_err(|err| io::Error::new(io::ErrorKind::InvalidData, err))?;
+        Ok(pgn)
+    }
+}
+
+fn position_hash(pgn: &PgnGame) -> Option<u64> {
+    pgn.tag(POSITION_HASH_TAG)
+        .and_then(|hash| u64::from_str_radix(hash, 16).ok())
+}
+
+fn slot_file_name(name: &str) -> String {
+    let name = name
+        .trim()
+        .chars()
+        .map(|c| {
+            if c.is_alphanumeric() || matches!(c, ' ' | '-' | '_') {
+                c
+            } else {
+                '_'
+            }
+        })
+        .collect::<String>();
+    format!("{}.pgn", name)
+}
+
+fn games_in(dir: &Path, slots: bool) -> Vec<RecentGame> {
+    let Ok(entries) = fs::read_dir(dir) else {
+        return Vec::new();
+    };
+    entries
+        .flatten()
+        .map(|entry| entry.path())
+        .filter(|path| path.extension().is_some_and(|ext| ext == "pgn"))
+        .filter_map(|path| {
+            let modified = fs::metadata(&path).and_then(|meta| meta.modified()).ok()?;
+            let slot = slots
+                .then(|| path.file_stem()?.to_str().map(str::to_string))
+                .flatten();
+            Some(RecentGame {
+                path,
+                slot,
+                modified,
+            })
+        })
+        .collect()
+}
+
+pub fn save_slot(name: &str, pgn: &PgnGame) -> io::Result<()> {
+    let Some(dir) = config::config_file(SLOTS_DIR) else {
+        return Ok(());
+    };
+    fs::create_dir_all(&dir)?;
+    fs::write(dir.join(slot_file_name(name)), pgn.to_string())
+}
+
+pub fn record_finished(pgn: &PgnGame) -> io::Result<()> {
+    let Some(dir) = config::config_file(FINISHED_DIR) else {
+        return Ok(());
+    };
+    fs::create_dir_all(&dir)?;
+    let timestamp = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map_or(0, |time| time.as_millis());
+    fs::write(dir.join(format!("game-{}.pgn", timestamp)), pgn.to_string())?;
+    let mut finished = games_in(&dir, false);
+    finished.sort_by_key(|game| Reverse(game.modified));
+    for old in finished.iter().skip(MAX_RECENT_GAMES) {
+        fs::remove_file(&old.path)?;
+    }
+    Ok(())
+}
+
+pub fn recent_games() -> Vec<RecentGame> {
+    let mut games = Vec::new();
+    if let Some(dir) = config::config_file(SLOTS_DIR) {
+        games.extend(games_in(&dir, true));
+    }
+    if let Some(dir) = config::config_file(FINISHED_DIR) {
+        games.extend(games_in(&dir, false));
+    }
+    games.sort_by_key(|game| Reverse(game.modified));
+    games
+}
@@ -0,0 +1,352 @@
+use std::{
+    fmt::Display,
+    fs, io,
+    path::{Path, PathBuf},
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        Arc, RwLock,
+    },
+};
+
+use rand::Rng;
+use rayon::iter::ParallelIterator;
+use rhai::{Array, Dynamic, Engine, EvalAltResult, ImmutableString, Scope, AST};
+
+use crate::{
+    book::OpeningBook,
+    config,
+    game::{Player, PlayerAction},
+    logic::{ChessBoard, Move, PieceColor},
+};
+
+pub const SCRIPT_EXTENSION: &str = "rhai";
+const BOTS_DIR: &str = "bots";
+const CHOOSE_MOVE: &str = "choose_move";
+const ACCEPT_DRAW: &str = "accept_draw";
+const MAX_OPERATIONS: u64 = 50_000_000;
+
+pub const BUILTIN_BOTS: [(&str, &str); 3] = [
+    ("random", include_str!("bots/random.rhai")),
+    ("greedy", include_str!("bots/greedy.rhai")),
+    ("opening", include_str!("bots/opening.rhai")),
+];
+
+#[derive(Debug)]
+pub enum ScriptError {
+    Io(io::Error),
+    Parse(String),
+    MissingFunction(&'static str),
+    UnknownBot(String),
+}
+
+impl Display for ScriptError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            ScriptError::Io(err) => write!(f, "{}", err),
+            ScriptError::Parse(err) => write!(f, "syntax error: {}", err),
+            ScriptError::MissingFunction(name) => {
+                write!(f, "script does not define fn {}(board)", name)
+            }
+            ScriptError::UnknownBot(name) => write!(f, "no built-in bot named {}", name),
+        }
+    }
+}
+
+impl std::error::Error for ScriptError {}
+
+impl From<io::Error> for ScriptError {
+    fn from(err: io::Error) -> Self {
+        ScriptError::Io(err)
+    }
+}
+
+pub fn is_script(path: &Path) -> bool {
+    path.extension().is_some_and(|ext| ext == SCRIPT_EXTENSION)
+}
+
+pub fn user_bots_dir() -> Option<PathBuf> {
+    config::config_file(BOTS_DIR)
+}
+
+#[derive(Clone)]
+struct ScriptBoard {
+    board: ChessBoard,
+    book: Arc<OpeningBook>,
+}
+
+#[derive(Clone)]
+struct ScriptMove {
+    mv: Move,
+    uci: String,
+    san: String,
+    from: String,
+    to: String,
+    piece: String,
+    captured: String,
+}
+
+impl ScriptMove {
+    fn new(mv: Move, board: &ChessBoard) -> Self {
+        let piece = |pos| {
+            board
+                .piece_at(pos)
+                .map_or(String::new(), |piece| piece.to_char().to_string())
+        };
+        Self {
+            mv,
+            uci: mv.to_uci(board),
+            san: mv.to_san(board),
+            from: board.square_name(mv.original),
+            to: board.square_name(mv.target),
+            piece: piece(mv.original),
+            captured: if mv.is_capture(board) {
+                piece(mv.target)
+            } else {
+                String::new()
+            },
+        }
+    }
+}
+
+impl ScriptBoard {
+    fn legal_moves(&mut self) -> Array {
+        let mut moves = self.board.legal_moves(self.board.turn).collect::<Vec<_>>();
+        moves.sort_by_key(|mv| (mv.original, mv.target));
+        moves
+            .into_iter()
+            .map(|mv| Dynamic::from(ScriptMove::new(mv, &self.board)))
+            .collect()
+    }
+
+    fn play(&mut self, mv: Move) -> Self {
+        let mut next = self.clone();
+        mv.perform(&mut next.board);
+        next
+    }
+
+    fn parse_move(&self, text: &str) -> Option<Move> {
+        Move::from_str(text, &self.board)
+            .or_else(|_| Move::from_san(text, &self.board))
+            .ok()
+            .filter(|mv| {
+                self.board
+                    .legal_moves(self.board.turn)
+                    .any(|legal| legal == *mv)
+            })
+    }
+}
+
+fn register_api(engine: &mut Engine) {
+    engine
+        .register_type_with_name::<ScriptBoard>("Board")
+        .register_get("fen", |board: &mut ScriptBoard| board.board.to_fen())
+        .register_get("turn", |board: &mut ScriptBoard| match board.board.turn {
+            PieceColor::White => "white".to_string(),
+            PieceColor::Black => "black".to_string(),
+        })
+        .register_get("ply", |board: &mut ScriptBoard| {
+            board.board.moves_made as i64
+        })
+        .register_fn("legal_moves", ScriptBoard::legal_moves)
+        .register_fn("play", |board: &mut ScriptBoard, mv: ScriptMove| {
+            board.play(mv.mv)
+        })
+        .register_fn(
+            "play",
+            |board: &mut ScriptBoard, text: &str| -> Result<ScriptBoard, Box<EvalAltResult>> {
+                match board.parse_move(text) {
+                    Some(mv) => Ok(board.play(mv)),
+                    None => Err(format!("illegal move: {}", text).into()),
+                }
+            },
+        )
+        .register_fn("piece_at", |board: &mut ScriptBoard, square: &str| {
+            board
+                .board
+                .parse_square(square)
+                .and_then(|pos| board.board.piece_at(pos))
+                .map_or(String::new(), |piece| piece.to_char().to_string())
+        })
+        .register_fn("is_check", |board: &mut ScriptBoard| {
+            board.board.is_in_check(board.board.turn)
+        })
+        .register_fn("is_game_over", |board: &mut ScriptBoard| {
+            board.board.win_state().is_some()
+        })
+        .register_fn("book_move", |board: &mut ScriptBoard| {
+            board
+                .book
+                .pick(&board.board, &mut rand::rng())
+                .map_or(Dynamic::UNIT, |mv| {
+                    Dynamic::from(ScriptMove::new(mv, &board.board))
+                })
+        })
+        .register_fn("to_string", |board: &mut ScriptBoard| board.board.to_fen());
+
+    engine
+        .register_type_with_name::<ScriptMove>("Move")
+        .register_get("uci", |mv: &mut ScriptMove| mv.uci.clone())
+        .register_get("san", |mv: &mut ScriptMove| mv.san.clone())
+        .register_get("from", |mv: &mut ScriptMove| mv.from.clone())
+        .register_get("to", |mv: &mut ScriptMove| mv.to.clone())
+        .register_get("piece", |mv: &mut ScriptMove| mv.piece.clone())
+        .register_get("captured", |mv: &mut ScriptMove| mv.captured.clone())
+        .register_get("is_capture", |mv: &mut ScriptMove| !mv.captured.is_empty())
+        .register_get("is_promotion", |mv: &mut ScriptMove| mv.mv.is_promotion())
+        .register_fn("to_string", |mv: &mut ScriptMove| mv.uci.clone());
+
+    engine.register_fn("random", |n: i64| {
+        if n > 0 {
+            rand::rng().random_range(0..n)
+        } else {
+            0
+        }
+    });
+}
+
+pub struct ScriptPlayer {
+    pub name: String,
+    engine: Engine,
+    ast: AST,
+    book: Arc<OpeningBook>,
+    // The last failure, kept for the caller since the game only sees a resignation.
+    pub error: Arc<RwLock<Option<String>>>,
+    cancel: Arc<AtomicBool>,
+}
+
+impl ScriptPlayer {
+    pub fn new(name: &str, source: &str) -> Result<Self, ScriptError> {
+        let mut engine = Engine::new();
+        engine.set_max_operations(MAX_OPERATIONS);
+        register_api(&mut engine);
+        let ast = engine
+            .compile(source)
+            .map_err(|err| ScriptError::Parse(err.to_string()))?;
+        if !ast
+            .iter_functions()
+            .any(|f| f.name == CHOOSE_MOVE && f.params.len() == 1)
+        {
+            return Err(ScriptError::MissingFunction(CHOOSE_MOVE));
+        }
+        Ok(Self {
+            name: name.to_string(),
+            engine,
+            ast,
+            book: Arc::new(OpeningBook::load_learning()),
+            error: Arc::new(RwLock::new(None)),
+            cancel: Arc::new(AtomicBool::new(false)),
+        })
+    }
+
+    pub fn load(path: &Path) -> Result<Self, ScriptError> {
+        let name = path
+            .file_stem()
+            .unwrap_or(path.as_os_str())
+            .to_string_lossy()
+            .to_string();
+        Self::new(&name, &fs::read_to_string(path)?)
+    }
+
+    pub fn builtin(name: &str) -> Result<Self, ScriptError> {
+        let (name, source) = BUILTIN_BOTS
+            .iter()
+            .find(|(builtin, _)| *builtin == name)
+            .ok_or_else(|| ScriptError::UnknownBot(name.to_string()))?;
+        Self::new(name, source)
+    }
+
+    // A file path, a bot in the user bots directory, or a built-in bot name.
+    pub fn find(spec: &str) -> Result<Self, ScriptError> {
+        let path = Path::new(spec);
+        if path.is_file() {
+            return Self::load(path);
+        }
+        let user_bot = user_bots_dir()
+            .map(|dir| dir.join(spec).with_extension(SCRIPT_EXTENSION))
+            .filter(|path| path.is_file());
+        match user_bot {
+            Some(path) => Self::load(&path),
+            None => Self::builtin(spec),
+        }
+    }
+
+    fn call(&mut self, function: &str, board: &ChessBoard) -> Result<Dynamic, String> {
+        let cancel = self.cancel.clone();
+        self.engine
+            .on_progress(move |_| cancel.load(Ordering::Relaxed).then_some(Dynamic::UNIT));
+        let board = ScriptBoard {
+            board: board.clone(),
+            book: self.book.clone(),
+        };
+        self.engine
+            .call_fn::<Dynamic>(&mut Scope::new(), &self.ast, function, (board,))
+            .map_err(|err| err.to_string())
+    }
+
+    fn report(&self, err: String) {
+        *self.error.write().unwrap() = Some(format!("{} failed: {}", self.name, err));
+    }
+
+    fn defines(&self, function: &str) -> bool {
+        self.ast
+            .iter_functions()
+            .any(|f| f.name == function && f.params.len() == 1)
+    }
+
+    fn choose_move(&mut self, board: &ChessBoard) -> Result<Option<Move>, String> {
+        let result = self.call(CHOOSE_MOVE, board)?;
+        if result.is_unit() {
+            return Ok(None);
+        }
+        let script_board = ScriptBoard {
+            board: board.clone(),
+            book: self.book.clone(),
+        };
+        let mv = if let Some(mv) = result.clone().try_cast::<ScriptMove>() {
+            Some(mv.mv).filter(|mv| board.legal_moves(board.turn).any(|legal| legal == *mv))
+        } else if let Some(text) = result.clone().try_cast::<ImmutableString>() {
+            script_board.parse_move(&text)
+        } else {
+            return Err(format!("{} returned a {}", CHOOSE_MOVE, result.type_name()));
+        };
+        mv.map(Some)
+            .ok_or_else(|| format!("{} returned an illegal move: {}", CHOOSE_MOVE, result))
+    }
+}
+
+impl Player for ScriptPlayer {
+    fn get_move(&mut self, board: Arc<RwLock<ChessBoard>>) -> Move {
+        match self.get_action(board, false) {
+            PlayerAction::Move(mv) => mv,
+            _ => panic!("The script did not return a move"),
+        }
+    }
+
+    fn get_action(&mut self, board: Arc<RwLock<ChessBoard>>, draw_offered: bool) -> PlayerAction {
+        let board = board.read().unwrap().clone();
+        if draw_offered && self.defines(ACCEPT_DRAW) {
+            match self
+                .call(ACCEPT_DRAW, &board)
+                .map(|accept| accept.as_bool())
+            {
+                Ok(Ok(true)) => return PlayerAction::AcceptDraw,
+                Ok(_) => {}
+                Err(err) => self.report(err),
+            }
+        }
+        let result = self.choose_move(&board);
+        match result {
+            _ if self.cancel.load(Ordering::Relaxed) => PlayerAction::Abort,
+            Ok(Some(mv)) => PlayerAction::Move(mv),
+            Ok(None) => PlayerAction::Resign,
+            Err(err) => {
+                self.report(err);
+                PlayerAction::Resign
+            }
+        }
+    }
+
+    fn set_cancel(&mut self, cancel: Arc<AtomicBool>) {
+        self.cancel = cancel;
+    }
+}
@@ -0,0 +1,178 @@
+use std::{
+    fs, io,
+    path::{Path, PathBuf},
+    time::Duration,
+};
+
+use rayon::ThreadPoolBuildError;
+
+use crate::{
+    ai::{EvalSettings, AI, MAX_SKILL},
+    config::{self, AppConfig, ConfigError},
+    tt::DEFAULT_HASH_MB,
+};
+
+pub const SETTINGS_FILE: &str = "engine.cfg";
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct EngineSettings {
+    pub depth: usize,
+    pub move_time: Option<Duration>,
+    pub threads: usize,
+    pub hash_mb: usize,
+    pub use_book: bool,
+    pub contempt: f64,
+    pub skill: Option<u8>,
+    pub temperature: f64,
+    pub seed: Option<u64>,
+    pub opponent_engine: Option<PathBuf>,
+    pub analysis_engine: Option<PathBuf>,
+}
+
+impl Default for EngineSettings {
+    fn default() -> Self {
+        Self {
+            depth: 4,
+            move_time: None,
+            threads: 0,
+            hash_mb: DEFAULT_HASH_MB,
+            use_book: true,
+            contempt: EvalSettings::default().contempt,
+            skill: None,
+            temperature: 0.0,
+            seed: None,
+            opponent_engine: None,
+            analysis_engine: None,
+        }
+    }
+}
+
+impl EngineSettings {
+    pub fn apply(&self, ai: &mut AI) -> Result<(), ThreadPoolBuildError> {
+        ai.depth = self.depth.max(1);
+        ai.move_time = self.move_time;
+        ai.tt.set_size_mb(self.hash_mb);
+        ai.eval.contempt = self.contempt;
+        ai.skill = self.skill;
+        ai.temperature = self.temperature.max(0.0);
+        ai.set_seed(self.seed);
+        if !self.use_book {
+            ai.book = None;
+        }
+        ai.set_threads(self.threads)
+    }
+
+    pub fn opponent_label(&self) -> String {
+        if let Some(path) = &self.opponent_engine {
+            return path
+                .file_stem()
+                .unwrap_or(path.as_os_str())
+                .to_string_lossy()
+                .to_string();
+        }
+        match self.skill {
+            Some(skill) => format!("AI skill {}", skill),
+            None => format!("AI depth {}", self.depth.max(1)),
+        }
+    }
+
+    pub fn set(&mut self, key: &str, value: &str) {
+        let value = value.trim();
+        match key.trim() {
+            "depth" => self.depth = value.parse().unwrap_or(self.depth),
+            "move_time_ms" => {
+                self.move_time = value
+                    .parse()
+                    .ok()
+                    .filter(|&ms| ms > 0)
+                    .map(Duration::from_millis)
+            }
+            "threads" => self.threads = value.parse().unwrap_or(self.threads),
+            "hash_mb" => self.hash_mb = value.parse().unwrap_or(self.hash_mb),
+            "book" => self.use_book = value.parse().unwrap_or(self.use_book),
+            "contempt" => self.contempt = value.parse().unwrap_or(self.contempt),
+            "temperature" => self.temperature = value.parse().unwrap_or(self.temperature),
+            "seed" => self.seed = value.parse().ok(),
+            "opponent_engine" => {
+                self.opponent_engine = (!value.is_empty()).then(|| PathBuf::from(value))
+            }
+            "analysis_engine" => {
+                self.analysis_engine = (!value.is_empty()).then(|| PathBuf::from(value))
+            }
+            "skill" => self.skill = value.parse().ok().filter(|&skill| skill < MAX_SKILL),
+            _ => {}
+        }
+    }
+
+    pub fn parse(text: &str) -> Self {
+        let mut settings = Self::default();
+        for line in text.lines() {
+            if let Some((key, value)) = line.split_once('=') {
+                settings.set(key, value);
+            }
+        }
+        settings
+    }
+
+    pub fn entries(&self) -> Vec<(&'static str, String)> {
+        let path = |path: &Option<PathBuf>| {
+            path.as_ref()
+                .map_or(String::new(), |path| path.display().to_string())
+        };
+        vec![
+            ("depth", self.depth.to_string()),
+            (
+                "move_time_ms",
+                self.move_time
+                    .map_or(0, |time| time.as_millis())
+                    .to_string(),
+            ),
+            ("threads", self.threads.to_string()),
+            ("hash_mb", self.hash_mb.to_string()),
+            ("book", self.use_book.to_string()),
+            ("contempt", self.contempt.to_string()),
+            ("skill", self.skill.unwrap_or(MAX_SKILL).to_string()),
+            ("temperature", self.temperature.to_string()),
+            (
+                "seed",
+                self.seed
+                    .map_or("random".to_string(), |seed| seed.to_string()),
+            ),
+            ("opponent_engine", path(&self.opponent_engine)),
+            ("analysis_engine", path(&self.analysis_engine)),
+        ]
+    }
+
+    pub fn to_config(&self) -> String {
+        self.entries()
+            .into_iter()
+            .map(|(key, value)| format!("{} = {}\n", key, value))
+            .collect()
+    }
+
+    pub fn load(path: &Path) -> io::Result<Self> {
+        Ok(Self::parse(&fs::read_to_string(path)?))
+    }
+
+    pub fn save(&self, path: &Path) -> io::Result<()> {
+        if let Some(parent) = path.parent() {
+            fs::create_dir_all(parent)?;
+        }
+        fs::write(path, self.to_config())
+    }
+
+    pub fn load_legacy() -> Option<Self> {
+        config::config_file(SETTINGS_FILE).and_then(|path| Self::load(&path).ok())
+    }
+
+    pub fn load_saved() -> Result<Self, ConfigError> {
+        AppConfig::load_saved().map(|config| config.engine)
+    }
+
+    // A config file that does not parse is replaced by one holding only these settings.
+    pub fn save_saved(&self) -> io::Result<()> {
+        let mut config = AppConfig::load_saved().unwrap_or_default();
+        config.engine = self.clone();
+        config.save_saved()
+    }
+}
@@ -0,0 +1,119 @@
+use std::{collections::BTreeMap, fs, io, path::Path};
+
+use crate::{
+    config,
+    logic::{PieceColor, WinState},
+};
+
+pub const STATS_FILE: &str = "stats.txt";
+
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct Record {
+    pub wins: usize,
+    pub losses: usize,
+    pub draws: usize,
+}
+
+impl Record {
+    pub fn games(&self) -> usize {
+        self.wins + self.losses + self.draws
+    }
+
+    pub fn score_percent(&self) -> f64 {
+        if self.games() == 0 {
+            return 0.0;
+        }
+        (self.wins as f64 + self.draws as f64 / 2.0) * 100.0 / self.games() as f64
+    }
+}
+
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct SessionStats {
+    pub records: BTreeMap<String, Record>,
+}
+
+impl SessionStats {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn record(&mut self, opponent: &str, win_state: WinState, color: PieceColor) {
+        let record = self.records.entry(opponent.to_string()).or_default();
+        match win_state.winner() {
+            Some(winner) if winner == color => record.wins += 1,
+            Some(_) => record.losses += 1,
+            None => record.draws += 1,
+        }
+    }
+
+    pub fn total(&self) -> Record {
+        self.records
+            .values()
+            .fold(Record::default(), |total, record| Record {
+                wins: total.wins + record.wins,
+                losses: total.losses + record.losses,
+                draws: total.draws + record.draws,
+            })
+    }
+
+    pub fn parse(text: &str) -> Self {
+        let mut stats = Self::new();
+        for line in text.lines() {
+            let mut fields = line.split('\t');
+            let (Some(opponent), Some(wins), Some(losses), Some(draws)) =
+                (fields.next(), fields.next(), fields.next(), fields.next())
+            else {
+                continue;
+            };
+            let (Ok(wins), Ok(losses), Ok(draws)) = (wins.parse(), losses.parse(), draws.parse())
+            else {
+                continue;
+            };
+            stats.records.insert(
+                opponent.to_string(),
+                Record {
+                    wins,
+                    losses,
+                    draws,
+                },
+            );
+        }
+        stats
+    }
+
+    pub fn to_text(&self) -> String {
+        self.records
+            .iter()
+            .map(|(opponent, record)| {
+                format!(
+                    "{}\t{}\t{}\t{}\n",
+                    opponent, record.wins, record.losses, record.draws
+                )
+            })
+            .collect()
+    }
+
+    pub fn load(path: &Path) -> io::Result<Self> {
+        Ok(Self::parse(&fs::read_to_string(path)?))
+    }
+
+    pub fn save(&self, path: &Path) -> io::Result<()> {
+        if let Some(parent) = path.parent() {
+            fs::create_dir_all(parent)?;
+        }
+        fs::write(path, self.to_text())
+    }
+
+    pub fn load_saved() -> Self {
+        config::config_file(STATS_FILE)
+            .and_then(|path| Self::load(&path).ok())
+            .unwrap_or_default()
+    }
+
+    pub fn save_saved(&self) -> io::Result<()> {
+        match config::config_file(STATS_FILE) {
+            Some(path) => self.save(&path),
+            None => Ok(()),
+        }
+    }
+}
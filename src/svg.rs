@@ -0,0 +1,932 @@
+use std::{collections::HashMap, f32::consts::PI, fmt::Display, str::FromStr};
+
+use image::RgbaImage;
+use tiny_skia::{
+    Color, FillRule, LineCap, LineJoin, Paint, Path, PathBuilder, Pixmap, Rect, Stroke, Transform,
+};
+
+const MAX_RENDER_SIZE: u32 = 4096;
+const SKIPPED_ELEMENTS: &[&str] = &[
+    "defs",
+    "clipPath",
+    "mask",
+    "symbol",
+    "marker",
+    "pattern",
+    "linearGradient",
+    "radialGradient",
+    "filter",
+    "style",
+    "script",
+    "title",
+    "desc",
+    "metadata",
+];
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum SvgError {
+    InvalidXml,
+    MissingRoot,
+    MissingSize,
+    InvalidPath(String),
+}
+
+impl Display for SvgError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            SvgError::InvalidXml => write!(f, "malformed XML"),
+            SvgError::MissingRoot => write!(f, "no <svg> element"),
+            SvgError::MissingSize => write!(f, "the <svg> element has no viewBox or size"),
+            SvgError::InvalidPath(data) => write!(f, "invalid path data: {}", data),
+        }
+    }
+}
+
+impl std::error::Error for SvgError {}
+
+#[derive(Clone, Debug)]
+struct Style {
+    fill: Option<Color>,
+    stroke: Option<Color>,
+    stroke_width: f32,
+    opacity: f32,
+    fill_opacity: f32,
+    stroke_opacity: f32,
+    fill_rule: FillRule,
+    line_cap: LineCap,
+    line_join: LineJoin,
+    miter_limit: f32,
+    hidden: bool,
+}
+
+impl Default for Style {
+    fn default() -> Self {
+        Self {
+            fill: Some(Color::BLACK),
+            stroke: None,
+            stroke_width: 1.0,
+            opacity: 1.0,
+            fill_opacity: 1.0,
+            stroke_opacity: 1.0,
+            fill_rule: FillRule::Winding,
+            line_cap: LineCap::Butt,
+            line_join: LineJoin::Miter,
+            miter_limit: 4.0,
+            hidden: false,
+        }
+    }
+}
+
+impl Style {
+    fn inherit(&self) -> Self {
+        Self {
+            opacity: 1.0,
+            fill_opacity: self.fill_opacity * self.opacity,
+            stroke_opacity: self.stroke_opacity * self.opacity,
+            ..self.clone()
+        }
+    }
+
+    fn apply(&mut self, name: &str, value: &str) {
+        let value = value.trim();
+        let number = || parse_length(value);
+        match name {
+            "fill" => self.fill = parse_paint(value).unwrap_or(self.fill),
+            "stroke" => self.stroke = parse_paint(value).unwrap_or(self.stroke),
+            "stroke-width" => self.stroke_width = number().unwrap_or(self.stroke_width),
+            "opacity" => self.opacity = number().unwrap_or(1.0).clamp(0.0, 1.0),
+            "fill-opacity" => {
+                self.fill_opacity = number().unwrap_or(1.0).clamp(0.0, 1.0);
+            }
+            "stroke-opacity" => {
+                self.stroke_opacity = number().unwrap_or(1.0).clamp(0.0, 1.0);
+            }
+            "fill-rule" => {
+                self.fill_rule = match value {
+                    "evenodd" => FillRule::EvenOdd,
+                    _ => FillRule::Winding,
+                }
+            }
+            "stroke-linecap" => {
+                self.line_cap = match value {
+                    "round" => LineCap::Round,
+                    "square" => LineCap::Square,
+                    _ => LineCap::Butt,
+                }
+            }
+            "stroke-linejoin" => {
+                self.line_join = match value {
+                    "round" => LineJoin::Round,
+                    "bevel" => LineJoin::Bevel,
+                    _ => LineJoin::Miter,
+                }
+            }
+            "stroke-miterlimit" => self.miter_limit = number().unwrap_or(self.miter_limit),
+            "display" => self.hidden |= value == "none",
+            "visibility" => self.hidden = value == "hidden" || value == "collapse",
+            _ => {}
+        }
+    }
+
+    fn apply_attributes(&mut self, attributes: &HashMap<String, String>) {
+        for (name, value) in attributes {
+            self.apply(name, value);
+        }
+        if let Some(style) = attributes.get("style") {
+            for declaration in style.split(';') {
+                if let Some((name, value)) = declaration.split_once(':') {
+                    self.apply(name.trim(), value);
+                }
+            }
+        }
+    }
+}
+
+#[derive(Clone, Debug)]
+struct Shape {
+    path: Path,
+    style: Style,
+    transform: Transform,
+}
+
+#[derive(Clone, Debug)]
+pub struct Svg {
+    view_box: Rect,
+    shapes: Vec<Shape>,
+}
+
+impl Svg {
+    pub fn width(&self) -> f32 {
+        self.view_box.width()
+    }
+
+    pub fn height(&self) -> f32 {
+        self.view_box.height()
+    }
+
+    pub fn render(&self, width: u32, height: u32) -> Option<RgbaImage> {
+        let mut pixmap = Pixmap::new(
+            width.clamp(1, MAX_RENDER_SIZE),
+            height.clamp(1, MAX_RENDER_SIZE),
+        )?;
+        let scale =
+            (pixmap.width() as f32 / self.width()).min(pixmap.height() as f32 / self.height());
+        let fit = Transform::from_translate(
+            (pixmap.width() as f32 - self.width() * scale) / 2.0,
+            (pixmap.height() as f32 - self.height() * scale) / 2.0,
+        )
+        .pre_scale(scale, scale)
+        .pre_translate(-self.view_box.x(), -self.view_box.y());
+        for shape in &self.shapes {
+            let transform = fit.pre_concat(shape.transform);
+            let style = &shape.style;
+            if let Some(color) = style.fill {
+                let paint = paint(color, style.fill_opacity * style.opacity);
+                pixmap.fill_path(&shape.path, &paint, style.fill_rule, transform, None);
+            }
+            if let Some(color) = style.stroke.filter(|_| style.stroke_width > 0.0) {
+                let paint = paint(color, style.stroke_opacity * style.opacity);
+                let stroke = Stroke {
+                    width: style.stroke_width,
+                    miter_limit: style.miter_limit,
+                    line_cap: style.line_cap,
+                    line_join: style.line_join,
+                    dash: None,
+                };
+                pixmap.stroke_path(&shape.path, &paint, &stroke, transform, None);
+            }
+        }
+        let pixels = pixmap
+            .pixels()
+            .iter()
+            .flat_map(|pixel| {
+                let color = pixel.demultiply();
+                [color.red(), color.green(), color.blue(), color.alpha()]
+            })
+            .collect();
+        RgbaImage::from_raw(pixmap.width(), pixmap.height(), pixels)
+    }
+}
+
+fn paint(color: Color, opacity: f32) -> Paint<'static> {
+    let mut color = color;
+    color.apply_opacity(opacity);
+    let mut paint = Paint::default();
+    paint.set_color(color);
+    paint.anti_alias = true;
+    paint
+}
+
+struct Context {
+    style: Style,
+    transform: Transform,
+    skipped: bool,
+}
+
+impl FromStr for Svg {
+    type Err = SvgError;
+
+    fn from_str(text: &str) -> Result<Self, Self::Err> {
+        let mut view_box = None;
+        let mut shapes = Vec::new();
+        let mut stack: Vec<Context> = Vec::new();
+        let root = Context {
+            style: Style::default(),
+            transform: Transform::identity(),
+            skipped: false,
+        };
+        for tag in Tags::new(text) {
+            let (name, attributes, self_closing) = match tag? {
+                Tag::Close => {
+                    stack.pop().ok_or(SvgError::InvalidXml)?;
+                    continue;
+                }
+                Tag::Open {
+                    name,
+                    attributes,
+                    self_closing,
+                } => (name, attributes, self_closing),
+            };
+            let parent = match stack.last() {
+                Some(parent) => parent,
+                None if name == "svg" => &root,
+                None => return Err(SvgError::MissingRoot),
+            };
+            let mut style = parent.style.inherit();
+            style.apply_attributes(&attributes);
+            let mut transform = parent.transform;
+            if let Some(value) = attributes.get("transform") {
+                transform = transform.pre_concat(parse_transform(value));
+            }
+            let skipped =
+                parent.skipped || style.hidden || SKIPPED_ELEMENTS.contains(&name.as_str());
+            if name == "svg" && stack.is_empty() {
+                view_box = Some(root_view_box(&attributes)?);
+            } else if !skipped {
+                if let Some(path) = shape_path(&name, &attributes)? {
+                    shapes.push(Shape {
+                        path,
+                        style: style.clone(),
+                        transform,
+                    });
+                }
+            }
+            if !self_closing {
+                stack.push(Context {
+                    style,
+                    transform,
+                    skipped,
+                });
+            }
+        }
+        Ok(Self {
+            view_box: view_box.ok_or(SvgError::MissingRoot)?,
+            shapes,
+        })
+    }
+}
+
+fn root_view_box(attributes: &HashMap<String, String>) -> Result<Rect, SvgError> {
+    let view_box = attributes
+        .get("viewBox")
+        .map(|value| parse_numbers(value))
+        .filter(|numbers| numbers.len() == 4)
+        .and_then(|v| Rect::from_xywh(v[0], v[1], v[2], v[3]));
+    let size = || {
+        let length = |name| attributes.get(name).and_then(|value| parse_length(value));
+        Rect::from_xywh(0.0, 0.0, length("width")?, length("height")?)
+    };
+    view_box.or_else(size).ok_or(SvgError::MissingSize)
+}
+
+fn shape_path(name: &str, attributes: &HashMap<String, String>) -> Result<Option<Path>, SvgError> {
+    let number = |name| {
+        attributes
+            .get(name)
+            .and_then(|value| parse_length(value))
+            .unwrap_or(0.0)
+    };
+    let path = match name {
+        "path" => {
+            let data = attributes.get("d").map_or("", String::as_str);
+            return parse_path(data).map_err(|_| SvgError::InvalidPath(data.to_string()));
+        }
+        "rect" => {
+            let (x, y, width, height) =
+                (number("x"), number("y"), number("width"), number("height"));
+            let (rx, ry) = match (attributes.contains_key("rx"), attributes.contains_key("ry")) {
+                (true, false) => (number("rx"), number("rx")),
+                (false, true) => (number("ry"), number("ry")),
+                _ => (number("rx"), number("ry")),
+            };
+            rounded_rect(
+                x,
+                y,
+                width,
+                height,
+                rx.min(width / 2.0),
+                ry.min(height / 2.0),
+            )
+        }
+        "circle" => PathBuilder::from_circle(number("cx"), number("cy"), number("r")),
+        "ellipse" => {
+            let (rx, ry) = (number("rx"), number("ry"));
+            Rect::from_xywh(number("cx") - rx, number("cy") - ry, rx * 2.0, ry * 2.0)
+                .and_then(PathBuilder::from_oval)
+        }
+        "line" => {
+            let mut builder = PathBuilder::new();
+            builder.move_to(number("x1"), number("y1"));
+            builder.line_to(number("x2"), number("y2"));
+            builder.finish()
+        }
+        "polyline" | "polygon" => {
+            let points = parse_numbers(attributes.get("points").map_or("", String::as_str));
+            let mut builder = PathBuilder::new();
+            for (i, point) in points.chunks_exact(2).enumerate() {
+                if i == 0 {
+                    builder.move_to(point[0], point[1]);
+                } else {
+                    builder.line_to(point[0], point[1]);
+                }
+            }
+            if name == "polygon" {
+                builder.close();
+            }
+            builder.finish()
+        }
+        _ => None,
+    };
+    Ok(path)
+}
+
+fn rounded_rect(x: f32, y: f32, width: f32, height: f32, rx: f32, ry: f32) -> Option<Path> {
+    let rect = Rect::from_xywh(x, y, width, height)?;
+    if rx <= 0.0 || ry <= 0.0 {
+        return Some(PathBuilder::from_rect(rect));
+    }
+    let mut builder = PathBuilder::new();
+    let (right, bottom) = (x + width, y + height);
+    builder.move_to(x + rx, y);
+    builder.line_to(right - rx, y);
+    arc_to(
+        &mut builder,
+        (right - rx, y),
+        (rx, ry),
+        0.0,
+        false,
+        true,
+        (right, y + ry),
+    );
+    builder.line_to(right, bottom - ry);
+    arc_to(
+        &mut builder,
+        (right, bottom - ry),
+        (rx, ry),
+        0.0,
+        false,
+        true,
+        (right - rx, bottom),
+    );
+    builder.line_to(x + rx, bottom);
+    arc_to(
+        &mut builder,
+        (x + rx, bottom),
+        (rx, ry),
+        0.0,
+        false,
+        true,
+        (x, bottom - ry),
+    );
+    builder.line_to(x, y + ry);
+    arc_to(
+        &mut builder,
+        (x, y + ry),
+        (rx, ry),
+        0.0,
+        false,
+        true,
+        (x + rx, y),
+    );
+    builder.close();
+    builder.finish()
+}
+
+fn parse_length(value: &str) -> Option<f32> {
+    let value = value.trim();
+    let number = value.trim_end_matches(|c: char| c.is_ascii_alphabetic() || c == '%');
+    number.parse().ok()
+}
+
+fn parse_numbers(value: &str) -> Vec<f32> {
+    let mut parser = PathParser::new(value);
+    std::iter::from_fn(|| parser.number()).collect()
+}
+
+fn parse_paint(value: &str) -> Option<Option<Color>> {
+    let value = value.trim();
+    if value == "inherit" {
+        return None;
+    }
+    if let Some(rest) = value.strip_prefix("url(") {
+        let fallback = rest
+            .split_once(')')
+            .map_or("", |(_, fallback)| fallback.trim());
+        return Some(parse_color(fallback).or(Some(Color::BLACK)));
+    }
+    if value == "none" || value == "transparent" {
+        return Some(None);
+    }
+    parse_color(value).map(Some)
+}
+
+fn parse_color(value: &str) -> Option<Color> {
+    if let Some(hex) = value.strip_prefix('#') {
+        let digits = hex
+            .chars()
+            .map(|c| c.to_digit(16).map(|d| d as u8))
+            .collect::<Option<Vec<_>>>()?;
+        return match digits[..] {
+            [r, g, b] => Some(Color::from_rgba8(r * 17, g * 17, b * 17, 255)),
+            [r1, r2, g1, g2, b1, b2] => Some(Color::from_rgba8(
+                r1 * 16 + r2,
+                g1 * 16 + g2,
+                b1 * 16 + b2,
+                255,
+            )),
+            _ => None,
+        };
+    }
+    if let Some(args) = value
+        .strip_prefix("rgb(")
+        .or_else(|| value.strip_prefix("rgba("))
+        .and_then(|rest| rest.strip_suffix(')'))
+    {
+        let channels = args
+            .split(',')
+            .map(|channel| {
+                let channel = channel.trim();
+                match channel.strip_suffix('%') {
+                    Some(percent) => percent.trim().parse::<f32>().ok().map(|p| p * 2.55),
+                    None => channel.parse::<f32>().ok(),
+                }
+            })
+            .collect::<Option<Vec<_>>>()?;
+        let channel = |i: usize| channels.get(i).map_or(0, |&c| c.clamp(0.0, 255.0) as u8);
+        let alpha = channels.get(3).map_or(1.0, |&a| a.clamp(0.0, 1.0));
+        return Some(Color::from_rgba8(
+            channel(0),
+            channel(1),
+            channel(2),
+            (alpha * 255.0) as u8,
+        ));
+    }
+    let (r, g, b) = match value.to_ascii_lowercase().as_str() {
+        "black" | "currentcolor" => (0, 0, 0),
+        "white" => (255, 255, 255),
+        "red" => (255, 0, 0),
+        "green" => (0, 128, 0),
+        "blue" => (0, 0, 255),
+        "yellow" => (255, 255, 0),
+        "orange" => (255, 165, 0),
+        "gray" | "grey" => (128, 128, 128),
+        "silver" => (192, 192, 192),
+        "brown" => (165, 42, 42),
+        "gold" => (255, 215, 0),
+        _ => return None,
+    };
+    Some(Color::from_rgba8(r, g, b, 255))
+}
+
+fn parse_transform(value: &str) -> Transform {
+    let mut transform = Transform::identity();
+    for function in value.split(')') {
+        let Some((name, args)) = function.split_once('(') else {
+            continue;
+        };
+        let args = parse_numbers(args);
+        let arg = |i: usize| args.get(i).copied();
+        let next = match (
+            name.trim_matches(|c: char| c.is_whitespace() || c == ','),
+            args.len(),
+        ) {
+            ("matrix", 6) => {
+                Transform::from_row(args[0], args[1], args[2], args[3], args[4], args[5])
+            }
+            ("translate", 1..) => Transform::from_translate(args[0], arg(1).unwrap_or(0.0)),
+            ("scale", 1..) => Transform::from_scale(args[0], arg(1).unwrap_or(args[0])),
+            ("rotate", 3) => Transform::from_rotate_at(args[0], args[1], args[2]),
+            ("rotate", 1..) => Transform::from_rotate(args[0]),
+            ("skewX", 1) => {
+                Transform::from_row(1.0, 0.0, args[0].to_radians().tan(), 1.0, 0.0, 0.0)
+            }
+            ("skewY", 1) => {
+                Transform::from_row(1.0, args[0].to_radians().tan(), 0.0, 1.0, 0.0, 0.0)
+            }
+            _ => continue,
+        };
+        transform = transform.pre_concat(next);
+    }
+    transform
+}
+
+struct PathParser<'a> {
+    bytes: &'a [u8],
+    pos: usize,
+}
+
+impl<'a> PathParser<'a> {
+    fn new(text: &'a str) -> Self {
+        Self {
+            bytes: text.as_bytes(),
+            pos: 0,
+        }
+    }
+
+    fn peek(&self) -> Option<u8> {
+        self.bytes.get(self.pos).copied()
+    }
+
+    fn skip_separators(&mut self) {
+        while self
+            .peek()
+            .is_some_and(|b| b.is_ascii_whitespace() || b == b',')
+        {
+            self.pos += 1;
+        }
+    }
+
+    fn skip_digits(&mut self) -> usize {
+        let start = self.pos;
+        while self.peek().is_some_and(|b| b.is_ascii_digit()) {
+            self.pos += 1;
+        }
+        self.pos - start
+    }
+
+    fn number(&mut self) -> Option<f32> {
+        self.skip_separators();
+        let start = self.pos;
+        if matches!(self.peek(), Some(b'-' | b'+')) {
+            self.pos += 1;
+        }
+        let mut digits = self.skip_digits();
+        if self.peek() == Some(b'.') {
+            self.pos += 1;
+            digits += self.skip_digits();
+        }
+        if digits == 0 {
+            self.pos = start;
+            return None;
+        }
+        if matches!(self.peek(), Some(b'e' | b'E')) {
+            let mantissa_end = self.pos;
+            self.pos += 1;
+            if matches!(self.peek(), Some(b'-' | b'+')) {
+                self.pos += 1;
+            }
+            if self.skip_digits() == 0 {
+                self.pos = mantissa_end;
+            }
+        }
+        std::str::from_utf8(&self.bytes[start..self.pos])
+            .ok()?
+            .parse()
+            .ok()
+    }
+
+    fn flag(&mut self) -> Option<bool> {
+        self.skip_separators();
+        let flag = match self.peek()? {
+            b'0' => false,
+            b'1' => true,
+            _ => return None,
+        };
+        self.pos += 1;
+        Some(flag)
+    }
+
+    fn command(&mut self) -> Option<u8> {
+        self.skip_separators();
+        let command = self.peek().filter(|b| b.is_ascii_alphabetic())?;
+        self.pos += 1;
+        Some(command)
+    }
+
+    fn at_end(&mut self) -> bool {
+        self.skip_separators();
+        self.pos >= self.bytes.len()
+    }
+}
+
+fn point(parser: &mut PathParser) -> Result<(f32, f32), ()> {
+    Ok((parser.number().ok_or(())?, parser.number().ok_or(())?))
+}
+
+fn parse_path(data: &str) -> Result<Option<Path>, ()> {
+    let mut parser = PathParser::new(data);
+    let mut builder = PathBuilder::new();
+    let mut command = None;
+    let mut current = (0.0, 0.0);
+    let mut start = (0.0, 0.0);
+    let mut last_control = None;
+    while !parser.at_end() {
+        if let Some(next) = parser.command() {
+            command = Some(next);
+        }
+        let cmd = command.ok_or(())?;
+        let relative = cmd.is_ascii_lowercase();
+        let offset = move |(x, y): (f32, f32)| {
+            if relative {
+                (current.0 + x, current.1 + y)
+            } else {
+                (x, y)
+            }
+        };
+        let mut control = None;
+        match cmd.to_ascii_uppercase() {
+            b'M' => {
+                let to = offset(point(&mut parser)?);
+                builder.move_to(to.0, to.1);
+                current = to;
+                start = to;
+                command = Some(if relative { b'l' } else { b'L' });
+            }
+            b'L' => {
+                let to = offset(point(&mut parser)?);
+                builder.line_to(to.0, to.1);
+                current = to;
+            }
+            b'H' => {
+                let x = parser.number().ok_or(())?;
+                current.0 = if relative { current.0 + x } else { x };
+                builder.line_to(current.0, current.1);
+            }
+            b'V' => {
+                let y = parser.number().ok_or(())?;
+                current.1 = if relative { current.1 + y } else { y };
+                builder.line_to(current.0, current.1);
+            }
+            b'C' => {
+                let (c1, c2, to) = (
+                    offset(point(&mut parser)?),
+                    offset(point(&mut parser)?),
+                    offset(point(&mut parser)?),
+                );
+                builder.cubic_to(c1.0, c1.1, c2.0, c2.1, to.0, to.1);
+                control = Some((b'C', c2));
+                current = to;
+            }
+            b'S' => {
+                let c1 = reflect(last_control, b'C', current);
+                let (c2, to) = (offset(point(&mut parser)?), offset(point(&mut parser)?));
+                builder.cubic_to(c1.0, c1.1, c2.0, c2.1, to.0, to.1);
+                control = Some((b'C', c2));
+                current = to;
+            }
+            b'Q' => {
+                let (c, to) = (offset(point(&mut parser)?), offset(point(&mut parser)?));
+                builder.quad_to(c.0, c.1, to.0, to.1);
+                control = Some((b'Q', c));
+                current = to;
+            }
+            b'T' => {
+                let c = reflect(last_control, b'Q', current);
+                let to = offset(point(&mut parser)?);
+                builder.quad_to(c.0, c.1, to.0, to.1);
+                control = Some((b'Q', c));
+                current = to;
+            }
+            b'A' => {
+                let (rx, ry) = point(&mut parser)?;
+                let rotation = parser.number().ok_or(())?;
+                let large_arc = parser.flag().ok_or(())?;
+                let sweep = parser.flag().ok_or(())?;
+                let to = offset(point(&mut parser)?);
+                arc_to(
+                    &mut builder,
+                    current,
+                    (rx, ry),
+                    rotation,
+                    large_arc,
+                    sweep,
+                    to,
+                );
+                current = to;
+            }
+            b'Z' => {
+                builder.close();
+                current = start;
+            }
+            _ => return Err(()),
+        }
+        last_control = control;
+    }
+    Ok(builder.finish())
+}
+
+fn reflect(last_control: Option<(u8, (f32, f32))>, kind: u8, current: (f32, f32)) -> (f32, f32) {
+    match last_control {
+        Some((last_kind, (x, y))) if last_kind == kind => {
+            (2.0 * current.0 - x, 2.0 * current.1 - y)
+        }
+        _ => current,
+    }
+}
+
+fn arc_to(
+    builder: &mut PathBuilder,
+    from: (f32, f32),
+    (rx, ry): (f32, f32),
+    rotation: f32,
+    large_arc: bool,
+    sweep: bool,
+    to: (f32, f32),
+) {
+    let (mut rx, mut ry) = (rx.abs(), ry.abs());
+    if rx == 0.0 || ry == 0.0 || from == to {
+        builder.line_to(to.0, to.1);
+        return;
+    }
+    let (sin, cos) = rotation.to_radians().sin_cos();
+    let dx = (from.0 - to.0) / 2.0;
+    let dy = (from.1 - to.1) / 2.0;
+    let x1 = cos * dx + sin * dy;
+    let y1 = -sin * dx + cos * dy;
+    let lambda = (x1 * x1) / (rx * rx) + (y1 * y1) / (ry * ry);
+    if lambda > 1.0 {
+        rx *= lambda.sqrt();
+        ry *= lambda.sqrt();
+    }
+    let numerator = rx * rx * ry * ry - rx * rx * y1 * y1 - ry * ry * x1 * x1;
+    let denominator = rx * rx * y1 * y1 + ry * ry * x1 * x1;
+    let mut coefficient = (numerator / denominator).max(0.0).sqrt();
+    if large_arc == sweep {
+        coefficient = -coefficient;
+    }
+    let cx1 = coefficient * rx * y1 / ry;
+    let cy1 = -coefficient * ry * x1 / rx;
+    let cx = cos * cx1 - sin * cy1 + (from.0 + to.0) / 2.0;
+    let cy = sin * cx1 + cos * cy1 + (from.1 + to.1) / 2.0;
+    let angle = |ux: f32, uy: f32, vx: f32, vy: f32| {
+        let sign = if ux * vy - uy * vx < 0.0 { -1.0 } else { 1.0 };
+        let dot = (ux * vx + uy * vy) / ((ux * ux + uy * uy).sqrt() * (vx * vx + vy * vy).sqrt());
+        sign * dot.clamp(-1.0, 1.0).acos()
+    };
+    let start = angle(1.0, 0.0, (x1 - cx1) / rx, (y1 - cy1) / ry);
+    let mut delta = angle(
+        (x1 - cx1) / rx,
+        (y1 - cy1) / ry,
+        (-x1 - cx1) / rx,
+        (-y1 - cy1) / ry,
+    );
+    if !sweep && delta > 0.0 {
+        delta -= 2.0 * PI;
+    } else if sweep && delta < 0.0 {
+        delta += 2.0 * PI;
+    }
+    let segments = (delta.abs() / (PI / 2.0)).ceil().max(1.0) as usize;
+    let step = delta / segments as f32;
+    let handle = 4.0 / 3.0 * (step / 4.0).tan();
+    let point = |theta: f32| {
+        let (sin_t, cos_t) = theta.sin_cos();
+        (
+            cx + rx * cos * cos_t - ry * sin * sin_t,
+            cy + rx * sin * cos_t + ry * cos * sin_t,
+        )
+    };
+    let derivative = |theta: f32| {
+        let (sin_t, cos_t) = theta.sin_cos();
+        (
+            -rx * cos * sin_t - ry * sin * cos_t,
+            -rx * sin * sin_t + ry * cos * cos_t,
+        )
+    };
+    for i in 0..segments {
+        let theta1 = start + step * i as f32;
+        let theta2 = theta1 + step;
+        let (p1, d1) = (point(theta1), derivative(theta1));
+        let (p2, d2) = (point(theta2), derivative(theta2));
+        let end = if i + 1 == segments { to } else { p2 };
+        builder.cubic_to(
+            p1.0 + handle * d1.0,
+            p1.1 + handle * d1.1,
+            p2.0 - handle * d2.0,
+            p2.1 - handle * d2.1,
+            end.0,
+            end.1,
+        );
+    }
+}
+
+enum Tag {
+    Open {
+        name: String,
+        attributes: HashMap<String, String>,
+        self_closing: bool,
+    },
+    Close,
+}
+
+struct Tags<'a> {
+    text: &'a str,
+}
+
+impl<'a> Tags<'a> {
+    fn new(text: &'a str) -> Self {
+        Self { text }
+    }
+
+    fn skip_past(&mut self, end: &str) -> Result<(), SvgError> {
+        let index = self.text.find(end).ok_or(SvgError::InvalidXml)?;
+        self.text = &self.text[index + end.len()..];
+        Ok(())
+    }
+
+    fn parse_tag(&mut self) -> Result<Tag, SvgError> {
+        let end = find_tag_end(self.text).ok_or(SvgError::InvalidXml)?;
+        let body = &self.text[1..end];
+        self.text = &self.text[end + 1..];
+        if body.starts_with('/') {
+            return Ok(Tag::Close);
+        }
+        let (body, self_closing) = match body.strip_suffix('/') {
+            Some(body) => (body, true),
+            None => (body, false),
+        };
+        let name_end = body.find(|c: char| c.is_whitespace()).unwrap_or(body.len());
+        let name = body[..name_end].to_string();
+        let name = name
+            .rsplit_once(':')
+            .map_or(name.clone(), |(_, local)| local.to_string());
+        Ok(Tag::Open {
+            name,
+            attributes: parse_attributes(&body[name_end..])?,
+            self_closing,
+        })
+    }
+}
+
+impl Iterator for Tags<'_> {
+    type Item = Result<Tag, SvgError>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        loop {
+            let start = self.text.find('<')?;
+            self.text = &self.text[start..];
+            let skipped = if self.text.starts_with("<!--") {
+                self.skip_past("-->")
+            } else if self.text.starts_with("<![CDATA[") {
+                self.skip_past("]]>")
+            } else if self.text.starts_with("<?") {
+                self.skip_past("?>")
+            } else if self.text.starts_with("<!") {
+                self.skip_past(">")
+            } else {
+                return Some(self.parse_tag());
+            };
+            if let Err(err) = skipped {
+                return Some(Err(err));
+            }
+        }
+    }
+}
+
+fn find_tag_end(text: &str) -> Option<usize> {
+    let mut quote = None;
+    for (i, c) in text.char_indices() {
+        match (quote, c) {
+            (None, '"' | '\'') => quote = Some(c),
+            (Some(open), _) if c == open => quote = None,
+            (None, '>') => return Some(i),
+            _ => {}
+        }
+    }
+    None
+}
+
+fn parse_attributes(text: &str) -> Result<HashMap<String, String>, SvgError> {
+    let mut attributes = HashMap::new();
+    let mut rest = text.trim_start();
+    while !rest.is_empty() {
+        let (name, after) = rest.split_once('=').ok_or(SvgError::InvalidXml)?;
+        let after = after.trim_start();
+        let quote = after.chars().next().filter(|c| *c == '"' || *c == '\'');
+        let quote = quote.ok_or(SvgError::InvalidXml)?;
+        let (value, after) = after[1..].split_once(quote).ok_or(SvgError::InvalidXml)?;
+        let name = name.trim();
+        let name = name.rsplit_once(':').map_or(name, |(_, local)| local);
+        attributes.insert(name.to_string(), decode_entities(value));
+        rest = after.trim_start();
+    }
+    Ok(attributes)
+}
+
+fn decode_entities(value: &str) -> String {
+    value
+        .replace("&lt;", "<")
+        .replace("&gt;", ">")
+        .replace("&quot;", "\"")
+        .replace("&apos;", "'")
+        .replace("&amp;", "&")
+}
@@ -0,0 +1,238 @@
+use std::{
+    fmt::Display,
+    fs, io,
+    path::{Path, PathBuf},
+};
+
+use image::Rgba;
+use toml_edit::{DocumentMut, Item};
+
+use crate::assets;
+
+const THEMES_DIR: &str = "themes";
+const THEME_EXTENSION: &str = "toml";
+pub const DEFAULT_THEME: &str = "Classic";
+pub const HIGH_CONTRAST_THEME: &str = "High contrast";
+
+#[derive(Debug)]
+pub enum ThemeError {
+    Io(io::Error),
+    InvalidToml(String),
+    InvalidColor(String),
+    InvalidValue(String),
+}
+
+impl Display for ThemeError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            ThemeError::Io(err) => write!(f, "{}", err),
+            ThemeError::InvalidToml(err) => write!(f, "invalid TOML: {}", err),
+            ThemeError::InvalidColor(key) => {
+                write!(f, "{} is not a #rrggbb or #rrggbbaa color", key)
+            }
+            ThemeError::InvalidValue(key) => write!(f, "invalid value for {}", key),
+        }
+    }
+}
+
+impl std::error::Error for ThemeError {}
+
+impl From<io::Error> for ThemeError {
+    fn from(err: io::Error) -> Self {
+        ThemeError::Io(err)
+    }
+}
+
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct ThemeFonts {
+    pub proportional: Option<PathBuf>,
+    pub monospace: Option<PathBuf>,
+    pub size: Option<f32>,
+}
+
+// Colors are premultiplied by alpha, theme files use plain #rrggbbaa.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Theme {
+    pub name: String,
+    pub light_square: Rgba<u8>,
+    pub dark_square: Rgba<u8>,
+    pub selected_square: Rgba<u8>,
+    pub valid_move: Rgba<u8>,
+    pub best_line: Rgba<u8>,
+    pub hanging: Rgba<u8>,
+    pub threatened: Rgba<u8>,
+    pub premove: Rgba<u8>,
+    pub background: Option<Rgba<u8>>,
+    pub piece_set: Option<String>,
+    pub fonts: ThemeFonts,
+}
+
+impl Default for Theme {
+    fn default() -> Self {
+        Self {
+            name: DEFAULT_THEME.to_string(),
+            light_square: Rgba([240, 217, 181, 255]),
+            dark_square: Rgba([181, 136, 99, 255]),
+            selected_square: Rgba([115, 154, 222, 128]),
+            valid_move: Rgba([81, 173, 94, 128]),
+            best_line: Rgba([40, 110, 200, 255]),
+            hanging: Rgba([200, 40, 40, 110]),
+            threatened: Rgba([220, 120, 20, 160]),
+            premove: Rgba([150, 60, 160, 128]),
+            background: None,
+            piece_set: None,
+            fonts: ThemeFonts::default(),
+        }
+    }
+}
+
+fn parse_color(value: &str) -> Option<Rgba<u8>> {
+    let hex = value.strip_prefix('#')?;
+    if !hex.is_ascii() || (hex.len() != 6 && hex.len() != 8) {
+        return None;
+    }
+    let channel = |i: usize| u8::from_str_radix(&hex[i * 2..i * 2 + 2], 16).ok();
+    let alpha = if hex.len() == 8 { channel(3)? } else { 255 };
+    let premultiply = |i: usize| Some((channel(i)? as u16 * alpha as u16 / 255) as u8);
+    Some(Rgba([
+        premultiply(0)?,
+        premultiply(1)?,
+        premultiply(2)?,
+        alpha,
+    ]))
+}
+
+impl Theme {
+    pub fn high_contrast() -> Self {
+        Self {
+            name: HIGH_CONTRAST_THEME.to_string(),
+            light_square: Rgba([255, 255, 255, 255]),
+            dark_square: Rgba([40, 90, 160, 255]),
+            selected_square: Rgba([230, 200, 0, 200]),
+            valid_move: Rgba([0, 180, 60, 200]),
+            best_line: Rgba([220, 30, 30, 255]),
+            hanging: Rgba([220, 0, 0, 200]),
+            threatened: Rgba([255, 120, 0, 255]),
+            premove: Rgba([170, 0, 200, 200]),
+            background: Some(Rgba([0, 0, 0, 255])),
+            ..Self::default()
+        }
+    }
+
+    pub fn builtin() -> Vec<Self> {
+        vec![Self::default(), Self::high_contrast()]
+    }
+
+    pub fn parse(text: &str, base_dir: Option<&Path>) -> Result<Self, ThemeError> {
+        let doc = text
+            .parse::<DocumentMut>()
+            .map_err(|err| ThemeError::InvalidToml(err.to_string()))?;
+        Self::from_document(&doc, base_dir)
+    }
+
+    fn from_document(doc: &DocumentMut, base_dir: Option<&Path>) -> Result<Self, ThemeError> {
+        let mut theme = Self::default();
+        let string = |item: Option<&Item>, key: &str| -> Result<Option<String>, ThemeError> {
+            match item {
+                None => Ok(None),
+                Some(item) => item
+                    .as_str()
+                    .map(|value| Some(value.to_string()))
+                    .ok_or_else(|| ThemeError::InvalidValue(key.to_string())),
+            }
+        };
+        let color = |item: Option<&Item>, key: &str| -> Result<Option<Rgba<u8>>, ThemeError> {
+            string(item, key)?
+                .map(|value| {
+                    parse_color(&value).ok_or_else(|| ThemeError::InvalidColor(key.to_string()))
+                })
+                .transpose()
+        };
+        let section = |name: &str, key: &str| doc.get(name).and_then(|table| table.get(key));
+        if let Some(name) = string(doc.get("name"), "name")? {
+            theme.name = name;
+        }
+        theme.piece_set = string(doc.get("piece_set"), "piece_set")?;
+        theme.background = color(doc.get("background"), "background")?;
+        for (key, field) in [
+            ("light", &mut theme.light_square),
+            ("dark", &mut theme.dark_square),
+        ] {
+            if let Some(value) = color(section("squares", key), &format!("squares.{}", key))? {
+                *field = value;
+            }
+        }
+        for (key, field) in [
+            ("selected", &mut theme.selected_square),
+            ("valid_move", &mut theme.valid_move),
+            ("best_line", &mut theme.best_line),
+            ("hanging", &mut theme.hanging),
+            ("threatened", &mut theme.threatened),
+            ("premove", &mut theme.premove),
+        ] {
+            if let Some(value) = color(section("highlights", key), &format!("highlights.{}", key))?
+            {
+                *field = value;
+            }
+        }
+        let font_path = |key: &str| -> Result<Option<PathBuf>, ThemeError> {
+            let path = string(section("fonts", key), &format!("fonts.{}", key))?;
+            Ok(path.map(|path| base_dir.map_or(PathBuf::from(&path), |dir| dir.join(&path))))
+        };
+        theme.fonts.proportional = font_path("proportional")?;
+        theme.fonts.monospace = font_path("monospace")?;
+        theme.fonts.size = match section("fonts", "size") {
+            None => None,
+            Some(item) => Some(
+                item.as_float()
+                    .or_else(|| item.as_integer().map(|size| size as f64))
+                    .filter(|size| *size > 0.0)
+                    .ok_or_else(|| ThemeError::InvalidValue("fonts.size".to_string()))?
+                    as f32,
+            ),
+        };
+        Ok(theme)
+    }
+
+    pub fn load(path: &Path) -> Result<Self, ThemeError> {
+        let doc = fs::read_to_string(path)?
+            .parse::<DocumentMut>()
+            .map_err(|err| ThemeError::InvalidToml(err.to_string()))?;
+        let mut theme = Self::from_document(&doc, path.parent())?;
+        if !doc.contains_key("name") {
+            if let Some(stem) = path.file_stem() {
+                theme.name = stem.to_string_lossy().to_string();
+            }
+        }
+        Ok(theme)
+    }
+}
+
+pub fn user_themes_dir() -> Option<PathBuf> {
+    assets::user_assets_dir().map(|dir| dir.join(THEMES_DIR))
+}
+
+// Returns the built-in themes overridden by the user's, and the files that failed to load.
+pub fn themes() -> (Vec<Theme>, Vec<(PathBuf, ThemeError)>) {
+    let mut themes = Theme::builtin();
+    let mut errors = Vec::new();
+    let mut paths = user_themes_dir()
+        .and_then(|dir| fs::read_dir(dir).ok())
+        .into_iter()
+        .flatten()
+        .flatten()
+        .map(|entry| entry.path())
+        .filter(|path| path.extension().is_some_and(|ext| ext == THEME_EXTENSION))
+        .collect::<Vec<_>>();
+    paths.sort();
+    for path in paths {
+        match Theme::load(&path) {
+            Ok(theme) => match themes.iter_mut().find(|other| other.name == theme.name) {
+                Some(existing) => *existing = theme,
+                None => themes.push(theme),
+            },
+            Err(err) => errors.push((path, err)),
+        }
+    }
+    (themes, errors)
+}
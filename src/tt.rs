@@ -0,0 +1,353 @@
+use std::{
+    collections::HashMap,
+    fs,
+    io::{self, Read, Write},
+    mem,
+    path::Path,
+    str::FromStr,
+    sync::{Mutex, MutexGuard},
+};
+
+use crate::{
+    config,
+    logic::{Move, MoveType, PieceType, MAX_BOARD_SIZE},
+};
+
+pub const PERSISTENT_FILE: &str = "tt.bin";
+const FILE_MAGIC: &[u8; 4] = b"CTT1";
+const RECORD_SIZE: usize = 32;
+pub const DEFAULT_HASH_MB: usize = 64;
+pub const MAX_HASH_MB: usize = 4096;
+const SLOT_SIZE: usize = mem::size_of::<(u64, Slot)>() + 1;
+const KEEP_GENERATIONS: u8 = 2;
+// Search threads probe and store at every node, so the entries are split across
+// independently locked shards to keep them from serializing on one lock.
+const SHARDS: usize = 64;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum Bound {
+    Exact,
+    Lower,
+    Upper,
+}
+
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Hash)]
+pub enum ReplacementPolicy {
+    #[default]
+    DepthPreferred,
+    AlwaysReplace,
+}
+
+impl ReplacementPolicy {
+    pub fn uci_name(&self) -> &'static str {
+        match self {
+            ReplacementPolicy::DepthPreferred => "depth",
+            ReplacementPolicy::AlwaysReplace => "always",
+        }
+    }
+}
+
+#[derive(Debug, PartialEq, Eq)]
+pub struct ParseReplacementPolicyError;
+
+impl FromStr for ReplacementPolicy {
+    type Err = ParseReplacementPolicyError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        [
+            ReplacementPolicy::DepthPreferred,
+            ReplacementPolicy::AlwaysReplace,
+        ]
+        .into_iter()
+        .find(|policy| policy.uci_name().eq_ignore_ascii_case(s.trim()))
+        .ok_or(ParseReplacementPolicyError)
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct TtEntry {
+    pub depth: usize,
+    pub score: f64,
+    pub bound: Bound,
+    pub best_move: Option<Move>,
+}
+
+#[derive(Clone, Copy, Debug)]
+struct Slot {
+    entry: TtEntry,
+    generation: u8,
+    pieces: u8,
+}
+
+#[derive(Debug)]
+pub struct TranspositionTable {
+    shards: Vec<Mutex<HashMap<u64, Slot>>>,
+    capacity: usize,
+    generation: u8,
+    pub policy: ReplacementPolicy,
+}
+
+impl Default for TranspositionTable {
+    fn default() -> Self {
+        Self::with_size_mb(DEFAULT_HASH_MB)
+    }
+}
+
+impl TranspositionTable {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn with_size_mb(mb: usize) -> Self {
+        let mut table = Self {
+            shards: (0..SHARDS).map(|_| Mutex::new(HashMap::new())).collect(),
+            capacity: 0,
+            generation: 0,
+            policy: ReplacementPolicy::default(),
+        };
+        table.set_size_mb(mb);
+        table
+    }
+
+    pub fn set_size_mb(&mut self, mb: usize) {
+        self.set_capacity(mb.clamp(1, MAX_HASH_MB) * 1024 * 1024 / SLOT_SIZE);
+    }
+
+    pub fn size_mb(&self) -> usize {
+        (self.capacity * SLOT_SIZE).div_ceil(1024 * 1024)
+    }
+
+    pub fn set_capacity(&mut self, capacity: usize) {
+        self.capacity = capacity.max(1);
+        let (capacity, generation) = (self.shard_capacity(), self.generation);
+        for shard in &mut self.shards {
+            let entries = shard.get_mut().unwrap();
+            if entries.len() > capacity {
+                make_room(entries, capacity, generation);
+            }
+            entries.shrink_to(capacity);
+        }
+    }
+
+    pub fn capacity(&self) -> usize {
+        self.capacity
+    }
+
+    fn shard_capacity(&self) -> usize {
+        self.capacity.div_ceil(SHARDS)
+    }
+
+    fn shard(&self, key: u64) -> MutexGuard<'_, HashMap<u64, Slot>> {
+        self.shards[key as usize % SHARDS].lock().unwrap()
+    }
+
+    pub fn usage_permille(&self) -> usize {
+        (self.len() * 1000 / self.capacity).min(1000)
+    }
+
+    /// Starts a search from a position with `pieces` pieces on the board. Entries are kept
+    /// between moves, except for positions with more pieces, which captures made them
+    /// unreachable.
+    pub fn new_search(&mut self, pieces: usize) {
+        self.generation = self.generation.wrapping_add(1);
+        let pieces = pieces.min(u8::MAX as usize) as u8;
+        for shard in &mut self.shards {
+            shard
+                .get_mut()
+                .unwrap()
+                .retain(|_, slot| slot.pieces <= pieces);
+        }
+    }
+
+    pub fn get(&self, key: u64) -> Option<TtEntry> {
+        self.shard(key).get(&key).map(|slot| slot.entry)
+    }
+
+    pub fn insert(&self, key: u64, pieces: usize, entry: TtEntry) {
+        let generation = self.generation;
+        let slot = Slot {
+            entry,
+            generation,
+            pieces: pieces.min(u8::MAX as usize) as u8,
+        };
+        let capacity = self.shard_capacity();
+        let mut entries = self.shard(key);
+        match entries.get_mut(&key) {
+            Some(existing)
+                if self.policy == ReplacementPolicy::DepthPreferred
+                    && existing.entry.depth > entry.depth =>
+            {
+                existing.generation = generation;
+            }
+            Some(existing) => *existing = slot,
+            None => {
+                if entries.len() >= capacity {
+                    make_room(&mut entries, capacity, generation);
+                }
+                if entries.len() < capacity {
+                    entries.insert(key, slot);
+                }
+            }
+        }
+    }
+
+    pub fn len(&self) -> usize {
+        self.shards
+            .iter()
+            .map(|shard| shard.lock().unwrap().len())
+            .sum()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    pub fn clear(&mut self) {
+        for shard in &mut self.shards {
+            shard.get_mut().unwrap().clear();
+        }
+    }
+
+    pub fn save(&self, path: &Path) -> io::Result<()> {
+        if let Some(parent) = path.parent() {
+            fs::create_dir_all(parent)?;
+        }
+        let mut out = io::BufWriter::new(fs::File::create(path)?);
+        out.write_all(FILE_MAGIC)?;
+        for shard in &self.shards {
+            for (key, Slot { entry, pieces, .. }) in shard.lock().unwrap().iter() {
+                let mut record = [0u8; RECORD_SIZE];
+                record[0..8].copy_from_slice(&key.to_le_bytes());
+                record[8..16].copy_from_slice(&entry.score.to_le_bytes());
+                record[16..20].copy_from_slice(&(entry.depth as u32).to_le_bytes());
+                record[20] = match entry.bound {
+                    Bound::Exact => 0,
+                    Bound::Lower => 1,
+                    Bound::Upper => 2,
+                };
+                if let Some(mv) = entry.best_move {
+                    record[21..29].copy_from_slice(&encode_move(mv));
+                }
+                record[29] = *pieces;
+                out.write_all(&record)?;
+            }
+        }
+        out.flush()
+    }
+
+    pub fn load(path: &Path) -> io::Result<Self> {
+        let mut data = Vec::new();
+        fs::File::open(path)?.read_to_end(&mut data)?;
+        let Some(records) = data.strip_prefix(FILE_MAGIC) else {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidData,
+                "not a transposition table file",
+            ));
+        };
+        let table = Self::new();
+        for record in records.chunks_exact(RECORD_SIZE) {
+            let key = u64::from_le_bytes(record[0..8].try_into().unwrap());
+            let bound = match record[20] {
+                0 => Bound::Exact,
+                1 => Bound::Lower,
+                2 => Bound::Upper,
+                _ => continue,
+            };
+            table.insert(
+                key,
+                record[29] as usize,
+                TtEntry {
+                    depth: u32::from_le_bytes(record[16..20].try_into().unwrap()) as usize,
+                    score: f64::from_le_bytes(record[8..16].try_into().unwrap()),
+                    bound,
+                    best_move: decode_move(record[21..29].try_into().unwrap()),
+                },
+            );
+        }
+        Ok(table)
+    }
+
+    pub fn load_persistent() -> Self {
+        config::config_file(PERSISTENT_FILE)
+            .and_then(|path| Self::load(&path).ok())
+            .unwrap_or_default()
+    }
+
+    pub fn save_persistent(&self) -> io::Result<()> {
+        match config::config_file(PERSISTENT_FILE) {
+            Some(path) => self.save(&path),
+            None => Ok(()),
+        }
+    }
+}
+
+fn make_room(entries: &mut HashMap<u64, Slot>, capacity: usize, generation: u8) {
+    entries.retain(|_, slot| generation.wrapping_sub(slot.generation) < KEEP_GENERATIONS);
+    let target = capacity * 3 / 4;
+    let max_depth = entries
+        .values()
+        .map(|slot| slot.entry.depth)
+        .max()
+        .unwrap_or(0);
+    for depth in 0..=max_depth {
+        if entries.len() <= target {
+            break;
+        }
+        entries.retain(|_, slot| slot.entry.depth > depth);
+    }
+}
+
+fn encode_move(mv: Move) -> [u8; 8] {
+    let mut bytes = [
+        1,
+        mv.original.0 as u8,
+        mv.original.1 as u8,
+        mv.target.0 as u8,
+        mv.target.1 as u8,
+        0,
+        0,
+        0,
+    ];
+    match mv.move_type {
+        MoveType::Normal => {}
+        MoveType::Castling { rook, direction } => {
+            bytes[0] = 2;
+            bytes[5] = rook.0 as u8;
+            bytes[6] = rook.1 as u8;
+            bytes[7] = direction as i8 as u8;
+        }
+        MoveType::EnPassant => bytes[0] = 3,
+        MoveType::Promotion(piece_type) => {
+            bytes[0] = 4;
+            bytes[5] = piece_type.index() as u8;
+        }
+        MoveType::Drop(piece_type) => {
+            bytes[0] = 5;
+            bytes[5] = piece_type.index() as u8;
+        }
+    }
+    bytes
+}
+
+fn decode_move(bytes: [u8; 8]) -> Option<Move> {
+    if bytes[1..5]
+        .iter()
+        .any(|&coord| coord as usize >= MAX_BOARD_SIZE)
+    {
+        return None;
+    }
+    let original = (bytes[1] as usize, bytes[2] as usize);
+    let target = (bytes[3] as usize, bytes[4] as usize);
+    let move_type = match bytes[0] {
+        1 => MoveType::Normal,
+        2 => MoveType::Castling {
+            rook: (bytes[5] as usize, bytes[6] as usize),
+            direction: bytes[7] as i8 as isize,
+        },
+        3 => MoveType::EnPassant,
+        4 => MoveType::Promotion(PieceType::from_index(bytes[5] as usize)?),
+        5 => MoveType::Drop(PieceType::from_index(bytes[5] as usize)?),
+        _ => return None,
+    };
+    Some(Move::new(original, target, move_type))
+}
@@ -0,0 +1,231 @@
+use crate::logic::{ChessBoard, Move};
+
+pub struct Step {
+    pub prompt: &'static str,
+    pub moves: &'static [&'static str],
+    pub reply: Option<&'static str>,
+}
+
+pub struct Lesson {
+    pub title: &'static str,
+    pub intro: &'static str,
+    pub fen: &'static str,
+    pub steps: &'static [Step],
+}
+
+pub const LESSONS: &[Lesson] = &[
+    Lesson {
+        title: "The rook",
+        intro: "Rooks move any number of squares along a rank or a file, but cannot jump over other pieces.",
+        fen: "7k/8/8/8/8/8/8/R6K w - - 0 1",
+        steps: &[
+            Step {
+                prompt: "Slide the rook up the file to a7.",
+                moves: &["a1a7"],
+                reply: Some("h8g8"),
+            },
+            Step {
+                prompt: "Now slide it along the rank to f7.",
+                moves: &["a7f7"],
+                reply: None,
+            },
+        ],
+    },
+    Lesson {
+        title: "The bishop",
+        intro: "Bishops move any number of squares diagonally, so they always stay on squares of one color.",
+        fen: "7k/8/8/8/8/8/8/2B4K w - - 0 1",
+        steps: &[
+            Step {
+                prompt: "Move the bishop to h6.",
+                moves: &["c1h6"],
+                reply: Some("h8g8"),
+            },
+            Step {
+                prompt: "Now use the other diagonal to reach f8.",
+                moves: &["h6f8"],
+                reply: None,
+            },
+        ],
+    },
+    Lesson {
+        title: "The queen",
+        intro: "The queen combines the rook and the bishop: she moves any number of squares in a straight line or diagonally.",
+        fen: "7k/8/8/8/8/8/8/3Q3K w - - 0 1",
+        steps: &[
+            Step {
+                prompt: "Move the queen straight up to d7.",
+                moves: &["d1d7"],
+                reply: Some("h8g8"),
+            },
+            Step {
+                prompt: "Now move her diagonally to a4.",
+                moves: &["d7a4"],
+                reply: None,
+            },
+        ],
+    },
+    Lesson {
+        title: "The knight",
+        intro: "Knights move in an L shape: two squares in one direction, then one square to the side. They are the only pieces that jump over others.",
+        fen: "7k/8/8/8/8/8/PPP5/1N5K w - - 0 1",
+        steps: &[
+            Step {
+                prompt: "Jump over the pawns to c3.",
+                moves: &["b1c3"],
+                reply: Some("h8g8"),
+            },
+            Step {
+                prompt: "Jump again to d5.",
+                moves: &["c3d5"],
+                reply: None,
+            },
+        ],
+    },
+    Lesson {
+        title: "The king",
+        intro: "The king moves one square in any direction. He may never move to a square where he would be in check.",
+        fen: "7k/8/8/8/8/8/8/4K3 w - - 0 1",
+        steps: &[Step {
+            prompt: "Step forward with the king.",
+            moves: &["e1d2", "e1e2", "e1f2"],
+            reply: None,
+        }],
+    },
+    Lesson {
+        title: "The pawn",
+        intro: "Pawns move straight forward one square, or two squares from their starting square. They capture one square diagonally forward.",
+        fen: "7k/8/8/3n4/8/8/4P3/4K3 w - - 0 1",
+        steps: &[
+            Step {
+                prompt: "Advance the pawn two squares.",
+                moves: &["e2e4"],
+                reply: Some("h8g8"),
+            },
+            Step {
+                prompt: "Capture the knight diagonally.",
+                moves: &["e4d5"],
+                reply: None,
+            },
+        ],
+    },
+    Lesson {
+        title: "Castling",
+        intro: "Castling moves the king two squares toward a rook, and the rook jumps to the square the king crossed. Neither piece may have moved, the squares between them must be empty, and the king may not be in check, pass through an attacked square or land in check.",
+        fen: "4k3/8/8/8/8/8/8/R3K2R w KQ - 0 1",
+        steps: &[Step {
+            prompt: "Castle by moving the king two squares toward either rook.",
+            moves: &["e1g1", "e1c1"],
+            reply: None,
+        }],
+    },
+    Lesson {
+        title: "En passant",
+        intro: "Black's pawn has just moved two squares from d7 to d5, passing the square your pawn attacks. On the very next move only, your pawn may capture it as if it had moved one square.",
+        fen: "4k3/8/8/3pP3/8/8/8/4K3 w - d6 0 2",
+        steps: &[Step {
+            prompt: "Capture en passant by moving your pawn to d6.",
+            moves: &["e5d6"],
+            reply: None,
+        }],
+    },
+    Lesson {
+        title: "Promotion",
+        intro: "A pawn that reaches the last rank is promoted to a queen, rook, bishop or knight of its own color.",
+        fen: "7k/4P3/8/8/8/8/8/4K3 w - - 0 1",
+        steps: &[Step {
+            prompt: "Push the pawn to e8 and choose a piece.",
+            moves: &["e7e8q", "e7e8r", "e7e8b", "e7e8n"],
+            reply: None,
+        }],
+    },
+];
+
+fn parse_move(board: &ChessBoard, uci: &str) -> Option<Move> {
+    board
+        .legal_move_list()
+        .iter()
+        .copied()
+        .find(|mv| mv.to_uci(board) == uci)
+}
+
+#[derive(Clone, Debug)]
+pub struct Tutorial {
+    pub lesson: usize,
+    pub step: usize,
+    pub board: ChessBoard,
+    pub mistake: bool,
+}
+
+impl Default for Tutorial {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Tutorial {
+    pub fn new() -> Self {
+        let mut tutorial = Self {
+            lesson: 0,
+            step: 0,
+            board: ChessBoard::new(),
+            mistake: false,
+        };
+        tutorial.start_lesson(0);
+        tutorial
+    }
+
+    pub fn start_lesson(&mut self, lesson: usize) {
+        self.lesson = lesson.min(LESSONS.len() - 1);
+        self.step = 0;
+        self.mistake = false;
+        self.board = ChessBoard::new();
+        self.board
+            .set_from_fen(self.current_lesson().fen)
+            .expect("tutorial positions are valid");
+    }
+
+    pub fn current_lesson(&self) -> &'static Lesson {
+        &LESSONS[self.lesson]
+    }
+
+    pub fn current_step(&self) -> Option<&'static Step> {
+        self.current_lesson().steps.get(self.step)
+    }
+
+    pub fn finished(&self) -> bool {
+        self.current_step().is_none()
+    }
+
+    pub fn allowed_moves(&self, from: (usize, usize)) -> Vec<Move> {
+        let Some(step) = self.current_step() else {
+            return Vec::new();
+        };
+        step.moves
+            .iter()
+            .filter_map(|uci| parse_move(&self.board, uci))
+            .filter(|mv| mv.original == from)
+            .collect()
+    }
+
+    pub fn play(&mut self, mv: Move) -> bool {
+        let Some(step) = self.current_step() else {
+            return false;
+        };
+        let accepted = step
+            .moves
+            .iter()
+            .filter_map(|uci| parse_move(&self.board, uci))
+            .any(|allowed| allowed == mv);
+        self.mistake = !accepted;
+        if !accepted {
+            return false;
+        }
+        mv.perform(&mut self.board);
+        if let Some(reply) = step.reply.and_then(|reply| parse_move(&self.board, reply)) {
+            reply.perform(&mut self.board);
+        }
+        self.step += 1;
+        true
+    }
+}
@@ -0,0 +1,317 @@
+use std::{
+    io::{self, BufRead, BufReader, Write},
+    path::Path,
+    process::{Child, ChildStdin, Command, Stdio},
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        mpsc::{self, Receiver, RecvTimeoutError},
+        Arc,
+    },
+    thread,
+    time::{Duration, Instant},
+};
+
+use crate::{
+    ai::mate_in_score,
+    analysis::{AnalysisLine, LiveEval},
+    clock::Clock,
+    engine::{Engine, SearchLimits},
+    logic::{ChessBoard, Move, PieceColor},
+};
+
+const STOP_POLL_INTERVAL: Duration = Duration::from_millis(50);
+const HANDSHAKE_TIMEOUT: Duration = Duration::from_secs(10);
+
+pub struct UciEngine {
+    pub name: String,
+    child: Child,
+    stdin: ChildStdin,
+    lines: Receiver<String>,
+    position: Option<ChessBoard>,
+    stop: Arc<AtomicBool>,
+    cancel: Option<Arc<AtomicBool>>,
+    multi_pv: usize,
+}
+
+impl UciEngine {
+    pub fn spawn(path: &Path) -> io::Result<Self> {
+        let mut child = Command::new(path)
+            .stdin(Stdio::piped())
+            .stdout(Stdio::piped())
+            .stderr(Stdio::null())
+            .spawn()?;
+        let stdin = child.stdin.take().expect("stdin is piped");
+        let stdout = BufReader::new(child.stdout.take().expect("stdout is piped"));
+        let (tx, rx) = mpsc::channel();
+        thread::spawn(move || {
+            for line in stdout.lines() {
+                let Ok(line) = line else {
+                    return;
+                };
+                if tx.send(line).is_err() {
+                    return;
+                }
+            }
+        });
+        let mut engine = Self {
+            name: path.display().to_string(),
+            child,
+            stdin,
+            lines: rx,
+            position: None,
+            stop: Arc::new(AtomicBool::new(false)),
+            cancel: None,
+            multi_pv: 1,
+        };
+        engine.send("uci")?;
+        let deadline = Instant::now() + HANDSHAKE_TIMEOUT;
+        loop {
+            let line = engine.read_line(deadline)?;
+            if let Some(name) = line.strip_prefix("id name ") {
+                engine.name = name.trim().to_string();
+            } else if line.trim() == "uciok" {
+                break;
+            }
+        }
+        engine.wait_ready()?;
+        Ok(engine)
+    }
+
+    fn send(&mut self, command: &str) -> io::Result<()> {
+        writeln!(self.stdin, "{}", command)?;
+        self.stdin.flush()
+    }
+
+    fn exited(&self) -> io::Error {
+        io::Error::new(
+            io::ErrorKind::UnexpectedEof,
+            format!("engine {} exited", self.name),
+        )
+    }
+
+    fn read_line(&mut self, deadline: Instant) -> io::Result<String> {
+        let timeout = deadline.saturating_duration_since(Instant::now());
+        self.lines.recv_timeout(timeout).map_err(|err| match err {
+            RecvTimeoutError::Timeout => io::Error::new(
+                io::ErrorKind::TimedOut,
+                format!("engine {} did not respond", self.name),
+            ),
+            RecvTimeoutError::Disconnected => self.exited(),
+        })
+    }
+
+    pub fn wait_ready(&mut self) -> io::Result<()> {
+        self.send("isready")?;
+        let deadline = Instant::now() + HANDSHAKE_TIMEOUT;
+        while self.read_line(deadline)?.trim() != "readyok" {}
+        Ok(())
+    }
+
+    pub fn set_option(&mut self, name: &str, value: &str) -> io::Result<()> {
+        self.send(&format!("setoption name {} value {}", name, value))
+    }
+
+    pub fn new_game(&mut self) -> io::Result<()> {
+        self.send("ucinewgame")?;
+        self.wait_ready()
+    }
+
+    fn run(
+        &mut self,
+        board: &ChessBoard,
+        go: &str,
+        on_info: &mut dyn FnMut(&LiveEval),
+    ) -> io::Result<String> {
+        self.send(&format!("position fen {}", board.to_fen()))?;
+        self.send(go)?;
+        let mut stopped = false;
+        let mut eval = LiveEval {
+            depth: 0,
+            lines: Vec::new(),
+        };
+        loop {
+            let line = match self.lines.recv_timeout(STOP_POLL_INTERVAL) {
+                Ok(line) => line,
+                Err(RecvTimeoutError::Timeout) => {
+                    if !stopped && self.should_stop() {
+                        self.send("stop")?;
+                        stopped = true;
+                    }
+                    continue;
+                }
+                Err(RecvTimeoutError::Disconnected) => return Err(self.exited()),
+            };
+            if let Some(rest) = line.strip_prefix("bestmove") {
+                return Ok(rest.split_whitespace().next().unwrap_or("").to_string());
+            }
+            if let Some(info) = line.strip_prefix("info ") {
+                if Self::parse_info(board, info, &mut eval) {
+                    on_info(&eval);
+                }
+            }
+        }
+    }
+
+    fn should_stop(&self) -> bool {
+        self.stop.load(Ordering::Relaxed)
+            || self
+                .cancel
+                .as_ref()
+                .is_some_and(|cancel| cancel.load(Ordering::Relaxed))
+    }
+
+    fn parse_info(board: &ChessBoard, info: &str, eval: &mut LiveEval) -> bool {
+        let mut depth = None;
+        let mut multi_pv = 1;
+        let mut score = None;
+        let mut pv = Vec::new();
+        let mut words = info.split_whitespace();
+        while let Some(word) = words.next() {
+            match word {
+                "depth" => depth = words.next().and_then(|d| d.parse().ok()),
+                "multipv" => multi_pv = words.next().and_then(|k| k.parse().ok()).unwrap_or(1),
+                "score" => {
+                    score = match (words.next(), words.next().and_then(|v| v.parse().ok())) {
+                        (Some("cp"), Some(cp)) => Some(cp as f64 / 100.0),
+                        (Some("mate"), Some(moves)) => Some(mate_in_score(moves)),
+                        _ => None,
+                    }
+                }
+                "pv" => {
+                    let mut board = board.clone();
+                    for mv in words.by_ref() {
+                        let Some(mv) = Move::from_str(mv, &board)
+                            .ok()
+                            .filter(|mv| mv.is_legal(&board))
+                        else {
+                            break;
+                        };
+                        mv.perform(&mut board);
+                        pv.push(mv);
+                    }
+                }
+                _ => {}
+            }
+        }
+        let (Some(depth), Some(score)) = (depth, score) else {
+            return false;
+        };
+        if pv.is_empty() || multi_pv == 0 || multi_pv > eval.lines.len() + 1 {
+            return false;
+        }
+        eval.depth = depth;
+        let line = AnalysisLine { score, pv };
+        match eval.lines.get_mut(multi_pv - 1) {
+            Some(existing) => *existing = line,
+            None => eval.lines.push(line),
+        }
+        true
+    }
+
+    fn parse_best_move(board: &ChessBoard, best: &str) -> io::Result<Move> {
+        Move::from_str(best, board)
+            .ok()
+            .filter(|mv| mv.is_legal(board))
+            .ok_or_else(|| {
+                io::Error::new(
+                    io::ErrorKind::InvalidData,
+                    format!("illegal move from engine: {}", best),
+                )
+            })
+    }
+
+    fn clock_args(board: &ChessBoard, clock: &Clock) -> String {
+        let mut args = format!(
+            " wtime {} btime {} winc {} binc {}",
+            clock.remaining(PieceColor::White).as_millis(),
+            clock.remaining(PieceColor::Black).as_millis(),
+            clock.time_control(PieceColor::White).increment.as_millis(),
+            clock.time_control(PieceColor::Black).increment.as_millis()
+        );
+        if let Some(moves_to_go) = clock.moves_to_go(board.turn) {
+            args.push_str(&format!(" movestogo {}", moves_to_go));
+        }
+        args
+    }
+
+    pub fn go_movetime(&mut self, board: &ChessBoard, move_time: Duration) -> io::Result<Move> {
+        Engine::set_position(self, board)?;
+        self.search(&SearchLimits::move_time(move_time), &mut |_| {})
+    }
+
+    pub fn go_clock(&mut self, board: &ChessBoard, clock: &Clock) -> io::Result<Move> {
+        Engine::set_position(self, board)?;
+        self.search(&SearchLimits::clock(clock), &mut |_| {})
+    }
+}
+
+impl Engine for UciEngine {
+    fn name(&self) -> String {
+        self.name.clone()
+    }
+
+    fn new_game(&mut self) -> io::Result<()> {
+        UciEngine::new_game(self)
+    }
+
+    fn set_option(&mut self, name: &str, value: &str) -> io::Result<()> {
+        UciEngine::set_option(self, name, value)
+    }
+
+    fn set_position(&mut self, board: &ChessBoard) -> io::Result<()> {
+        self.position = Some(board.clone());
+        Ok(())
+    }
+
+    fn search(
+        &mut self,
+        limits: &SearchLimits,
+        on_info: &mut dyn FnMut(&LiveEval),
+    ) -> io::Result<Move> {
+        let board = self
+            .position
+            .clone()
+            .ok_or_else(|| io::Error::new(io::ErrorKind::InvalidInput, "no position set"))?;
+        if limits.lines() != self.multi_pv {
+            self.multi_pv = limits.lines();
+            UciEngine::set_option(self, "MultiPV", &self.multi_pv.to_string())?;
+        }
+        let mut go = String::from("go");
+        if let Some(depth) = limits.depth {
+            go.push_str(&format!(" depth {}", depth));
+        }
+        if let Some(move_time) = limits.move_time {
+            go.push_str(&format!(" movetime {}", move_time.as_millis()));
+        }
+        if let Some(clock) = &limits.clock {
+            go.push_str(&Self::clock_args(&board, clock));
+        }
+        if go == "go" {
+            go.push_str(" infinite");
+        }
+        let best = self.run(&board, &go, on_info)?;
+        Self::parse_best_move(&board, &best)
+    }
+
+    fn set_stop(&mut self, stop: Arc<AtomicBool>) {
+        self.stop = stop;
+    }
+
+    fn set_cancel(&mut self, cancel: Arc<AtomicBool>) {
+        self.cancel = Some(cancel);
+    }
+}
+
+impl Drop for UciEngine {
+    fn drop(&mut self) {
+        let _ = self.send("quit");
+        for _ in 0..10 {
+            if self.child.try_wait().ok().flatten().is_some() {
+                return;
+            }
+            thread::sleep(Duration::from_millis(50));
+        }
+        let _ = self.child.kill();
+        let _ = self.child.wait();
+    }
+}
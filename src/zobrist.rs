@@ -0,0 +1,41 @@
+use crate::{fairy::MAX_FAIRY_PIECES, logic::MAX_BOARD_SIZE};
+
+const MAX_SQUARES: usize = MAX_BOARD_SIZE * MAX_BOARD_SIZE;
+
+const fn splitmix64(state: u64) -> (u64, u64) {
+    let state = state.wrapping_add(0x9E37_79B9_7F4A_7C15);
+    let mut z = state;
+    z = (z ^ (z >> 30)).wrapping_mul(0xBF58_476D_1CE4_E5B9);
+    z = (z ^ (z >> 27)).wrapping_mul(0x94D0_49BB_1331_11EB);
+    (state, z ^ (z >> 31))
+}
+
+const fn generate<const N: usize>(seed: u64) -> [u64; N] {
+    let mut keys = [0; N];
+    let mut state = seed;
+    let mut i = 0;
+    while i < N {
+        let (next, key) = splitmix64(state);
+        state = next;
+        keys[i] = key;
+        i += 1;
+    }
+    keys
+}
+
+pub const PIECE_KEYS: [u64; 12 * 64] = generate(0x5EED_0001);
+pub static WIDE_PIECE_KEYS: [u64; 12 * (MAX_SQUARES - 64)] = generate(0x5EED_0005);
+pub static FAIRY_PIECE_KEYS: [u64; 2 * MAX_FAIRY_PIECES * MAX_SQUARES] = generate(0x5EED_0006);
+pub const CASTLING_KEYS: [u64; 4] = generate(0x5EED_0002);
+pub const EN_PASSANT_KEYS: [u64; MAX_BOARD_SIZE] = generate(0x5EED_0003);
+pub const SIDE_KEY: u64 = generate::<1>(0x5EED_0004)[0];
+
+pub fn piece_key(piece: usize, square: usize) -> u64 {
+    if piece >= 12 {
+        FAIRY_PIECE_KEYS[(piece - 12) * MAX_SQUARES + square]
+    } else if square < 64 {
+        PIECE_KEYS[piece * 64 + square]
+    } else {
+        WIDE_PIECE_KEYS[piece * (MAX_SQUARES - 64) + square - 64]
+    }
+}
@@ -0,0 +1,56 @@
+use std::sync::{Arc, RwLock};
+
+use chess::{
+    game::{ChessGame, Player, PlayerAction},
+    logic::{ChessBoard, Move, PieceColor, WinState},
+};
+
+struct Scripted {
+    action: PlayerAction,
+    moves: Vec<&'static str>,
+}
+
+impl Player for Scripted {
+    fn get_move(&mut self, board: Arc<RwLock<ChessBoard>>) -> Move {
+        let board = board.read().unwrap();
+        let uci = self.moves.remove(0);
+        board
+            .legal_move_list()
+            .iter()
+            .copied()
+            .find(|mv| mv.to_uci(&board) == uci)
+            .unwrap()
+    }
+
+    fn get_action(&mut self, _board: Arc<RwLock<ChessBoard>>, _draw_offered: bool) -> PlayerAction {
+        self.action
+    }
+}
+
+fn fools_mate(action: PlayerAction) -> Option<WinState> {
+    let white = Scripted {
+        action,
+        moves: vec!["f2f3", "g2g4"],
+    };
+    let black = Scripted {
+        action,
+        moves: vec!["e7e5", "d8h4"],
+    };
+    ChessGame::new(Box::new(white), Box::new(black), || {}).play()
+}
+
+#[test]
+fn accepting_a_draw_that_was_never_offered_asks_for_a_move() {
+    assert_eq!(
+        fools_mate(PlayerAction::AcceptDraw),
+        Some(WinState::Checkmate(PieceColor::Black))
+    );
+}
+
+#[test]
+fn repeating_a_draw_offer_asks_for_a_move() {
+    assert_eq!(
+        fools_mate(PlayerAction::OfferDraw),
+        Some(WinState::Checkmate(PieceColor::Black))
+    );
+}
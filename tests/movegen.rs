@@ -0,0 +1,105 @@
+use chess::logic::{BoardBuilder, ChessBoard, Move, MoveList, MoveType};
+
+fn board(fen: &str) -> ChessBoard {
+    let mut board = ChessBoard::new();
+    board.set_from_fen(fen).unwrap();
+    board
+}
+
+#[test]
+fn most_legal_moves_in_a_position() {
+    let board = board("R6R/3Q4/1Q4Q1/4Q3/2Q4Q/Q4Q2/pp1Q4/kBNN1KB1 w - - 0 1");
+    assert_eq!(board.legal_move_list().len(), 218);
+    assert_eq!(board.perft(1), 218);
+}
+
+#[test]
+fn long_digit_runs_are_rejected() {
+    for fen in [
+        "99999999999999999999/8/8/8/8/8/8/8 w - - 0 1",
+        "K18446744073709551615/8 w - - 0 1",
+        "8/8/P99999999999999999999/8/8/8/8/8 w - - 0 1",
+    ] {
+        let err = ChessBoard::new().set_from_fen(fen).unwrap_err();
+        assert_eq!(err.kind, chess::logic::FenErrorKind::RowTooWide, "{}", fen);
+    }
+}
+
+#[test]
+fn single_rank_boards_load() {
+    let board = board("P w - - 0 1");
+    assert_eq!((board.width, board.height), (1, 1));
+    assert_eq!(board.to_fen(), "P w - - 0 1");
+}
+
+#[test]
+fn perft_matches_reference_counts() {
+    for (fen, depth, nodes) in [
+        (chess::logic::START_FEN, 3, 8902),
+        (
+            "r3k2r/p1ppqpb1/bn2pnp1/3PN3/1p2P3/2N2Q1p/PPPBBPPP/R3K2R w KQkq - 0 1",
+            2,
+            2039,
+        ),
+        ("8/2p5/3p4/KP5r/1R3p1k/8/4P1P1/8 w - - 0 1", 4, 43238),
+    ] {
+        assert_eq!(board(fen).perft(depth), nodes, "{}", fen);
+    }
+}
+
+#[test]
+fn en_passant_round_trips_and_can_be_played() {
+    let fen = "rnbqkbnr/ppp1p1pp/8/3pPp2/8/8/PPPP1PPP/RNBQKBNR w KQkq f6 0 3";
+    let mut position = board(fen);
+    assert_eq!(position.to_fen(), fen);
+    let capture = Move::from_str("e5f6", &position).unwrap();
+    assert_eq!(capture.move_type, MoveType::EnPassant);
+    assert!(position.legal_move_list().contains(&capture));
+    capture.perform(&mut position);
+    assert!(position.piece_at((5, 3)).is_none());
+    assert_eq!(position.en_passant, None);
+
+    let built = BoardBuilder::from_board(&board(fen)).build().unwrap();
+    assert_eq!(built.to_fen(), fen);
+    assert!(ChessBoard::new()
+        .set_from_fen("rnbqkbnr/ppp1p1pp/8/3pPp2/8/8/PPPP1PPP/RNBQKBNR w KQkq e6 0 3")
+        .is_err());
+}
+
+#[test]
+fn captures_and_quiets_partition_all_moves() {
+    let mut positions = vec![
+        board("r3k2r/p1ppqpb1/bn2pnp1/3PN3/1p2P3/2N2Q1p/PPPBBPPP/R3K2R w KQkq - 0 1"),
+        board("rnbqkbnr/ppp1p1pp/8/3pPp2/8/8/PPPP1PPP/RNBQKBNR w KQkq f6 0 3"),
+        board("r3k2r/Pppp1ppp/1b3nbN/nP6/BBP1P3/q4N2/Pp1P2PP/R2Q1RK1 w kq - 0 1"),
+    ];
+    for board in positions.clone() {
+        for m in board.legal_move_list().iter() {
+            let mut child = board.clone();
+            m.perform(&mut child);
+            positions.push(child);
+        }
+    }
+    for board in positions {
+        let (mut all, mut captures, mut quiets) =
+            (MoveList::new(), MoveList::new(), MoveList::new());
+        board.generate_moves_into(&mut all);
+        board.generate_captures_into(&mut captures);
+        board.generate_quiets_into(&mut quiets);
+        assert!(captures
+            .iter()
+            .all(|m| m.is_capture(&board) || m.is_promotion()));
+        assert!(quiets
+            .iter()
+            .all(|m| !m.is_capture(&board) && !m.is_promotion()));
+        let mut split = captures
+            .iter()
+            .chain(quiets.iter())
+            .copied()
+            .collect::<Vec<_>>();
+        let mut all = all.to_vec();
+        split.sort_by_key(|m| (m.original, m.target, m.to_uci(&board)));
+        all.sort_by_key(|m| (m.original, m.target, m.to_uci(&board)));
+        assert_eq!(split, all, "{}", board.to_fen());
+    }
+}
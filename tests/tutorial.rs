@@ -0,0 +1,28 @@
+use chess::tutorial::{Tutorial, LESSONS};
+
+#[test]
+fn every_lesson_can_be_completed_with_legal_moves() {
+    for (index, lesson) in LESSONS.iter().enumerate() {
+        let mut tutorial = Tutorial::new();
+        tutorial.start_lesson(index);
+        while let Some(step) = tutorial.current_step() {
+            let mover = tutorial.board.turn;
+            let uci = step.moves[0];
+            let mv = tutorial
+                .board
+                .legal_move_list()
+                .iter()
+                .copied()
+                .find(|mv| mv.to_uci(&tutorial.board) == uci)
+                .unwrap_or_else(|| panic!("{}: {} is not legal", lesson.title, uci));
+            assert!(tutorial.play(mv), "{}: {} was rejected", lesson.title, uci);
+            if let Some(reply) = step.reply {
+                assert_eq!(
+                    tutorial.board.turn, mover,
+                    "{}: {} failed",
+                    lesson.title, reply
+                );
+            }
+        }
+    }
+}